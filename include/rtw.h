@@ -0,0 +1,44 @@
+/* C API of the ray tracer, built into a shared library with `cargo rustc --lib
+ * --release --features ffi --crate-type cdylib` (target/release/libray_tracing_weekend.so,
+ * .dylib or .dll). */
+#ifndef RTW_H
+#define RTW_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define RTW_OK 0
+#define RTW_INVALID_ARGUMENT (-1)
+#define RTW_BUFFER_TOO_SMALL (-2)
+#define RTW_RENDER_FAILED (-3)
+
+typedef struct RtwScene RtwScene;
+
+RtwScene *rtw_create_scene(void);
+void rtw_destroy_scene(RtwScene *scene);
+
+/* Each returns the new material's index for rtw_add_sphere, or a negative status. */
+int32_t rtw_add_lambertian(RtwScene *scene, double r, double g, double b);
+int32_t rtw_add_metal(RtwScene *scene, double r, double g, double b, double fuzz);
+int32_t rtw_add_dielectric(RtwScene *scene, double ior);
+int32_t rtw_add_light(RtwScene *scene, double r, double g, double b);
+
+int32_t rtw_add_sphere(RtwScene *scene, double x, double y, double z, double radius,
+                       int32_t material);
+int32_t rtw_set_camera(RtwScene *scene, double from_x, double from_y, double from_z,
+                       double at_x, double at_y, double at_z, double vfov);
+int32_t rtw_set_background(RtwScene *scene, double r, double g, double b);
+
+/* 8-bit RGBA, top row first; buffer_len must be at least width * height * 4. */
+int32_t rtw_render_to_buffer(const RtwScene *scene, uint32_t width, uint32_t height,
+                             uint32_t samples, uint8_t *buffer, size_t buffer_len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
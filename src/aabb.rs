@@ -0,0 +1,150 @@
+use crate::rtweekend::*;
+
+/// Axis-aligned bounding box, one interval per axis.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Aabb {
+    pub x: Interval,
+    pub y: Interval,
+    pub z: Interval,
+}
+
+impl Aabb {
+    pub const EMPTY: Self = Self {
+        x: Interval::EMPTY,
+        y: Interval::EMPTY,
+        z: Interval::EMPTY,
+    };
+
+    pub const UNIVERSE: Self = Self {
+        x: Interval::UNIVERSE,
+        y: Interval::UNIVERSE,
+        z: Interval::UNIVERSE,
+    };
+
+    pub fn new(x: Interval, y: Interval, z: Interval) -> Self {
+        Self { x, y, z }
+    }
+
+    /// Box spanning two corner points given in any order.
+    pub fn from_points(a: Point3, b: Point3) -> Self {
+        Self {
+            x: Interval::new(a.x.min(b.x), a.x.max(b.x)),
+            y: Interval::new(a.y.min(b.y), a.y.max(b.y)),
+            z: Interval::new(a.z.min(b.z), a.z.max(b.z)),
+        }
+    }
+
+    /// Smallest box containing both boxes.
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self {
+            x: self.x.union(&other.x),
+            y: self.y.union(&other.y),
+            z: self.z.union(&other.z),
+        }
+    }
+
+    pub fn axis_interval(&self, axis: usize) -> Interval {
+        match axis {
+            0 => self.x,
+            1 => self.y,
+            _ => self.z,
+        }
+    }
+
+    /// Axis along which the box is longest (0, 1 or 2 for x, y or z).
+    pub fn longest_axis(&self) -> usize {
+        let (x, y, z) = (self.x.size(), self.y.size(), self.z.size());
+        if x >= y && x >= z {
+            0
+        } else if y >= z {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// The box grown to at least `delta` thick along every axis, so flat objects (a
+    /// quad in an axis plane) still have a box rays can hit.
+    pub fn pad(&self, delta: f64) -> Self {
+        let pad = |i: Interval| if i.size() < delta { i.expand(delta) } else { i };
+        Self {
+            x: pad(self.x),
+            y: pad(self.y),
+            z: pad(self.z),
+        }
+    }
+
+    /// Whether `r` passes through the box anywhere within `ray_t`.
+    pub fn hit(&self, r: &Ray, ray_t: Interval) -> bool {
+        let inv_dir = Vec3::new(1.0 / r.dir.x, 1.0 / r.dir.y, 1.0 / r.dir.z);
+        self.overlap(r.orig, inv_dir, ray_t).is_some()
+    }
+
+    /// Part of `ray_t` a ray from `orig` spends inside the box, given the reciprocal of
+    /// its direction (computed once per ray by accelerators that test many boxes).
+    /// Slab test with min/max only: an axis the ray runs parallel to gives infinities
+    /// that drop out on their own.
+    pub fn overlap(&self, orig: Point3, inv_dir: Vec3, ray_t: Interval) -> Option<Interval> {
+        let slab = |i: Interval, o: f64, inv: f64| {
+            let t0 = (i.min - o) * inv;
+            let t1 = (i.max - o) * inv;
+            (t0.min(t1), t0.max(t1))
+        };
+        let (x0, x1) = slab(self.x, orig.x, inv_dir.x);
+        let (y0, y1) = slab(self.y, orig.y, inv_dir.y);
+        let (z0, z1) = slab(self.z, orig.z, inv_dir.z);
+        let t_min = ray_t.min.max(x0).max(y0).max(z0);
+        let t_max = ray_t.max.min(x1).min(y1).min(z1);
+        (t_min <= t_max).then(|| Interval::new(t_min, t_max))
+    }
+
+    pub fn min(&self) -> Point3 {
+        Point3::new(self.x.min, self.y.min, self.z.min)
+    }
+
+    pub fn max(&self) -> Point3 {
+        Point3::new(self.x.max, self.y.max, self.z.max)
+    }
+
+    pub fn center(&self) -> Point3 {
+        0.5 * (self.min() + self.max())
+    }
+
+    /// Total area of the six faces; 0 for an empty box.
+    pub fn surface_area(&self) -> f64 {
+        let d = self.max() - self.min();
+        if d.x < 0.0 || d.y < 0.0 || d.z < 0.0 {
+            return 0.0;
+        }
+        2.0 * (d.x * d.y + d.y * d.z + d.z * d.x)
+    }
+
+    /// Whether the box is finite on every axis (not empty and not unbounded).
+    pub fn is_bounded(&self) -> bool {
+        [self.x, self.y, self.z]
+            .iter()
+            .all(|i| i.min.is_finite() && i.max.is_finite() && i.min <= i.max)
+    }
+
+    pub fn corners(&self) -> [Point3; 8] {
+        let (lo, hi) = (self.min(), self.max());
+        [
+            Point3::new(lo.x, lo.y, lo.z),
+            Point3::new(hi.x, lo.y, lo.z),
+            Point3::new(lo.x, hi.y, lo.z),
+            Point3::new(hi.x, hi.y, lo.z),
+            Point3::new(lo.x, lo.y, hi.z),
+            Point3::new(hi.x, lo.y, hi.z),
+            Point3::new(lo.x, hi.y, hi.z),
+            Point3::new(hi.x, hi.y, hi.z),
+        ]
+    }
+
+    /// Box around a disk of `radius` centered at `center` and facing `normal`.
+    pub fn around_disk(center: Point3, normal: Vec3, radius: f64) -> Self {
+        let n = normal.normalized();
+        let extent = |c: f64| radius * (1.0 - c * c).max(0.0).sqrt();
+        let half = Vec3::new(extent(n.x), extent(n.y), extent(n.z));
+        Self::from_points(center - half, center + half)
+    }
+}
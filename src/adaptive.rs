@@ -0,0 +1,110 @@
+use crate::parallel::*;
+use crate::rtweekend::*;
+
+/// The proxy pass renders at 1/PROXY_SCALE of the resolution in each direction.
+const PROXY_SCALE: u32 = 4;
+const PROXY_SAMPLES: u32 = 8;
+/// Fewest and most samples a pixel gets, as fractions/multiples of `samples_per_pixel`.
+const MIN_FRACTION: f64 = 0.25;
+const MAX_MULTIPLE: f64 = 4.0;
+
+/// Samples to take in each film pixel, allocated from a low-resolution proxy render so
+/// noisy regions (glass, soft shadows, caustics) get more of the budget than flat ones.
+#[derive(Debug, Clone)]
+pub struct SampleMap {
+    width: u32,
+    samples: Vec<u32>,
+}
+
+impl SampleMap {
+    pub fn samples(&self, i: u32, j: u32) -> u32 {
+        self.samples[(j * self.width + i) as usize]
+    }
+
+    pub fn total(&self) -> u64 {
+        self.samples.iter().map(|&n| n as u64).sum()
+    }
+
+    pub fn min(&self) -> u32 {
+        self.samples.iter().copied().min().unwrap_or(0)
+    }
+
+    pub fn max(&self) -> u32 {
+        self.samples.iter().copied().max().unwrap_or(0)
+    }
+}
+
+// Bilinear lookup into a `width` x `height` grid of values at pixel centers.
+fn upsample(grid: &[f64], width: u32, height: u32, x: f64, y: f64) -> f64 {
+    let x = (x - 0.5).clamp(0.0, (width - 1) as f64);
+    let y = (y - 0.5).clamp(0.0, (height - 1) as f64);
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (fx, fy) = (x - x0 as f64, y - y0 as f64);
+    let at = |i: u32, j: u32| grid[(j * width + i) as usize];
+    let top = at(x0, y0) * (1.0 - fx) + at(x1, y0) * fx;
+    let bottom = at(x0, y1) * (1.0 - fx) + at(x1, y1) * fx;
+    top * (1.0 - fy) + bottom * fy
+}
+
+impl Camera {
+    /// Renders a quick proxy of the frame and spreads the full render's sample budget
+    /// (`samples_per_pixel` times the pixel count) in proportion to the proxy's relative
+    /// noise, keeping a floor everywhere so no region is starved.
+    pub fn sample_map(&self, scene: &Scene) -> SampleMap {
+        let mut proxy = self.clone();
+        proxy.image_width = (self.image_width / PROXY_SCALE).max(1);
+        proxy.samples_per_pixel = PROXY_SAMPLES;
+        proxy.adaptive = false;
+        proxy.initialize();
+        let (pw, ph) = (proxy.film_width(), proxy.film_height());
+
+        // Standard error of each proxy pixel relative to its brightness. The constant
+        // keeps near-black pixels from claiming the whole budget.
+        let error: Vec<f64> = (0..pw * ph)
+            .into_par_iter()
+            .map(|idx| {
+                let (i, j) = (idx % pw, idx / pw);
+                let mut rng = proxy.pixel_rng(i, j);
+                let (mut sum, mut sum_sq) = (0.0, 0.0);
+                for _ in 0..PROXY_SAMPLES {
+                    let y = luminance(proxy.sample_once(i, j, scene, &mut rng));
+                    sum += y;
+                    sum_sq += y * y;
+                }
+                let n = PROXY_SAMPLES as f64;
+                let mean = sum / n;
+                let variance = (sum_sq / n - mean * mean).max(0.0);
+                (variance / n).sqrt() / (mean + 0.1)
+            })
+            .collect();
+
+        let (width, height) = (self.film_width(), self.film_height());
+        let (sx, sy) = (pw as f64 / width as f64, ph as f64 / height as f64);
+        let weights: Vec<f64> = (0..width * height)
+            .map(|idx| {
+                let (i, j) = ((idx % width) as f64 + 0.5, (idx / width) as f64 + 0.5);
+                upsample(&error, pw, ph, i * sx, j * sy)
+            })
+            .collect();
+
+        let spp = self.samples_per_pixel as f64;
+        let floor = (spp * MIN_FRACTION).max(1.0);
+        let ceiling = (spp * MAX_MULTIPLE).max(floor);
+        let mean_weight = weights.iter().sum::<f64>() / weights.len() as f64;
+        // Whatever the floor doesn't use is handed out by weight.
+        let spare = spp - floor;
+        let samples = weights
+            .iter()
+            .map(|&w| {
+                let share = if mean_weight > 0.0 {
+                    w / mean_weight
+                } else {
+                    1.0
+                };
+                (floor + spare * share).clamp(floor, ceiling).round() as u32
+            })
+            .collect();
+        SampleMap { width, samples }
+    }
+}
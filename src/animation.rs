@@ -0,0 +1,361 @@
+use crate::interrupt;
+use crate::output::{ImageFormat, OutputVars, expand_template};
+use crate::rtweekend::*;
+use crate::video::VideoEncoder;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Values that can be blended between keyframes.
+pub trait Lerp: Copy {
+    fn lerp(self, other: Self, s: f64) -> Self;
+}
+
+impl Lerp for f64 {
+    fn lerp(self, other: Self, s: f64) -> Self {
+        self + s * (other - self)
+    }
+}
+
+impl Lerp for Vec3 {
+    fn lerp(self, other: Self, s: f64) -> Self {
+        self + s * (other - self)
+    }
+}
+
+impl Lerp for Quat {
+    fn lerp(self, other: Self, s: f64) -> Self {
+        self.slerp(other, s)
+    }
+}
+
+/// How a track moves between two keyframes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Easing {
+    #[default]
+    Linear,
+    /// Eases in and out of every keyframe (smoothstep), so motion starts and stops gently.
+    Smooth,
+}
+
+/// A value keyframed over time. Before the first and after the last keyframe it holds.
+#[derive(Debug, Clone)]
+pub struct Track<T> {
+    /// (time, value) pairs sorted by time.
+    keys: Vec<(f64, T)>,
+    pub easing: Easing,
+}
+
+impl<T: Lerp> Track<T> {
+    pub fn new(mut keys: Vec<(f64, T)>) -> Self {
+        keys.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self {
+            keys,
+            easing: Easing::Linear,
+        }
+    }
+
+    pub fn constant(value: T) -> Self {
+        Self::new(vec![(0.0, value)])
+    }
+
+    pub fn smooth(self) -> Self {
+        Self {
+            easing: Easing::Smooth,
+            ..self
+        }
+    }
+
+    pub fn keys(&self) -> &[(f64, T)] {
+        &self.keys
+    }
+
+    /// Value at `time`. Empty tracks have no value.
+    pub fn at(&self, time: f64) -> Option<T> {
+        let &(first_time, first) = self.keys.first()?;
+        if time <= first_time {
+            return Some(first);
+        }
+        for pair in self.keys.windows(2) {
+            let ((t0, v0), (t1, v1)) = (pair[0], pair[1]);
+            if time <= t1 {
+                let mut s = if t1 > t0 {
+                    (time - t0) / (t1 - t0)
+                } else {
+                    1.0
+                };
+                if self.easing == Easing::Smooth {
+                    s = s * s * (3.0 - 2.0 * s);
+                }
+                return Some(v0.lerp(v1, s));
+            }
+        }
+        self.keys.last().map(|&(_, v)| v)
+    }
+}
+
+/// Keyframed camera settings. Tracks left as `None` keep the base camera's value.
+#[derive(Debug, Clone, Default)]
+pub struct CameraAnimation {
+    pub lookfrom: Option<Track<Point3>>,
+    pub lookat: Option<Track<Point3>>,
+    pub vfov: Option<Track<f64>>,
+    pub focus_dist: Option<Track<f64>>,
+    pub defocus_angle: Option<Track<f64>>,
+    pub roll: Option<Track<f64>>,
+    /// Switches the camera to quaternion aiming; keys are slerped.
+    pub orientation: Option<Track<Quat>>,
+}
+
+fn sample<T: Lerp>(track: &Option<Track<T>>, time: f64) -> Option<T> {
+    track.as_ref().and_then(|t| t.at(time))
+}
+
+impl CameraAnimation {
+    /// Sets `camera` to how it looks at `time`.
+    pub fn apply(&self, camera: &mut Camera, time: f64) {
+        if let Some(lookfrom) = sample(&self.lookfrom, time) {
+            camera.lookfrom = lookfrom;
+        }
+        if let Some(lookat) = sample(&self.lookat, time) {
+            camera.lookat = lookat;
+        }
+        if let Some(vfov) = sample(&self.vfov, time) {
+            camera.vfov = vfov;
+        }
+        if let Some(focus_dist) = sample(&self.focus_dist, time) {
+            camera.focus_dist = focus_dist;
+        }
+        if let Some(defocus_angle) = sample(&self.defocus_angle, time) {
+            camera.defocus_angle = defocus_angle;
+        }
+        if let Some(roll) = sample(&self.roll, time) {
+            camera.roll = roll;
+        }
+        if let Some(orientation) = sample(&self.orientation, time) {
+            camera.orientation = Some(orientation);
+        }
+        camera.initialize();
+    }
+}
+
+/// A frame sequence: which frames to render, at what rate, and how the camera moves.
+/// Objects are animated with `Animated` or `MotionPath`, which follow the time carried
+/// by each ray, so everything in the scene is keyed to the same clock.
+#[derive(Debug, Clone)]
+pub struct Animation {
+    pub fps: f64,
+    pub start_frame: u32,
+    /// Last frame, inclusive.
+    pub end_frame: u32,
+    pub camera: CameraAnimation,
+    /// Fraction of a frame the shutter stays open, for motion blur (0.5 is a 180°
+    /// shutter, 0 freezes motion).
+    pub shutter: f64,
+}
+
+impl Animation {
+    pub fn new(fps: f64, start_frame: u32, end_frame: u32) -> Self {
+        Self {
+            fps,
+            start_frame,
+            end_frame,
+            camera: CameraAnimation::default(),
+            shutter: 0.0,
+        }
+    }
+
+    /// One full orbit of the camera around its look-at point over the frame range,
+    /// keeping its height and distance.
+    pub fn turntable(camera: &Camera, fps: f64, frames: u32) -> Self {
+        let mut animation = Self::new(fps, 1, frames.max(1));
+        let offset = camera.lookfrom - camera.lookat;
+        let keys = (0..=frames)
+            .map(|k| {
+                let angle = 2.0 * PI * k as f64 / frames.max(1) as f64;
+                let (sin, cos) = angle.sin_cos();
+                let rotated = Vec3::new(
+                    offset.x * cos + offset.z * sin,
+                    offset.y,
+                    -offset.x * sin + offset.z * cos,
+                );
+                (animation.frame_time(k + 1), camera.lookat + rotated)
+            })
+            .collect();
+        animation.camera.lookfrom = Some(Track::new(keys));
+        animation
+    }
+
+    /// Scene time at the start of `frame`.
+    pub fn frame_time(&self, frame: u32) -> f64 {
+        frame as f64 / self.fps
+    }
+
+    /// `base` as it is set up for `frame`, with its shutter opening at the frame's time.
+    pub fn camera_at(&self, base: &Camera, frame: u32) -> Camera {
+        let time = self.frame_time(frame);
+        let mut camera = base.clone();
+        camera.shutter_open = time;
+        camera.shutter_close = time + self.shutter / self.fps;
+        self.camera.apply(&mut camera, time);
+        camera
+    }
+
+    /// Renders every frame to the path `template` expands to (see `expand_template`),
+    /// e.g. `frames/frame_{frame:04}.ppm`, in `format` or else the one the extension
+    /// calls for (see `ImageFormat::from_path`).
+    pub fn render(
+        &self,
+        base: &Camera,
+        scene: &Scene,
+        template: &str,
+        scene_name: &str,
+        format: Option<ImageFormat>,
+    ) -> io::Result<()> {
+        for frame in self.start_frame..=self.end_frame {
+            // Stop after the frame that was interrupted, leaving it partly rendered.
+            if interrupt::requested() {
+                break;
+            }
+            let camera = self.camera_at(base, frame);
+            let mut vars = OutputVars::new(scene_name, &camera);
+            vars.frame = frame;
+            let path = expand_template(template, &vars)?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            log::info!(
+                target: "render",
+                "Frame {} of {} -> {}",
+                frame - self.start_frame + 1,
+                self.end_frame - self.start_frame + 1,
+                path.display()
+            );
+            let format = format.or_else(|| ImageFormat::from_path(&path));
+            camera.render_to(scene, fs::File::create(&path)?, format.unwrap_or_default())?;
+        }
+        Ok(())
+    }
+
+    /// Renders every frame straight into a video file through `ffmpeg`.
+    pub fn render_video(&self, base: &Camera, scene: &Scene, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut encoder = VideoEncoder::new(path, self.fps)?;
+        for frame in self.start_frame..=self.end_frame {
+            if interrupt::requested() {
+                break;
+            }
+            log::info!(
+                target: "render",
+                "Frame {} of {} -> {}",
+                frame - self.start_frame + 1,
+                self.end_frame - self.start_frame + 1,
+                path.display()
+            );
+            encoder.write_frame(&self.camera_at(base, frame), scene)?;
+        }
+        encoder.finish()
+    }
+}
+
+/// Keyframed transform for any hittable: scale, then rotation about `axis`, then
+/// translation, each evaluated at the ray's time. Use it for turntables of an object or
+/// anything else that moves, spins or grows over an animation.
+pub struct Animated {
+    object: Arc<dyn Hittable>,
+    pub translation: Track<Vec3>,
+    /// Axis the rotation turns about, through the object's origin.
+    pub axis: Vec3,
+    /// Rotation in degrees.
+    pub rotation: Track<f64>,
+    /// Uniform scale.
+    pub scale: Track<f64>,
+}
+
+impl Animated {
+    pub fn new(object: Arc<dyn Hittable>) -> Self {
+        Self {
+            object,
+            translation: Track::constant(Vec3::zero()),
+            axis: Vec3::new(0.0, 1.0, 0.0),
+            rotation: Track::constant(0.0),
+            scale: Track::constant(1.0),
+        }
+    }
+
+    /// Spins the object `turns` times about `axis` between `start` and `end`.
+    pub fn spinning(
+        object: Arc<dyn Hittable>,
+        axis: Vec3,
+        start: f64,
+        end: f64,
+        turns: f64,
+    ) -> Self {
+        Self {
+            axis,
+            rotation: Track::new(vec![(start, 0.0), (end, 360.0 * turns)]),
+            ..Self::new(object)
+        }
+    }
+
+    // Rodrigues' rotation of `v` by `degrees` about the unit `axis`.
+    fn rotate(axis: Vec3, v: Vec3, degrees: f64) -> Vec3 {
+        let (sin, cos) = degrees_to_radians(degrees).sin_cos();
+        v * cos + axis.cross(v) * sin + axis * axis.dot(v) * (1.0 - cos)
+    }
+}
+
+impl Hittable for Animated {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let axis = self.axis.normalized();
+        let offset = self.translation.at(r.tm).unwrap_or_default();
+        let angle = self.rotation.at(r.tm).unwrap_or(0.0);
+        let scale = self.scale.at(r.tm).unwrap_or(1.0);
+
+        // The same `t` works in both spaces since the inverse transform is affine.
+        let local = Ray {
+            orig: Self::rotate(axis, r.orig - offset, -angle) / scale,
+            dir: Self::rotate(axis, r.dir, -angle) / scale,
+            ..*r
+        };
+        let mut rec = self.object.hit(&local, ray_t)?;
+        rec.p = Self::rotate(axis, rec.p * scale, angle) + offset;
+        rec.normal = Self::rotate(axis, rec.normal, angle);
+        rec.geometric_normal = Self::rotate(axis, rec.geometric_normal, angle);
+        rec.tangent = Self::rotate(axis, rec.tangent, angle);
+        rec.p_error *= scale;
+        rec.uv_scale *= scale;
+        Some(rec)
+    }
+
+    // Any rotation keeps the object inside the sphere around its origin that holds its
+    // box, so pad that sphere by the largest scale and sweep it along the translation.
+    fn bounding_box(&self) -> Aabb {
+        let bbox = self.object.bounding_box();
+        if !bbox.is_bounded() {
+            return bbox;
+        }
+        let reach = bbox
+            .corners()
+            .iter()
+            .map(|c| c.length())
+            .fold(0.0, f64::max);
+        let max_scale = self
+            .scale
+            .keys()
+            .iter()
+            .map(|&(_, s)| s.abs())
+            .fold(0.0, f64::max);
+        let r = reach * max_scale;
+        let pad = Vec3::new(r, r, r);
+        let translations = self.translation.keys();
+        if translations.is_empty() {
+            return Aabb::from_points(-pad, pad);
+        }
+        translations.iter().fold(Aabb::EMPTY, |acc, &(_, offset)| {
+            acc.union(&Aabb::from_points(offset - pad, offset + pad))
+        })
+    }
+}
@@ -0,0 +1,185 @@
+use crate::rtweekend::*;
+use rand::SeedableRng;
+
+const AUDIT_SAMPLES: u32 = 20_000;
+// Fixed, so an audit reports the same numbers every run.
+const AUDIT_SEED: u64 = 0xa0d17;
+const AUDIT_ANGLES_DEG: [f64; 4] = [0.0, 45.0, 70.0, 85.0];
+/// Allowed overshoot for models that only approximately conserve energy. The qualitative
+/// Oren-Nayar fit reflects about 0.2% too much at grazing angles.
+const ENERGY_TOLERANCE: f64 = 0.005;
+
+/// Directional-hemispherical reflectance of one material at one incidence angle.
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    pub name: String,
+    pub angle_deg: f64,
+    /// Fraction of light scattered (reflected or transmitted), per channel.
+    pub albedo: Color,
+    /// Standard error of the largest channel.
+    pub std_error: f64,
+}
+
+impl AuditEntry {
+    /// More light out than in, by more than the sampling noise explains.
+    pub fn gains_energy(&self) -> bool {
+        let max = self.albedo.max_component();
+        max > 1.0 + (4.0 * self.std_error).max(ENERGY_TOLERANCE)
+    }
+}
+
+/// Estimates the albedo of `mat` for light arriving `angle_deg` away from the normal of
+/// a flat surface, by averaging `scatter` weights.
+pub fn directional_albedo(mat: &dyn Material, angle_deg: f64, samples: u32) -> (Color, f64) {
+    let theta = degrees_to_radians(angle_deg);
+    let rec = HitRecord {
+        p: Point3::zero(),
+        normal: Vec3::new(0.0, 0.0, 1.0),
+        geometric_normal: Vec3::new(0.0, 0.0, 1.0),
+        t: 1.0,
+        tangent: Vec3::new(1.0, 0.0, 0.0),
+        front_face: true,
+        ..Default::default()
+    };
+    let r_in = Ray::new(
+        Point3::new(theta.sin(), 0.0, theta.cos()),
+        Vec3::new(-theta.sin(), 0.0, -theta.cos()),
+    );
+
+    let mut rng = SmallRng::seed_from_u64(AUDIT_SEED);
+    let mut sum = Color::BLACK;
+    let (mut max_sum, mut max_sum_sq) = (0.0, 0.0);
+    for _ in 0..samples {
+        if let Some((_, weight)) = mat.scatter(&r_in, &rec, &mut rng) {
+            let max = weight.max_component();
+            sum += weight;
+            max_sum += max;
+            max_sum_sq += max * max;
+        }
+    }
+    let n = samples.max(1) as f64;
+    let variance = (max_sum_sq / n - (max_sum / n).powi(2)).max(0.0);
+    (sum / n, (variance / n).sqrt())
+}
+
+/// Audits one material at the standard incidence angles.
+pub fn audit_material(name: &str, mat: &dyn Material) -> Vec<AuditEntry> {
+    AUDIT_ANGLES_DEG
+        .iter()
+        .map(|&angle_deg| {
+            let (albedo, std_error) = directional_albedo(mat, angle_deg, AUDIT_SAMPLES);
+            AuditEntry {
+                name: name.to_string(),
+                angle_deg,
+                albedo,
+                std_error,
+            }
+        })
+        .collect()
+}
+
+/// The built-in materials over a sweep of roughness and IOR values, all with white
+/// base colors so any result above 1.0 is an energy gain.
+pub(crate) fn audit_materials() -> Vec<(String, Arc<dyn Material + Send + Sync>)> {
+    let white = Color::new(1.0, 1.0, 1.0);
+    let mut materials: Vec<(String, Arc<dyn Material + Send + Sync>)> = vec![
+        ("lambertian".into(), Arc::new(Lambertian::new(white))),
+        ("dielectric 1.5".into(), Arc::new(Dielectric::new(1.5))),
+        (
+            "dielectric 1.5 exact fresnel".into(),
+            Arc::new(Dielectric::new(1.5).with_fresnel(Fresnel::Exact)),
+        ),
+        (
+            "aluminium conductor".into(),
+            Arc::new(Metal::conductor(Conductor::ALUMINIUM, 0.0)),
+        ),
+    ];
+    for roughness in [0.05, 0.3, 0.6, 1.0] {
+        materials.push((
+            format!("oren-nayar sigma {roughness}"),
+            Arc::new(OrenNayar::new(white, roughness)),
+        ));
+        materials.push((
+            format!("metal fuzz {roughness}"),
+            Arc::new(Metal::new(white, roughness)),
+        ));
+        materials.push((
+            format!("rough metal {roughness}"),
+            Arc::new(RoughMetal::new(white, roughness)),
+        ));
+        materials.push((
+            format!("anisotropic metal {roughness}/0.1"),
+            Arc::new(AnisotropicMetal::new(white, roughness, 0.1)),
+        ));
+        for ior in [1.33, 1.5, 2.4] {
+            materials.push((
+                format!("rough dielectric ior {ior} roughness {roughness}"),
+                Arc::new(RoughDielectric::new(ior, roughness)),
+            ));
+        }
+        let plastic = Principled {
+            roughness,
+            ..Principled::new(white)
+        };
+        let lacquer = Principled {
+            clearcoat: 1.0,
+            sheen: 1.0,
+            ..plastic
+        };
+        let glass = Principled {
+            transmission: 1.0,
+            ..plastic
+        };
+        materials.push((format!("principled plastic {roughness}"), Arc::new(plastic)));
+        materials.push((format!("principled lacquer {roughness}"), Arc::new(lacquer)));
+        materials.push((format!("principled glass {roughness}"), Arc::new(glass)));
+        materials.push((
+            format!("coated lambertian {roughness}"),
+            Arc::new(Coated::new(
+                Arc::new(Lambertian::new(white)),
+                1.5,
+                roughness,
+            )),
+        ));
+    }
+    materials
+}
+
+/// Measures every built-in material and logs the results under the `render` target,
+/// warning about the ones that reflect more light than they receive. Returns the entries
+/// that failed.
+pub fn run() -> Vec<AuditEntry> {
+    log::info!(
+        target: "render",
+        "Energy audit: {} samples per material and angle, angles {:?} deg",
+        AUDIT_SAMPLES, AUDIT_ANGLES_DEG
+    );
+    let mut failures = Vec::new();
+    for (name, mat) in audit_materials() {
+        let entries = audit_material(&name, mat.as_ref());
+        let worst = entries
+            .iter()
+            .map(|e| e.albedo.max_component())
+            .fold(0.0, f64::max);
+        let failed: Vec<_> = entries.into_iter().filter(|e| e.gains_energy()).collect();
+        if failed.is_empty() {
+            log::info!(target: "render", "{:<48} max albedo {:.4} ok", name, worst);
+        } else {
+            log::warn!(target: "render", "{:<48} max albedo {:.4} GAINS ENERGY", name, worst);
+        }
+        failures.extend(failed);
+    }
+    for entry in &failures {
+        log::warn!(
+            target: "render",
+            "  {} at {} deg: albedo ({:.4}, {:.4}, {:.4}) +- {:.4}",
+            entry.name,
+            entry.angle_deg,
+            entry.albedo.r,
+            entry.albedo.g,
+            entry.albedo.b,
+            entry.std_error
+        );
+    }
+    failures
+}
@@ -0,0 +1,271 @@
+use crate::parallel;
+use crate::rtweekend::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+const BENCH_SEED: u64 = 0x5eed_1234;
+const BENCH_WIDTH: u32 = 320;
+const BENCH_SAMPLES: u32 = 16;
+const BENCH_DEPTH: u32 = 8;
+
+/// Counts every ray cast against the wrapped object (camera, bounce and shadow rays alike).
+struct CountingHittable {
+    inner: HittableList,
+    rays: AtomicU64,
+}
+
+impl Hittable for CountingHittable {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        self.rays.fetch_add(1, Ordering::Relaxed);
+        self.inner.hit(r, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.inner.bounding_box()
+    }
+}
+
+pub struct BenchResult {
+    pub name: &'static str,
+    pub seconds: f64,
+    pub rays: u64,
+}
+
+impl BenchResult {
+    pub fn mrays_per_second(&self) -> f64 {
+        self.rays as f64 / self.seconds / 1e6
+    }
+}
+
+fn bench_camera(lookfrom: Point3, lookat: Point3, vfov: f64) -> Camera {
+    Camera::builder()
+        .image_width(BENCH_WIDTH)
+        .samples_per_pixel(BENCH_SAMPLES)
+        .max_depth(BENCH_DEPTH)
+        .vfov(vfov)
+        .lookfrom(lookfrom)
+        .lookat(lookat)
+        .build()
+}
+
+// Random sphere field in the spirit of the book cover, laid out from a fixed seed.
+fn spheres_scene(rng: &mut StdRng) -> (HittableList, Camera) {
+    let mut world = HittableList::new();
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, -1000.0, 0.0),
+        1000.0,
+        Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))),
+    )));
+    for a in -8..8 {
+        for b in -8..8 {
+            let center = Point3::new(
+                a as f64 + 0.9 * rng.random::<f64>(),
+                0.2,
+                b as f64 + 0.9 * rng.random::<f64>(),
+            );
+            let choose_mat = rng.random::<f64>();
+            let color = Color::new(rng.random(), rng.random(), rng.random());
+            let mat: Arc<dyn Material + Send + Sync> = if choose_mat < 0.6 {
+                Arc::new(Lambertian::new(color * color))
+            } else if choose_mat < 0.85 {
+                Arc::new(Metal::new(0.5 * (color + Color::new(1.0, 1.0, 1.0)), 0.2))
+            } else {
+                Arc::new(Dielectric::new(1.5))
+            };
+            world.add(Arc::new(Sphere::new(center, 0.2, mat)));
+        }
+    }
+    world.add(Arc::new(Sphere::new(
+        Point3::new(0.0, 1.0, 0.0),
+        1.0,
+        Arc::new(Dielectric::new(1.5)),
+    )));
+    let cam = bench_camera(Point3::new(13.0, 2.0, 3.0), Point3::zero(), 20.0);
+    (world, cam)
+}
+
+fn cornell_scene() -> (Scene, Camera) {
+    let red: Arc<dyn Material + Send + Sync> =
+        Arc::new(Lambertian::new(Color::new(0.65, 0.05, 0.05)));
+    let white: Arc<dyn Material + Send + Sync> =
+        Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+    let green: Arc<dyn Material + Send + Sync> =
+        Arc::new(Lambertian::new(Color::new(0.12, 0.45, 0.15)));
+    let light = Arc::new(DiffuseLight::new(Color::new(15.0, 15.0, 15.0)));
+
+    let mut world = HittableList::new();
+    let (x, y, z) = (
+        Vec3::new(555.0, 0.0, 0.0),
+        Vec3::new(0.0, 555.0, 0.0),
+        Vec3::new(0.0, 0.0, 555.0),
+    );
+    world.add(Arc::new(Quad::new(
+        Point3::new(555.0, 0.0, 0.0),
+        y,
+        z,
+        green,
+    )));
+    world.add(Arc::new(Quad::new(Point3::zero(), y, z, red)));
+    world.add(Arc::new(Quad::new(Point3::zero(), x, z, white.clone())));
+    world.add(Arc::new(Quad::new(
+        Point3::new(555.0, 555.0, 555.0),
+        -x,
+        -z,
+        white.clone(),
+    )));
+    world.add(Arc::new(Quad::new(
+        Point3::new(0.0, 0.0, 555.0),
+        x,
+        y,
+        white.clone(),
+    )));
+    world.add(Arc::new(Sphere::new(
+        Point3::new(190.0, 90.0, 190.0),
+        90.0,
+        Arc::new(Dielectric::new(1.5)),
+    )));
+    world.add(Arc::new(Cylinder::new(
+        Point3::new(370.0, 0.0, 370.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        80.0,
+        300.0,
+        true,
+        white,
+    )));
+
+    let lamp: Arc<dyn Hittable> = Arc::new(Quad::new(
+        Point3::new(343.0, 554.0, 332.0),
+        Vec3::new(-130.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, -105.0),
+        light,
+    ));
+    let mut scene = Scene::new(world);
+    scene.add_area_light(lamp);
+    scene.background = Arc::new(GradientBackground::new(Color::BLACK, Color::BLACK));
+
+    let cam = bench_camera(
+        Point3::new(278.0, 278.0, -800.0),
+        Point3::new(278.0, 278.0, 0.0),
+        40.0,
+    );
+    (scene, cam)
+}
+
+fn lights_scene(rng: &mut StdRng) -> (Scene, Camera) {
+    let (mut world, cam) = spheres_scene(rng);
+    world.add(Arc::new(Cone::new(
+        Point3::new(-4.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        1.0,
+        2.0,
+        true,
+        Arc::new(Lambertian::new(Color::new(0.7, 0.6, 0.2))),
+    )));
+    let mut scene = Scene::new(world);
+    scene.add_light(Arc::new(PointLight::new(
+        Point3::new(0.0, 6.0, 0.0),
+        Color::new(40.0, 40.0, 40.0),
+    )));
+    scene.add_light(Arc::new(DirectionalLight::new(
+        Vec3::new(-1.0, -2.0, -0.5),
+        Color::new(1.5, 1.4, 1.2),
+    )));
+    scene.background = Arc::new(PreethamSky::new(25.0, 200.0, 3.0));
+    (scene, cam)
+}
+
+/// The standardized scenes, built identically on every run.
+fn bench_scenes() -> Vec<(&'static str, Scene, Camera)> {
+    let mut rng = StdRng::seed_from_u64(BENCH_SEED);
+    let (spheres, spheres_cam) = spheres_scene(&mut rng);
+    let (cornell, cornell_cam) = cornell_scene();
+    let (lights, lights_cam) = lights_scene(&mut rng);
+    vec![
+        ("spheres", Scene::new(spheres), spheres_cam),
+        ("cornell", cornell, cornell_cam),
+        ("lights", lights, lights_cam),
+    ]
+}
+
+pub fn run_bench_scene(name: &'static str, mut scene: Scene, cam: &Camera) -> BenchResult {
+    let counter = Arc::new(CountingHittable {
+        inner: std::mem::take(&mut scene.world),
+        rays: AtomicU64::new(0),
+    });
+    scene.world = HittableList::new_with(counter.clone());
+
+    let start = Instant::now();
+    let _pixels = TileScheduler::cpu_only().render(cam, &scene);
+    let seconds = start.elapsed().as_secs_f64();
+
+    BenchResult {
+        name,
+        seconds,
+        rays: counter.rays.load(Ordering::Relaxed),
+    }
+}
+
+const RNG_DRAWS: u32 = 20_000_000;
+
+// Millions of numbers per second drawn by `random_f64` and, for comparison, by fetching
+// `rand::rng()` for every number the way it used to, on one thread.
+fn bench_rng() -> (f64, f64) {
+    let rate = |draw: &mut dyn FnMut() -> f64| {
+        let start = Instant::now();
+        let mut sum = 0.0;
+        for _ in 0..RNG_DRAWS {
+            sum += draw();
+        }
+        std::hint::black_box(sum);
+        RNG_DRAWS as f64 / start.elapsed().as_secs_f64() / 1e6
+    };
+    // `rand::rng()` seeds from the OS, which wasm32 builds do without.
+    #[cfg(not(target_arch = "wasm32"))]
+    let thread_rng = rate(&mut || rand::rng().random::<f64>());
+    #[cfg(target_arch = "wasm32")]
+    let thread_rng = f64::NAN;
+    let mut rng = SmallRng::seed_from_u64(BENCH_SEED);
+    (rate(&mut || random_f64(&mut rng)), thread_rng)
+}
+
+/// Renders every standard scene and logs a machine-comparable score (geometric mean of
+/// Mrays/s across scenes) under the `render` target.
+pub fn run() {
+    log::info!(
+        target: "render",
+        "Benchmark: {}x{}, {} spp, depth {}, {} threads",
+        BENCH_WIDTH,
+        (BENCH_WIDTH as f64 / (16.0 / 9.0)).round(),
+        BENCH_SAMPLES,
+        BENCH_DEPTH,
+        parallel::current_num_threads()
+    );
+
+    let mut results = Vec::new();
+    for (name, scene, cam) in bench_scenes() {
+        let result = run_bench_scene(name, scene, &cam);
+        log::info!(
+            target: "render",
+            "{:<10} {:>8.3} s/frame {:>10.3} Mrays/s ({} rays)",
+            result.name,
+            result.seconds,
+            result.mrays_per_second(),
+            result.rays
+        );
+        results.push(result);
+    }
+
+    let (local, thread_rng) = bench_rng();
+    log::info!(
+        target: "render",
+        "rng        {:>8.1} M/s per thread ({:.1} M/s with rand::rng)",
+        local, thread_rng
+    );
+
+    let log_sum: f64 = results.iter().map(|r| r.mrays_per_second().ln()).sum();
+    let score = (log_sum / results.len() as f64).exp();
+    let total: f64 = results.iter().map(|r| r.seconds).sum();
+    log::info!(target: "render", "score      {:>8.3} Mrays/s (total {:.3} s)", score, total);
+}
@@ -0,0 +1,505 @@
+use crate::parallel;
+use crate::rtweekend::*;
+
+/// How a `Bvh` decides where to split.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BvhBuild {
+    /// Half the objects on each side along the widest axis. Fast to build, but
+    /// uneven scenes (a huge ground under many small objects) come out loose.
+    Median,
+    /// Binned surface area heuristic: the split with the least expected cost of
+    /// tracing a ray through the children, out of a few candidates per axis.
+    #[default]
+    Sah,
+}
+
+/// Objects per leaf with the median split, and at or below which the SAH build always
+/// makes a leaf.
+const LEAF_SIZE: usize = 2;
+/// Largest leaf the SAH build makes when splitting wouldn't pay off.
+const MAX_LEAF_SIZE: usize = 8;
+/// Candidate split planes per axis for the SAH build, at bin boundaries.
+const SAH_BINS: usize = 16;
+/// Cost of visiting a node, relative to testing one object.
+const TRAVERSAL_COST: f64 = 1.0;
+/// Subtrees at least this big are built on separate threads.
+const PARALLEL_BUILD: usize = 4096;
+
+// Node of a flattened tree. An inner node's first child directly follows it and its
+// second child is at `start`; a leaf holds `count` entries of the tree's order from
+// `start`.
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    bbox: Aabb,
+    start: u32,
+    count: u32,
+    /// Split axis of an inner node, for visiting the nearer child first.
+    axis: u8,
+}
+
+// Tree as it's built, before flattening.
+enum BuildNode {
+    Leaf {
+        bbox: Aabb,
+        start: usize,
+        count: usize,
+    },
+    Inner {
+        bbox: Aabb,
+        axis: usize,
+        children: Box<[BuildNode; 2]>,
+    },
+}
+
+// Hierarchy over a set of boxes, shared by the bottom and top levels. Leaves refer to
+// the boxes by index.
+#[derive(Debug, Clone, Default)]
+struct Tree {
+    nodes: Vec<Node>,
+    order: Vec<u32>,
+}
+
+impl Tree {
+    fn build(boxes: &[Aabb], method: BvhBuild) -> Self {
+        let mut order: Vec<u32> = (0..boxes.len() as u32).collect();
+        let mut nodes = Vec::with_capacity(2 * boxes.len());
+        if !boxes.is_empty() {
+            let centroids: Vec<Point3> = boxes.iter().map(Aabb::center).collect();
+            let root = build_node(boxes, &centroids, &mut order, 0, method);
+            flatten(root, &mut nodes);
+        }
+        Self { nodes, order }
+    }
+
+    // Recomputes every node's box from new `boxes`, keeping the tree's shape. Cheap,
+    // but the tree loosens as entries move away from where it was built.
+    fn refit(&mut self, boxes: &[Aabb]) {
+        // Children come after their parents, so a backwards pass sees them first.
+        for index in (0..self.nodes.len()).rev() {
+            let node = self.nodes[index];
+            self.nodes[index].bbox = if node.count > 0 {
+                let entries = &self.order[node.start as usize..(node.start + node.count) as usize];
+                entries
+                    .iter()
+                    .fold(Aabb::EMPTY, |acc, &k| acc.union(&boxes[k as usize]))
+            } else {
+                self.nodes[index + 1]
+                    .bbox
+                    .union(&self.nodes[node.start as usize].bbox)
+            };
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.nodes.first().map_or(Aabb::EMPTY, |node| node.bbox)
+    }
+
+    // Closest hit of `r`, asking `hit_entry` about the entries in every leaf it reaches.
+    fn hit(
+        &self,
+        r: &Ray,
+        ray_t: Interval,
+        hit_entry: impl Fn(usize, Interval) -> Option<HitRecord>,
+    ) -> Option<HitRecord> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+        let inv_dir = Vec3::new(1.0 / r.dir.x, 1.0 / r.dir.y, 1.0 / r.dir.z);
+        let mut closest = ray_t.max;
+        let mut best = None;
+        let mut stack = Vec::with_capacity(64);
+        stack.push(0usize);
+        while let Some(index) = stack.pop() {
+            ray_stats::count(Counter::NodeVisits);
+            let node = &self.nodes[index];
+            if node
+                .bbox
+                .overlap(r.orig, inv_dir, Interval::new(ray_t.min, closest))
+                .is_none()
+            {
+                continue;
+            }
+            if node.count > 0 {
+                for &k in &self.order[node.start as usize..(node.start + node.count) as usize] {
+                    if let Some(rec) = hit_entry(k as usize, Interval::new(ray_t.min, closest)) {
+                        closest = rec.t;
+                        best = Some(rec);
+                    }
+                }
+            } else {
+                // Push the farther child first so the nearer one is visited first and
+                // tightens `closest` early.
+                let (first, second) = (index + 1, node.start as usize);
+                if inv_dir[node.axis as usize] < 0.0 {
+                    stack.push(first);
+                    stack.push(second);
+                } else {
+                    stack.push(second);
+                    stack.push(first);
+                }
+            }
+        }
+        best
+    }
+}
+
+// Builds the subtree over `entries`, which start at `offset` in the tree's order,
+// reordering them so each leaf's entries are contiguous.
+fn build_node(
+    boxes: &[Aabb],
+    centroids: &[Point3],
+    entries: &mut [u32],
+    offset: usize,
+    method: BvhBuild,
+) -> BuildNode {
+    let bbox = entries
+        .iter()
+        .fold(Aabb::EMPTY, |acc, &k| acc.union(&boxes[k as usize]));
+    let leaf = BuildNode::Leaf {
+        bbox,
+        start: offset,
+        count: entries.len(),
+    };
+    if entries.len() <= LEAF_SIZE {
+        return leaf;
+    }
+
+    let spread = entries.iter().fold(Aabb::EMPTY, |acc, &k| {
+        let c = centroids[k as usize];
+        acc.union(&Aabb::from_points(c, c))
+    });
+    let widest = spread.longest_axis();
+
+    let sah = match method {
+        BvhBuild::Sah => sah_split(boxes, centroids, entries, &bbox, &spread),
+        BvhBuild::Median => None,
+    };
+    let (axis, mid) = match sah {
+        Some((axis, plane, cost)) => {
+            if cost >= entries.len() as f64 && entries.len() <= MAX_LEAF_SIZE {
+                return leaf;
+            }
+            let mid = partition(entries, |k| centroids[k as usize][axis] < plane);
+            (axis, mid)
+        }
+        None => (widest, 0),
+    };
+    // Fall back to the median whenever the plane leaves a side empty, e.g. when every
+    // centroid is in the same place.
+    let (axis, mid) = if mid == 0 || mid == entries.len() {
+        let mid = entries.len() / 2;
+        entries.select_nth_unstable_by(mid, |&a, &b| {
+            centroids[a as usize][widest].total_cmp(&centroids[b as usize][widest])
+        });
+        (widest, mid)
+    } else {
+        (axis, mid)
+    };
+
+    let (left, right) = entries.split_at_mut(mid);
+    let (left, right) = if left.len() + right.len() >= PARALLEL_BUILD {
+        parallel::join(
+            || build_node(boxes, centroids, left, offset, method),
+            || build_node(boxes, centroids, right, offset + mid, method),
+        )
+    } else {
+        (
+            build_node(boxes, centroids, left, offset, method),
+            build_node(boxes, centroids, right, offset + mid, method),
+        )
+    };
+    BuildNode::Inner {
+        bbox,
+        axis,
+        children: Box::new([left, right]),
+    }
+}
+
+// Best binned SAH split of `entries` as (axis, plane, cost), the cost in units of one
+// object test.
+fn sah_split(
+    boxes: &[Aabb],
+    centroids: &[Point3],
+    entries: &[u32],
+    bbox: &Aabb,
+    spread: &Aabb,
+) -> Option<(usize, f64, f64)> {
+    let area = bbox.surface_area();
+    if !area.is_finite() || area <= 0.0 {
+        return None;
+    }
+    let mut best: Option<(usize, f64, f64)> = None;
+    for axis in 0..3 {
+        let extent = spread.axis_interval(axis);
+        if extent.size() <= 0.0 {
+            continue;
+        }
+        let scale = SAH_BINS as f64 / extent.size();
+        let bin_of = |c: Point3| (((c[axis] - extent.min) * scale) as usize).min(SAH_BINS - 1);
+        let mut counts = [0usize; SAH_BINS];
+        let mut bounds = [Aabb::EMPTY; SAH_BINS];
+        for &k in entries {
+            let bin = bin_of(centroids[k as usize]);
+            counts[bin] += 1;
+            bounds[bin] = bounds[bin].union(&boxes[k as usize]);
+        }
+
+        // Area times count of everything right of each plane, swept from the right.
+        let mut right_cost = [0.0; SAH_BINS];
+        let (mut right_box, mut right_count) = (Aabb::EMPTY, 0);
+        for bin in (1..SAH_BINS).rev() {
+            right_box = right_box.union(&bounds[bin]);
+            right_count += counts[bin];
+            right_cost[bin] = right_box.surface_area() * right_count as f64;
+        }
+        let (mut left_box, mut left_count) = (Aabb::EMPTY, 0);
+        for bin in 1..SAH_BINS {
+            left_box = left_box.union(&bounds[bin - 1]);
+            left_count += counts[bin - 1];
+            if left_count == 0 || left_count == entries.len() {
+                continue;
+            }
+            let cost = TRAVERSAL_COST
+                + (left_box.surface_area() * left_count as f64 + right_cost[bin]) / area;
+            if best.is_none_or(|(_, _, best_cost)| cost < best_cost) {
+                let plane = extent.min + bin as f64 / scale;
+                best = Some((axis, plane, cost));
+            }
+        }
+    }
+    best
+}
+
+// Moves the entries satisfying `left` to the front and returns how many there are.
+fn partition(entries: &mut [u32], left: impl Fn(u32) -> bool) -> usize {
+    let mut mid = 0;
+    for k in 0..entries.len() {
+        if left(entries[k]) {
+            entries.swap(k, mid);
+            mid += 1;
+        }
+    }
+    mid
+}
+
+// Appends `node` and its subtree to `nodes` depth first.
+fn flatten(node: BuildNode, nodes: &mut Vec<Node>) {
+    match node {
+        BuildNode::Leaf { bbox, start, count } => nodes.push(Node {
+            bbox,
+            start: start as u32,
+            count: count as u32,
+            axis: 0,
+        }),
+        BuildNode::Inner {
+            bbox,
+            axis,
+            children,
+        } => {
+            let index = nodes.len();
+            nodes.push(Node {
+                bbox,
+                start: 0,
+                count: 0,
+                axis: axis as u8,
+            });
+            let [left, right] = *children;
+            flatten(left, nodes);
+            nodes[index].start = nodes.len() as u32;
+            flatten(right, nodes);
+        }
+    }
+}
+
+/// Bounding volume hierarchy over a fixed set of objects, so a ray only tests the
+/// objects whose boxes it passes through instead of all of them. Objects without a
+/// bounded box (see `Hittable::bounding_box`) are tested by every ray.
+pub struct Bvh {
+    objects: Vec<Arc<dyn Hittable>>,
+    unbounded: Vec<Arc<dyn Hittable>>,
+    tree: Tree,
+}
+
+impl Bvh {
+    pub fn new(objects: Vec<Arc<dyn Hittable>>) -> Self {
+        Self::with_build(objects, BvhBuild::default())
+    }
+
+    pub fn with_build(objects: Vec<Arc<dyn Hittable>>, method: BvhBuild) -> Self {
+        let (objects, unbounded): (Vec<_>, Vec<_>) = objects
+            .into_iter()
+            .partition(|object| object.bounding_box().is_bounded());
+        let boxes: Vec<Aabb> = objects.iter().map(|o| o.bounding_box()).collect();
+        let start = Stopwatch::start();
+        let tree = Tree::build(&boxes, method);
+        log::debug!(
+            target: "bvh",
+            "Built BVH over {} objects ({} nodes, {} unbounded) in {:.1} ms",
+            objects.len(),
+            tree.nodes.len(),
+            unbounded.len(),
+            start.elapsed().as_secs_f64() * 1000.0
+        );
+        Self {
+            tree,
+            objects,
+            unbounded,
+        }
+    }
+
+    pub fn from_list(list: HittableList) -> Self {
+        Self::new(list.objects)
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len() + self.unbounded.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn all(&self) -> impl Iterator<Item = &Arc<dyn Hittable>> {
+        self.objects.iter().chain(&self.unbounded)
+    }
+}
+
+impl Hittable for Bvh {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let mut rec = None;
+        let mut closest = ray_t.max;
+        for object in &self.unbounded {
+            if let Some(hit) = object.hit(r, Interval::new(ray_t.min, closest)) {
+                closest = hit.t;
+                rec = Some(hit);
+            }
+        }
+        self.tree
+            .hit(r, Interval::new(ray_t.min, closest), |k, interval| {
+                self.objects[k].hit(r, interval)
+            })
+            .or(rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        if self.unbounded.is_empty() {
+            self.tree.bounding_box()
+        } else {
+            Aabb::UNIVERSE
+        }
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let weight = 1.0 / self.len() as f64;
+        self.all()
+            .map(|object| weight * object.pdf_value(origin, direction))
+            .sum()
+    }
+
+    fn random(&self, origin: Point3, rng: &mut SmallRng) -> Vec3 {
+        let n = self.len();
+        if n == 0 {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+        let index = ((random_f64(rng) * n as f64) as usize).min(n - 1);
+        self.all().nth(index).unwrap().random(origin, rng)
+    }
+
+    fn named_bounds(&self, name: &str) -> Option<Aabb> {
+        self.all().find_map(|object| object.named_bounds(name))
+    }
+}
+
+/// One placement of a shared bottom-level `Bvh` in a `TopLevelBvh`.
+#[derive(Clone)]
+pub struct BvhInstance {
+    pub blas: Arc<Bvh>,
+    pub transform: Transform,
+    /// Put on the hit record as `instance_id`, for per-instance material overrides.
+    pub id: u32,
+}
+
+impl BvhInstance {
+    pub fn new(blas: Arc<Bvh>, transform: Transform, id: u32) -> Self {
+        Self {
+            blas,
+            transform,
+            id,
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.transform.bounds(&self.blas.bounding_box())
+    }
+}
+
+/// Two-level hierarchy for instanced geometry: a top-level tree over the world-space
+/// boxes of the instances, each pointing at a bottom-level `Bvh` built once in its own
+/// space and shared by every placement. A forest of 10k instanced trees holds one tree
+/// BVH, and moving instances only touches the top level (`set_transform`, `rebuild`).
+pub struct TopLevelBvh {
+    instances: Vec<BvhInstance>,
+    tree: Tree,
+}
+
+impl TopLevelBvh {
+    pub fn new(instances: Vec<BvhInstance>) -> Self {
+        let mut tlas = Self {
+            instances,
+            tree: Tree::default(),
+        };
+        tlas.rebuild();
+        tlas
+    }
+
+    pub fn instances(&self) -> &[BvhInstance] {
+        &self.instances
+    }
+
+    /// Moves instance `index`, refitting the top level around it. Call `rebuild` once
+    /// instances have moved far from where they were when the top level was built.
+    pub fn set_transform(&mut self, index: usize, transform: Transform) {
+        self.instances[index].transform = transform;
+        self.tree.refit(&self.boxes());
+    }
+
+    /// Rebuilds the top level from scratch. The bottom levels are left alone.
+    pub fn rebuild(&mut self) {
+        self.tree = Tree::build(&self.boxes(), BvhBuild::Sah);
+    }
+
+    fn boxes(&self) -> Vec<Aabb> {
+        self.instances
+            .iter()
+            .map(BvhInstance::bounding_box)
+            .collect()
+    }
+}
+
+impl Hittable for TopLevelBvh {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        self.tree.hit(r, ray_t, |k, interval| {
+            let instance = &self.instances[k];
+            let t = &instance.transform;
+            // `t` along the ray is the same in both spaces, since the map is affine.
+            let local = Ray {
+                orig: t.inverse_point(r.orig),
+                dir: t.inverse_vector(r.dir),
+                ..*r
+            };
+            let mut rec = instance.blas.hit(&local, interval)?;
+            rec.p = t.point(rec.p);
+            rec.normal = t.rotation.rotate(rec.normal);
+            rec.geometric_normal = t.rotation.rotate(rec.geometric_normal);
+            rec.tangent = t.rotation.rotate(rec.tangent);
+            rec.p_error *= t.scale;
+            rec.uv_scale *= t.scale;
+            rec.instance_id = instance.id;
+            Some(rec)
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.tree.bounding_box()
+    }
+}
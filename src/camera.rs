@@ -1,9 +1,22 @@
+use crate::interrupt;
+use crate::output::ImageFormat;
+use crate::parallel::*;
+use crate::quat::look_basis;
 use crate::rtweekend::*;
-use rayon::prelude::*;
-use std::fs::File;
-use std::io::BufWriter;
+use crate::spectrum::{sample_wavelength, wavelength_to_rgb};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fs;
 use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 
+/// First bytes of a `Camera::with_checkpoint` file.
+const CHECKPOINT_MAGIC: &[u8; 8] = b"RTWCKPT1";
+
+/// Settings serialize; the derived viewport doesn't, so call `initialize` after loading.
+/// Settings left out when deserializing take the `Camera::builder` defaults.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Camera {
     pub aspect_ratio: f64,
     pub image_width: u32,
@@ -15,21 +28,134 @@ pub struct Camera {
     pub vup: Vec3,
     pub defocus_angle: f64,
     pub focus_dist: f64,
+    /// Width of a pixel relative to its height (1.0 for square pixels, 2.0 for 2x anamorphic).
+    pub pixel_aspect_ratio: f64,
+    /// Extra margin rendered around the active picture on each side, as a fraction
+    /// of the active width/height (0.05 renders a 5% overscan border).
+    pub overscan: f64,
+    /// Probe a few tile sizes/thread counts before rendering and use the fastest.
+    pub auto_tune: bool,
+    /// Name of a scene file object to focus on. `SceneFile` sets `focus_dist` from it
+    /// when handing out the camera.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub focus_on: Option<String>,
+    /// Optional per-region focus distances (split diopter and friends).
+    pub focus_map: Option<FocusMap>,
+    /// Exposure interval in scene time. Rays are spread across it for motion blur.
+    pub shutter_open: f64,
+    pub shutter_close: f64,
+    /// Rolling shutter readout time: the delay between exposing the top and the bottom
+    /// row of the frame, like a CMOS sensor. 0.0 is a global shutter.
+    pub rolling_shutter: f64,
+    /// Sum light over the shutter interval instead of averaging it, so longer exposures
+    /// get brighter like real film. Meant for light painting with moving emitters in a
+    /// dark scene, where the trails would otherwise fade as the shutter stays open.
+    pub accumulate_exposure: bool,
+    /// Physically based exposure (ISO, shutter speed, f-number). Without it radiance
+    /// maps to pixels one to one.
+    pub exposure: Option<Exposure>,
+    /// Trace a single wavelength per sample instead of RGB, so dispersive dielectrics
+    /// split white light into colors. Needs more samples to converge.
+    pub spectral: bool,
+    /// Render a low-resolution proxy first and give noisier pixels more samples, keeping
+    /// the total close to `samples_per_pixel` per pixel.
+    pub adaptive: bool,
+    /// Rotation about the viewing direction in degrees, counter-clockwise as seen from
+    /// behind the camera.
+    pub roll: f64,
+    /// Aim the camera with a quaternion instead of `lookat` and `vup`. Unlike the
+    /// look-at setup it can point anywhere, straight up and down included.
+    pub orientation: Option<Quat>,
+    /// Perspective, fisheye or 360° panorama. The wide projections are pinhole only:
+    /// they ignore `defocus_angle` and the focus map.
+    pub projection: Projection,
+    /// Lens shift as a fraction of the picture's width and height, positive to the
+    /// right and up. Shifting up instead of tilting the camera keeps verticals parallel.
+    pub shift_x: f64,
+    pub shift_y: f64,
+    /// Tilt of the plane of sharp focus about the horizontal axis, in degrees, as a
+    /// tilted lens would give (Scheimpflug). Positive leans the top of the plane away,
+    /// e.g. to keep a ground plane sharp from near to far.
+    pub tilt: f64,
+    /// Like `tilt`, about the vertical axis. Positive turns the right side away.
+    pub swing: f64,
+    /// Radial lens distortion: positive bows straight lines outward (barrel),
+    /// negative inward (pincushion). Around 0.1 is already clearly visible.
+    pub distortion: f64,
+    /// Natural vignetting, from 0 (none) to 1 (the full cosine-fourth falloff of a
+    /// simple lens toward the corners).
+    pub vignetting: f64,
+    /// Lateral chromatic aberration: how much smaller blue is imaged than green, and red
+    /// larger, as a fraction (0.003 gives visible color fringes toward the corners).
+    pub lateral_aberration: f64,
+    /// Longitudinal chromatic aberration: how much farther blue focuses than green, and
+    /// red nearer, as a fraction of the focus distance. Shows as colored bokeh.
+    pub longitudinal_aberration: f64,
+    /// Light transport used for every sample: path tracing by default, or one of the
+    /// diagnostic views (ambient occlusion, normals, depth). `with_integrator` puts any
+    /// other `Integrator` in its place.
+    pub integrator: IntegratorKind,
+    /// Pixel reconstruction filter. The default box counts each sample for its own
+    /// pixel only; a Gaussian or Mitchell filter shares samples with the neighbors for
+    /// smoother edges at the same sample count.
+    pub filter: Filter,
+    /// How tiles are rendered: each path start to finish (the default), or in
+    /// wavefront stages over large batches of paths.
+    pub backend: Backend,
+    /// Look for samples that come out NaN or infinite, report their pixels and the
+    /// materials they went wrong on, and draw them magenta. Off by default, as it costs
+    /// a little time per sample.
+    pub check_finite: bool,
+    /// Seed for the render's random numbers, so the same seed, scene and settings draw
+    /// the same samples and give the same image. Unset, every render draws a new one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
 
+    #[serde(skip)]
+    sample_map: Option<Arc<SampleMap>>,
+    #[serde(skip)]
+    nonfinite: Option<Arc<NonFiniteLog>>,
+    #[serde(skip)]
+    progress: Option<Arc<RenderProgress>>,
+    #[serde(skip)]
+    devices: Vec<Arc<dyn RenderDevice>>,
+    #[serde(skip)]
+    checkpoint: Option<(PathBuf, u64)>,
+    #[serde(skip)]
+    custom_integrator: Option<Arc<dyn Integrator>>,
+    #[serde(skip)]
     u: Vec3,
+    #[serde(skip)]
     v: Vec3,
+    #[serde(skip)]
     w: Vec3,
+    #[serde(skip)]
     pixel_sample_scale: f64,
+    #[serde(skip)]
     image_height: u32,
+    #[serde(skip)]
+    film_width: u32,
+    #[serde(skip)]
+    film_height: u32,
+    #[serde(skip)]
     center: Point3,
+    #[serde(skip)]
     pixel00_loc: Point3,
+    #[serde(skip)]
     pixel_delta_u: Vec3,
+    #[serde(skip)]
     pixel_delta_v: Vec3,
+    #[serde(skip)]
     defocus_disk_u: Vec3,
+    #[serde(skip)]
     defocus_disk_v: Vec3,
+    #[serde(skip)]
+    focus_normal: Vec3,
 }
 
 impl Camera {
+    #[deprecated(note = "use `Camera::builder()`, which names every setting")]
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         aspect_ratio: f64,
         image_width: u32,
@@ -42,37 +168,103 @@ impl Camera {
         defocus_angle: f64,
         focus_dist: f64,
     ) -> Self {
-        let mut cam = Self {
-            aspect_ratio,
-            image_width,
-            samples_per_pixel,
-            max_depth,
-            vfov,
-            lookfrom,
-            lookat,
-            vup,
-            defocus_angle,
-            focus_dist,
+        Self::builder()
+            .aspect_ratio(aspect_ratio)
+            .image_width(image_width)
+            .samples_per_pixel(samples_per_pixel)
+            .max_depth(max_depth)
+            .vfov(vfov)
+            .lookfrom(lookfrom)
+            .lookat(lookat)
+            .vup(vup)
+            .defocus_angle(defocus_angle)
+            .focus_dist(focus_dist)
+            .build()
+    }
+
+    /// Starts a camera from defaults: a 400 pixel wide 16:9 image at 100 samples per
+    /// pixel, looking from the origin down -Z with a 90° field of view and no blur.
+    pub fn builder() -> CameraBuilder {
+        CameraBuilder {
+            camera: Self::defaults(),
+            focus_dist: None,
+        }
+    }
+
+    fn defaults() -> Self {
+        Self {
+            aspect_ratio: 16.0 / 9.0,
+            image_width: 400,
+            samples_per_pixel: 100,
+            max_depth: 50,
+            vfov: 90.0,
+            lookfrom: Point3::zero(),
+            lookat: Point3::new(0.0, 0.0, -1.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+            defocus_angle: 0.0,
+            focus_dist: 1.0,
+            pixel_aspect_ratio: 1.0,
+            overscan: 0.0,
+            auto_tune: false,
+            focus_on: None,
+            focus_map: None,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+            rolling_shutter: 0.0,
+            accumulate_exposure: false,
+            exposure: None,
+            spectral: false,
+            adaptive: false,
+            roll: 0.0,
+            orientation: None,
+            projection: Projection::Perspective,
+            shift_x: 0.0,
+            shift_y: 0.0,
+            tilt: 0.0,
+            swing: 0.0,
+            distortion: 0.0,
+            vignetting: 0.0,
+            lateral_aberration: 0.0,
+            longitudinal_aberration: 0.0,
+            integrator: IntegratorKind::default(),
+            filter: Filter::default(),
+            backend: Backend::default(),
+            check_finite: false,
+            seed: None,
+            sample_map: None,
+            nonfinite: None,
+            progress: None,
+            devices: Vec::new(),
+            checkpoint: None,
+            custom_integrator: None,
             u: Point3::default(),
             v: Point3::default(),
             w: Point3::default(),
-            pixel_sample_scale: 1.0 / samples_per_pixel as f64,
+            pixel_sample_scale: 0.01,
             image_height: 0,
+            film_width: 0,
+            film_height: 0,
             center: Point3::default(),
             pixel00_loc: Point3::default(),
             pixel_delta_u: Vec3::default(),
             pixel_delta_v: Vec3::default(),
             defocus_disk_u: Vec3::default(),
             defocus_disk_v: Vec3::default(),
-        };
-        cam.initialize();
-        cam
+            focus_normal: Vec3::default(),
+        }
     }
 
     pub fn initialize(&mut self) {
-        self.image_height = (self.image_width as f64 / self.aspect_ratio)
+        self.pixel_sample_scale = 1.0 / self.samples_per_pixel as f64;
+        // `aspect_ratio` is the display aspect, so non-square pixels need fewer/more rows.
+        self.image_height = (self.image_width as f64 * self.pixel_aspect_ratio / self.aspect_ratio)
             .round()
             .max(1.0) as u32;
+
+        let margin_x = (self.image_width as f64 * self.overscan).round() as u32;
+        let margin_y = (self.image_height as f64 * self.overscan).round() as u32;
+        self.film_width = self.image_width + 2 * margin_x;
+        self.film_height = self.image_height + 2 * margin_y;
         //self.lookfrom = Point3::zero();
         //self.lookat = Point3::new(0.0, 0.0, -1.0);
         //self.vup = Vec3::new(0.0, 1.0, 0.0);
@@ -81,12 +273,24 @@ impl Camera {
         let theta = degrees_to_radians(self.vfov);
         let h = (theta / 2.0).tan();
         let viewport_height = 2.0 * h * self.focus_dist;
-        let viewport_width =
-            viewport_height * ((self.image_width as f64) / (self.image_height as f64));
+        let viewport_width = viewport_height
+            * ((self.image_width as f64 * self.pixel_aspect_ratio) / (self.image_height as f64));
 
-        self.w = (self.lookfrom - self.lookat).normalized();
-        self.u = self.vup.cross(self.w).normalized();
-        self.v = self.w.cross(self.u);
+        let (u, v, w) = match self.orientation {
+            Some(q) => {
+                let q = q.normalized();
+                (
+                    q.rotate(Vec3::new(1.0, 0.0, 0.0)),
+                    q.rotate(Vec3::new(0.0, 1.0, 0.0)),
+                    q.rotate(Vec3::new(0.0, 0.0, 1.0)),
+                )
+            }
+            None => look_basis(self.lookat - self.lookfrom, self.vup),
+        };
+        let (sin, cos) = degrees_to_radians(self.roll).sin_cos();
+        self.u = u * cos + v * sin;
+        self.v = v * cos - u * sin;
+        self.w = w;
 
         let viewport_u = viewport_width * self.u;
         let viewport_v = viewport_height * -self.v;
@@ -97,13 +301,24 @@ impl Camera {
         let viewport_upper_left = self.center
             - self.focus_dist * self.w
             - viewport_u / 2.0                     // Move to left edge
-            - viewport_v / 2.0; // Move to top edge (since viewport_v is downwards)
+            - viewport_v / 2.0 // Move to top edge (since viewport_v is downwards)
+            + self.shift_x * viewport_u
+            - self.shift_y * viewport_v;
 
-        self.pixel00_loc = viewport_upper_left + 0.5 * (self.pixel_delta_u + self.pixel_delta_v);
+        // Pixel (0, 0) sits in the top-left corner of the overscan border, outside the viewport.
+        self.pixel00_loc = viewport_upper_left + 0.5 * (self.pixel_delta_u + self.pixel_delta_v)
+            - (margin_x as f64 * self.pixel_delta_u)
+            - (margin_y as f64 * self.pixel_delta_v);
 
         let defocus_radius = self.focus_dist * degrees_to_radians(self.defocus_angle / 2.0).tan();
         self.defocus_disk_u = self.u * defocus_radius;
         self.defocus_disk_v = self.v * defocus_radius;
+
+        // Normal of the plane of focus, facing the camera.
+        let (sin_t, cos_t) = degrees_to_radians(self.tilt).sin_cos();
+        let tilted = self.w * cos_t + self.v * sin_t;
+        let (sin_s, cos_s) = degrees_to_radians(self.swing).sin_cos();
+        self.focus_normal = tilted * cos_s + self.u * sin_s;
     }
     /*
     pub fn render<W: Hittable>(&self, world: &W) -> io::Result<()> {
@@ -123,7 +338,7 @@ impl Camera {
             }
 
             for i in 0..self.image_width {
-                let mut pixel_color = Color::zero();
+                let mut pixel_color = Color::BLACK;
                 for _sample in 0..self.samples_per_pixel {
                     let r = self.get_ray(i, j);
                     pixel_color += self.ray_color(&r, self.max_depth, world);
@@ -140,115 +355,794 @@ impl Camera {
         Ok(())
     }
     */
-    pub fn render<W: Hittable + Sync>(&self, world: &W) -> io::Result<()> {
-        // `world` needs to be Sync because it's accessed by multiple threads.
+    /// Renders `scene` as a plain PPM image on stdout.
+    pub fn render(&self, scene: &Scene) -> io::Result<()> {
+        self.render_to(scene, io::stdout().lock(), ImageFormat::PlainPpm)
+    }
+
+    /// Renders `scene` as an image in `format` into `out`.
+    pub fn render_to<W: Write>(
+        &self,
+        scene: &Scene,
+        out: W,
+        format: ImageFormat,
+    ) -> io::Result<()> {
+        let pixel_colors = self.render_pixels(scene);
+        log::debug!(target: "render", "Parallel computation finished, writing output");
+        format.write(out, self.film_width, self.film_height, &pixel_colors)?;
+        log::debug!(target: "render", "Output complete");
+        Ok(())
+    }
+
+    /// Renders `scene` and returns the film's pixels in row-major order, top row first.
+    pub fn render_pixels(&self, scene: &Scene) -> Vec<Color> {
+        let full = Tile {
+            x: 0,
+            y: 0,
+            width: self.film_width,
+            height: self.film_height,
+        };
+        self.render_film(scene, full).pixels(0.0)
+    }
+
+    /// Renders only the film pixels in `crop` (which must lie on the film) and returns
+    /// them in row-major order, top row first, `crop.width` by `crop.height`.
+    pub fn render_crop(&self, scene: &Scene, crop: Tile) -> Vec<Color> {
+        let pixels = self.render_film(scene, crop).pixels(0.0);
+        (crop.y..crop.y + crop.height)
+            .flat_map(|j| {
+                let start = (j * self.film_width + crop.x) as usize;
+                pixels[start..start + crop.width as usize].iter().copied()
+            })
+            .collect()
+    }
+
+    /// Reports how far each render has got into `progress` as tiles finish.
+    pub fn with_progress(self, progress: Arc<RenderProgress>) -> Self {
+        Self {
+            progress: Some(progress),
+            ..self
+        }
+    }
+
+    pub fn progress(&self) -> Option<&RenderProgress> {
+        self.progress.as_deref()
+    }
+
+    /// Renders with `integrator` instead of the built-in one `integrator` names, e.g. a
+    /// light transport algorithm of your own. It isn't saved with the camera.
+    pub fn with_integrator(self, integrator: Arc<dyn Integrator>) -> Self {
+        Self {
+            custom_integrator: Some(integrator),
+            ..self
+        }
+    }
+
+    /// The built-in path tracer the camera renders with, `None` for a diagnostic view or
+    /// an integrator from `with_integrator`.
+    pub(crate) fn path_tracer(&self) -> Option<PathTracer> {
+        match self.custom_integrator {
+            Some(_) => None,
+            None => self.integrator.path_tracer(),
+        }
+    }
+
+    /// Renders tiles on `device` as well as the local CPU, e.g. a `RemoteDevice` for
+    /// another machine or a `gpu::GpuDevice`.
+    pub fn with_device(mut self, device: Arc<dyn RenderDevice>) -> Self {
+        self.devices.push(device);
+        self
+    }
+
+    /// Saves the film to `path` when a render is interrupted (see `interrupt`), and
+    /// carries on from it when the next render starts, so only unfinished tiles are
+    /// rendered again. The file goes away once a render completes. `scene` identifies
+    /// what's being rendered, e.g. `SceneFile::fingerprint`; a checkpoint saved for
+    /// another scene, sample count or film size is ignored.
+    pub fn with_checkpoint(self, path: impl Into<PathBuf>, scene: u64) -> Self {
+        Self {
+            checkpoint: Some((path.into(), scene)),
+            ..self
+        }
+    }
+
+    // The film saved at `path` if there is one that fits `scene` and `tiles`, or a new
+    // one.
+    fn resume_film(&self, path: &Path, scene: u64, tiles: &[Tile]) -> Film {
+        let new = || Film::new(self.film_width, self.film_height, self.filter);
+        if !path.exists() {
+            return new();
+        }
+        let film = match fs::File::open(path)
+            .and_then(|file| self.read_checkpoint(io::BufReader::new(file), scene))
+        {
+            Ok(film) => film,
+            Err(e) => {
+                log::warn!(target: "render", "Ignoring checkpoint {}: {}", path.display(), e);
+                return new();
+            }
+        };
+        let finished = film.finished_tiles();
+        if !finished.iter().all(|tile| tiles.contains(tile)) {
+            log::warn!(
+                target: "render",
+                "Ignoring checkpoint {}: it was rendered with a different tile size",
+                path.display()
+            );
+            return new();
+        }
+        log::info!(
+            target: "render",
+            "Resuming from {}: {} of {} tiles already rendered",
+            path.display(),
+            finished.len(),
+            tiles.len()
+        );
+        film
+    }
+
+    // Checkpoints are CHECKPOINT_MAGIC, the sample count and scene, then the film.
+    fn read_checkpoint(&self, mut input: impl io::Read, scene: u64) -> io::Result<Film> {
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut header = [0u8; 20];
+        input.read_exact(&mut header)?;
+        if &header[..8] != CHECKPOINT_MAGIC {
+            return Err(invalid("not a checkpoint".to_string()));
+        }
+        let samples = u32::from_le_bytes(header[8..12].try_into().unwrap());
+        if samples != self.samples_per_pixel {
+            return Err(invalid(format!(
+                "it was rendered at {} samples per pixel, not {}",
+                samples, self.samples_per_pixel
+            )));
+        }
+        if u64::from_le_bytes(header[12..].try_into().unwrap()) != scene {
+            return Err(invalid(
+                "it was rendered from a different scene".to_string(),
+            ));
+        }
+        Film::read_from(input, self.film_width, self.film_height, self.filter)
+    }
+
+    // Writes `film` to the checkpoint file by way of a temporary one, so an existing
+    // checkpoint survives a failed write.
+    fn save_checkpoint(&self, path: &Path, scene: u64, film: &Film) -> io::Result<()> {
+        let temporary = path.with_extension("partial");
+        let mut out = io::BufWriter::new(fs::File::create(&temporary)?);
+        out.write_all(CHECKPOINT_MAGIC)?;
+        out.write_all(&self.samples_per_pixel.to_le_bytes())?;
+        out.write_all(&scene.to_le_bytes())?;
+        film.write_to(out)?;
+        fs::rename(&temporary, path)
+    }
+
+    // Renders the pixels in `region` into a film the size of the camera's.
+    fn render_film(&self, scene: &Scene, region: Tile) -> Film {
+        // `scene` needs to be Sync because it's accessed by multiple threads.
         // `self` is also accessed by multiple threads (for its methods and fields),
         // so Camera itself needs to be Sync (which it should be if its fields are).
 
-        let num_pixels = (self.image_width * self.image_height) as usize;
-
         // --- Start of logging ---
-        eprintln!("Starting parallel render...");
-        eprintln!(
+        log::info!(target: "render", "Starting parallel render...");
+        log::debug!(
+            target: "render",
             "Image Dimensions: {}x{}",
             self.image_width, self.image_height
         );
-        eprintln!("Samples per pixel: {}", self.samples_per_pixel);
-        eprintln!("Max depth: {}", self.max_depth);
+        if self.film_width != self.image_width || self.film_height != self.image_height {
+            log::debug!(
+                target: "render",
+                "Film Dimensions (with overscan): {}x{}",
+                self.film_width, self.film_height
+            );
+        }
+        log::debug!(target: "render", "Samples per pixel: {}", self.samples_per_pixel);
+        log::debug!(target: "render", "Max depth: {}", self.max_depth);
+        if region.pixel_count() != (self.film_width * self.film_height) as usize {
+            log::info!(
+                target: "render",
+                "Crop: {}x{} at ({}, {})",
+                region.width, region.height, region.x, region.y
+            );
+        }
         // --- End of logging ---
 
-        // Calculate all pixel colors in parallel
-        let pixel_colors: Vec<Color> = (0..num_pixels)
-            .into_par_iter() // Convert range to parallel iterator
-            .map(|pixel_idx| {
-                // Calculate (i, j) from the flat pixel_idx
-                // These are the logical pixel coordinates (0 to width-1, 0 to height-1)
-                let i = (pixel_idx % self.image_width as usize) as u32;
-                // For PPM, j=0 is the top row.
-                // If pixel_idx=0 is top-left, then j = (pixel_idx / self.image_width as usize) as u32;
-                // maps correctly.
-                let j_for_ray = (pixel_idx / self.image_width as usize) as u32;
-
-                let mut accumulated_color = Color::zero();
-                for _sample in 0..self.samples_per_pixel {
-                    let r = self.get_ray(i, j_for_ray); // Use the logical j for ray generation
-                    accumulated_color += self.ray_color(&r, self.max_depth, world);
+        let mut camera = self.clone();
+        // Fixed up front so every tile, on every device, draws from the same sequence.
+        camera.seed = Some(self.seed.unwrap_or_else(random_seed));
+        if self.adaptive {
+            let map = camera.sample_map(scene);
+            log::info!(
+                target: "render",
+                "Adaptive sampling: {} to {} samples per pixel ({:.1} on average)",
+                map.min(),
+                map.max(),
+                map.total() as f64 / (self.film_width * self.film_height) as f64
+            );
+            camera.sample_map = Some(Arc::new(map));
+        }
+        if self.check_finite {
+            camera.nonfinite = Some(Arc::new(NonFiniteLog::default()));
+        }
+
+        // Calculate all pixel colors in parallel, tile by tile
+        let mut scheduler = match self.backend {
+            Backend::Megakernel => TileScheduler::cpu_only(),
+            Backend::Wavefront => TileScheduler::new(vec![Arc::new(WavefrontDevice::default())]),
+        };
+        if self.auto_tune && self.backend == Backend::Megakernel {
+            scheduler.autotune(&camera, scene);
+        }
+        for device in &self.devices {
+            scheduler.add_device(device.clone());
+        }
+        let film = match &self.checkpoint {
+            Some((path, scene)) => self.resume_film(path, *scene, &scheduler.tiles(region)),
+            None => Film::new(self.film_width, self.film_height, self.filter),
+        };
+        let start = Stopwatch::start();
+        let before = RayStats::snapshot();
+        scheduler.render_region(&camera, scene, &film, region);
+        if ray_stats::enabled() {
+            RayStats::snapshot()
+                .since(&before)
+                .log_summary(start.elapsed());
+        }
+        if let Some(log) = &camera.nonfinite {
+            log.log_summary();
+        }
+        if let Some((path, scene)) = &self.checkpoint {
+            let saved = if interrupt::requested() {
+                self.save_checkpoint(path, *scene, &film)
+                    .map(|()| log::info!(target: "render", "Saved checkpoint {}", path.display()))
+            } else if path.exists() {
+                fs::remove_file(path)
+            } else {
+                Ok(())
+            };
+            if let Err(e) = saved {
+                log::error!(target: "render", "Checkpoint {}: {}", path.display(), e);
+            }
+        }
+        film
+    }
+
+    /// Current orientation of the camera, roll included, whichever way it was aimed.
+    pub fn view_rotation(&self) -> Quat {
+        Quat::from_basis(self.u, self.v, self.w)
+    }
+
+    pub fn image_height(&self) -> u32 {
+        self.image_height
+    }
+
+    pub fn film_width(&self) -> u32 {
+        self.film_width
+    }
+
+    pub fn film_height(&self) -> u32 {
+        self.film_height
+    }
+
+    /// Film position, in pixels from the top-left corner, where a pinhole at the camera
+    /// center images `p`. `None` behind the camera and for the wide projections. Depth
+    /// of field and lens distortion are ignored.
+    pub fn project(&self, p: Point3) -> Option<(f64, f64)> {
+        if self.projection != Projection::Perspective {
+            return None;
+        }
+        let d = p - self.center;
+        let depth = -d.dot(self.w);
+        if depth <= 0.0 {
+            return None;
+        }
+        let rel = self.center + d * (self.focus_dist / depth) - self.pixel00_loc;
+        let x = rel.dot(self.pixel_delta_u) / self.pixel_delta_u.length_squared() + 0.5;
+        let y = rel.dot(self.pixel_delta_v) / self.pixel_delta_v.length_squared() + 0.5;
+        Some((x, y))
+    }
+
+    /// Area of one pixel on an image plane one unit in front of the camera.
+    pub(crate) fn pixel_area(&self) -> f64 {
+        self.pixel_delta_u.length() * self.pixel_delta_v.length()
+            / (self.focus_dist * self.focus_dist)
+    }
+
+    /// Unit direction the camera looks in.
+    pub(crate) fn forward(&self) -> Vec3 {
+        -self.w
+    }
+
+    /// Position of the pinhole (the center of the lens).
+    pub(crate) fn center(&self) -> Point3 {
+        self.center
+    }
+
+    /// Averages `samples_per_pixel` paths through film pixel (i, j), or as many as the
+    /// adaptive sample map assigns it.
+    pub fn sample_pixel(&self, i: u32, j: u32, scene: &Scene) -> Color {
+        let (samples, scale) = match &self.sample_map {
+            Some(map) => {
+                let n = map.samples(i, j).max(1);
+                (n, 1.0 / n as f64)
+            }
+            None => (self.samples_per_pixel, self.pixel_sample_scale),
+        };
+        let mut rng = self.pixel_rng(i, j);
+        let mut accumulated_color = Color::BLACK;
+        for _sample in 0..samples {
+            accumulated_color += self.sample_once(i, j, scene, &mut rng);
+        }
+        accumulated_color * scale * self.exposure_scale()
+    }
+
+    /// Renders every sample of the pixels in `tile` into `film_tile`.
+    pub fn render_tile(&self, scene: &Scene, film_tile: &mut FilmTile) {
+        let tile = film_tile.tile;
+        let exposure = self.exposure_scale();
+        // Each row fills its own copy of the tile, so samples never have to be held in
+        // memory, and the copies are summed in order, so the sums come out the same
+        // however rayon splits the rows.
+        let rows: Vec<FilmTile> = (tile.y..tile.y + tile.height)
+            .into_par_iter()
+            .map(|j| {
+                let mut part = film_tile.empty_copy();
+                for i in tile.x..tile.x + tile.width {
+                    let mut rng = self.pixel_rng(i, j);
+                    for _ in 0..self.pixel_samples(i, j) {
+                        let offset = self.sample_square(&mut rng);
+                        let color = self.sample_at(i, j, offset, scene, &mut rng) * exposure;
+                        part.add_sample(
+                            i as f64 + 0.5 + offset.x,
+                            j as f64 + 0.5 + offset.y,
+                            color,
+                        );
+                    }
                 }
-                accumulated_color * self.pixel_sample_scale
+                part
             })
-            .collect(); // Collect results into a Vec
+            .collect();
+        for part in &rows {
+            film_tile.add_tile(part);
+        }
+    }
 
-        eprintln!("\nParallel computation finished. Writing to output...");
+    /// Generator for the samples of film pixel (`i`, `j`), from `seed` (a fresh one if
+    /// it's unset).
+    pub(crate) fn pixel_rng(&self, i: u32, j: u32) -> SmallRng {
+        pixel_rng(self.seed.unwrap_or_else(random_seed), i, j)
+    }
 
-        // Write to stdout (or a file) sequentially
-        let mut output_buffer = BufWriter::new(io::stdout().lock()); // Or File::create for file output
-        // If writing to a file, e.g., "image.ppm":
-        // let mut output_buffer = BufWriter::new(File::create("image.ppm")?);
+    // Samples to take in film pixel (i, j): the adaptive sample map's count if there
+    // is one.
+    pub(crate) fn pixel_samples(&self, i: u32, j: u32) -> u32 {
+        match &self.sample_map {
+            Some(map) => map.samples(i, j).max(1),
+            None => self.samples_per_pixel,
+        }
+    }
 
-        writeln!(output_buffer, "P3")?;
-        writeln!(output_buffer, "{} {}", self.image_width, self.image_height)?;
-        writeln!(output_buffer, "255")?;
+    // One path through film pixel (i, j).
+    pub(crate) fn sample_once(&self, i: u32, j: u32, scene: &Scene, rng: &mut SmallRng) -> Color {
+        let offset = self.sample_square(rng);
+        self.sample_at(i, j, offset, scene, rng)
+    }
 
-        // Iterate through the collected pixel_colors and write them out.
-        // PPM writes rows from top to bottom.
-        // Our pixel_colors Vec is ordered such that pixel_colors[0] is pixel (0,0) [top-left],
-        // pixel_colors[1] is (1,0), ..., pixel_colors[width-1] is (width-1,0),
-        // pixel_colors[width] is (0,1), etc.
-        for pixel_color in pixel_colors {
-            write_color(&mut output_buffer, pixel_color)?;
+    // One path through film pixel (i, j), at `offset` from its center.
+    fn sample_at(&self, i: u32, j: u32, offset: Vec2, scene: &Scene, rng: &mut SmallRng) -> Color {
+        let (r, weight) = self.generate_ray(i, j, offset, rng);
+        if self.nonfinite.is_none() {
+            return self.ray_color(&r, self.max_depth, scene, rng) * weight;
         }
+        // The path tracer can tell which material a path went wrong on.
+        let (color, culprit) = match self.path_tracer() {
+            Some(tracer) => {
+                let state = tracer.trace_path(&r, self.max_depth, scene, rng, None);
+                (state.radiance, state.culprit)
+            }
+            None => (self.ray_color(&r, self.max_depth, scene, rng), None),
+        };
+        self.check_sample(i, j, color * weight, culprit)
+    }
 
-        output_buffer.flush()?; // Ensure all data is written
-        eprintln!("\nDone. Output complete.");
+    // `color` as it should go on the film: swapped for `NONFINITE_COLOR`, and logged,
+    // if it isn't finite and the render checks for that.
+    pub(crate) fn check_sample(
+        &self,
+        i: u32,
+        j: u32,
+        color: Color,
+        culprit: Option<&str>,
+    ) -> Color {
+        match &self.nonfinite {
+            Some(log) if !color.is_finite() => log.record(i, j, color, culprit),
+            _ => color,
+        }
+    }
 
-        Ok(())
+    /// Camera ray through film pixel (`i`, `j`) at `offset` from its center, with the
+    /// factor to apply to the radiance it brings back: the response to its wavelength in
+    /// spectral mode, the color channel it carries with chromatic aberration, and
+    /// vignetting.
+    pub(crate) fn generate_ray(
+        &self,
+        i: u32,
+        j: u32,
+        offset: Vec2,
+        rng: &mut SmallRng,
+    ) -> (Ray, Color) {
+        let aberration = (self.lateral_aberration != 0.0 || self.longitudinal_aberration != 0.0)
+            && self.projection == Projection::Perspective;
+        let (mut r, weight) = if self.spectral {
+            let lambda = sample_wavelength(rng);
+            // Spread follows the wavelength continuously: 650 nm is red, 450 nm blue.
+            let spread = if aberration {
+                (550.0 - lambda) / 100.0
+            } else {
+                0.0
+            };
+            let mut r = self.get_ray_dispersed(i, j, offset, spread, rng);
+            r.wavelength = Some(lambda);
+            // Shading is still done in RGB, so weighting each channel by the
+            // wavelength's response keeps non-dispersive colors unchanged on average.
+            (r, wavelength_to_rgb(lambda))
+        } else if aberration {
+            // Trace one color channel per sample, each along its own bent ray.
+            let channel = rng.random_range(0..3);
+            let r = self.get_ray_dispersed(i, j, offset, channel as f64 - 1.0, rng);
+            let mut mask = Color::BLACK;
+            mask[channel] = 3.0;
+            (r, mask)
+        } else {
+            let r = self.get_ray_dispersed(i, j, offset, 0.0, rng);
+            (r, Color::new(1.0, 1.0, 1.0))
+        };
+        r.cone = self.pixel_cone();
+        (r, weight * self.vignette(&r))
     }
 
-    fn ray_color<W: Hittable>(&self, r: &Ray, depth: u32, world: &W) -> Color {
-        // Define the interval for valid hits. Use a small t_min to avoid self-intersection.
-        if depth <= 0 {
-            return Color::zero();
+    // Angle a pixel covers at the middle of the film, the spread of a camera ray's cone.
+    fn pixel_cone(&self) -> f64 {
+        self.pixel_delta_u.length() / self.focus_dist
+    }
+
+    // Light reaching the film falls off with the fourth power of the cosine of the
+    // angle off the optical axis.
+    fn vignette(&self, r: &Ray) -> f64 {
+        if self.vignetting <= 0.0 || self.projection != Projection::Perspective {
+            return 1.0;
         }
+        let cos = -r.dir.normalized().dot(self.w);
+        let falloff = (cos * cos) * (cos * cos);
+        1.0 + self.vignetting * (falloff - 1.0)
+    }
 
-        let hit_interval = Interval::new(0.001, INFINITY);
+    pub(crate) fn exposure_scale(&self) -> f64 {
+        let photographic = self.exposure.map_or(1.0, |e| e.scale());
+        if self.accumulate_exposure {
+            (self.shutter_close - self.shutter_open) * photographic
+        } else {
+            photographic
+        }
+    }
 
-        if let Some(rec) = world.hit(r, hit_interval) {
-            if let Some((scattered, attenuation)) = rec.mat.scatter(r, &rec) {
-                return attenuation * self.ray_color(&scattered, depth - 1, world);
-            }
-            return Color::zero();
+    fn ray_color(&self, r: &Ray, depth: u32, scene: &Scene, rng: &mut SmallRng) -> Color {
+        match &self.custom_integrator {
+            Some(integrator) => integrator.radiance(r, scene, depth, rng),
+            None => self.integrator.radiance(r, scene, depth, rng),
         }
+    }
 
-        // If no hit, it's the background (sky gradient)
-        let unit_direction = r.dir.normalized();
-        let a = 0.5 * (unit_direction.y + 1.0); // Using public field .y
-        (1.0 - a) * Color::new(1.0, 1.0, 1.0) + a * Color::new(0.5, 0.7, 1.0)
+    pub fn get_ray(&self, i: u32, j: u32, rng: &mut SmallRng) -> Ray {
+        let offset = self.sample_square(rng);
+        Ray {
+            cone: self.pixel_cone(),
+            ..self.get_ray_dispersed(i, j, offset, 0.0, rng)
+        }
     }
 
-    pub fn get_ray(&self, i: u32, j: u32) -> Ray {
-        let offset = self.sample_square();
-        let pixel_sample = self.pixel00_loc
+    // `get_ray` for light bent `spread` times the chromatic aberration away from green:
+    // -1 for red, 1 for blue.
+    fn get_ray_dispersed(
+        &self,
+        i: u32,
+        j: u32,
+        offset: Vec2,
+        spread: f64,
+        rng: &mut SmallRng,
+    ) -> Ray {
+        let mut pixel_sample = self.pixel00_loc
             + ((i as f64 + offset.x) * self.pixel_delta_u)
             + ((j as f64 + offset.y) * self.pixel_delta_v);
+        if self.distortion != 0.0 {
+            // Radial distortion about the optical axis, with the radius measured in
+            // half picture diagonals.
+            let axis_point = self.center - self.focus_dist * self.w;
+            let half_diagonal = 0.5
+                * ((self.image_width as f64 * self.pixel_delta_u).length_squared()
+                    + (self.image_height as f64 * self.pixel_delta_v).length_squared())
+                .sqrt();
+            let d = pixel_sample - axis_point;
+            let r2 = d.length_squared() / (half_diagonal * half_diagonal);
+            pixel_sample = axis_point + d * (1.0 + self.distortion * r2);
+        }
+        if self.lateral_aberration != 0.0 {
+            // Each color is magnified slightly differently, fringing edges off-center.
+            let axis_point = self.center - self.focus_dist * self.w;
+            pixel_sample =
+                axis_point + (pixel_sample - axis_point) * (1.0 + self.lateral_aberration * spread);
+        }
+        let film_x = (i as f64 + 0.5 + offset.x) / self.film_width as f64;
+        let film_y = (j as f64 + 0.5 + offset.y) / self.film_height as f64;
+        let aspect = (self.film_width as f64 * self.pixel_aspect_ratio) / self.film_height as f64;
+        if let Some(d) = self.projection.direction(film_x, film_y, aspect) {
+            let direction = d.x * self.u + d.y * self.v + d.z * self.w;
+            return Ray::with_time(self.center, direction, self.sample_time(j, rng));
+        }
+
         let ray_origin = if self.defocus_angle <= 0.0 {
             self.center
         } else {
-            self.defocus_disk_sample()
+            self.defocus_disk_sample(rng)
+        };
+
+        // Moving the target point along the pixel's chief ray changes the plane of focus
+        // for this pixel while keeping the same aperture.
+        let focus_point = match self.focus_map_dist(i, j) {
+            Some(dist) => self.center + (pixel_sample - self.center) * (dist / self.focus_dist),
+            None if self.tilt != 0.0 || self.swing != 0.0 => {
+                // Where the chief ray meets the tilted plane of focus, which still passes
+                // through the center of the untilted one.
+                let chief = pixel_sample - self.center;
+                let along = chief.dot(self.focus_normal);
+                let s = -self.focus_dist * self.w.dot(self.focus_normal) / along;
+                if s > 0.0 && s.is_finite() {
+                    self.center + s * chief
+                } else {
+                    // The plane never crosses this ray, so it's focused at infinity.
+                    ray_origin + chief
+                }
+            }
+            None => pixel_sample,
         };
-        let ray_direction = pixel_sample - ray_origin;
+        // Each color also focuses at a slightly different distance.
+        let focus_point = self.center
+            + (focus_point - self.center) * (1.0 + self.longitudinal_aberration * spread);
+        let ray_direction = focus_point - ray_origin;
 
-        Ray::new(ray_origin, ray_direction)
+        Ray::with_time(ray_origin, ray_direction, self.sample_time(j, rng))
     }
 
-    fn sample_square(&self) -> Vec3 {
+    // Rows start exposing one after another with a rolling shutter, so each scanline
+    // sees the scene slightly later than the one above it.
+    fn sample_time(&self, j: u32, rng: &mut SmallRng) -> f64 {
+        let row_start =
+            self.shutter_open + self.rolling_shutter * (j as f64 / self.film_height as f64);
+        row_start + random_f64(rng) * (self.shutter_close - self.shutter_open)
+    }
+
+    /// Whether any part of `bbox` can show up in the picture. `margin` widens the view
+    /// by that fraction on every side, to keep objects just outside the frame.
+    pub fn sees_box(&self, bbox: &Aabb, margin: f64) -> bool {
+        if !bbox.is_bounded() || self.projection != Projection::Perspective {
+            return true;
+        }
+        // Rays leave from anywhere on the lens, so grow the box by the aperture radius.
+        let lens_radius = self.defocus_disk_u.length();
+        let pad = Vec3::new(lens_radius, lens_radius, lens_radius);
+        let bbox = Aabb::from_points(bbox.min() - pad, bbox.max() + pad);
+
+        let top_left = self.pixel00_loc - 0.5 * (self.pixel_delta_u + self.pixel_delta_v);
+        let across = self.film_width as f64 * self.pixel_delta_u;
+        let down = self.film_height as f64 * self.pixel_delta_v;
+        let middle = top_left + 0.5 * (across + down);
+        let corners = [
+            top_left,
+            top_left + across,
+            top_left + across + down,
+            top_left + down,
+        ]
+        .map(|c| middle + (1.0 + margin) * (c - middle) - self.center);
+
+        let points = bbox.corners();
+        for k in 0..4 {
+            let mut normal = corners[k].cross(corners[(k + 1) % 4]);
+            if normal.dot(-self.w) < 0.0 {
+                normal = -normal;
+            }
+            // Every corner outside the same side plane means the box is out of view.
+            if points.iter().all(|p| normal.dot(*p - self.center) < 0.0) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Distance from the camera to the nearest point of `bbox`.
+    pub fn distance_to_box(&self, bbox: &Aabb) -> f64 {
+        let c = self.center;
+        let nearest = Point3::new(bbox.x.clamp(c.x), bbox.y.clamp(c.y), bbox.z.clamp(c.z));
+        (nearest - c).length()
+    }
+
+    /// Moves the camera back along its current viewing direction and aims it at the
+    /// middle of `bounds`, close enough that all of it fits in the frame. `padding` is
+    /// the extra room to leave around it, as a fraction of its size. Focus is set on
+    /// the middle of the box. Unbounded boxes leave the camera untouched.
+    pub fn frame(&mut self, bounds: &Aabb, padding: f64) {
+        if !bounds.is_bounded() {
+            return;
+        }
+        let radius = ((bounds.max() - bounds.min()).length() / 2.0 * (1.0 + padding)).max(1e-6);
+        // The bounding sphere has to fit in the narrower of the two fields of view.
+        let half_v = degrees_to_radians(self.vfov) / 2.0;
+        let half_h = (half_v.tan() * self.aspect_ratio).atan();
+        let distance = radius / half_v.min(half_h).sin();
+
+        let mut direction = match self.orientation {
+            Some(q) => q.rotate(Vec3::new(0.0, 0.0, 1.0)),
+            None => self.lookfrom - self.lookat,
+        };
+        if direction.length_squared() == 0.0 {
+            direction = Vec3::new(0.0, 0.0, 1.0);
+        }
+        self.lookat = bounds.center();
+        self.lookfrom = self.lookat + distance * direction.normalized();
+        self.focus_dist = distance;
+        self.initialize();
+    }
+
+    /// Focuses on `target`: the plane of focus goes through it, square to the view.
+    pub fn focus_at(&mut self, target: Point3) {
+        let depth = (self.center - target).dot(self.w);
+        if depth > 0.0 {
+            self.focus_dist = depth;
+            self.initialize();
+        }
+    }
+
+    fn focus_map_dist(&self, i: u32, j: u32) -> Option<f64> {
+        let map = self.focus_map.as_ref()?;
+        let x = (i as f64 + 0.5) / self.film_width as f64;
+        let y = (j as f64 + 0.5) / self.film_height as f64;
+        map.focus_dist_at(x, y)
+    }
+
+    pub(crate) fn sample_square(&self, rng: &mut SmallRng) -> Vec2 {
         if self.samples_per_pixel == 1 {
-            return Vec3::zero();
+            return Vec2::ZERO;
         }
-        Vec3::new(random_f64() - 0.5, random_f64() - 0.5, 0.0)
+        Vec2::new(random_f64(rng) - 0.5, random_f64(rng) - 0.5)
     }
 
-    fn defocus_disk_sample(&self) -> Point3 {
-        let p = Vec3::random_in_unit_disk();
+    fn defocus_disk_sample(&self, rng: &mut SmallRng) -> Point3 {
+        let p = Vec3::random_in_unit_disk(rng);
         self.center + (p.x * self.defocus_disk_u) + (p.y * self.defocus_disk_v)
     }
 }
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+/// Builds a `Camera` one named setting at a time, from the defaults `Camera::builder`
+/// lists. Anything not set keeps its default.
+#[derive(Clone)]
+pub struct CameraBuilder {
+    camera: Camera,
+    // Unset means focus on `lookat`.
+    focus_dist: Option<f64>,
+}
+
+macro_rules! setters {
+    ($($(#[$doc:meta])* $name:ident: $ty:ty),* $(,)?) => {
+        $(
+            $(#[$doc])*
+            pub fn $name(mut self, $name: $ty) -> Self {
+                self.camera.$name = $name;
+                self
+            }
+        )*
+    };
+}
+
+impl CameraBuilder {
+    setters! {
+        aspect_ratio: f64,
+        image_width: u32,
+        samples_per_pixel: u32,
+        max_depth: u32,
+        /// Vertical field of view in degrees.
+        vfov: f64,
+        lookfrom: Point3,
+        lookat: Point3,
+        vup: Vec3,
+        /// Aperture cone angle in degrees; 0 keeps everything sharp.
+        defocus_angle: f64,
+        pixel_aspect_ratio: f64,
+        overscan: f64,
+        auto_tune: bool,
+        rolling_shutter: f64,
+        accumulate_exposure: bool,
+        spectral: bool,
+        adaptive: bool,
+        roll: f64,
+        projection: Projection,
+        /// Lens shift as a fraction of the picture width, positive to the right.
+        shift_x: f64,
+        /// Lens shift as a fraction of the picture height, positive upward.
+        shift_y: f64,
+        /// Tilt of the plane of focus about the horizontal axis, in degrees.
+        tilt: f64,
+        /// Tilt of the plane of focus about the vertical axis, in degrees.
+        swing: f64,
+        /// Radial distortion, positive for barrel and negative for pincushion.
+        distortion: f64,
+        /// Cosine-fourth vignetting strength from 0 to 1.
+        vignetting: f64,
+        /// Difference in magnification between colors, as a fraction.
+        lateral_aberration: f64,
+        /// Difference in focus distance between colors, as a fraction.
+        longitudinal_aberration: f64,
+        /// Light transport used for every sample.
+        integrator: IntegratorKind,
+        /// Pixel reconstruction filter.
+        filter: Filter,
+        /// Path-at-a-time or wavefront rendering.
+        backend: Backend,
+        /// Report samples that come out NaN or infinite and draw them magenta.
+        check_finite: bool,
+    }
+
+    /// Distance to the plane in focus. Defaults to the distance to `lookat`.
+    pub fn focus_dist(mut self, focus_dist: f64) -> Self {
+        self.focus_dist = Some(focus_dist);
+        self
+    }
+
+    /// Name of the scene file object to focus on.
+    pub fn focus_on(mut self, name: &str) -> Self {
+        self.camera.focus_on = Some(name.to_string());
+        self
+    }
+
+    pub fn focus_map(mut self, focus_map: FocusMap) -> Self {
+        self.camera.focus_map = Some(focus_map);
+        self
+    }
+
+    /// Exposure interval in scene time, for motion blur.
+    pub fn shutter(mut self, open: f64, close: f64) -> Self {
+        self.camera.shutter_open = open;
+        self.camera.shutter_close = close;
+        self
+    }
+
+    pub fn exposure(mut self, exposure: Exposure) -> Self {
+        self.camera.exposure = Some(exposure);
+        self
+    }
+
+    /// Seed that makes renders repeatable.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.camera.seed = Some(seed);
+        self
+    }
+
+    /// Aims with a quaternion instead of `lookat` and `vup`.
+    pub fn orientation(mut self, orientation: Quat) -> Self {
+        self.camera.orientation = Some(orientation);
+        self
+    }
+
+    pub fn build(self) -> Camera {
+        let mut camera = self.camera;
+        camera.focus_dist = self
+            .focus_dist
+            .unwrap_or_else(|| (camera.lookat - camera.lookfrom).length());
+        camera.initialize();
+        camera
+    }
+}
@@ -1,7 +1,217 @@
 use crate::rtweekend::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::io::{self, Write};
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Sub};
 
-pub type Color = Vec3;
+/// Linear RGB color. A type of its own rather than a `Vec3` so a position or direction
+/// can't be added to a color by mistake; `from_vec3` and `to_vec3` convert where a
+/// color really is read as a vector, like a normal map. Scene files write colors the
+/// same way as vectors.
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(from = "Vec3", into = "Vec3")]
+pub struct Color {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+}
+
+impl Color {
+    pub const BLACK: Self = Self::new(0.0, 0.0, 0.0);
+    pub const WHITE: Self = Self::new(1.0, 1.0, 1.0);
+
+    pub const fn new(r: f64, g: f64, b: f64) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Gray with every channel at `value`.
+    pub const fn splat(value: f64) -> Self {
+        Self::new(value, value, value)
+    }
+
+    /// Every channel uniform in [0, 1).
+    pub fn random(rng: &mut SmallRng) -> Self {
+        Self::new(random_f64(rng), random_f64(rng), random_f64(rng))
+    }
+
+    /// Every channel uniform in [min, max).
+    pub fn random_interval(rng: &mut SmallRng, min: f64, max: f64) -> Self {
+        Self::new(
+            random_f64_range(rng, min, max),
+            random_f64_range(rng, min, max),
+            random_f64_range(rng, min, max),
+        )
+    }
+
+    pub fn from_vec3(v: Vec3) -> Self {
+        Self::new(v.x, v.y, v.z)
+    }
+
+    pub fn to_vec3(self) -> Vec3 {
+        Vec3::new(self.r, self.g, self.b)
+    }
+
+    /// Relative luminance, taking the channels as linear Rec. 709.
+    pub fn luminance(self) -> f64 {
+        0.2126 * self.r + 0.7152 * self.g + 0.0722 * self.b
+    }
+
+    /// `self` at `t = 0`, `other` at `t = 1`.
+    pub fn lerp(self, other: Self, t: f64) -> Self {
+        self * (1.0 - t) + other * t
+    }
+
+    pub fn is_finite(self) -> bool {
+        self.r.is_finite() && self.g.is_finite() && self.b.is_finite()
+    }
+
+    pub fn near_zero(self) -> bool {
+        let s = 1e-8;
+        self.r.abs() < s && self.g.abs() < s && self.b.abs() < s
+    }
+
+    pub fn max_component(self) -> f64 {
+        self.r.max(self.g).max(self.b)
+    }
+
+    pub fn min_component(self) -> f64 {
+        self.r.min(self.g).min(self.b)
+    }
+
+    /// Applies `f` to every channel.
+    pub fn map(self, f: impl Fn(f64) -> f64) -> Self {
+        Self::new(f(self.r), f(self.g), f(self.b))
+    }
+}
+
+impl From<Vec3> for Color {
+    fn from(v: Vec3) -> Self {
+        Self::from_vec3(v)
+    }
+}
+
+impl From<Color> for Vec3 {
+    fn from(c: Color) -> Self {
+        c.to_vec3()
+    }
+}
+
+impl Add for Color {
+    type Output = Self;
+
+    fn add(self, o: Self) -> Self {
+        Self::new(self.r + o.r, self.g + o.g, self.b + o.b)
+    }
+}
+
+impl AddAssign for Color {
+    fn add_assign(&mut self, o: Self) {
+        *self = *self + o;
+    }
+}
+
+impl Sub for Color {
+    type Output = Self;
+
+    fn sub(self, o: Self) -> Self {
+        Self::new(self.r - o.r, self.g - o.g, self.b - o.b)
+    }
+}
+
+impl Mul for Color {
+    type Output = Self;
+
+    fn mul(self, o: Self) -> Self {
+        Self::new(self.r * o.r, self.g * o.g, self.b * o.b)
+    }
+}
+
+impl MulAssign for Color {
+    fn mul_assign(&mut self, o: Self) {
+        *self = *self * o;
+    }
+}
+
+impl Mul<f64> for Color {
+    type Output = Self;
+
+    fn mul(self, s: f64) -> Self {
+        Self::new(self.r * s, self.g * s, self.b * s)
+    }
+}
+
+impl Mul<Color> for f64 {
+    type Output = Color;
+
+    fn mul(self, c: Color) -> Color {
+        c * self
+    }
+}
+
+impl MulAssign<f64> for Color {
+    fn mul_assign(&mut self, s: f64) {
+        *self = *self * s;
+    }
+}
+
+impl Div for Color {
+    type Output = Self;
+
+    fn div(self, o: Self) -> Self {
+        Self::new(self.r / o.r, self.g / o.g, self.b / o.b)
+    }
+}
+
+impl Div<f64> for Color {
+    type Output = Self;
+
+    fn div(self, s: f64) -> Self {
+        self * (1.0 / s)
+    }
+}
+
+impl DivAssign<f64> for Color {
+    fn div_assign(&mut self, s: f64) {
+        *self *= 1.0 / s;
+    }
+}
+
+impl Sum for Color {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::BLACK, |acc, c| acc + c)
+    }
+}
+
+impl Index<usize> for Color {
+    type Output = f64;
+
+    fn index(&self, index: usize) -> &f64 {
+        match index {
+            0 => &self.r,
+            1 => &self.g,
+            2 => &self.b,
+            _ => panic!("Index out of bounds for Color"),
+        }
+    }
+}
+
+impl IndexMut<usize> for Color {
+    fn index_mut(&mut self, index: usize) -> &mut f64 {
+        match index {
+            0 => &mut self.r,
+            1 => &mut self.g,
+            2 => &mut self.b,
+            _ => panic!("Index out of bounds for Color"),
+        }
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.r, self.g, self.b)
+    }
+}
 
 #[inline]
 pub fn linear_to_gamma(linear_component: f64) -> f64 {
@@ -12,21 +222,95 @@ pub fn linear_to_gamma(linear_component: f64) -> f64 {
     }
 }
 
-pub fn write_color<W: Write>(out: &mut W, pixel_color: Color) -> io::Result<()> {
-    let mut r = pixel_color.x;
-    let mut g = pixel_color.y;
-    let mut b = pixel_color.z;
-
-    r = linear_to_gamma(r);
-    g = linear_to_gamma(g);
-    b = linear_to_gamma(b);
+/// Relative luminance of a linear Rec. 709 color.
+#[inline]
+pub fn luminance(c: Color) -> f64 {
+    c.luminance()
+}
 
+/// Gamma-encoded 8-bit value of each channel, as written to images.
+pub fn to_rgb8(pixel_color: Color) -> [u8; 3] {
     let intensity = Interval::new(0.000, 0.999);
-    let rbyte = (255.999 * intensity.clamp(r)) as u8;
-    let gbyte = (255.999 * intensity.clamp(g)) as u8;
-    let bbyte = (255.999 * intensity.clamp(b)) as u8;
+    let byte = |x: f64| (255.999 * intensity.clamp(linear_to_gamma(x))) as u8;
+    [
+        byte(pixel_color.r),
+        byte(pixel_color.g),
+        byte(pixel_color.b),
+    ]
+}
 
-    write!(out, "{} {} {}\n", rbyte, gbyte, bbyte)?;
+/// Gamma-encoded 16-bit value of each channel, for formats with the depth to keep the
+/// gradations 8 bits band.
+pub fn to_rgb16(pixel_color: Color) -> [u16; 3] {
+    let word = |x: f64| (65535.0 * linear_to_gamma(x).min(1.0)).round() as u16;
+    [
+        word(pixel_color.r),
+        word(pixel_color.g),
+        word(pixel_color.b),
+    ]
+}
+
+/// Row-major `pixels` as gamma-encoded, opaque 8-bit RGBA: the layout of a browser
+/// canvas's `ImageData`.
+pub fn to_rgba8(pixels: &[Color]) -> Vec<u8> {
+    pixels
+        .iter()
+        .flat_map(|&pixel| {
+            let [r, g, b] = to_rgb8(pixel);
+            [r, g, b, 255]
+        })
+        .collect()
+}
+
+pub fn write_color<W: Write>(out: &mut W, pixel_color: Color) -> io::Result<()> {
+    let [rbyte, gbyte, bbyte] = to_rgb8(pixel_color);
+
+    writeln!(out, "{} {} {}", rbyte, gbyte, bbyte)?;
 
     Ok(())
 }
+
+/// Writes row-major `pixels` as a plain (P3) PPM image.
+pub fn write_ppm<W: Write>(out: W, width: u32, height: u32, pixels: &[Color]) -> io::Result<()> {
+    let mut out = io::BufWriter::new(out);
+    writeln!(out, "P3")?;
+    writeln!(out, "{} {}", width, height)?;
+    writeln!(out, "255")?;
+    for &pixel_color in pixels {
+        write_color(&mut out, pixel_color)?;
+    }
+    out.flush()
+}
+
+/// Writes row-major `pixels` as a binary (P6) PPM image: the same picture as
+/// `write_ppm` in about a quarter of the space, and much quicker to write.
+pub fn write_ppm_binary<W: Write>(
+    out: W,
+    width: u32,
+    height: u32,
+    pixels: &[Color],
+) -> io::Result<()> {
+    let mut out = io::BufWriter::new(out);
+    write!(out, "P6\n{} {}\n255\n", width, height)?;
+    for &pixel_color in pixels {
+        out.write_all(&to_rgb8(pixel_color))?;
+    }
+    out.flush()
+}
+
+/// Writes row-major `pixels` as a 16-bit RGB PAM (P7) image, for grading without the
+/// banding of 8 bits.
+pub fn write_pam<W: Write>(out: W, width: u32, height: u32, pixels: &[Color]) -> io::Result<()> {
+    let mut out = io::BufWriter::new(out);
+    write!(
+        out,
+        "P7\nWIDTH {}\nHEIGHT {}\nDEPTH 3\nMAXVAL 65535\nTUPLTYPE RGB\nENDHDR\n",
+        width, height
+    )?;
+    for &pixel_color in pixels {
+        for word in to_rgb16(pixel_color) {
+            out.write_all(&word.to_be_bytes())?;
+        }
+    }
+    out.flush()
+}
@@ -0,0 +1,111 @@
+use crate::disk::hit_local_disk;
+use crate::rtweekend::*;
+
+/// Finite cone with a base disk of `radius` at `base`, tapering to its apex `height` along `axis`.
+pub struct Cone {
+    pub base: Point3,
+    pub axis: Vec3,
+    pub radius: f64,
+    pub height: f64,
+    pub capped: bool,
+    pub mat: Arc<dyn Material + Send + Sync>,
+    frame: Onb,
+}
+
+impl Cone {
+    pub fn new(
+        base: Point3,
+        axis: Vec3,
+        radius: f64,
+        height: f64,
+        capped: bool,
+        mat: Arc<dyn Material + Send + Sync>,
+    ) -> Self {
+        Self {
+            base,
+            axis: axis.normalized(),
+            radius,
+            height,
+            capped,
+            mat,
+            frame: Onb::new(axis),
+        }
+    }
+}
+
+impl Hittable for Cone {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let o = self.frame.to_local(r.orig - self.base);
+        let d = self.frame.to_local(r.dir);
+
+        // x^2 + y^2 = k^2 (height - z)^2, with k the radius/height slope.
+        let k = self.radius / self.height;
+        let k2 = k * k;
+        let oz = self.height - o.z;
+
+        let a = d.x * d.x + d.y * d.y - k2 * d.z * d.z;
+        let h = o.x * d.x + o.y * d.y + k2 * oz * d.z;
+        let c = o.x * o.x + o.y * o.y - k2 * oz * oz;
+
+        let mut roots = [INFINITY, INFINITY];
+        if a.abs() < 1e-12 {
+            // Ray parallel to the cone's slope: the quadratic degenerates to a linear equation.
+            if h.abs() > 1e-12 {
+                roots[0] = -c / (2.0 * h);
+            }
+        } else {
+            let discriminant = h * h - a * c;
+            if discriminant >= 0.0 {
+                let sqrtd = discriminant.sqrt();
+                roots = [(-h - sqrtd) / a, (-h + sqrtd) / a];
+                if roots[0] > roots[1] {
+                    roots.swap(0, 1);
+                }
+            }
+        }
+
+        let mut closest = ray_t.max;
+        let mut best: Option<(f64, Vec3, f64, f64)> = None; // (t, local normal, u, v)
+
+        for root in roots {
+            if !Interval::new(ray_t.min, closest).surrounds(root) {
+                continue;
+            }
+            let p = o + root * d;
+            if p.z < 0.0 || p.z > self.height {
+                continue;
+            }
+            let phi = p.y.atan2(p.x) + PI;
+            let normal = Vec3::new(p.x, p.y, k2 * (self.height - p.z)).normalized();
+            best = Some((root, normal, phi / (2.0 * PI), p.z / self.height));
+            closest = root;
+            break;
+        }
+
+        if self.capped
+            && let Some((t, u, v)) =
+                hit_local_disk(o, d, 0.0, self.radius, Interval::new(ray_t.min, closest))
+        {
+            best = Some((t, Vec3::new(0.0, 0.0, -1.0), u, v));
+        }
+
+        let (t, local_normal, u, v) = best?;
+        let local = o + t * d;
+        let mut rec = HitRecord {
+            p: r.at(t),
+            t,
+            uv: Vec2::new(u, v),
+            tangent: self.frame.transform(Vec3::new(-local.y, local.x, 0.0)),
+            mat: self.mat.clone(),
+            ..Default::default()
+        };
+        rec.set_face_normal(r, self.frame.transform(local_normal));
+
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let apex = self.base + self.height * self.axis;
+        Aabb::around_disk(self.base, self.axis, self.radius).union(&Aabb::from_points(apex, apex))
+    }
+}
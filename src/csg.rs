@@ -0,0 +1,121 @@
+use crate::rtweekend::*;
+
+/// Safety cap on the number of surface crossings collected per operand.
+const MAX_CROSSINGS: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsgOp {
+    Union,
+    Intersection,
+    /// `left` minus `right`.
+    Difference,
+}
+
+impl CsgOp {
+    fn inside(self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            CsgOp::Union => in_left || in_right,
+            CsgOp::Intersection => in_left && in_right,
+            CsgOp::Difference => in_left && !in_right,
+        }
+    }
+}
+
+/// Boolean combination of two closed solids. Both operands are traced along the
+/// whole ray line and their entry/exit crossings are merged, so operands can be
+/// any closed hittable, including other `Csg` nodes.
+pub struct Csg {
+    pub op: CsgOp,
+    pub left: Arc<dyn Hittable>,
+    pub right: Arc<dyn Hittable>,
+}
+
+impl Csg {
+    pub fn new(op: CsgOp, left: Arc<dyn Hittable>, right: Arc<dyn Hittable>) -> Self {
+        Self { op, left, right }
+    }
+
+    pub fn union(left: Arc<dyn Hittable>, right: Arc<dyn Hittable>) -> Self {
+        Self::new(CsgOp::Union, left, right)
+    }
+
+    pub fn intersection(left: Arc<dyn Hittable>, right: Arc<dyn Hittable>) -> Self {
+        Self::new(CsgOp::Intersection, left, right)
+    }
+
+    pub fn difference(left: Arc<dyn Hittable>, right: Arc<dyn Hittable>) -> Self {
+        Self::new(CsgOp::Difference, left, right)
+    }
+
+    // Every surface crossing of `object` along the full ray line, in order.
+    // A crossing is an entry when the ray hits the outside of the surface.
+    fn crossings(object: &dyn Hittable, r: &Ray) -> Vec<HitRecord> {
+        let mut crossings = Vec::new();
+        let mut t_min = -INFINITY;
+        while crossings.len() < MAX_CROSSINGS {
+            let Some(rec) = object.hit(r, Interval::new(t_min, INFINITY)) else {
+                break;
+            };
+            t_min = rec.t + 1e-7 * rec.t.abs().max(1.0);
+            crossings.push(rec);
+        }
+        crossings
+    }
+
+    // Whether the ray starts (at t = -inf) inside the solid: its first crossing is an exit.
+    fn starts_inside(crossings: &[HitRecord]) -> bool {
+        crossings.first().is_some_and(|rec| !rec.front_face)
+    }
+}
+
+impl Hittable for Csg {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let left = Self::crossings(self.left.as_ref(), r);
+        let right = Self::crossings(self.right.as_ref(), r);
+
+        let mut in_left = Self::starts_inside(&left);
+        let mut in_right = Self::starts_inside(&right);
+        let mut inside = self.op.inside(in_left, in_right);
+
+        let (mut li, mut ri) = (0, 0);
+        while li < left.len() || ri < right.len() {
+            let take_left = ri >= right.len() || (li < left.len() && left[li].t <= right[ri].t);
+            let rec = if take_left {
+                in_left = left[li].front_face;
+                li += 1;
+                &left[li - 1]
+            } else {
+                in_right = right[ri].front_face;
+                ri += 1;
+                &right[ri - 1]
+            };
+
+            let now_inside = self.op.inside(in_left, in_right);
+            if now_inside == inside {
+                continue;
+            }
+            inside = now_inside;
+
+            if rec.t >= ray_t.max {
+                return None;
+            }
+            if ray_t.surrounds(rec.t) {
+                // The normal already faces the ray; the side is decided by the combined solid,
+                // which flips it for surfaces of a subtracted operand.
+                let mut hit = rec.clone();
+                hit.front_face = now_inside;
+                return Some(hit);
+            }
+        }
+
+        None
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        match self.op {
+            CsgOp::Union => self.left.bounding_box().union(&self.right.bounding_box()),
+            // Both can only remove material from the left operand.
+            CsgOp::Intersection | CsgOp::Difference => self.left.bounding_box(),
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use crate::rtweekend::*;
+
+/// What `Scene::cull_for_camera` removes from the camera's view.
+#[derive(Debug, Clone, Copy)]
+pub struct CullOptions {
+    /// Cull objects entirely outside the view frustum.
+    pub frustum: bool,
+    /// Widens the frustum by this fraction on each side before testing.
+    pub frustum_margin: f64,
+    /// Cull objects whose nearest point is farther than this from the camera.
+    pub max_distance: Option<f64>,
+    /// Keep culled objects for shadows, reflections and indirect light instead of
+    /// dropping them from the scene entirely.
+    pub keep_for_secondary: bool,
+}
+
+impl Default for CullOptions {
+    fn default() -> Self {
+        Self {
+            frustum: true,
+            frustum_margin: 0.1,
+            max_distance: None,
+            keep_for_secondary: true,
+        }
+    }
+}
+
+/// How many top-level objects `cull_for_camera` kept, hid from the camera, or dropped.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CullReport {
+    pub visible: usize,
+    pub hidden: usize,
+    pub dropped: usize,
+}
+
+impl Scene {
+    /// Removes top-level world objects the camera can't see. They move to
+    /// `camera_culled` when `keep_for_secondary` is set and are dropped otherwise.
+    /// Objects without a finite bounding box are always kept.
+    pub fn cull_for_camera(&mut self, camera: &Camera, options: &CullOptions) -> CullReport {
+        let mut report = CullReport::default();
+        let objects = std::mem::take(&mut self.world.objects);
+        for object in objects {
+            let bbox = object.bounding_box();
+            let outside = (options.frustum && !camera.sees_box(&bbox, options.frustum_margin))
+                || options
+                    .max_distance
+                    .is_some_and(|d| bbox.is_bounded() && camera.distance_to_box(&bbox) > d);
+            if !outside {
+                report.visible += 1;
+                self.world.add(object);
+            } else if options.keep_for_secondary {
+                report.hidden += 1;
+                self.camera_culled.add(object);
+            } else {
+                report.dropped += 1;
+            }
+        }
+        report
+    }
+}
@@ -0,0 +1,106 @@
+use crate::disk::hit_local_disk;
+use crate::rtweekend::*;
+
+/// Finite cylinder standing on `base`, extending `height` along `axis`.
+pub struct Cylinder {
+    pub base: Point3,
+    pub axis: Vec3,
+    pub radius: f64,
+    pub height: f64,
+    pub capped: bool,
+    pub mat: Arc<dyn Material + Send + Sync>,
+    frame: Onb,
+}
+
+impl Cylinder {
+    pub fn new(
+        base: Point3,
+        axis: Vec3,
+        radius: f64,
+        height: f64,
+        capped: bool,
+        mat: Arc<dyn Material + Send + Sync>,
+    ) -> Self {
+        Self {
+            base,
+            axis: axis.normalized(),
+            radius,
+            height,
+            capped,
+            mat,
+            frame: Onb::new(axis),
+        }
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        // Work in a frame where the axis is +z and the base is at the origin.
+        let o = self.frame.to_local(r.orig - self.base);
+        let d = self.frame.to_local(r.dir);
+
+        let mut closest = ray_t.max;
+        let mut best: Option<(f64, Vec3, f64, f64)> = None; // (t, local normal, u, v)
+
+        let a = d.x * d.x + d.y * d.y;
+        if a > 1e-12 {
+            let h = o.x * d.x + o.y * d.y;
+            let c = o.x * o.x + o.y * o.y - self.radius * self.radius;
+            let discriminant = h * h - a * c;
+            if discriminant >= 0.0 {
+                let sqrtd = discriminant.sqrt();
+                for root in [(-h - sqrtd) / a, (-h + sqrtd) / a] {
+                    if !Interval::new(ray_t.min, closest).surrounds(root) {
+                        continue;
+                    }
+                    let p = o + root * d;
+                    if p.z < 0.0 || p.z > self.height {
+                        continue;
+                    }
+                    let phi = p.y.atan2(p.x) + PI;
+                    let normal = Vec3::new(p.x, p.y, 0.0) / self.radius;
+                    best = Some((root, normal, phi / (2.0 * PI), p.z / self.height));
+                    closest = root;
+                    break;
+                }
+            }
+        }
+
+        if self.capped {
+            for (height, normal) in [
+                (0.0, Vec3::new(0.0, 0.0, -1.0)),
+                (self.height, Vec3::new(0.0, 0.0, 1.0)),
+            ] {
+                if let Some((t, u, v)) =
+                    hit_local_disk(o, d, height, self.radius, Interval::new(ray_t.min, closest))
+                {
+                    best = Some((t, normal, u, v));
+                    closest = t;
+                }
+            }
+        }
+
+        let (t, local_normal, u, v) = best?;
+        let local = o + t * d;
+        let mut rec = HitRecord {
+            p: r.at(t),
+            t,
+            uv: Vec2::new(u, v),
+            tangent: self.frame.transform(Vec3::new(-local.y, local.x, 0.0)),
+            mat: self.mat.clone(),
+            ..Default::default()
+        };
+        rec.set_face_normal(r, self.frame.transform(local_normal));
+
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let top = self.base + self.height * self.axis;
+        Aabb::around_disk(self.base, self.axis, self.radius).union(&Aabb::around_disk(
+            top,
+            self.axis,
+            self.radius,
+        ))
+    }
+}
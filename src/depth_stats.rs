@@ -0,0 +1,179 @@
+use crate::parallel::*;
+use crate::rtweekend::*;
+use std::io::{self, Write};
+
+/// Bookkeeping for one path while it is traced by `Camera::trace`.
+#[derive(Debug, Clone)]
+pub struct PathStats {
+    /// Product of the bounce weights so far, so contributions are measured at the pixel.
+    pub throughput: Color,
+    /// Luminance reaching the pixel from each bounce (0 = the first surface hit).
+    pub energy: Vec<f64>,
+    /// Number of bounces the path made before it ended.
+    pub depth: u32,
+    /// Everything that happened along the path, for `Camera::debug_pixel`. Only kept
+    /// when created with `logging`.
+    pub events: Option<Vec<PathEvent>>,
+}
+
+impl PathStats {
+    pub fn new(max_depth: u32) -> Self {
+        Self {
+            throughput: Color::new(1.0, 1.0, 1.0),
+            energy: vec![0.0; max_depth as usize + 1],
+            depth: 0,
+            events: None,
+        }
+    }
+
+    /// Stats that also keep a log of every bounce.
+    pub fn logging(max_depth: u32) -> Self {
+        Self {
+            events: Some(Vec::new()),
+            ..Self::new(max_depth)
+        }
+    }
+
+    /// Adds the event built by `event` to the log, if there is one.
+    pub fn log(&mut self, event: impl FnOnce() -> PathEvent) {
+        if let Some(events) = &mut self.events {
+            events.push(event());
+        }
+    }
+
+    pub fn record(&mut self, bounce: u32, radiance: Color) {
+        if let Some(slot) = self.energy.get_mut(bounce as usize) {
+            *slot += luminance(self.throughput * radiance).max(0.0);
+        }
+    }
+
+    pub fn terminate(&mut self, depth: u32) {
+        self.depth = depth;
+    }
+}
+
+/// Where paths ended and which bounces their light came from, from `Camera::depth_statistics`.
+pub struct DepthStats {
+    pub width: u32,
+    pub height: u32,
+    pub max_depth: u32,
+    /// Mean number of bounces per pixel, row by row from the top left (the depth AOV).
+    pub mean_depth: Vec<f64>,
+    /// Light reaching the image from each bounce, summed over all paths.
+    pub energy_by_bounce: Vec<f64>,
+    /// Paths still going when `max_depth` cut them off.
+    pub truncated_paths: u64,
+    pub total_paths: u64,
+}
+
+impl DepthStats {
+    /// Smallest `max_depth` that keeps `fraction` of the light seen in this run. The
+    /// light lost to truncation at the current depth isn't known, so gather stats with
+    /// a generous `max_depth`.
+    pub fn suggested_max_depth(&self, fraction: f64) -> u32 {
+        let total: f64 = self.energy_by_bounce.iter().sum();
+        if total <= 0.0 {
+            return 1;
+        }
+        let mut captured = 0.0;
+        for (bounce, energy) in self.energy_by_bounce.iter().enumerate() {
+            captured += energy;
+            if captured >= fraction * total {
+                // Light from bounce k needs k + 1 ray segments.
+                return bounce as u32 + 1;
+            }
+        }
+        self.max_depth
+    }
+
+    /// Writes the depth AOV as a grayscale P3 image, black = 0 and white = `max_depth`.
+    pub fn write_depth_aov<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "P3")?;
+        writeln!(out, "{} {}", self.width, self.height)?;
+        writeln!(out, "255")?;
+        for depth in &self.mean_depth {
+            let level = (255.0 * depth / self.max_depth.max(1) as f64).clamp(0.0, 255.0) as u8;
+            writeln!(out, "{} {} {}", level, level, level)?;
+        }
+        Ok(())
+    }
+
+    pub fn log_summary(&self) {
+        log::info!(target: "render", "Depth statistics ({} paths):", self.total_paths);
+        let total: f64 = self.energy_by_bounce.iter().sum();
+        for (bounce, energy) in self.energy_by_bounce.iter().enumerate() {
+            if total > 0.0 && *energy > 0.0 {
+                log::info!(target: "render", "  bounce {:>3}: {:6.2}%", bounce, 100.0 * energy / total);
+            }
+        }
+        log::info!(
+            target: "render",
+            "  {:.2}% of paths hit max_depth {}",
+            100.0 * self.truncated_paths as f64 / self.total_paths.max(1) as f64,
+            self.max_depth
+        );
+        log::info!(
+            target: "render",
+            "  max_depth {} captures 99% of the light",
+            self.suggested_max_depth(0.99)
+        );
+    }
+}
+
+impl Camera {
+    /// Traces `samples_per_pixel` paths through every pixel and records how deep they
+    /// went and where their light came from, instead of producing an image.
+    pub fn depth_statistics(&self, scene: &Scene) -> DepthStats {
+        let (width, height) = (self.film_width(), self.film_height());
+        let bounces = self.max_depth as usize + 1;
+
+        let rows: Vec<(Vec<f64>, Vec<f64>, u64)> = (0..height)
+            .into_par_iter()
+            .map(|j| {
+                let mut depths = Vec::with_capacity(width as usize);
+                let mut energy = vec![0.0; bounces];
+                let mut truncated = 0;
+                for i in 0..width {
+                    let mut rng = self.pixel_rng(i, j);
+                    let mut depth_sum = 0.0;
+                    for _ in 0..self.samples_per_pixel {
+                        let mut stats = PathStats::new(self.max_depth);
+                        let r = self.get_ray(i, j, &mut rng);
+                        let tracer = PathTracer::nee();
+                        tracer.trace(&r, self.max_depth, scene, &mut rng, Some(&mut stats));
+                        depth_sum += stats.depth as f64;
+                        if stats.depth >= self.max_depth {
+                            truncated += 1;
+                        }
+                        for (total, e) in energy.iter_mut().zip(&stats.energy) {
+                            *total += e;
+                        }
+                    }
+                    depths.push(depth_sum / self.samples_per_pixel.max(1) as f64);
+                }
+                (depths, energy, truncated)
+            })
+            .collect();
+
+        let mut mean_depth = Vec::with_capacity((width * height) as usize);
+        let mut energy_by_bounce = vec![0.0; bounces];
+        let mut truncated_paths = 0;
+        for (depths, energy, truncated) in rows {
+            mean_depth.extend(depths);
+            for (total, e) in energy_by_bounce.iter_mut().zip(&energy) {
+                *total += e;
+            }
+            truncated_paths += truncated;
+        }
+
+        DepthStats {
+            width,
+            height,
+            max_depth: self.max_depth,
+            mean_depth,
+            energy_by_bounce,
+            truncated_paths,
+            total_paths: width as u64 * height as u64 * self.samples_per_pixel as u64,
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use crate::rtweekend::*;
+
+pub struct Disk {
+    pub center: Point3,
+    pub normal: Vec3,
+    pub radius: f64,
+    pub mat: Arc<dyn Material + Send + Sync>,
+    frame: Onb,
+}
+
+impl Disk {
+    pub fn new(
+        center: Point3,
+        normal: Vec3,
+        radius: f64,
+        mat: Arc<dyn Material + Send + Sync>,
+    ) -> Self {
+        Self {
+            center,
+            normal: normal.normalized(),
+            radius,
+            mat,
+            frame: Onb::new(normal),
+        }
+    }
+}
+
+/// Intersects a local-space ray with the disk of `radius` lying in the plane `z = height`.
+/// Returns the hit distance and the (u, v) polar coordinates of the hit.
+pub(crate) fn hit_local_disk(
+    origin: Vec3,
+    direction: Vec3,
+    height: f64,
+    radius: f64,
+    ray_t: Interval,
+) -> Option<(f64, f64, f64)> {
+    if direction.z.abs() < 1e-8 {
+        return None;
+    }
+    let t = (height - origin.z) / direction.z;
+    if !ray_t.surrounds(t) {
+        return None;
+    }
+    let x = origin.x + t * direction.x;
+    let y = origin.y + t * direction.y;
+    let rho2 = x * x + y * y;
+    if rho2 > radius * radius {
+        return None;
+    }
+    let phi = y.atan2(x) + PI;
+    Some((t, phi / (2.0 * PI), rho2.sqrt() / radius))
+}
+
+impl Hittable for Disk {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let origin = self.frame.to_local(r.orig - self.center);
+        let direction = self.frame.to_local(r.dir);
+        let (t, u, v) = hit_local_disk(origin, direction, 0.0, self.radius, ray_t)?;
+        let local = origin + t * direction;
+
+        let mut rec = HitRecord {
+            p: r.at(t),
+            t,
+            uv: Vec2::new(u, v),
+            tangent: self.frame.transform(Vec3::new(-local.y, local.x, 0.0)),
+            mat: self.mat.clone(),
+            ..Default::default()
+        };
+        rec.set_face_normal(r, self.normal);
+
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::around_disk(self.center, self.normal, self.radius)
+    }
+}
@@ -0,0 +1,274 @@
+use crate::rtweekend::*;
+use crate::scene_file::SceneFile;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Mutex;
+use std::thread;
+
+/// First bytes of every connection, so a worker turns away anything else.
+const MAGIC: &[u8; 4] = b"RTW1";
+
+// Messages are a kind byte and a length-prefixed payload.
+const SCENE: u8 = 1;
+const CAMERA: u8 = 2;
+const TILE: u8 = 3;
+const SAMPLES: u8 = 4;
+const ERROR: u8 = 5;
+
+/// Largest payload either side accepts, so a bad length can't exhaust memory.
+const MAX_MESSAGE: u64 = 64 << 20;
+
+/// Renders tiles on another machine running `rt worker`, for splitting big frames over a
+/// small cluster. Add one per worker with `Camera::with_device`: the scheduler then
+/// hands tiles to the workers and the local CPU alike, faster ones taking more, and
+/// merges whatever comes back.
+///
+/// The scene file is sent when the device first connects and the camera whenever it
+/// changes, and the worker builds its own copy of the scene from them; meshes and
+/// textures travel inline in the scene file. Adaptive sampling's sample
+/// map stays local; tiles rendered remotely get the plain `samples_per_pixel`, and the
+/// built-in integrator rather than one from `Camera::with_integrator`. If a worker
+/// drops out, its tiles are rendered locally from then on.
+pub struct RemoteDevice {
+    addr: String,
+    scene: String,
+    connection: Mutex<Connection>,
+}
+
+#[derive(Default)]
+struct Connection {
+    stream: Option<(BufReader<TcpStream>, BufWriter<TcpStream>)>,
+    camera: String,
+    failed: bool,
+}
+
+impl RemoteDevice {
+    pub fn new(addr: &str, scene: &SceneFile) -> io::Result<Self> {
+        let scene = serde_json::to_string(scene).map_err(io::Error::other)?;
+        if scene.len() as u64 > MAX_MESSAGE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "scene too large to send to a worker",
+            ));
+        }
+        Ok(Self {
+            addr: addr.to_string(),
+            scene,
+            connection: Mutex::new(Connection::default()),
+        })
+    }
+
+    /// Connects now rather than on the first tile, to catch a worker that isn't there
+    /// before the render starts.
+    pub fn connect(&self) -> io::Result<()> {
+        let mut connection = self.connection.lock().unwrap();
+        self.ensure_connected(&mut connection)
+    }
+
+    fn ensure_connected(&self, connection: &mut Connection) -> io::Result<()> {
+        if connection.stream.is_some() {
+            return Ok(());
+        }
+        let stream = TcpStream::connect(&self.addr)?;
+        stream.set_nodelay(true)?;
+        let reader = BufReader::new(stream.try_clone()?);
+        let mut writer = BufWriter::new(stream);
+        writer.write_all(MAGIC)?;
+        write_message(&mut writer, SCENE, self.scene.as_bytes())?;
+        connection.stream = Some((reader, writer));
+        connection.camera.clear();
+        Ok(())
+    }
+
+    fn render_remote(
+        &self,
+        connection: &mut Connection,
+        camera: &Camera,
+        film_tile: &mut FilmTile,
+    ) -> io::Result<()> {
+        self.ensure_connected(connection)?;
+        let settings = serde_json::to_string(camera).map_err(io::Error::other)?;
+        let (reader, writer) = connection.stream.as_mut().unwrap();
+        if settings != connection.camera {
+            write_message(writer, CAMERA, settings.as_bytes())?;
+            connection.camera = settings;
+        }
+        let tile = film_tile.tile;
+        let mut payload = Vec::with_capacity(16);
+        for value in [tile.x, tile.y, tile.width, tile.height] {
+            payload.extend_from_slice(&value.to_le_bytes());
+        }
+        write_message(writer, TILE, &payload)?;
+        writer.flush()?;
+        match read_message(reader)? {
+            (SAMPLES, bytes) => film_tile.read_bytes(&bytes),
+            (ERROR, message) => Err(io::Error::other(String::from_utf8_lossy(&message))),
+            _ => Err(invalid("unexpected reply")),
+        }
+    }
+}
+
+impl RenderDevice for RemoteDevice {
+    fn name(&self) -> String {
+        format!("remote {}", self.addr)
+    }
+
+    fn render_tile(&self, camera: &Camera, scene: &Scene, film_tile: &mut FilmTile) {
+        let mut connection = self.connection.lock().unwrap();
+        if !connection.failed {
+            match self.render_remote(&mut connection, camera, film_tile) {
+                Ok(()) => return,
+                Err(e) => {
+                    log::warn!(
+                        target: "distributed",
+                        "Worker {} failed ({}), rendering its tiles here",
+                        self.addr, e
+                    );
+                    connection.failed = true;
+                    connection.stream = None;
+                }
+            }
+        }
+        // Start the tile again, in case the failure came partway through reading it.
+        *film_tile = FilmTile::new(
+            film_tile.tile,
+            camera.filter,
+            camera.film_width(),
+            camera.film_height(),
+        );
+        camera.render_tile(scene, film_tile);
+    }
+}
+
+/// Takes connections from `RemoteDevice`s on `addr` and renders the tiles they send,
+/// until the listener fails. Each connection gets its own thread and scene. There's no
+/// authentication: anyone who can reach `addr` can have scenes rendered, so only listen
+/// beyond the loopback interface on a trusted network.
+pub fn run_worker(addr: impl ToSocketAddrs) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    let local = listener.local_addr()?;
+    log::info!(target: "distributed", "Render worker listening on {}", local);
+    if !local.ip().is_loopback() {
+        log::warn!(
+            target: "distributed",
+            "Worker reachable beyond this machine; it renders for anyone who connects"
+        );
+    }
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::warn!(target: "distributed", "Connection failed: {}", e);
+                continue;
+            }
+        };
+        thread::spawn(move || {
+            let peer = stream
+                .peer_addr()
+                .map_or_else(|_| "?".to_string(), |a| a.to_string());
+            log::info!(target: "distributed", "Coordinator {} connected", peer);
+            match serve_coordinator(stream) {
+                Ok(tiles) => {
+                    log::info!(target: "distributed", "Coordinator {} done after {} tiles", peer, tiles)
+                }
+                Err(e) => log::warn!(target: "distributed", "Coordinator {} dropped: {}", peer, e),
+            }
+        });
+    }
+    Ok(())
+}
+
+// Answers one coordinator until it hangs up, returning the number of tiles rendered.
+fn serve_coordinator(stream: TcpStream) -> io::Result<usize> {
+    stream.set_nodelay(true)?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid("not a render coordinator"));
+    }
+
+    let mut scene: Option<Scene> = None;
+    let mut camera: Option<Camera> = None;
+    let mut tiles = 0;
+    loop {
+        let (kind, payload) = match read_message(&mut reader) {
+            Ok(message) => message,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(tiles),
+            Err(e) => return Err(e),
+        };
+        let text = || String::from_utf8_lossy(&payload);
+        match kind {
+            SCENE => {
+                let (_, mut built) = SceneFile::from_json(&text())?.build();
+                built.accelerate(Accelerator::default());
+                scene = Some(built);
+            }
+            CAMERA => {
+                let mut settings: Camera = serde_json::from_str(&text())
+                    .map_err(|e| invalid(&format!("camera: {}", e)))?;
+                settings.initialize();
+                camera = Some(settings);
+            }
+            TILE if payload.len() == 16 => {
+                let value =
+                    |i: usize| u32::from_le_bytes(payload[i * 4..i * 4 + 4].try_into().unwrap());
+                let tile = Tile {
+                    x: value(0),
+                    y: value(1),
+                    width: value(2),
+                    height: value(3),
+                };
+                let (Some(scene), Some(camera)) = (&scene, &camera) else {
+                    write_message(&mut writer, ERROR, b"tile sent before the scene and camera")?;
+                    writer.flush()?;
+                    continue;
+                };
+                let inside = |start: u32, size: u32, film: u32| {
+                    size > 0 && start.checked_add(size).is_some_and(|end| end <= film)
+                };
+                if !inside(tile.x, tile.width, camera.film_width())
+                    || !inside(tile.y, tile.height, camera.film_height())
+                {
+                    write_message(&mut writer, ERROR, b"tile outside the film")?;
+                    writer.flush()?;
+                    continue;
+                }
+                let mut film_tile = FilmTile::new(
+                    tile,
+                    camera.filter,
+                    camera.film_width(),
+                    camera.film_height(),
+                );
+                camera.render_tile(scene, &mut film_tile);
+                write_message(&mut writer, SAMPLES, &film_tile.to_bytes())?;
+                writer.flush()?;
+                tiles += 1;
+            }
+            _ => return Err(invalid("unknown message")),
+        }
+    }
+}
+
+fn write_message(out: &mut impl Write, kind: u8, payload: &[u8]) -> io::Result<()> {
+    out.write_all(&[kind])?;
+    out.write_all(&(payload.len() as u64).to_le_bytes())?;
+    out.write_all(payload)
+}
+
+fn read_message(input: &mut impl Read) -> io::Result<(u8, Vec<u8>)> {
+    let mut header = [0; 9];
+    input.read_exact(&mut header)?;
+    let len = u64::from_le_bytes(header[1..].try_into().unwrap());
+    if len > MAX_MESSAGE {
+        return Err(invalid("message too large"));
+    }
+    let mut payload = vec![0; len as usize];
+    input.read_exact(&mut payload)?;
+    Ok((header[0], payload))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
@@ -0,0 +1,125 @@
+use crate::parallel::*;
+use crate::rtweekend::*;
+use std::time::Instant;
+
+/// Render time and memory extrapolated from a sparse grid of probe pixels.
+#[derive(Debug, Clone)]
+pub struct RenderEstimate {
+    pub probe_pixels: u32,
+    pub film_pixels: u64,
+    pub samples_per_pixel: u32,
+    pub threads: usize,
+    /// Average time one thread spends on one pixel, in seconds.
+    pub seconds_per_pixel: f64,
+    /// Slowest probe pixel, in seconds, to spot expensive regions.
+    pub slowest_pixel: f64,
+    pub estimated_seconds: f64,
+    /// Resident memory after building the scene, where the OS reports it.
+    pub scene_bytes: Option<u64>,
+    /// Memory the renderer allocates for the image on top of the scene.
+    pub framebuffer_bytes: u64,
+}
+
+impl RenderEstimate {
+    /// Scene plus framebuffer memory, when the scene's footprint is known.
+    pub fn peak_bytes(&self) -> Option<u64> {
+        self.scene_bytes.map(|scene| scene + self.framebuffer_bytes)
+    }
+
+    pub fn log_summary(&self) {
+        let mib = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+        log::info!(
+            target: "render",
+            "Dry run: {} probe pixels at {} samples each",
+            self.probe_pixels, self.samples_per_pixel
+        );
+        log::info!(
+            target: "render",
+            "  {:.3} ms per pixel (slowest probe {:.3} ms)",
+            1e3 * self.seconds_per_pixel,
+            1e3 * self.slowest_pixel
+        );
+        log::info!(
+            target: "render",
+            "  estimated render time: {} for {} pixels on {} threads",
+            format_duration(self.estimated_seconds),
+            self.film_pixels,
+            self.threads
+        );
+        match self.peak_bytes() {
+            Some(peak) => {
+                log::info!(target: "render", "  estimated peak memory: {:.1} MiB", mib(peak))
+            }
+            None => log::info!(
+                target: "render",
+                "  framebuffer memory: {:.1} MiB (scene memory unknown on this platform)",
+                mib(self.framebuffer_bytes)
+            ),
+        }
+    }
+}
+
+fn format_duration(seconds: f64) -> String {
+    let total = seconds.round() as u64;
+    let (h, m, s) = (total / 3600, total / 60 % 60, total % 60);
+    if h > 0 {
+        format!("{}h {:02}m {:02}s", h, m, s)
+    } else if m > 0 {
+        format!("{}m {:02}s", m, s)
+    } else {
+        format!("{:.1}s", seconds)
+    }
+}
+
+// Resident set size of this process, from /proc on Linux.
+fn resident_memory() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    Some(pages * 4096)
+}
+
+impl Camera {
+    /// Renders a `grid` x `grid` lattice of pixels spread over the film at full quality
+    /// and extrapolates how long the whole frame will take, without producing an image.
+    pub fn estimate(&self, scene: &Scene, grid: u32) -> RenderEstimate {
+        let (width, height) = (self.film_width(), self.film_height());
+        let grid_x = grid.clamp(1, width);
+        let grid_y = grid.clamp(1, height);
+        let probes: Vec<(u32, u32)> = (0..grid_y)
+            .flat_map(|gy| {
+                (0..grid_x).map(move |gx| {
+                    let i = ((gx as f64 + 0.5) * width as f64 / grid_x as f64) as u32;
+                    let j = ((gy as f64 + 0.5) * height as f64 / grid_y as f64) as u32;
+                    (i, j)
+                })
+            })
+            .collect();
+
+        let times: Vec<f64> = probes
+            .par_iter()
+            .map(|&(i, j)| {
+                let start = Instant::now();
+                std::hint::black_box(self.sample_pixel(i, j, scene));
+                start.elapsed().as_secs_f64()
+            })
+            .collect();
+
+        let seconds_per_pixel = times.iter().sum::<f64>() / times.len() as f64;
+        let slowest_pixel = times.iter().cloned().fold(0.0, f64::max);
+        let film_pixels = width as u64 * height as u64;
+        let threads = current_num_threads();
+        // The film, plus at most as much again in rendered tiles waiting to be copied into it.
+        let framebuffer_bytes = 2 * film_pixels * std::mem::size_of::<Color>() as u64;
+        RenderEstimate {
+            probe_pixels: probes.len() as u32,
+            film_pixels,
+            samples_per_pixel: self.samples_per_pixel,
+            threads,
+            seconds_per_pixel,
+            slowest_pixel,
+            estimated_seconds: seconds_per_pixel * film_pixels as f64 / threads as f64,
+            scene_bytes: resident_memory(),
+            framebuffer_bytes,
+        }
+    }
+}
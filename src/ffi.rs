@@ -0,0 +1,213 @@
+use crate::rtweekend::*;
+use crate::scene_file::{BackgroundDesc, MAIN_CAMERA, MaterialDesc, SceneFile, ShapeDesc};
+use std::panic::{self, AssertUnwindSafe};
+
+// Status codes, as in include/rtw.h.
+const RTW_OK: i32 = 0;
+const RTW_INVALID_ARGUMENT: i32 = -1;
+const RTW_BUFFER_TOO_SMALL: i32 = -2;
+const RTW_RENDER_FAILED: i32 = -3;
+
+/// Scene being put together through the C API: a `SceneFile` under the hood, built
+/// when it's rendered. Handles come from `rtw_create_scene` and go back through
+/// `rtw_destroy_scene`.
+pub struct RtwScene {
+    file: SceneFile,
+}
+
+/// A new scene with no objects, the default camera and the default sky.
+#[unsafe(no_mangle)]
+pub extern "C" fn rtw_create_scene() -> *mut RtwScene {
+    let file = SceneFile::new(Camera::builder().build());
+    Box::into_raw(Box::new(RtwScene { file }))
+}
+
+/// # Safety
+/// `scene` must be null or come from `rtw_create_scene` and not have been destroyed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rtw_destroy_scene(scene: *mut RtwScene) {
+    if !scene.is_null() {
+        drop(unsafe { Box::from_raw(scene) });
+    }
+}
+
+// Adds `material` and returns its index, or RTW_INVALID_ARGUMENT for a null scene.
+unsafe fn add_material(scene: *mut RtwScene, material: MaterialDesc) -> i32 {
+    match unsafe { scene.as_mut() } {
+        Some(scene) => scene.file.add_material(material) as i32,
+        None => RTW_INVALID_ARGUMENT,
+    }
+}
+
+/// Adds a diffuse material, returning its index for `rtw_add_sphere`.
+///
+/// # Safety
+/// As for `rtw_destroy_scene`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rtw_add_lambertian(scene: *mut RtwScene, r: f64, g: f64, b: f64) -> i32 {
+    let albedo = Color::new(r, g, b);
+    unsafe { add_material(scene, MaterialDesc::Lambertian { albedo }) }
+}
+
+/// Adds a metal, `fuzz` from 0 (a mirror) to 1.
+///
+/// # Safety
+/// As for `rtw_destroy_scene`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rtw_add_metal(
+    scene: *mut RtwScene,
+    r: f64,
+    g: f64,
+    b: f64,
+    fuzz: f64,
+) -> i32 {
+    let albedo = Color::new(r, g, b);
+    unsafe { add_material(scene, MaterialDesc::Metal { albedo, fuzz }) }
+}
+
+/// Adds glass or another clear material with index of refraction `ior`.
+///
+/// # Safety
+/// As for `rtw_destroy_scene`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rtw_add_dielectric(scene: *mut RtwScene, ior: f64) -> i32 {
+    unsafe { add_material(scene, MaterialDesc::Dielectric { ior }) }
+}
+
+/// Adds a light emitting `(r, g, b)` from its front.
+///
+/// # Safety
+/// As for `rtw_destroy_scene`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rtw_add_light(scene: *mut RtwScene, r: f64, g: f64, b: f64) -> i32 {
+    let emit = Color::new(r, g, b);
+    let two_sided = false;
+    unsafe { add_material(scene, MaterialDesc::DiffuseLight { emit, two_sided }) }
+}
+
+/// Adds a sphere made of `material`, an index from one of the `rtw_add_*` material
+/// functions.
+///
+/// # Safety
+/// As for `rtw_destroy_scene`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rtw_add_sphere(
+    scene: *mut RtwScene,
+    x: f64,
+    y: f64,
+    z: f64,
+    radius: f64,
+    material: i32,
+) -> i32 {
+    let Some(scene) = (unsafe { scene.as_mut() }) else {
+        return RTW_INVALID_ARGUMENT;
+    };
+    let material = match usize::try_from(material) {
+        Ok(index) if index < scene.file.materials.len() => index,
+        _ => return RTW_INVALID_ARGUMENT,
+    };
+    if radius.is_nan() || radius <= 0.0 {
+        return RTW_INVALID_ARGUMENT;
+    }
+    let shape = ShapeDesc::Sphere {
+        center: Point3::new(x, y, z),
+        radius,
+        motion: Vec3::zero(),
+    };
+    match scene.file.add(shape, material) {
+        Ok(()) => RTW_OK,
+        Err(_) => RTW_INVALID_ARGUMENT,
+    }
+}
+
+/// Points the camera from one point at another, with a vertical field of view in
+/// degrees.
+///
+/// # Safety
+/// As for `rtw_destroy_scene`.
+#[unsafe(no_mangle)]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn rtw_set_camera(
+    scene: *mut RtwScene,
+    from_x: f64,
+    from_y: f64,
+    from_z: f64,
+    at_x: f64,
+    at_y: f64,
+    at_z: f64,
+    vfov: f64,
+) -> i32 {
+    let Some(scene) = (unsafe { scene.as_mut() }) else {
+        return RTW_INVALID_ARGUMENT;
+    };
+    let camera = &mut scene.file.camera;
+    camera.lookfrom = Point3::new(from_x, from_y, from_z);
+    camera.lookat = Point3::new(at_x, at_y, at_z);
+    camera.vfov = vfov;
+    camera.focus_dist = (camera.lookfrom - camera.lookat).length();
+    RTW_OK
+}
+
+/// Replaces the sky with a flat color; black for scenes lit only by their lights.
+///
+/// # Safety
+/// As for `rtw_destroy_scene`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rtw_set_background(scene: *mut RtwScene, r: f64, g: f64, b: f64) -> i32 {
+    let Some(scene) = (unsafe { scene.as_mut() }) else {
+        return RTW_INVALID_ARGUMENT;
+    };
+    let color = Color::new(r, g, b);
+    scene.file.background = BackgroundDesc::Gradient {
+        bottom: color,
+        top: color,
+    };
+    RTW_OK
+}
+
+/// Renders the scene `width` x `height` with `samples` per pixel into `buffer` as
+/// 8-bit RGBA, row-major with the top row first, so it needs `width * height * 4`
+/// bytes. Returns 0, or a negative `RTW_*` status.
+///
+/// # Safety
+/// As for `rtw_destroy_scene`, and `buffer` must point to `buffer_len` writable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn rtw_render_to_buffer(
+    scene: *const RtwScene,
+    width: u32,
+    height: u32,
+    samples: u32,
+    buffer: *mut u8,
+    buffer_len: usize,
+) -> i32 {
+    let Some(scene) = (unsafe { scene.as_ref() }) else {
+        return RTW_INVALID_ARGUMENT;
+    };
+    if width == 0 || height == 0 || samples == 0 || buffer.is_null() {
+        return RTW_INVALID_ARGUMENT;
+    }
+    if buffer_len < width as usize * height as usize * 4 {
+        return RTW_BUFFER_TOO_SMALL;
+    }
+
+    // A panic mustn't unwind into the caller's frames.
+    let rendered = panic::catch_unwind(AssertUnwindSafe(|| {
+        let mut file = scene.file.clone();
+        file.camera.image_width = width;
+        file.camera.aspect_ratio = width as f64 / height as f64;
+        file.camera.pixel_aspect_ratio = 1.0;
+        file.camera.overscan = 0.0;
+        file.camera.samples_per_pixel = samples;
+        let camera = file.camera(MAIN_CAMERA)?;
+        let (_, mut built) = file.build();
+        built.accelerate(Accelerator::default());
+        Some(to_rgba8(&camera.render_pixels(&built)))
+    }));
+    match rendered {
+        Ok(Some(rgba)) if rgba.len() == width as usize * height as usize * 4 => {
+            unsafe { std::ptr::copy_nonoverlapping(rgba.as_ptr(), buffer, rgba.len()) };
+            RTW_OK
+        }
+        _ => RTW_RENDER_FAILED,
+    }
+}
@@ -0,0 +1,446 @@
+use crate::rtweekend::*;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Start of a film saved with `Film::write_to`.
+const FILM_MAGIC: &[u8; 8] = b"RTWFILM1";
+
+/// Pixel reconstruction filter: how much a sample counts towards each pixel around it,
+/// by its offset from the pixel's center (in pixels). All of them are separable.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Filter {
+    /// Equal weight within `radius`. With 0.5 every sample counts for its own pixel
+    /// only, the plain average.
+    Box { radius: f64 },
+    /// Weight falling off linearly to zero at `radius`.
+    Tent { radius: f64 },
+    /// Gaussian of falloff `alpha`, shifted down to reach zero at `radius`.
+    Gaussian { radius: f64, alpha: f64 },
+    /// Mitchell-Netravali cubic. B = C = 1/3 is the usual compromise between blurring
+    /// and ringing; its small negative lobes sharpen edges.
+    Mitchell { radius: f64, b: f64, c: f64 },
+    /// Blackman-Harris window: smooth like a Gaussian with a tighter central lobe.
+    BlackmanHarris { radius: f64 },
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self::Box { radius: 0.5 }
+    }
+}
+
+impl Filter {
+    /// Filters with their usual radii, by name: `box`, `tent`, `gaussian`, `mitchell`
+    /// or `blackman-harris`.
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "box" => Some(Self::Box { radius: 0.5 }),
+            "tent" => Some(Self::Tent { radius: 1.0 }),
+            "gaussian" => Some(Self::Gaussian {
+                radius: 1.5,
+                alpha: 2.0,
+            }),
+            "mitchell" => Some(Self::Mitchell {
+                radius: 2.0,
+                b: 1.0 / 3.0,
+                c: 1.0 / 3.0,
+            }),
+            "blackman-harris" => Some(Self::BlackmanHarris { radius: 2.0 }),
+            _ => None,
+        }
+    }
+
+    /// The same filter with its support scaled to `radius`.
+    pub fn with_radius(self, radius: f64) -> Self {
+        match self {
+            Self::Box { .. } => Self::Box { radius },
+            Self::Tent { .. } => Self::Tent { radius },
+            Self::Gaussian { radius: r, alpha } => Self::Gaussian {
+                radius,
+                // Keep the shape, stretched to the new radius.
+                alpha: alpha * (r / radius) * (r / radius),
+            },
+            Self::Mitchell { b, c, .. } => Self::Mitchell { radius, b, c },
+            Self::BlackmanHarris { .. } => Self::BlackmanHarris { radius },
+        }
+    }
+
+    /// Distance from a pixel's center beyond which samples don't count for it.
+    pub fn radius(&self) -> f64 {
+        match *self {
+            Self::Box { radius }
+            | Self::Tent { radius }
+            | Self::Gaussian { radius, .. }
+            | Self::Mitchell { radius, .. }
+            | Self::BlackmanHarris { radius } => radius,
+        }
+    }
+
+    /// Weight of a sample `dx`, `dy` pixels off a pixel's center.
+    pub fn evaluate(&self, dx: f64, dy: f64) -> f64 {
+        match *self {
+            // Half-open, so a sample on the border between pixels counts once.
+            Self::Box { radius } => {
+                let inside = |d: f64| d >= -radius && d < radius;
+                if inside(dx) && inside(dy) { 1.0 } else { 0.0 }
+            }
+            _ => self.evaluate_1d(dx) * self.evaluate_1d(dy),
+        }
+    }
+
+    fn evaluate_1d(&self, d: f64) -> f64 {
+        let d = d.abs();
+        match *self {
+            Self::Box { .. } => 1.0,
+            Self::Tent { radius } => (1.0 - d / radius).max(0.0),
+            Self::Gaussian { radius, alpha } => {
+                ((-alpha * d * d).exp() - (-alpha * radius * radius).exp()).max(0.0)
+            }
+            Self::Mitchell { radius, b, c } => {
+                // The cubic is defined over [-2, 2].
+                let x = 2.0 * d / radius;
+                if x >= 2.0 {
+                    0.0
+                } else if x >= 1.0 {
+                    ((-b - 6.0 * c) * x * x * x
+                        + (6.0 * b + 30.0 * c) * x * x
+                        + (-12.0 * b - 48.0 * c) * x
+                        + (8.0 * b + 24.0 * c))
+                        / 6.0
+                } else {
+                    ((12.0 - 9.0 * b - 6.0 * c) * x * x * x
+                        + (-18.0 + 12.0 * b + 6.0 * c) * x * x
+                        + (6.0 - 2.0 * b))
+                        / 6.0
+                }
+            }
+            Self::BlackmanHarris { radius } => {
+                if d >= radius {
+                    return 0.0;
+                }
+                let x = 2.0 * PI * (d / (2.0 * radius) + 0.5);
+                0.35875 - 0.48829 * x.cos() + 0.14128 * (2.0 * x).cos() - 0.01168 * (3.0 * x).cos()
+            }
+        }
+    }
+
+    // Pixels (inclusive range along one axis) whose centers are within the radius of
+    // film coordinate `x`, clamped to `0..size`.
+    fn span(&self, x: f64, size: u32) -> (i64, i64) {
+        let r = self.radius();
+        let lo = (x - 0.5 - r).ceil().max(0.0) as i64;
+        let hi = ((x - 0.5 + r).floor() as i64).min(size as i64 - 1);
+        (lo, hi)
+    }
+}
+
+/// The image being rendered: filter-weighted sums of samples per pixel, plus splats
+/// from renderers that don't know which pixel a sample lands on until they've traced
+/// it (light tracing). Any number of threads can add to it at once. Tiles are rendered
+/// into a `FilmTile` and merged, so threads only contend on the border pixels the
+/// filter shares between tiles.
+pub struct Film {
+    pub width: u32,
+    pub height: u32,
+    pub filter: Filter,
+    // Color channels and weight as f64 bits, added to with compare-and-swap.
+    sums: Vec<[AtomicU64; 4]>,
+    splats: Vec<[AtomicU64; 3]>,
+    // Tiles merged so far, which a render resumed from this film skips.
+    finished: Mutex<Vec<Tile>>,
+}
+
+impl Film {
+    pub fn new(width: u32, height: u32, filter: Filter) -> Self {
+        let n = (width * height) as usize;
+        Self {
+            width,
+            height,
+            filter,
+            sums: (0..n).map(|_| Default::default()).collect(),
+            splats: (0..n).map(|_| Default::default()).collect(),
+            finished: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Adds a sample at film position (`x`, `y`), in pixels from the top-left corner,
+    /// to every pixel the filter reaches.
+    pub fn add_sample(&self, x: f64, y: f64, color: Color) {
+        let (x0, x1) = self.filter.span(x, self.width);
+        let (y0, y1) = self.filter.span(y, self.height);
+        for j in y0..=y1 {
+            for i in x0..=x1 {
+                let weight = self
+                    .filter
+                    .evaluate(x - (i as f64 + 0.5), y - (j as f64 + 0.5));
+                if weight != 0.0 {
+                    let sum = &self.sums[j as usize * self.width as usize + i as usize];
+                    let weighted = color * weight;
+                    for (cell, value) in
+                        sum.iter().zip([weighted.r, weighted.g, weighted.b, weight])
+                    {
+                        atomic_add(cell, value);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Adds `color` to the pixel containing film position (`x`, `y`), unfiltered and
+    /// unweighted. Positions off the film are dropped.
+    pub fn add_splat(&self, x: f64, y: f64, color: Color) {
+        if x < 0.0 || y < 0.0 || x >= self.width as f64 || y >= self.height as f64 {
+            return;
+        }
+        let idx = y as usize * self.width as usize + x as usize;
+        for (cell, value) in self.splats[idx].iter().zip([color.r, color.g, color.b]) {
+            atomic_add(cell, value);
+        }
+    }
+
+    /// An empty tile covering `tile` and the border its samples can reach.
+    pub fn tile(&self, tile: Tile) -> FilmTile {
+        FilmTile::new(tile, self.filter, self.width, self.height)
+    }
+
+    /// Adds a finished tile's samples to the film.
+    pub fn merge_tile(&self, tile: FilmTile) {
+        self.finished.lock().unwrap().push(tile.tile);
+        for (idx, (sum, weight)) in tile.sums.iter().zip(&tile.weights).enumerate() {
+            if *weight == 0.0 {
+                continue;
+            }
+            let i = tile.x0 + idx as u32 % tile.width;
+            let j = tile.y0 + idx as u32 / tile.width;
+            let cells = &self.sums[(j * self.width + i) as usize];
+            for (cell, value) in cells.iter().zip([sum.r, sum.g, sum.b, *weight]) {
+                atomic_add(cell, value);
+            }
+        }
+    }
+
+    /// The developed image in row-major order, top row first: each pixel's weighted
+    /// average plus its splats times `splat_scale`.
+    pub fn pixels(&self, splat_scale: f64) -> Vec<Color> {
+        let load = |c: &AtomicU64| f64::from_bits(c.load(Ordering::Relaxed));
+        self.sums
+            .iter()
+            .zip(&self.splats)
+            .map(|([r, g, b, w], [sr, sg, sb])| {
+                let weight = load(w);
+                let average = if weight != 0.0 {
+                    Color::new(load(r), load(g), load(b)) / weight
+                } else {
+                    Color::BLACK
+                };
+                average + Color::new(load(sr), load(sg), load(sb)) * splat_scale
+            })
+            .collect()
+    }
+
+    /// Tiles merged into the film so far, in the order they finished.
+    pub fn finished_tiles(&self) -> Vec<Tile> {
+        self.finished.lock().unwrap().clone()
+    }
+
+    /// Saves the film's sums, splats and finished tiles, to carry on with later through
+    /// `read_from`.
+    pub fn write_to<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let finished = self.finished_tiles();
+        out.write_all(FILM_MAGIC)?;
+        for value in [self.width, self.height, finished.len() as u32] {
+            out.write_all(&value.to_le_bytes())?;
+        }
+        for tile in &finished {
+            for value in [tile.x, tile.y, tile.width, tile.height] {
+                out.write_all(&value.to_le_bytes())?;
+            }
+        }
+        for (sum, splat) in self.sums.iter().zip(&self.splats) {
+            for cell in sum.iter().chain(splat) {
+                out.write_all(&cell.load(Ordering::Relaxed).to_le_bytes())?;
+            }
+        }
+        out.flush()
+    }
+
+    /// A `width` x `height` film saved with `write_to`, to be rendered into with `filter`,
+    /// which should be the one it was started with. A film of any other size is refused
+    /// before anything is allocated for it.
+    pub fn read_from<R: Read>(
+        mut input: R,
+        width: u32,
+        height: u32,
+        filter: Filter,
+    ) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut magic = [0u8; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != FILM_MAGIC {
+            return Err(invalid("not a saved film"));
+        }
+        let mut read_u32 = || -> io::Result<u32> {
+            let mut bytes = [0u8; 4];
+            input.read_exact(&mut bytes)?;
+            Ok(u32::from_le_bytes(bytes))
+        };
+        let (saved_width, saved_height) = (read_u32()?, read_u32()?);
+        if (saved_width, saved_height) != (width, height) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "saved film is {}x{}, not {}x{}",
+                    saved_width, saved_height, width, height
+                ),
+            ));
+        }
+        let count = read_u32()?;
+        let inside = |start: u32, size: u32, film: u32| {
+            start.checked_add(size).is_some_and(|end| end <= film)
+        };
+        let mut finished = Vec::new();
+        for _ in 0..count {
+            let tile = Tile {
+                x: read_u32()?,
+                y: read_u32()?,
+                width: read_u32()?,
+                height: read_u32()?,
+            };
+            if !inside(tile.x, tile.width, width) || !inside(tile.y, tile.height, height) {
+                return Err(invalid("saved film has a tile off its edge"));
+            }
+            finished.push(tile);
+        }
+
+        let film = Self::new(width, height, filter);
+        let mut bytes = [0u8; 8];
+        for (sum, splat) in film.sums.iter().zip(&film.splats) {
+            for cell in sum.iter().chain(splat) {
+                input.read_exact(&mut bytes)?;
+                cell.store(u64::from_le_bytes(bytes), Ordering::Relaxed);
+            }
+        }
+        *film.finished.lock().unwrap() = finished;
+        Ok(film)
+    }
+}
+
+/// One thread's share of the film: the samples of one `Tile`, kept apart until
+/// `Film::merge_tile`. It covers the tile plus the border its filter reaches into.
+pub struct FilmTile {
+    pub tile: Tile,
+    filter: Filter,
+    x0: u32,
+    y0: u32,
+    width: u32,
+    height: u32,
+    sums: Vec<Color>,
+    weights: Vec<f64>,
+}
+
+impl FilmTile {
+    /// An empty tile covering `tile` of a `width` x `height` film, and the border its
+    /// samples can reach; the same as `Film::tile` without needing the film.
+    pub fn new(tile: Tile, filter: Filter, width: u32, height: u32) -> Self {
+        let reach = filter.radius().ceil() as u32;
+        let x0 = tile.x.saturating_sub(reach);
+        let y0 = tile.y.saturating_sub(reach);
+        let x1 = (tile.x + tile.width + reach).min(width);
+        let y1 = (tile.y + tile.height + reach).min(height);
+        let n = ((x1 - x0) * (y1 - y0)) as usize;
+        Self {
+            tile,
+            filter,
+            x0,
+            y0,
+            width: x1 - x0,
+            height: y1 - y0,
+            sums: vec![Color::BLACK; n],
+            weights: vec![0.0; n],
+        }
+    }
+
+    /// `Film::add_sample` for a sample inside this tile.
+    pub fn add_sample(&mut self, x: f64, y: f64, color: Color) {
+        let (x0, x1) = self.filter.span(x - self.x0 as f64, self.width);
+        let (y0, y1) = self.filter.span(y - self.y0 as f64, self.height);
+        for j in y0..=y1 {
+            for i in x0..=x1 {
+                let dx = x - (self.x0 as f64 + i as f64 + 0.5);
+                let dy = y - (self.y0 as f64 + j as f64 + 0.5);
+                let weight = self.filter.evaluate(dx, dy);
+                if weight != 0.0 {
+                    let idx = j as usize * self.width as usize + i as usize;
+                    self.sums[idx] += color * weight;
+                    self.weights[idx] += weight;
+                }
+            }
+        }
+    }
+
+    /// An empty tile of the same shape, e.g. for one thread's share of this one's samples.
+    pub fn empty_copy(&self) -> Self {
+        Self {
+            sums: vec![Color::BLACK; self.sums.len()],
+            weights: vec![0.0; self.weights.len()],
+            ..*self
+        }
+    }
+
+    /// Adds the samples of `other`, a tile made the same way, to this one's.
+    pub fn add_tile(&mut self, other: &FilmTile) {
+        debug_assert!(self.tile == other.tile && self.weights.len() == other.weights.len());
+        for (sum, s) in self.sums.iter_mut().zip(&other.sums) {
+            *sum += *s;
+        }
+        for (weight, w) in self.weights.iter_mut().zip(&other.weights) {
+            *weight += w;
+        }
+    }
+
+    /// The tile's sums and weights as little-endian floats, to send to another process.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.weights.len() * 32);
+        for (sum, weight) in self.sums.iter().zip(&self.weights) {
+            for value in [sum.r, sum.g, sum.b, *weight] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    /// Replaces the tile's samples with ones from `to_bytes` on a tile made the same way.
+    pub fn read_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        if bytes.len() != self.weights.len() * 32 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "film tile data doesn't fit the tile",
+            ));
+        }
+        let pixels = bytes.chunks_exact(32).map(|pixel| {
+            let value = |i: usize| f64::from_le_bytes(pixel[i * 8..i * 8 + 8].try_into().unwrap());
+            (Color::new(value(0), value(1), value(2)), value(3))
+        });
+        for ((sum, weight), (s, w)) in self.sums.iter_mut().zip(&mut self.weights).zip(pixels) {
+            *sum = s;
+            *weight = w;
+        }
+        Ok(())
+    }
+}
+
+fn atomic_add(cell: &AtomicU64, value: f64) {
+    if value == 0.0 {
+        return;
+    }
+    let mut current = cell.load(Ordering::Relaxed);
+    loop {
+        let next = (f64::from_bits(current) + value).to_bits();
+        match cell.compare_exchange_weak(current, next, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => return,
+            Err(actual) => current = actual,
+        }
+    }
+}
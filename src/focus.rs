@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+
+/// Rectangle of the film, in normalized coordinates (0..1, origin top-left), with its own focus distance.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct FocusRegion {
+    pub x0: f64,
+    pub y0: f64,
+    pub x1: f64,
+    pub y1: f64,
+    pub focus_dist: f64,
+}
+
+impl FocusRegion {
+    pub fn new(x0: f64, y0: f64, x1: f64, y1: f64, focus_dist: f64) -> Self {
+        Self {
+            x0,
+            y0,
+            x1,
+            y1,
+            focus_dist,
+        }
+    }
+
+    fn contains(&self, x: f64, y: f64) -> bool {
+        self.x0 <= x && x <= self.x1 && self.y0 <= y && y <= self.y1
+    }
+}
+
+/// Per-pixel focus distance overrides, for shots where subjects at different depths
+/// must all be sharp. The lens aperture is unchanged; only the plane of focus moves.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FocusMap {
+    /// Split-diopter: film positions on the far side of a straight line focus at `focus_dist`,
+    /// the rest at the camera's own focus distance. The line sits `offset` from the frame
+    /// center (in normalized film units) and `angle` degrees from vertical.
+    SplitDiopter {
+        offset: f64,
+        angle: f64,
+        focus_dist: f64,
+    },
+    /// Rectangular regions; the first one containing the pixel wins.
+    Regions(Vec<FocusRegion>),
+}
+
+impl FocusMap {
+    pub fn split_diopter(offset: f64, angle: f64, focus_dist: f64) -> Self {
+        FocusMap::SplitDiopter {
+            offset,
+            angle,
+            focus_dist,
+        }
+    }
+
+    /// Focus distance at normalized film position (x, y), or `None` to keep the default.
+    pub fn focus_dist_at(&self, x: f64, y: f64) -> Option<f64> {
+        match self {
+            FocusMap::SplitDiopter {
+                offset,
+                angle,
+                focus_dist,
+            } => {
+                let a = angle.to_radians();
+                let side = (x - 0.5) * a.cos() + (y - 0.5) * a.sin() - offset;
+                (side > 0.0).then_some(*focus_dist)
+            }
+            FocusMap::Regions(regions) => regions
+                .iter()
+                .find(|region| region.contains(x, y))
+                .map(|region| region.focus_dist),
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use crate::audit::audit_materials;
+use crate::rtweekend::*;
+
+const FURNACE_WIDTH: u32 = 24;
+const FURNACE_SAMPLES: u32 = 128;
+/// Deep enough that light bouncing around inside glass almost always gets out.
+const FURNACE_DEPTH: u32 = 64;
+/// Allowed deviation from 1 beyond the sampling noise, for rays still inside a
+/// dielectric at `FURNACE_DEPTH` and the like.
+const FURNACE_TOLERANCE: f64 = 0.005;
+/// White materials that should send back all the light they receive, so any shortfall
+/// is energy the model loses. The others may lose some by design, e.g. microfacet
+/// models that leave out multiple scattering, and are only checked for gains.
+const LOSSLESS: [&str; 3] = ["lambertian", "dielectric", "metal fuzz"];
+
+/// Radiance of one material in the white furnace.
+#[derive(Debug, Clone)]
+pub struct FurnaceEntry {
+    pub name: String,
+    /// Average over the sphere; 1 for a material that neither gains nor loses energy.
+    pub radiance: Color,
+    /// Standard error of the brightest channel's average.
+    pub std_error: f64,
+    /// Whether the material should conserve energy exactly.
+    pub lossless: bool,
+}
+
+impl FurnaceEntry {
+    fn margin(&self) -> f64 {
+        (4.0 * self.std_error).max(FURNACE_TOLERANCE)
+    }
+
+    pub fn gains_energy(&self) -> bool {
+        self.radiance.max_component() > 1.0 + self.margin()
+    }
+
+    pub fn loses_energy(&self) -> bool {
+        self.lossless && self.radiance.min_component() < 1.0 - self.margin()
+    }
+}
+
+/// A unit sphere of `mat` filling the view, under a uniform white sky: the white
+/// furnace. Light arriving at the camera has bounced off or through the sphere any
+/// number of times, so unlike `audit::directional_albedo` this checks whole paths
+/// through the integrator, multiple bounces inside glass included.
+pub fn furnace_scene(mat: Arc<dyn Material + Send + Sync>) -> (Camera, Scene) {
+    let camera = Camera::builder()
+        .aspect_ratio(1.0)
+        .image_width(FURNACE_WIDTH)
+        .samples_per_pixel(FURNACE_SAMPLES)
+        .max_depth(FURNACE_DEPTH)
+        // Narrow enough that every pixel lands on the sphere.
+        .vfov(18.0)
+        .lookfrom(Point3::new(0.0, 0.0, 4.0))
+        .lookat(Point3::zero())
+        .build();
+    let mut world = HittableList::new();
+    world.add(Arc::new(Sphere::new(Point3::zero(), 1.0, mat)));
+    let mut scene = Scene::new(world);
+    let white = Color::new(1.0, 1.0, 1.0);
+    scene.background = Arc::new(GradientBackground::new(white, white));
+    (camera, scene)
+}
+
+/// Renders `mat` in the furnace and averages the picture.
+pub fn measure(name: &str, mat: Arc<dyn Material + Send + Sync>, lossless: bool) -> FurnaceEntry {
+    let (camera, scene) = furnace_scene(mat);
+    let pixels = TileScheduler::cpu_only().render(&camera, &scene);
+    let n = pixels.len() as f64;
+    let radiance = pixels.iter().fold(Color::BLACK, |sum, &c| sum + c) / n;
+    let max = radiance.max_component();
+    let variance = pixels
+        .iter()
+        .map(|c| (c.max_component() - max).powi(2))
+        .sum::<f64>()
+        / n;
+    FurnaceEntry {
+        name: name.to_string(),
+        radiance,
+        std_error: (variance / n).sqrt(),
+        lossless,
+    }
+}
+
+/// Renders every built-in material in the white furnace and logs each one's radiance
+/// under the `render` target, warning about the ones that gain energy, or lose it when
+/// they shouldn't. Returns the entries that failed.
+pub fn run() -> Vec<FurnaceEntry> {
+    log::info!(
+        target: "render",
+        "White furnace: {}x{} at {} spp, depth {}",
+        FURNACE_WIDTH, FURNACE_WIDTH, FURNACE_SAMPLES, FURNACE_DEPTH
+    );
+    let mut failures = Vec::new();
+    for (name, mat) in audit_materials() {
+        let lossless = LOSSLESS.iter().any(|prefix| name.starts_with(prefix));
+        let entry = measure(&name, mat, lossless);
+        let verdict = if entry.gains_energy() {
+            "GAINS ENERGY"
+        } else if entry.loses_energy() {
+            "LOSES ENERGY"
+        } else {
+            "ok"
+        };
+        let level = if verdict == "ok" {
+            log::Level::Info
+        } else {
+            log::Level::Warn
+        };
+        log::log!(
+            target: "render",
+            level,
+            "{:<48} radiance ({:.4}, {:.4}, {:.4}) +- {:.4} {}",
+            name, entry.radiance.r, entry.radiance.g, entry.radiance.b, entry.std_error, verdict
+        );
+        if verdict != "ok" {
+            failures.push(entry);
+        }
+    }
+    failures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn white_lambertian_conserves_energy() {
+        let white = Color::new(1.0, 1.0, 1.0);
+        let entry = measure("lambertian", Arc::new(Lambertian::new(white)), true);
+        assert!(!entry.gains_energy(), "radiance {:?}", entry.radiance);
+        assert!(!entry.loses_energy(), "radiance {:?}", entry.radiance);
+    }
+}
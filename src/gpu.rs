@@ -0,0 +1,424 @@
+use crate::rtweekend::*;
+use crate::scene_file::{BackgroundDesc, MaterialDesc, SceneFile, ShapeDesc};
+use rand::Rng;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use wgpu::util::DeviceExt;
+
+const SHADER: &str = include_str!("gpu.wgsl");
+
+/// Most camera rays traced per dispatch; tiles with more samples take several.
+const BATCH: usize = 1 << 18;
+/// Invocations per workgroup, as declared in the shader.
+const WORKGROUP: u32 = 64;
+/// Most spheres in a BVH leaf.
+const LEAF_SIZE: usize = 4;
+
+// Material kinds, numbered as in the shader.
+const LAMBERTIAN: u32 = 0;
+const METAL: u32 = 1;
+const DIELECTRIC: u32 = 2;
+const LIGHT: u32 = 3;
+
+/// Renders tiles on a GPU with a wgpu compute shader, e.g. as well as the CPU with
+/// `Camera::with_device`.
+///
+/// The shader traces spheres, in a BVH built when the scene is uploaded, made of the
+/// Lambertian, metal, dielectric and light materials, under a gradient background.
+/// `new` turns any other scene down, and fails where there's no GPU, so the render
+/// can go ahead on the CPU alone. Camera rays are generated on the CPU, so every
+/// camera setting applies; the GPU follows them as the naive path tracer does, finding
+/// lights only by hitting them. Spectral renders, the diagnostic integrators and
+/// `Camera::with_integrator` are rendered on the CPU, as is every tile after the GPU
+/// fails. The GPU draws its own random numbers from the seed, so a seeded render only
+/// repeats exactly if each tile goes to the same device as before.
+pub struct GpuDevice {
+    adapter: String,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    spheres: wgpu::Buffer,
+    nodes: wgpu::Buffer,
+    background: (Color, Color),
+    failed: AtomicBool,
+}
+
+// A sphere with its material, as the shader reads it.
+struct GpuSphere {
+    center: Point3,
+    radius: f64,
+    kind: u32,
+    albedo: Color,
+    /// Fuzz, index of refraction, or 1 for a two-sided light.
+    param: f64,
+}
+
+impl GpuSphere {
+    fn bounds(&self) -> Aabb {
+        let r = Vec3::new(self.radius, self.radius, self.radius);
+        Aabb::from_points(self.center - r, self.center + r)
+    }
+}
+
+// A BVH node, as the shader reads it: a leaf's first sphere and count, or an inner
+// node's second child and 0.
+struct GpuNode {
+    bounds: Aabb,
+    offset: u32,
+    count: u32,
+}
+
+// A camera sample traced on the GPU, and where it lands on the film.
+struct GpuSample {
+    i: u32,
+    j: u32,
+    x: f64,
+    y: f64,
+    weight: Color,
+}
+
+fn unsupported(what: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("gpu: can't trace {}", what),
+    )
+}
+
+// The scene's objects as spheres, or an error naming the first thing that isn't one
+// the shader can trace.
+fn gpu_spheres(scene: &SceneFile) -> io::Result<Vec<GpuSphere>> {
+    scene
+        .objects
+        .iter()
+        .map(|object| {
+            let ShapeDesc::Sphere {
+                center,
+                radius,
+                motion,
+            } = &object.shape
+            else {
+                return Err(unsupported("a shape other than a sphere"));
+            };
+            if motion.length_squared() > 0.0 {
+                return Err(unsupported("a moving sphere"));
+            }
+            if object.sides != Sides::Both {
+                return Err(unsupported("a one-sided sphere"));
+            }
+            let material = scene.material(&object.material).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("scene: unknown {}", object.material),
+                )
+            })?;
+            let (kind, albedo, param) = match material {
+                MaterialDesc::Lambertian { albedo } => (LAMBERTIAN, *albedo, 0.0),
+                MaterialDesc::Metal { albedo, fuzz } => (METAL, *albedo, fuzz.clamp(0.0, 1.0)),
+                MaterialDesc::Dielectric { ior } => (DIELECTRIC, Color::new(1.0, 1.0, 1.0), *ior),
+                MaterialDesc::DiffuseLight { emit, two_sided } => {
+                    (LIGHT, *emit, if *two_sided { 1.0 } else { 0.0 })
+                }
+                _ => return Err(unsupported("a material other than the basic four")),
+            };
+            Ok(GpuSphere {
+                center: *center,
+                radius: *radius,
+                kind,
+                albedo,
+                param,
+            })
+        })
+        .collect()
+}
+
+// Appends the node over `spheres`, which start at `first` in the final order, and its
+// subtree, each inner node followed by its first child. Sorts `spheres` into that order.
+fn build_bvh(spheres: &mut [GpuSphere], first: usize, nodes: &mut Vec<GpuNode>) {
+    let bounds = spheres
+        .iter()
+        .fold(Aabb::EMPTY, |acc, s| acc.union(&s.bounds()));
+    let index = nodes.len();
+    nodes.push(GpuNode {
+        bounds,
+        offset: first as u32,
+        count: spheres.len() as u32,
+    });
+    if spheres.len() <= LEAF_SIZE {
+        return;
+    }
+    // Halve the spheres along the axis their centers spread furthest on.
+    let centers = spheres.iter().fold(Aabb::EMPTY, |acc, s| {
+        acc.union(&Aabb::from_points(s.center, s.center))
+    });
+    let axis = centers.longest_axis();
+    spheres.sort_by(|a, b| a.center[axis].total_cmp(&b.center[axis]));
+    let (left, right) = spheres.split_at_mut(spheres.len() / 2);
+    let half = left.len();
+    build_bvh(left, first, nodes);
+    nodes[index].offset = nodes.len() as u32;
+    nodes[index].count = 0;
+    build_bvh(right, first + half, nodes);
+}
+
+// Appends 32-bit words, laid out as the shader's structs expect.
+fn push_f32s(bytes: &mut Vec<u8>, values: &[f64]) {
+    for &value in values {
+        bytes.extend_from_slice(&(value as f32).to_le_bytes());
+    }
+}
+
+fn push_u32s(bytes: &mut Vec<u8>, values: &[u32]) {
+    for &value in values {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+fn gpu_error(e: impl std::fmt::Display) -> io::Error {
+    io::Error::other(format!("gpu: {}", e))
+}
+
+impl GpuDevice {
+    /// Uploads `scene` to the first GPU wgpu finds (`WGPU_BACKEND` picks the API).
+    /// Fails if the scene has anything the shader can't trace, or there's no GPU.
+    pub fn new(scene: &SceneFile) -> io::Result<Self> {
+        let mut spheres = gpu_spheres(scene)?;
+        let background = match scene.background {
+            BackgroundDesc::Gradient { bottom, top } => (bottom, top),
+            BackgroundDesc::Sky { .. } => return Err(unsupported("a sky background")),
+        };
+        let mut nodes = Vec::new();
+        build_bvh(&mut spheres, 0, &mut nodes);
+
+        let mut sphere_bytes = Vec::new();
+        for s in &spheres {
+            push_f32s(
+                &mut sphere_bytes,
+                &[s.center.x, s.center.y, s.center.z, s.radius],
+            );
+            push_f32s(
+                &mut sphere_bytes,
+                &[s.albedo.r, s.albedo.g, s.albedo.b, s.param],
+            );
+            push_u32s(&mut sphere_bytes, &[s.kind, 0, 0, 0]);
+        }
+        // Storage buffers can't be empty.
+        if spheres.is_empty() {
+            sphere_bytes.resize(48, 0);
+        }
+        let mut node_bytes = Vec::new();
+        for node in &nodes {
+            let (min, max) = (node.bounds.min(), node.bounds.max());
+            push_f32s(&mut node_bytes, &[min.x, min.y, min.z]);
+            push_u32s(&mut node_bytes, &[node.offset]);
+            push_f32s(&mut node_bytes, &[max.x, max.y, max.z]);
+            push_u32s(&mut node_bytes, &[node.count]);
+        }
+
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::from_env_or_default());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            ..Default::default()
+        }))
+        .map_err(|e| io::Error::new(io::ErrorKind::NotFound, format!("gpu: {}", e)))?;
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("rt"),
+            required_limits: adapter.limits(),
+            ..Default::default()
+        }))
+        .map_err(gpu_error)?;
+
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("path tracer"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("path tracer"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        if let Some(e) = pollster::block_on(device.pop_error_scope()) {
+            return Err(gpu_error(e));
+        }
+        let storage = |label, contents: &[u8]| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some(label),
+                contents,
+                usage: wgpu::BufferUsages::STORAGE,
+            })
+        };
+        let spheres = storage("spheres", &sphere_bytes);
+        let nodes = storage("bvh", &node_bytes);
+
+        let info = adapter.get_info();
+        Ok(Self {
+            adapter: format!("{} ({:?})", info.name, info.backend),
+            device,
+            queue,
+            pipeline,
+            spheres,
+            nodes,
+            background,
+            failed: AtomicBool::new(false),
+        })
+    }
+
+    // Generates the tile's camera rays and traces them on the GPU a batch at a time.
+    fn render_gpu(&self, camera: &Camera, film_tile: &mut FilmTile) -> io::Result<()> {
+        let tile = film_tile.tile;
+        let exposure = camera.exposure_scale();
+        let mut samples = Vec::new();
+        let mut rays = Vec::new();
+        for j in tile.y..tile.y + tile.height {
+            for i in tile.x..tile.x + tile.width {
+                let mut rng = camera.pixel_rng(i, j);
+                for _ in 0..camera.pixel_samples(i, j) {
+                    let offset = camera.sample_square(&mut rng);
+                    let (ray, weight) = camera.generate_ray(i, j, offset, &mut rng);
+                    push_f32s(&mut rays, &[ray.orig.x, ray.orig.y, ray.orig.z]);
+                    push_u32s(&mut rays, &[rng.random()]);
+                    push_f32s(&mut rays, &[ray.dir.x, ray.dir.y, ray.dir.z]);
+                    push_u32s(&mut rays, &[0]);
+                    samples.push(GpuSample {
+                        i,
+                        j,
+                        x: i as f64 + 0.5 + offset.x,
+                        y: j as f64 + 0.5 + offset.y,
+                        weight: weight * exposure,
+                    });
+                    if samples.len() == BATCH {
+                        self.trace_batch(camera, &mut samples, &mut rays, film_tile)?;
+                    }
+                }
+            }
+        }
+        if !samples.is_empty() {
+            self.trace_batch(camera, &mut samples, &mut rays, film_tile)?;
+        }
+        Ok(())
+    }
+
+    // Traces `rays`, adds what they bring back to the film and empties both lists.
+    fn trace_batch(
+        &self,
+        camera: &Camera,
+        samples: &mut Vec<GpuSample>,
+        rays: &mut Vec<u8>,
+        film_tile: &mut FilmTile,
+    ) -> io::Result<()> {
+        let (bottom, top) = self.background;
+        let mut params = Vec::with_capacity(32);
+        push_f32s(&mut params, &[bottom.r, bottom.g, bottom.b]);
+        push_u32s(&mut params, &[samples.len() as u32]);
+        push_f32s(&mut params, &[top.r, top.g, top.b]);
+        push_u32s(&mut params, &[camera.max_depth]);
+
+        self.device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let buffer = |label, contents: &[u8], usage| {
+            self.device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(label),
+                    contents,
+                    usage,
+                })
+        };
+        let params = buffer("params", &params, wgpu::BufferUsages::UNIFORM);
+        let rays_buffer = buffer("camera rays", rays, wgpu::BufferUsages::STORAGE);
+        let size = (samples.len() * 16) as u64;
+        let radiance = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("radiance"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("readback"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[&params, &self.spheres, &self.nodes, &rays_buffer, &radiance]
+                .iter()
+                .enumerate()
+                .map(|(binding, buffer)| wgpu::BindGroupEntry {
+                    binding: binding as u32,
+                    resource: buffer.as_entire_binding(),
+                })
+                .collect::<Vec<_>>(),
+        });
+
+        let mut encoder = self.device.create_command_encoder(&Default::default());
+        {
+            let mut pass = encoder.begin_compute_pass(&Default::default());
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups((samples.len() as u32).div_ceil(WORKGROUP), 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&radiance, 0, &readback, 0, size);
+        self.queue.submit([encoder.finish()]);
+        if let Some(e) = pollster::block_on(self.device.pop_error_scope()) {
+            return Err(gpu_error(e));
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        readback.map_async(wgpu::MapMode::Read, .., move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::PollType::Wait).map_err(gpu_error)?;
+        receiver.recv().map_err(gpu_error)?.map_err(gpu_error)?;
+        {
+            let bytes = readback.get_mapped_range(..);
+            let values = bytes
+                .chunks_exact(4)
+                .map(|word| f32::from_le_bytes([word[0], word[1], word[2], word[3]]) as f64);
+            let colors: Vec<f64> = values.collect();
+            for (sample, rgba) in samples.iter().zip(colors.chunks_exact(4)) {
+                let color = Color::new(rgba[0], rgba[1], rgba[2]) * sample.weight;
+                let color = camera.check_sample(sample.i, sample.j, color, None);
+                film_tile.add_sample(sample.x, sample.y, color);
+            }
+        }
+        readback.unmap();
+        samples.clear();
+        rays.clear();
+        Ok(())
+    }
+}
+
+impl RenderDevice for GpuDevice {
+    fn name(&self) -> String {
+        format!("gpu {}", self.adapter)
+    }
+
+    fn render_tile(&self, camera: &Camera, scene: &Scene, film_tile: &mut FilmTile) {
+        let on_gpu = camera.path_tracer().is_some() && !camera.spectral;
+        if on_gpu && !self.failed.load(Ordering::Relaxed) {
+            match self.render_gpu(camera, film_tile) {
+                Ok(()) => return,
+                Err(e) => {
+                    log::warn!(
+                        target: "render",
+                        "GPU {} failed ({}), rendering its tiles on the CPU",
+                        self.adapter, e
+                    );
+                    self.failed.store(true, Ordering::Relaxed);
+                }
+            }
+            // Start the tile again, in case batches went in before the failure.
+            *film_tile = FilmTile::new(
+                film_tile.tile,
+                camera.filter,
+                camera.film_width(),
+                camera.film_height(),
+            );
+        }
+        camera.render_tile(scene, film_tile);
+    }
+}
@@ -0,0 +1,234 @@
+// Path tracing kernel for `GpuDevice`. Each invocation takes one camera ray and follows
+// it through a BVH of spheres the way the naive `PathTracer` does: lights are found by
+// hitting them, and Russian roulette starts at the same bounce.
+
+struct Params {
+    // Gradient background, from straight down to straight up.
+    bottom: vec3<f32>,
+    ray_count: u32,
+    top: vec3<f32>,
+    max_depth: u32,
+}
+
+struct Sphere {
+    center: vec3<f32>,
+    radius: f32,
+    albedo: vec3<f32>,
+    // Metal's fuzz, a dielectric's index of refraction, or 1 for a light that emits
+    // from both sides.
+    param: f32,
+    kind: u32,
+}
+
+struct Node {
+    min: vec3<f32>,
+    // A leaf's first sphere, or an inner node's second child (its first follows it).
+    offset: u32,
+    max: vec3<f32>,
+    // Spheres in a leaf, 0 for an inner node.
+    count: u32,
+}
+
+struct CameraRay {
+    origin: vec3<f32>,
+    seed: u32,
+    direction: vec3<f32>,
+}
+
+const LAMBERTIAN: u32 = 0u;
+const METAL: u32 = 1u;
+const DIELECTRIC: u32 = 2u;
+const LIGHT: u32 = 3u;
+
+const ROULETTE_DEPTH: u32 = 3u;
+const NO_HIT: u32 = 0xffffffffu;
+const FAR: f32 = 1e30;
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> spheres: array<Sphere>;
+@group(0) @binding(2) var<storage, read> nodes: array<Node>;
+@group(0) @binding(3) var<storage, read> rays: array<CameraRay>;
+@group(0) @binding(4) var<storage, read_write> radiance: array<vec4<f32>>;
+
+var<private> rng_state: u32;
+
+// PCG hash, advanced once per number.
+fn random() -> f32 {
+    rng_state = rng_state * 747796405u + 2891336453u;
+    var word = ((rng_state >> ((rng_state >> 28u) + 4u)) ^ rng_state) * 277803737u;
+    word = (word >> 22u) ^ word;
+    return f32(word >> 8u) / 16777216.0;
+}
+
+fn random_unit_vector() -> vec3<f32> {
+    let z = 1.0 - 2.0 * random();
+    let r = sqrt(max(0.0, 1.0 - z * z));
+    let phi = 6.2831853 * random();
+    return vec3<f32>(r * cos(phi), r * sin(phi), z);
+}
+
+// Distance along the unit direction `dir` to the nearer hit in (0, t_max), or FAR. The
+// discriminant is taken from the ray's closest approach to the center, which keeps
+// its precision for big spheres far from the origin.
+fn hit_sphere(s: Sphere, origin: vec3<f32>, dir: vec3<f32>, t_max: f32) -> f32 {
+    let oc = s.center - origin;
+    let b = dot(oc, dir);
+    let q = oc - b * dir;
+    let discriminant = s.radius * s.radius - dot(q, q);
+    if discriminant < 0.0 {
+        return FAR;
+    }
+    let k = b + select(-1.0, 1.0, b >= 0.0) * sqrt(discriminant);
+    if k == 0.0 {
+        return FAR;
+    }
+    let c = dot(oc, oc) - s.radius * s.radius;
+    let t0 = min(c / k, k);
+    let t1 = max(c / k, k);
+    if t0 > 0.0 && t0 < t_max {
+        return t0;
+    }
+    if t1 > 0.0 && t1 < t_max {
+        return t1;
+    }
+    return FAR;
+}
+
+fn hit_box(node: Node, origin: vec3<f32>, inv_dir: vec3<f32>, t_max: f32) -> bool {
+    let t0 = (node.min - origin) * inv_dir;
+    let t1 = (node.max - origin) * inv_dir;
+    let near = min(t0, t1);
+    let far = max(t0, t1);
+    let enter = max(max(near.x, near.y), max(near.z, 0.0));
+    let exit = min(min(far.x, far.y), min(far.z, t_max));
+    return enter <= exit;
+}
+
+struct Hit {
+    t: f32,
+    sphere: u32,
+}
+
+fn closest_hit(origin: vec3<f32>, dir: vec3<f32>) -> Hit {
+    var hit = Hit(FAR, NO_HIT);
+    let tiny = abs(dir) < vec3<f32>(1e-20);
+    let inv_dir = 1.0 / select(dir, vec3<f32>(1e-20), tiny);
+    var stack: array<u32, 32>;
+    var top = 0u;
+    var index = 0u;
+    loop {
+        let node = nodes[index];
+        if hit_box(node, origin, inv_dir, hit.t) {
+            if node.count == 0u {
+                stack[top] = node.offset;
+                top += 1u;
+                index += 1u;
+                continue;
+            }
+            for (var k = node.offset; k < node.offset + node.count; k += 1u) {
+                let t = hit_sphere(spheres[k], origin, dir, hit.t);
+                if t < hit.t {
+                    hit = Hit(t, k);
+                }
+            }
+        }
+        if top == 0u {
+            break;
+        }
+        top -= 1u;
+        index = stack[top];
+    }
+    return hit;
+}
+
+// Start of a ray leaving `p` in direction `dir`, nudged off the surface with normal `n`
+// by `error` and then a few ulps, so it can't hit the same surface again right away.
+fn offset_origin(p: vec3<f32>, n: vec3<f32>, error: f32, dir: vec3<f32>) -> vec3<f32> {
+    let side = select(-n, n, dot(n, dir) >= 0.0);
+    let moved = p + error * side;
+    let ulps = vec3<i32>(256.0 * side);
+    let stepped = bitcast<vec3<f32>>(
+        bitcast<vec3<i32>>(moved) + select(ulps, -ulps, moved < vec3<f32>(0.0))
+    );
+    return select(stepped, moved + side / 65536.0, abs(moved) < vec3<f32>(1.0 / 32.0));
+}
+
+// Schlick's approximation, as in `Dielectric::reflanctance`.
+fn reflectance(cosine: f32, ri: f32) -> f32 {
+    let r0 = (1.0 - ri) / (1.0 + ri);
+    return r0 * r0 + (1.0 - r0 * r0) * pow(1.0 - cosine, 5.0);
+}
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let index = id.x;
+    if index >= params.ray_count {
+        return;
+    }
+    let camera_ray = rays[index];
+    rng_state = camera_ray.seed;
+    var origin = camera_ray.origin;
+    var dir = normalize(camera_ray.direction);
+    var throughput = vec3<f32>(1.0);
+    var total = vec3<f32>(0.0);
+
+    for (var bounce = 0u; bounce < params.max_depth; bounce += 1u) {
+        let hit = closest_hit(origin, dir);
+        if hit.sphere == NO_HIT {
+            let a = 0.5 * (dir.y + 1.0);
+            total += throughput * mix(params.bottom, params.top, a);
+            break;
+        }
+        let s = spheres[hit.sphere];
+        let p = origin + hit.t * dir;
+        let outward = (p - s.center) / s.radius;
+        let front = dot(dir, outward) < 0.0;
+        let n = select(-outward, outward, front);
+
+        if s.kind == LIGHT {
+            if front || s.param > 0.0 {
+                total += throughput * s.albedo;
+            }
+            break;
+        }
+
+        var next: vec3<f32>;
+        var attenuation = s.albedo;
+        if s.kind == LAMBERTIAN {
+            next = n + random_unit_vector();
+            if all(abs(next) < vec3<f32>(1e-8)) {
+                next = n;
+            }
+        } else if s.kind == METAL {
+            next = reflect(dir, n) + s.param * random_unit_vector();
+            if dot(next, n) <= 0.0 {
+                break;
+            }
+        } else {
+            let ri = select(s.param, 1.0 / s.param, front);
+            let cos_theta = min(dot(-dir, n), 1.0);
+            let sin_theta = sqrt(max(0.0, 1.0 - cos_theta * cos_theta));
+            if ri * sin_theta > 1.0 || reflectance(cos_theta, ri) > random() {
+                next = reflect(dir, n);
+            } else {
+                next = refract(dir, n, ri);
+            }
+            attenuation = vec3<f32>(1.0);
+        }
+
+        throughput *= attenuation;
+        if bounce + 1u >= ROULETTE_DEPTH {
+            let survival = min(max(throughput.x, max(throughput.y, throughput.z)), 0.95);
+            if random() >= survival {
+                break;
+            }
+            throughput /= survival;
+        }
+
+        dir = normalize(next);
+        // Rounding grows with the size of the coordinates involved.
+        let magnitude = max(max(abs(s.center.x), abs(s.center.y)), abs(s.center.z)) + s.radius;
+        origin = offset_origin(p, outward, 1e-6 * magnitude, dir);
+    }
+    radiance[index] = vec4<f32>(total, 1.0);
+}
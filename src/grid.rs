@@ -0,0 +1,213 @@
+use crate::rtweekend::*;
+
+/// Cells per object, roughly, when sizing the grid.
+const CELLS_PER_OBJECT: f64 = 2.0;
+/// Most cells along any axis.
+const MAX_RESOLUTION: usize = 128;
+
+/// Uniform grid accelerator: the scene's box cut into equal cells, each listing the
+/// objects overlapping it, walked cell by cell along the ray (3D-DDA) until a hit
+/// inside the current cell is found. For evenly spread objects of similar size, like
+/// a field of small spheres, it finds the first hit with less work than a BVH; a few
+/// big objects among many small ones suit a `Bvh` better. Objects without a bounded
+/// box are tested by every ray.
+pub struct UniformGrid {
+    objects: Vec<Arc<dyn Hittable>>,
+    unbounded: Vec<Arc<dyn Hittable>>,
+    bbox: Aabb,
+    resolution: [usize; 3],
+    cell_size: Vec3,
+    /// Where each cell's objects start in `cell_objects`; one extra entry at the end.
+    cell_start: Vec<u32>,
+    cell_objects: Vec<u32>,
+}
+
+impl UniformGrid {
+    pub fn new(objects: Vec<Arc<dyn Hittable>>) -> Self {
+        let (objects, unbounded): (Vec<_>, Vec<_>) = objects
+            .into_iter()
+            .partition(|object| object.bounding_box().is_bounded());
+        let boxes: Vec<Aabb> = objects.iter().map(|o| o.bounding_box()).collect();
+        let bbox = boxes.iter().fold(Aabb::EMPTY, |acc, b| acc.union(b));
+
+        // Cells as close to cubes as the box allows, about CELLS_PER_OBJECT per object.
+        let extent = bbox.max() - bbox.min();
+        let volume = (extent.x * extent.y * extent.z).max(1e-30);
+        let per_unit = (CELLS_PER_OBJECT * boxes.len() as f64 / volume).cbrt();
+        let mut resolution = [1; 3];
+        let mut cell_size = extent;
+        if !boxes.is_empty() {
+            for axis in 0..3 {
+                resolution[axis] =
+                    ((extent[axis] * per_unit).round() as usize).clamp(1, MAX_RESOLUTION);
+                cell_size[axis] = extent[axis] / resolution[axis] as f64;
+            }
+        }
+
+        let mut grid = Self {
+            objects,
+            unbounded,
+            bbox,
+            resolution,
+            cell_size,
+            cell_start: Vec::new(),
+            cell_objects: Vec::new(),
+        };
+        let mut cells = vec![Vec::new(); resolution.iter().product()];
+        for (k, b) in boxes.iter().enumerate() {
+            let lo = grid.cell_of(b.min());
+            let hi = grid.cell_of(b.max());
+            for z in lo[2]..=hi[2] {
+                for y in lo[1]..=hi[1] {
+                    for x in lo[0]..=hi[0] {
+                        cells[grid.cell_index([x, y, z])].push(k as u32);
+                    }
+                }
+            }
+        }
+        grid.cell_start.push(0);
+        for cell in cells {
+            grid.cell_objects.extend(cell);
+            grid.cell_start.push(grid.cell_objects.len() as u32);
+        }
+        grid
+    }
+
+    pub fn from_list(list: HittableList) -> Self {
+        Self::new(list.objects)
+    }
+
+    pub fn resolution(&self) -> [usize; 3] {
+        self.resolution
+    }
+
+    fn len(&self) -> usize {
+        self.objects.len() + self.unbounded.len()
+    }
+
+    fn all(&self) -> impl Iterator<Item = &Arc<dyn Hittable>> {
+        self.objects.iter().chain(&self.unbounded)
+    }
+
+    // Cell containing `p`, clamped to the grid.
+    fn cell_of(&self, p: Point3) -> [usize; 3] {
+        let mut cell = [0; 3];
+        for (axis, c) in cell.iter_mut().enumerate() {
+            let offset = (p[axis] - self.bbox.axis_interval(axis).min) / self.cell_size[axis];
+            *c = if offset.is_finite() {
+                (offset.max(0.0) as usize).min(self.resolution[axis] - 1)
+            } else {
+                0
+            };
+        }
+        cell
+    }
+
+    fn cell_index(&self, [x, y, z]: [usize; 3]) -> usize {
+        (z * self.resolution[1] + y) * self.resolution[0] + x
+    }
+
+    // Closest hit among the bounded objects, walking the cells the ray crosses.
+    fn hit_cells(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        // Where the ray is inside the grid's box.
+        let inv_dir = Vec3::new(1.0 / r.dir.x, 1.0 / r.dir.y, 1.0 / r.dir.z);
+        let inside = self.bbox.overlap(r.orig, inv_dir, ray_t)?;
+        let (t_enter, t_exit) = (inside.min, inside.max);
+
+        let mut cell = self.cell_of(r.at(t_enter));
+        let mut step = [0isize; 3];
+        let mut t_next = [INFINITY; 3];
+        let mut t_delta = [INFINITY; 3];
+        for axis in 0..3 {
+            let d = r.dir[axis];
+            if d == 0.0 {
+                continue;
+            }
+            let cell_min =
+                self.bbox.axis_interval(axis).min + cell[axis] as f64 * self.cell_size[axis];
+            t_delta[axis] = self.cell_size[axis] / d.abs();
+            if d > 0.0 {
+                step[axis] = 1;
+                t_next[axis] = (cell_min + self.cell_size[axis] - r.orig[axis]) / d;
+            } else {
+                step[axis] = -1;
+                t_next[axis] = (cell_min - r.orig[axis]) / d;
+            }
+        }
+
+        let mut closest = ray_t.max;
+        let mut best = None;
+        loop {
+            ray_stats::count(Counter::NodeVisits);
+            let index = self.cell_index(cell);
+            let range = self.cell_start[index] as usize..self.cell_start[index + 1] as usize;
+            for &k in &self.cell_objects[range] {
+                if let Some(rec) =
+                    self.objects[k as usize].hit(r, Interval::new(ray_t.min, closest))
+                {
+                    closest = rec.t;
+                    best = Some(rec);
+                }
+            }
+            // A hit beyond this cell may be beaten by an object in a later cell, so only
+            // stop once the closest hit so far lies within the cells already walked.
+            let axis = (0..3)
+                .min_by(|&a, &b| t_next[a].total_cmp(&t_next[b]))
+                .unwrap_or(0);
+            if closest <= t_next[axis] || t_next[axis] > t_exit {
+                return best;
+            }
+            let next = cell[axis] as isize + step[axis];
+            if next < 0 || next >= self.resolution[axis] as isize {
+                return best;
+            }
+            cell[axis] = next as usize;
+            t_next[axis] += t_delta[axis];
+        }
+    }
+}
+
+impl Hittable for UniformGrid {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let mut rec = None;
+        let mut closest = ray_t.max;
+        for object in &self.unbounded {
+            if let Some(hit) = object.hit(r, Interval::new(ray_t.min, closest)) {
+                closest = hit.t;
+                rec = Some(hit);
+            }
+        }
+        if self.objects.is_empty() {
+            return rec;
+        }
+        self.hit_cells(r, Interval::new(ray_t.min, closest)).or(rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        if self.unbounded.is_empty() {
+            self.bbox
+        } else {
+            Aabb::UNIVERSE
+        }
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let weight = 1.0 / self.len() as f64;
+        self.all()
+            .map(|object| weight * object.pdf_value(origin, direction))
+            .sum()
+    }
+
+    fn random(&self, origin: Point3, rng: &mut SmallRng) -> Vec3 {
+        let n = self.len();
+        if n == 0 {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+        let index = ((random_f64(rng) * n as f64) as usize).min(n - 1);
+        self.all().nth(index).unwrap().random(origin, rng)
+    }
+
+    fn named_bounds(&self, name: &str) -> Option<Aabb> {
+        self.all().find_map(|object| object.named_bounds(name))
+    }
+}
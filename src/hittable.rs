@@ -3,10 +3,35 @@ use crate::rtweekend::*;
 #[derive(Clone)]
 pub struct HitRecord {
     pub p: Point3,
+    /// Shading normal, on the side the ray came from: what materials shade with. It
+    /// can lean away from the true surface, e.g. when interpolated across a mesh or
+    /// bent by a normal map.
     pub normal: Vec3,
+    /// Normal of the surface that was actually hit, on the same side as `normal`.
+    pub geometric_normal: Vec3,
     pub t: f64,
+    /// Surface coordinates for texturing.
+    pub uv: Vec2,
+    /// Surface direction of increasing `u` (not necessarily unit or orthogonal to `normal`).
+    pub tangent: Vec3,
     pub mat: Arc<dyn Material + Send + Sync>,
     pub front_face: bool,
+    /// ID of the `Instance` that was hit, 0 outside of instances.
+    pub instance_id: u32,
+    /// Material parameter overrides from the `Instance` that was hit.
+    pub overrides: MaterialOverrides,
+    /// ID of the innermost `Named` object that was hit, 0 if it has none.
+    pub object_id: u32,
+    /// Material ID given by the innermost `Named` object that set one, 0 if none did.
+    pub material_id: u32,
+    /// How far rounding in the intersection may have put `p` from the true surface.
+    /// Primitives whose error grows with their size, like big spheres, set it.
+    pub p_error: f64,
+    /// Rough world-space length of one unit of `uv` on the surface around `p`, or 0
+    /// where the primitive doesn't say.
+    pub uv_scale: f64,
+    /// Width of the ray's footprint on the surface, from its cone; 0 for point samples.
+    pub footprint: f64,
 }
 
 impl HitRecord {
@@ -18,6 +43,57 @@ impl HitRecord {
         } else {
             -outward_normal
         };
+        self.geometric_normal = self.normal;
+    }
+
+    /// Replaces the shading normal, given facing the same way as the outward normal
+    /// passed to `set_face_normal`, which must be called first.
+    #[inline]
+    pub fn set_shading_normal(&mut self, outward_normal: Vec3) {
+        self.normal = if self.front_face {
+            outward_normal
+        } else {
+            -outward_normal
+        };
+    }
+
+    /// Whether the shading and geometric normals agree on light going from `wi` to `wo`
+    /// (both pointing away from the surface) being reflected or transmitted. Where they
+    /// disagree, following the shading normal would let light leak through the surface
+    /// or reflect off its underside, so such paths carry nothing.
+    pub fn shading_agrees(&self, wo: Vec3, wi: Vec3) -> bool {
+        let shading = wo.dot(self.normal) * wi.dot(self.normal) > 0.0;
+        let geometric = wo.dot(self.geometric_normal) * wi.dot(self.geometric_normal) > 0.0;
+        shading == geometric
+    }
+
+    /// Width of the ray's footprint in `uv` units, for choosing a texture's level of
+    /// detail; 0 when unknown.
+    pub fn uv_footprint(&self) -> f64 {
+        if self.uv_scale > 0.0 {
+            self.footprint / self.uv_scale
+        } else {
+            0.0
+        }
+    }
+
+    /// Where a ray leaving the surface in direction `dir` should start, so it can't hit
+    /// the surface again right away.
+    pub fn spawn_origin(&self, dir: Vec3) -> Point3 {
+        offset_ray_origin(self.p, self.geometric_normal, self.p_error, dir)
+    }
+
+    /// Orthonormal (tangent, bitangent, normal) frame at the hit, following `u` where
+    /// the primitive provides a tangent and an arbitrary frame otherwise.
+    pub fn tangent_frame(&self) -> (Vec3, Vec3, Vec3) {
+        let n = self.normal;
+        let t = self.tangent - self.tangent.dot(n) * n;
+        if t.near_zero() {
+            let onb = Onb::new(n);
+            return (onb.u, onb.v, n);
+        }
+        let t = t.normalized();
+        (t, n.cross(t), n)
     }
 }
 
@@ -26,14 +102,50 @@ impl Default for HitRecord {
         Self {
             p: Point3::default(),    // or Point3::zero()
             normal: Vec3::default(), // or Vec3::zero()
+            geometric_normal: Vec3::default(),
             t: 0.0,
+            uv: Vec2::ZERO,
+            tangent: Vec3::default(),
             // Use your placeholder material for the default
             mat: Arc::new(NullMaterial),
             front_face: true, //false,
+            instance_id: 0,
+            overrides: MaterialOverrides::default(),
+            object_id: 0,
+            material_id: 0,
+            p_error: 0.0,
+            uv_scale: 0.0,
+            footprint: 0.0,
         }
     }
 }
 
 pub trait Hittable: Send + Sync {
     fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord>;
+
+    /// Box enclosing the object. Unbounded objects (and ones that don't know their
+    /// extent) keep the default, which never gets culled.
+    fn bounding_box(&self) -> Aabb {
+        Aabb::UNIVERSE
+    }
+
+    /// Solid-angle density of `random(origin, rng)` generating `direction`.
+    fn pdf_value(&self, _origin: Point3, _direction: Vec3) -> f64 {
+        0.0
+    }
+
+    /// Random direction from `origin` towards a point on this object.
+    fn random(&self, _origin: Point3, _rng: &mut SmallRng) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+
+    /// Name given with `Named` or `SceneNode::named`, if any.
+    fn name(&self) -> Option<&str> {
+        None
+    }
+
+    /// World-space box of the object called `name`, in this object or below it.
+    fn named_bounds(&self, name: &str) -> Option<Aabb> {
+        (self.name() == Some(name)).then(|| self.bounding_box())
+    }
 }
@@ -42,4 +42,30 @@ impl Hittable for HittableList {
 
         closest_hitrecord
     }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let weight = 1.0 / self.objects.len() as f64;
+        self.objects
+            .iter()
+            .map(|object| weight * object.pdf_value(origin, direction))
+            .sum()
+    }
+
+    fn random(&self, origin: Point3, rng: &mut SmallRng) -> Vec3 {
+        let index =
+            ((random_f64(rng) * self.objects.len() as f64) as usize).min(self.objects.len() - 1);
+        self.objects[index].random(origin, rng)
+    }
+
+    fn named_bounds(&self, name: &str) -> Option<Aabb> {
+        self.objects
+            .iter()
+            .find_map(|object| object.named_bounds(name))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.objects.iter().fold(Aabb::EMPTY, |bbox, object| {
+            bbox.union(&object.bounding_box())
+        })
+    }
 }
@@ -0,0 +1,185 @@
+use crate::parallel::*;
+use crate::rtweekend::*;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// Which ID an ID pass records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdPass {
+    /// `HitRecord::object_id`, from `Named` objects.
+    Object,
+    /// `HitRecord::material_id`.
+    Material,
+}
+
+impl IdPass {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "object" => Some(Self::Object),
+            "material" => Some(Self::Material),
+            _ => None,
+        }
+    }
+}
+
+/// One ID per pixel (0 where nothing with an ID was hit), for per-object masks in
+/// compositing.
+#[derive(Debug, Clone)]
+pub struct IdImage {
+    pub width: u32,
+    pub height: u32,
+    pub ids: Vec<u32>,
+}
+
+/// Color an ID is drawn in: fixed per ID, so a mask can be keyed on it across frames.
+pub fn id_color(id: u32) -> [u8; 3] {
+    if id == 0 {
+        return [0, 0, 0];
+    }
+    let h = (instance_random(id) * (1u64 << 24) as f64) as u32;
+    [(h >> 16) as u8, (h >> 8) as u8, h as u8]
+}
+
+impl IdImage {
+    /// Writes the IDs color-coded as a P3 image (see `id_color`).
+    pub fn write_color_coded<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "P3")?;
+        writeln!(out, "{} {}", self.width, self.height)?;
+        writeln!(out, "255")?;
+        for &id in &self.ids {
+            let [r, g, b] = id_color(id);
+            writeln!(out, "{} {} {}", r, g, b)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the raw IDs as text, one row of the image per line.
+    pub fn write_integers<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        for row in self.ids.chunks(self.width as usize) {
+            let line: Vec<String> = row.iter().map(u32::to_string).collect();
+            writeln!(out, "{}", line.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Per-pixel coverage of every ID seen in the pixel, like a Cryptomatte: anti-aliased
+/// edges keep a fraction for each object instead of snapping to one, so a matte pulled
+/// from it blends cleanly with the beauty render.
+#[derive(Debug, Clone)]
+pub struct CoverageImage {
+    pub width: u32,
+    pub height: u32,
+    /// `(id, coverage)` per pixel, most coverage first, at most `ranks` of them.
+    pub pixels: Vec<Vec<(u32, f64)>>,
+}
+
+impl CoverageImage {
+    /// Fraction of each pixel covered by `id`.
+    pub fn matte(&self, id: u32) -> Vec<f64> {
+        self.pixels
+            .iter()
+            .map(|entries| {
+                entries
+                    .iter()
+                    .find(|&&(e, _)| e == id)
+                    .map_or(0.0, |&(_, c)| c)
+            })
+            .collect()
+    }
+
+    /// Every ID that covers part of some pixel, in increasing order.
+    pub fn ids(&self) -> Vec<u32> {
+        let mut ids: Vec<u32> = self.pixels.iter().flatten().map(|&(id, _)| id).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    }
+
+    /// Writes the matte for `id` as a grayscale P3 image.
+    pub fn write_matte<W: Write>(&self, id: u32, out: &mut W) -> io::Result<()> {
+        writeln!(out, "P3")?;
+        writeln!(out, "{} {}", self.width, self.height)?;
+        writeln!(out, "255")?;
+        for c in self.matte(id) {
+            let g = (256.0 * c).clamp(0.0, 255.0) as u8;
+            writeln!(out, "{} {} {}", g, g, g)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the coverage pairs as text, one pixel per line in scanline order, each as
+    /// `id:coverage` separated by spaces.
+    pub fn write_ranks<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        writeln!(out, "{} {}", self.width, self.height)?;
+        for entries in &self.pixels {
+            let line: Vec<String> = entries
+                .iter()
+                .map(|(id, c)| format!("{}:{:.4}", id, c))
+                .collect();
+            writeln!(out, "{}", line.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl Camera {
+    /// Traces `samples_per_pixel` camera rays per pixel and keeps the ID seen by most
+    /// of them, so edges get one clean ID instead of a blend.
+    pub fn render_ids(&self, scene: &Scene, pass: IdPass) -> IdImage {
+        let (width, height) = (self.film_width(), self.film_height());
+        let ids = (0..width * height)
+            .into_par_iter()
+            .map(|idx| {
+                self.pixel_coverage(scene, pass, idx % width, idx / width)
+                    .first()
+                    .map_or(0, |&(id, _)| id)
+            })
+            .collect();
+        IdImage { width, height, ids }
+    }
+
+    /// Coverage of the `ranks` IDs covering most of each pixel, from
+    /// `samples_per_pixel` camera rays. Pixels covered by more IDs than that drop the
+    /// smallest contributions (0, for nothing with an ID, counts as an ID).
+    pub fn render_coverage(&self, scene: &Scene, pass: IdPass, ranks: usize) -> CoverageImage {
+        let (width, height) = (self.film_width(), self.film_height());
+        let pixels = (0..width * height)
+            .into_par_iter()
+            .map(|idx| {
+                let mut entries = self.pixel_coverage(scene, pass, idx % width, idx / width);
+                entries.truncate(ranks);
+                entries
+            })
+            .collect();
+        CoverageImage {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    // IDs hit by the pixel's camera rays with the fraction of rays hitting each, most
+    // first. Ties go to the smaller ID so the result doesn't depend on hash order.
+    fn pixel_coverage(&self, scene: &Scene, pass: IdPass, i: u32, j: u32) -> Vec<(u32, f64)> {
+        let hit_interval = Interval::new(scene.epsilon(), INFINITY);
+        let samples = self.samples_per_pixel.max(1);
+        let mut rng = self.pixel_rng(i, j);
+        let mut counts: HashMap<u32, u32> = HashMap::new();
+        for _ in 0..samples {
+            let id = scene
+                .hit_primary(&self.get_ray(i, j, &mut rng), hit_interval)
+                .map_or(0, |rec| match pass {
+                    IdPass::Object => rec.object_id,
+                    IdPass::Material => rec.material_id,
+                });
+            *counts.entry(id).or_default() += 1;
+        }
+        let mut entries: Vec<(u32, u32)> = counts.into_iter().collect();
+        entries.sort_unstable_by_key(|&(id, n)| (std::cmp::Reverse(n), id));
+        entries
+            .into_iter()
+            .map(|(id, n)| (id, n as f64 / samples as f64))
+            .collect()
+    }
+}
@@ -0,0 +1,118 @@
+use crate::rtweekend::*;
+
+/// Per-instance adjustments to a shared material, carried on the hit record so one
+/// material can be reused by many objects that still look slightly different.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MaterialOverrides {
+    /// Multiplied into the material's base color.
+    pub tint: Color,
+    /// Multiplied into the material's roughness.
+    pub roughness_scale: f64,
+    /// Largest hue rotation picked from the instance ID, as a fraction of the color wheel.
+    pub hue_jitter: f64,
+}
+
+impl Default for MaterialOverrides {
+    fn default() -> Self {
+        Self {
+            tint: Color::new(1.0, 1.0, 1.0),
+            roughness_scale: 1.0,
+            hue_jitter: 0.0,
+        }
+    }
+}
+
+impl MaterialOverrides {
+    pub fn is_identity(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn apply_color(&self, color: Color, instance_id: u32) -> Color {
+        let color = color * self.tint;
+        if self.hue_jitter == 0.0 {
+            return color;
+        }
+        let angle = (2.0 * instance_random(instance_id) - 1.0) * self.hue_jitter * 2.0 * PI;
+        rotate_hue(color, angle)
+    }
+
+    pub fn apply_roughness(&self, roughness: f64) -> f64 {
+        (roughness * self.roughness_scale).clamp(0.0, 1.0)
+    }
+}
+
+/// Stable pseudo-random number in [0, 1) for an instance ID (SplitMix64 finalizer).
+pub fn instance_random(instance_id: u32) -> f64 {
+    let mut z = (instance_id as u64).wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+// Rotates a color around the gray axis, which shifts its hue and keeps its brightness.
+pub(crate) fn rotate_hue(c: Color, angle: f64) -> Color {
+    let k = Vec3::new(1.0, 1.0, 1.0) / 3f64.sqrt();
+    let (sin, cos) = angle.sin_cos();
+    let c = c.to_vec3();
+    let rotated = c * cos + k.cross(c) * sin + k * k.dot(c) * (1.0 - cos);
+    Color::from_vec3(rotated).map(|channel| channel.max(0.0))
+}
+
+/// One placement of a shared object, tagged with an ID and material overrides.
+pub struct Instance {
+    object: Arc<dyn Hittable>,
+    pub id: u32,
+    pub overrides: MaterialOverrides,
+}
+
+impl Instance {
+    pub fn new(object: Arc<dyn Hittable>, id: u32) -> Self {
+        Self {
+            object,
+            id,
+            overrides: MaterialOverrides::default(),
+        }
+    }
+
+    pub fn with_overrides(
+        object: Arc<dyn Hittable>,
+        id: u32,
+        overrides: MaterialOverrides,
+    ) -> Self {
+        Self {
+            object,
+            id,
+            overrides,
+        }
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let mut rec = self.object.hit(r, ray_t)?;
+        rec.instance_id = self.id;
+        rec.overrides = self.overrides;
+        Some(rec)
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        self.object.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: Point3, rng: &mut SmallRng) -> Vec3 {
+        self.object.random(origin, rng)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.object.bounding_box()
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.object.name()
+    }
+
+    fn named_bounds(&self, name: &str) -> Option<Aabb> {
+        self.object.named_bounds(name)
+    }
+}
@@ -0,0 +1,403 @@
+use crate::rtweekend::*;
+use serde::{Deserialize, Serialize};
+
+/// Light transport: turns a camera ray into the color seen along it. The camera only
+/// generates rays and develops the film, so any integrator works with any camera.
+pub trait Integrator: Send + Sync {
+    /// Color seen along `r`, following paths of at most `max_depth` bounces.
+    fn radiance(&self, r: &Ray, scene: &Scene, max_depth: u32, rng: &mut SmallRng) -> Color;
+}
+
+/// Unidirectional path tracer.
+#[derive(Debug, Clone, Copy)]
+pub struct PathTracer {
+    /// Sample lights directly at every bounce (shadow rays to the explicit lights and
+    /// half of the bounces aimed at emissive geometry). Without it light is only found
+    /// by paths that happen to hit it, so explicit lights go dark and small emitters
+    /// turn noisy; useful as a reference.
+    pub next_event: bool,
+    /// Bounces after which Russian roulette may end a path, with a chance that grows as
+    /// its throughput falls. Survivors are weighted up, so the image stays unbiased
+    /// while dim paths stop wasting time. `u32::MAX` turns it off.
+    pub roulette_depth: u32,
+}
+
+impl PathTracer {
+    const ROULETTE_DEPTH: u32 = 3;
+
+    pub fn naive() -> Self {
+        Self {
+            next_event: false,
+            roulette_depth: Self::ROULETTE_DEPTH,
+        }
+    }
+
+    pub fn nee() -> Self {
+        Self {
+            next_event: true,
+            roulette_depth: Self::ROULETTE_DEPTH,
+        }
+    }
+
+    /// `radiance` that can also record where the path's light came from.
+    pub(crate) fn trace(
+        &self,
+        r: &Ray,
+        max_depth: u32,
+        scene: &Scene,
+        rng: &mut SmallRng,
+        stats: Option<&mut PathStats>,
+    ) -> Color {
+        self.trace_path(r, max_depth, scene, rng, stats).radiance
+    }
+
+    /// `trace`, returning everything the path gathered rather than just its color.
+    pub(crate) fn trace_path(
+        &self,
+        r: &Ray,
+        max_depth: u32,
+        scene: &Scene,
+        rng: &mut SmallRng,
+        mut stats: Option<&mut PathStats>,
+    ) -> PathState {
+        let mut state = PathState::new();
+        let mut ray = *r;
+        for bounce in 0..max_depth {
+            let hit = intersect(&ray, bounce, scene);
+            match self.shade(
+                &ray,
+                hit,
+                bounce,
+                scene,
+                &mut state,
+                rng,
+                stats.as_deref_mut(),
+            ) {
+                Some(next) => ray = next,
+                None => return state,
+            }
+        }
+
+        if let Some(stats) = stats {
+            stats.log(|| PathEvent::MaxDepth);
+            stats.terminate(max_depth);
+        }
+        state
+    }
+
+    /// One bounce of a path: adds the light picked up at `hit`, the closest hit of `ray`
+    /// (`None` if it escaped), to `state` and returns the ray to carry on along, or
+    /// `None` if the path ends here.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn shade(
+        &self,
+        ray: &Ray,
+        hit: Option<HitRecord>,
+        bounce: u32,
+        scene: &Scene,
+        state: &mut PathState,
+        rng: &mut SmallRng,
+        mut stats: Option<&mut PathStats>,
+    ) -> Option<Ray> {
+        let Some(rec) = hit else {
+            // If no hit, it's the background (sky gradient by default)
+            let background = scene.background_color(ray.dir, bounce == 0);
+            if let Some(stats) = stats {
+                stats.log(|| PathEvent::Escaped {
+                    bounce,
+                    direction: ray.dir,
+                    background,
+                });
+                stats.record(bounce, background);
+                stats.terminate(bounce);
+            }
+            state.radiance += state.throughput * background;
+            state.check_finite("background");
+            return None;
+        };
+
+        let emitted = rec.mat.emitted(&rec);
+        let direct = if self.next_event {
+            direct_light(ray, &rec, scene)
+        } else {
+            Color::BLACK
+        };
+        state.radiance += state.throughput * (emitted + direct);
+        state.check_finite(rec.mat.name());
+        if let Some(stats) = stats.as_deref_mut() {
+            stats.record(bounce, emitted + direct);
+        }
+        let Some((mut scattered, attenuation)) = rec.mat.scatter(ray, &rec, rng) else {
+            if let Some(stats) = stats {
+                stats.log(|| PathEvent::Hit(Bounce::new(bounce, &rec, emitted, direct, None)));
+                stats.terminate(bounce + 1);
+            }
+            return None;
+        };
+        scattered.tm = ray.tm;
+        scattered.media = scattered.media.or(ray.media);
+        scattered.wavelength = ray.wavelength;
+
+        // Mix the material's own sampling with sampling towards emissive geometry.
+        let (weight, pdf, toward_light) = if self.next_event
+            && !scene.emitters.objects.is_empty()
+            && rec.mat.scattering_pdf(ray, &rec, &scattered) > 0.0
+        {
+            let toward_light = random_f64(rng) < 0.5;
+            if toward_light {
+                scattered = Ray::with_time(rec.p, scene.emitters.random(rec.p, rng), ray.tm);
+                scattered.media = ray.media;
+                scattered.wavelength = ray.wavelength;
+            }
+            let scattering_pdf = rec.mat.scattering_pdf(ray, &rec, &scattered);
+            let pdf_value =
+                0.5 * scene.emitters.pdf_value(rec.p, scattered.dir) + 0.5 * scattering_pdf;
+            // eval() is the BSDF times cosine, so this is f * cos / pdf for either strategy.
+            let weight = if pdf_value > 0.0 {
+                rec.mat.eval(ray, &rec, scattered.dir.normalized()) / pdf_value
+            } else {
+                Color::BLACK
+            };
+            (weight, Some(pdf_value), toward_light)
+        } else {
+            (attenuation, None, false)
+        };
+        // Materials scatter from the hit point itself.
+        scattered.orig = rec.spawn_origin(scattered.dir);
+        if let Some(stats) = stats.as_deref_mut() {
+            stats.log(|| {
+                let scatter = ScatterLog {
+                    direction: scattered.dir,
+                    weight,
+                    pdf,
+                    toward_light,
+                };
+                PathEvent::Hit(Bounce::new(bounce, &rec, emitted, direct, Some(scatter)))
+            });
+        }
+        // Paths the shading normal would send through the surface the wrong way are
+        // dropped rather than leaking light (see `HitRecord::shading_agrees`).
+        if pdf.is_some_and(|pdf| pdf <= 0.0) || !rec.shading_agrees(-ray.dir, scattered.dir) {
+            if let Some(stats) = stats {
+                stats.terminate(bounce + 1);
+            }
+            return None;
+        }
+        state.throughput *= weight;
+        state.check_finite(rec.mat.name());
+
+        if bounce + 1 >= self.roulette_depth {
+            let survival = state.throughput.max_component().min(0.95);
+            if random_f64(rng) >= survival {
+                if let Some(stats) = stats {
+                    stats.log(|| PathEvent::Roulette { bounce });
+                    stats.terminate(bounce + 1);
+                }
+                return None;
+            }
+            state.throughput /= survival;
+        }
+        if let Some(stats) = stats {
+            stats.throughput = state.throughput;
+        }
+        Some(scattered)
+    }
+}
+
+/// What a path has gathered so far.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PathState {
+    /// Product of the bounce weights, what light found further along is scaled by.
+    pub throughput: Color,
+    pub radiance: Color,
+    /// What the path was on when its throughput or radiance first stopped being
+    /// finite: a material's name, or "background".
+    pub culprit: Option<&'static str>,
+}
+
+impl PathState {
+    pub fn new() -> Self {
+        Self {
+            throughput: Color::new(1.0, 1.0, 1.0),
+            radiance: Color::BLACK,
+            culprit: None,
+        }
+    }
+
+    // Blames `culprit` if this is where the path first went NaN or infinite.
+    fn check_finite(&mut self, culprit: &'static str) {
+        if self.culprit.is_none() && !(self.throughput.is_finite() && self.radiance.is_finite()) {
+            self.culprit = Some(culprit);
+        }
+    }
+}
+
+// Closest hit of the ray a path follows after `bounce` bounces. Camera rays skip the
+// objects culled from the camera.
+pub(crate) fn intersect(ray: &Ray, bounce: u32, scene: &Scene) -> Option<HitRecord> {
+    // Rays leaving surfaces start off them (`HitRecord::spawn_origin`), so any hit in
+    // front of the origin counts.
+    let hit_interval = Interval::new(0.0, INFINITY);
+    if bounce == 0 {
+        scene.hit_primary(ray, hit_interval)
+    } else {
+        scene.hit(ray, hit_interval)
+    }
+}
+
+impl Integrator for PathTracer {
+    fn radiance(&self, r: &Ray, scene: &Scene, max_depth: u32, rng: &mut SmallRng) -> Color {
+        self.trace(r, max_depth, scene, rng, None)
+    }
+}
+
+// Next-event estimation: one shadow ray per explicit light in the scene.
+fn direct_light(r: &Ray, rec: &HitRecord, scene: &Scene) -> Color {
+    let mut direct = Color::BLACK;
+    for light in scene.lights.iter() {
+        let sample = light.sample(rec.p);
+        let f = rec.mat.eval(r, rec, sample.direction);
+        if f.near_zero() || !rec.shading_agrees(-r.dir, sample.direction) {
+            continue;
+        }
+        let shadow_ray = Ray::with_time(rec.spawn_origin(sample.direction), sample.direction, r.tm);
+        ray_stats::count(Counter::ShadowRays);
+        if scene
+            .hit(
+                &shadow_ray,
+                Interval::new(0.0, sample.distance - scene.epsilon()),
+            )
+            .is_none()
+        {
+            // Finite-distance lights fall off with distance measured in meters.
+            let falloff = if sample.distance.is_finite() {
+                scene.units.falloff_scale()
+            } else {
+                1.0
+            };
+            direct += f * sample.radiance * falloff;
+        }
+    }
+    direct
+}
+
+/// Ambient occlusion: white where a random direction over the hemisphere around the
+/// surface escapes within `distance`, black where it is blocked. Lighting and materials
+/// are ignored, which makes it a quick check of geometry and contact shadows.
+#[derive(Debug, Clone, Copy)]
+pub struct AmbientOcclusion {
+    pub distance: f64,
+}
+
+impl Integrator for AmbientOcclusion {
+    fn radiance(&self, r: &Ray, scene: &Scene, _max_depth: u32, rng: &mut SmallRng) -> Color {
+        let Some(rec) = scene.hit_primary(r, Interval::new(0.0, INFINITY)) else {
+            return Color::new(1.0, 1.0, 1.0);
+        };
+        // Cosine-weighted, so the average matches what a diffuse surface would see.
+        let mut dir = rec.normal + Vec3::random_unit_vector(rng);
+        if dir.near_zero() {
+            dir = rec.normal;
+        }
+        let probe = Ray::with_time(rec.spawn_origin(dir), dir, r.tm);
+        let reach = self.distance / dir.length();
+        match scene.hit(&probe, Interval::new(0.0, reach)) {
+            Some(_) => Color::BLACK,
+            None => Color::new(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+/// Shading normal at the first hit, mapped from [-1, 1] to [0, 1] per axis (black
+/// where nothing is hit).
+#[derive(Debug, Clone, Copy)]
+pub struct NormalView;
+
+impl Integrator for NormalView {
+    fn radiance(&self, r: &Ray, scene: &Scene, _max_depth: u32, _rng: &mut SmallRng) -> Color {
+        match scene.hit_primary(r, Interval::new(0.0, INFINITY)) {
+            Some(rec) => Color::from_vec3(0.5 * (rec.normal + Vec3::new(1.0, 1.0, 1.0))),
+            None => Color::BLACK,
+        }
+    }
+}
+
+/// Distance to the first hit as gray, black at the camera to white at `far` and
+/// beyond (white where nothing is hit).
+#[derive(Debug, Clone, Copy)]
+pub struct DepthView {
+    pub far: f64,
+}
+
+impl Integrator for DepthView {
+    fn radiance(&self, r: &Ray, scene: &Scene, _max_depth: u32, _rng: &mut SmallRng) -> Color {
+        let d = scene
+            .hit_primary(r, Interval::new(0.0, INFINITY))
+            .map_or(f64::INFINITY, |rec| rec.t * r.dir.length());
+        let g = (d / self.far).min(1.0);
+        Color::new(g, g, g)
+    }
+}
+
+/// The camera's choice of integrator, as stored in scene files.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum IntegratorKind {
+    /// Path tracing that finds lights only by hitting them.
+    Path,
+    /// Path tracing with next-event estimation.
+    #[default]
+    PathNee,
+    AmbientOcclusion {
+        distance: f64,
+    },
+    Normals,
+    Depth {
+        far: f64,
+    },
+}
+
+impl IntegratorKind {
+    /// The path tracer this runs, `None` for the diagnostic views.
+    pub(crate) fn path_tracer(&self) -> Option<PathTracer> {
+        match self {
+            Self::Path => Some(PathTracer::naive()),
+            Self::PathNee => Some(PathTracer::nee()),
+            _ => None,
+        }
+    }
+
+    /// Parses the command-line spelling: `path`, `nee`, `ao[:DISTANCE]`, `normals` or
+    /// `depth[:FAR]`.
+    pub fn parse(s: &str) -> Option<Self> {
+        let (name, arg) = match s.split_once(':') {
+            Some((name, arg)) => (name, Some(arg.parse::<f64>().ok()?)),
+            None => (s, None),
+        };
+        match name {
+            "path" => Some(Self::Path),
+            "nee" => Some(Self::PathNee),
+            "ao" => Some(Self::AmbientOcclusion {
+                distance: arg.unwrap_or(1.0),
+            }),
+            "normals" => Some(Self::Normals),
+            "depth" => Some(Self::Depth {
+                far: arg.unwrap_or(20.0),
+            }),
+            _ => None,
+        }
+    }
+}
+
+impl Integrator for IntegratorKind {
+    fn radiance(&self, r: &Ray, scene: &Scene, max_depth: u32, rng: &mut SmallRng) -> Color {
+        match *self {
+            Self::Path => PathTracer::naive().radiance(r, scene, max_depth, rng),
+            Self::PathNee => PathTracer::nee().radiance(r, scene, max_depth, rng),
+            Self::AmbientOcclusion { distance } => {
+                AmbientOcclusion { distance }.radiance(r, scene, max_depth, rng)
+            }
+            Self::Normals => NormalView.radiance(r, scene, max_depth, rng),
+            Self::Depth { far } => DepthView { far }.radiance(r, scene, max_depth, rng),
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Set once a render has been asked to stop; only `clear` resets it.
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Asks renders in progress to stop. The tile scheduler finishes the tiles it's working
+/// on and returns the film as it stands, so the image written afterwards holds everything
+/// rendered so far, with the unrendered tiles left black.
+pub fn request() {
+    REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// Whether `request` has been called, e.g. by the Ctrl-C handler.
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::Relaxed)
+}
+
+/// Lets renders run to completion again after an interrupt.
+pub fn clear() {
+    REQUESTED.store(false, Ordering::Relaxed);
+}
+
+/// Makes Ctrl-C (SIGINT) and SIGTERM call `request` instead of killing the process; a
+/// second one kills it as usual. Returns false where signals aren't supported.
+pub fn install_handler() -> bool {
+    #[cfg(unix)]
+    {
+        extern "C" fn on_signal(signal: libc::c_int) {
+            REQUESTED.store(true, Ordering::Relaxed);
+            // Both calls are async-signal-safe.
+            unsafe { libc::signal(signal, libc::SIG_DFL) };
+        }
+        let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        unsafe {
+            libc::signal(libc::SIGINT, handler) != libc::SIG_ERR
+                && libc::signal(libc::SIGTERM, handler) != libc::SIG_ERR
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        false
+    }
+}
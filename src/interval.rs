@@ -1,4 +1,5 @@
 use crate::rtweekend::INFINITY;
+use std::ops::Add;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Interval {
@@ -33,6 +34,26 @@ impl Interval {
         max: INFINITY,
     };
 
+    /// The interval widened by `delta` in total, half on each side.
+    pub fn expand(&self, delta: f64) -> Self {
+        let padding = delta / 2.0;
+        Self::new(self.min - padding, self.max + padding)
+    }
+
+    /// Overlap of the two intervals, empty (`min > max`) if they don't overlap.
+    pub fn intersect(&self, other: &Interval) -> Self {
+        Self::new(self.min.max(other.min), self.max.min(other.max))
+    }
+
+    /// Smallest interval containing both.
+    pub fn union(&self, other: &Interval) -> Self {
+        Self::new(self.min.min(other.min), self.max.max(other.max))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min > self.max
+    }
+
     pub fn clamp(&self, x: f64) -> f64 {
         if x < self.min {
             self.min
@@ -49,3 +70,76 @@ impl Default for Interval {
         Self::EMPTY
     }
 }
+
+/// The interval shifted by `offset`.
+impl Add<f64> for Interval {
+    type Output = Self;
+
+    fn add(self, offset: f64) -> Self {
+        Self::new(self.min + offset, self.max + offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expand_widens_both_sides() {
+        let i = Interval::new(1.0, 2.0).expand(0.5);
+        assert_eq!(i, Interval::new(0.75, 2.25));
+        assert_eq!(i.size(), 1.5);
+    }
+
+    #[test]
+    fn expand_by_negative_delta_shrinks() {
+        assert_eq!(
+            Interval::new(0.0, 4.0).expand(-2.0),
+            Interval::new(1.0, 3.0)
+        );
+    }
+
+    #[test]
+    fn intersect_overlapping() {
+        let a = Interval::new(0.0, 2.0);
+        let b = Interval::new(1.0, 3.0);
+        assert_eq!(a.intersect(&b), Interval::new(1.0, 2.0));
+        assert_eq!(b.intersect(&a), Interval::new(1.0, 2.0));
+    }
+
+    #[test]
+    fn intersect_disjoint_is_empty() {
+        let i = Interval::new(0.0, 1.0).intersect(&Interval::new(2.0, 3.0));
+        assert!(i.is_empty());
+        assert!(!i.contains(0.5) && !i.contains(2.5));
+    }
+
+    #[test]
+    fn intersect_with_universe_is_identity() {
+        let a = Interval::new(-1.0, 5.0);
+        assert_eq!(a.intersect(&Interval::UNIVERSE), a);
+        assert!(a.intersect(&Interval::EMPTY).is_empty());
+    }
+
+    #[test]
+    fn union_spans_both() {
+        let a = Interval::new(0.0, 1.0);
+        let b = Interval::new(3.0, 4.0);
+        assert_eq!(a.union(&b), Interval::new(0.0, 4.0));
+        assert_eq!(a.union(&Interval::EMPTY), a);
+        assert_eq!(Interval::EMPTY.union(&Interval::EMPTY), Interval::EMPTY);
+    }
+
+    #[test]
+    fn add_offsets_both_ends() {
+        let i = Interval::new(1.0, 2.0) + 3.0;
+        assert_eq!(i, Interval::new(4.0, 5.0));
+        assert_eq!(Interval::new(1.0, 2.0) + -1.0, Interval::new(0.0, 1.0));
+    }
+
+    #[test]
+    fn add_keeps_empty_and_universe() {
+        assert!((Interval::EMPTY + 1.0).is_empty());
+        assert_eq!(Interval::UNIVERSE + 1.0, Interval::UNIVERSE);
+    }
+}
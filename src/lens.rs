@@ -0,0 +1,131 @@
+use crate::rtweekend::*;
+use serde::{Deserialize, Serialize};
+
+/// Physical sensor (film gate) size in millimeters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SensorFormat {
+    pub width_mm: f64,
+    pub height_mm: f64,
+}
+
+impl SensorFormat {
+    pub const FULL_FRAME: Self = Self::new(36.0, 24.0);
+    pub const APS_C: Self = Self::new(23.6, 15.6);
+    pub const APS_C_CANON: Self = Self::new(22.3, 14.9);
+    pub const MICRO_FOUR_THIRDS: Self = Self::new(17.3, 13.0);
+    pub const SUPER_35: Self = Self::new(24.89, 18.66);
+
+    pub const fn new(width_mm: f64, height_mm: f64) -> Self {
+        Self {
+            width_mm,
+            height_mm,
+        }
+    }
+
+    /// Focal length multiplier relative to full frame (by diagonal).
+    pub fn crop_factor(&self) -> f64 {
+        let diagonal = |s: &Self| (s.width_mm * s.width_mm + s.height_mm * s.height_mm).sqrt();
+        diagonal(&Self::FULL_FRAME) / diagonal(self)
+    }
+}
+
+/// Photographic lens: focal length and aperture as an f-number.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lens {
+    pub focal_length_mm: f64,
+    pub f_number: f64,
+}
+
+impl Lens {
+    pub const WIDE_24MM_F2_8: Self = Self::new(24.0, 2.8);
+    pub const STANDARD_35MM_F1_4: Self = Self::new(35.0, 1.4);
+    pub const NORMAL_50MM_F1_8: Self = Self::new(50.0, 1.8);
+    pub const PORTRAIT_85MM_F1_8: Self = Self::new(85.0, 1.8);
+    pub const TELE_135MM_F2: Self = Self::new(135.0, 2.0);
+
+    pub const fn new(focal_length_mm: f64, f_number: f64) -> Self {
+        Self {
+            focal_length_mm,
+            f_number,
+        }
+    }
+
+    pub fn preset(name: &str) -> Option<Self> {
+        match name {
+            "24mm f/2.8" => Some(Self::WIDE_24MM_F2_8),
+            "35mm f/1.4" => Some(Self::STANDARD_35MM_F1_4),
+            "50mm f/1.8" => Some(Self::NORMAL_50MM_F1_8),
+            "85mm f/1.8" => Some(Self::PORTRAIT_85MM_F1_8),
+            "135mm f/2" => Some(Self::TELE_135MM_F2),
+            _ => None,
+        }
+    }
+
+    /// Horizontal field of view in degrees when the sensor width fills the frame.
+    pub fn horizontal_fov(&self, sensor: SensorFormat) -> f64 {
+        2.0 * (sensor.width_mm / (2.0 * self.focal_length_mm))
+            .atan()
+            .to_degrees()
+    }
+
+    /// Entrance pupil diameter, in millimeters.
+    pub fn aperture_diameter_mm(&self) -> f64 {
+        self.focal_length_mm / self.f_number
+    }
+}
+
+/// Photographic exposure settings. With one in place the renderer treats radiance as
+/// luminance in cd/m² (so a `DiffuseLight` of 1000 is a 1000 nit panel) and scales it
+/// the way the camera would, so scenes can be lit in real-world units and relit by
+/// changing the exposure rather than every light.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Exposure {
+    pub iso: f64,
+    /// Shutter speed in seconds. This only sets brightness; the motion blur interval
+    /// is still `Camera::shutter_open`..`shutter_close`.
+    pub shutter_speed: f64,
+    pub f_number: f64,
+}
+
+impl Exposure {
+    /// The "sunny 16" rule: right for a subject in direct sunlight.
+    pub const SUNNY_16: Self = Self::new(100.0, 1.0 / 100.0, 16.0);
+    /// A typical bright interior.
+    pub const INDOOR: Self = Self::new(800.0, 1.0 / 60.0, 2.8);
+
+    pub const fn new(iso: f64, shutter_speed: f64, f_number: f64) -> Self {
+        Self {
+            iso,
+            shutter_speed,
+            f_number,
+        }
+    }
+
+    /// Exposure value normalized to ISO 100.
+    pub fn ev100(&self) -> f64 {
+        (self.f_number * self.f_number / self.shutter_speed * 100.0 / self.iso).log2()
+    }
+
+    /// Factor from scene luminance to pixel value: the luminance that saturates the
+    /// sensor maps to 1 (the standard saturation-based sensitivity, with its 1.2 factor
+    /// for lens losses).
+    pub fn scale(&self) -> f64 {
+        1.0 / (1.2 * self.ev100().exp2())
+    }
+}
+
+impl Camera {
+    /// Sets `vfov` and `defocus_angle` from a sensor and lens, so the shot can be set up
+    /// in photographic terms. The sensor width is fitted to the image width, and the
+    /// aperture is converted from millimeters to the scene's `units`. Call after setting
+    /// `focus_dist`, since the defocus cone depends on it.
+    pub fn set_lens(&mut self, sensor: SensorFormat, lens: Lens, units: Unit) {
+        let half_h = degrees_to_radians(lens.horizontal_fov(sensor) / 2.0).tan();
+        self.vfov = 2.0 * (half_h / self.aspect_ratio).atan().to_degrees();
+
+        let aperture_radius = 0.5 * lens.aperture_diameter_mm() * 0.001 / units.meters_per_unit();
+        self.defocus_angle = 2.0 * (aperture_radius / self.focus_dist).atan().to_degrees();
+
+        self.initialize();
+    }
+}
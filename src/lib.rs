@@ -0,0 +1,84 @@
+pub mod aabb;
+pub mod adaptive;
+pub mod animation;
+pub mod audit;
+pub mod bench;
+pub mod bvh;
+pub mod camera;
+pub mod color;
+pub mod cone;
+pub mod csg;
+pub mod culling;
+pub mod cylinder;
+pub mod depth_stats;
+pub mod disk;
+pub mod distributed;
+pub mod estimate;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod film;
+pub mod focus;
+pub mod furnace;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+pub mod grid;
+pub mod hittable;
+pub mod hittable_list;
+pub mod id_pass;
+pub mod instance;
+pub mod integrator;
+pub mod interrupt;
+pub mod interval;
+pub mod lens;
+pub mod light;
+pub mod light_tracer;
+pub mod loader;
+pub mod material;
+pub mod material_library;
+pub mod medium;
+pub mod mesh;
+pub mod microfacet;
+pub mod motion;
+pub mod noise;
+pub mod nonfinite;
+pub mod ocean;
+pub mod onb;
+pub mod output;
+pub mod parallel;
+pub mod pixel_debug;
+pub mod ply;
+pub mod png;
+pub mod projection;
+pub mod quad;
+pub mod quat;
+pub mod ray;
+pub mod ray_stats;
+pub mod rtweekend;
+pub mod scatter;
+pub mod scene;
+pub mod scene_file;
+pub mod scene_graph;
+pub mod scheduler;
+pub mod server;
+pub mod sided;
+pub mod sky;
+pub mod spectrum;
+pub mod sphere;
+pub mod sppm;
+pub mod stereo;
+pub mod stl;
+pub mod svo;
+pub mod texture;
+pub mod texture_registry;
+pub mod thin_film;
+pub mod transform;
+pub mod triangle;
+pub mod units;
+pub mod vec2;
+pub mod vec3;
+pub mod video;
+pub mod vox;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod watch;
+pub mod wavefront;
@@ -0,0 +1,90 @@
+use crate::rtweekend::*;
+
+/// Incident light arriving at a shading point from a single light.
+pub struct LightSample {
+    /// Unit direction from the shading point towards the light.
+    pub direction: Vec3,
+    /// Distance to the light along `direction` (`INFINITY` for directional lights).
+    pub distance: f64,
+    /// Radiance arriving at the shading point, falloff already applied.
+    pub radiance: Color,
+}
+
+/// Explicit (delta) light sources, sampled with shadow rays from `ray_color`.
+pub trait Light: Send + Sync {
+    fn sample(&self, p: Point3) -> LightSample;
+
+    /// Starts a photon for photon mapping: a ray leaving the light, aimed so it can
+    /// reach `bounds`, and the power it carries. `units` is the scene's unit, for
+    /// lights whose power depends on distance. `None` if the light can't emit photons.
+    fn emit(&self, _bounds: &Aabb, _units: Unit, _rng: &mut SmallRng) -> Option<(Ray, Color)> {
+        None
+    }
+}
+
+pub struct PointLight {
+    pub position: Point3,
+    pub intensity: Color,
+}
+
+impl PointLight {
+    pub fn new(position: Point3, intensity: Color) -> Self {
+        Self {
+            position,
+            intensity,
+        }
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, p: Point3) -> LightSample {
+        let to_light = self.position - p;
+        let distance = to_light.length();
+        LightSample {
+            direction: to_light / distance,
+            distance,
+            radiance: self.intensity / (distance * distance),
+        }
+    }
+
+    fn emit(&self, _bounds: &Aabb, units: Unit, rng: &mut SmallRng) -> Option<(Ray, Color)> {
+        let ray = Ray::new(self.position, Vec3::random_unit_vector(rng));
+        Some((ray, 4.0 * PI * self.intensity * units.falloff_scale()))
+    }
+}
+
+pub struct DirectionalLight {
+    /// Direction the light travels in (e.g. straight down is (0, -1, 0)).
+    pub direction: Vec3,
+    pub irradiance: Color,
+}
+
+impl DirectionalLight {
+    pub fn new(direction: Vec3, irradiance: Color) -> Self {
+        Self {
+            direction: direction.normalized(),
+            irradiance,
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _p: Point3) -> LightSample {
+        LightSample {
+            direction: -self.direction,
+            distance: INFINITY,
+            radiance: self.irradiance,
+        }
+    }
+
+    // From a disk facing the light that covers the bounding sphere of `bounds`.
+    fn emit(&self, bounds: &Aabb, _units: Unit, rng: &mut SmallRng) -> Option<(Ray, Color)> {
+        let radius = (bounds.max() - bounds.min()).length() / 2.0;
+        let onb = Onb::new(self.direction);
+        let disk = Vec3::random_in_unit_disk(rng);
+        let origin =
+            bounds.center() - radius * self.direction + radius * (disk.x * onb.u + disk.y * onb.v);
+        let power = self.irradiance * (PI * radius * radius);
+        Some((Ray::new(origin, self.direction), power))
+    }
+}
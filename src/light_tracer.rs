@@ -0,0 +1,118 @@
+use crate::output::ImageFormat;
+use crate::parallel::*;
+use crate::rtweekend::*;
+use crate::sppm::trace_photon;
+use std::io::{self, Write};
+
+/// Traces paths from the lights instead of the camera, splatting onto the film
+/// wherever a path lands on a diffuse surface the camera can see. Every pixel value
+/// comes straight from the emitted power, so it's a check on emission and falloff
+/// against the path tracer, and caustics, which light paths find easily, converge
+/// quickly.
+///
+/// It only sees light that reaches the camera off a diffuse surface: the background
+/// and emitters seen directly, and anything seen only in a mirror, stay black. The
+/// camera is treated as a perspective pinhole.
+#[derive(Debug, Clone, Copy)]
+pub struct LightTracer {
+    /// Light paths traced per film pixel.
+    pub paths_per_pixel: u32,
+}
+
+impl LightTracer {
+    pub fn new(paths_per_pixel: u32) -> Self {
+        Self { paths_per_pixel }
+    }
+
+    /// Renders `scene` through `camera` and returns the pixels like
+    /// `Camera::render_pixels`.
+    pub fn render_pixels(&self, camera: &Camera, scene: &Scene) -> Vec<Color> {
+        let (width, height) = (camera.film_width(), camera.film_height());
+        let film = Film::new(width, height, Filter::default());
+        let paths = width as usize * height as usize * self.paths_per_pixel as usize;
+        let Some(region) = visible_region(camera, scene) else {
+            // The camera sees nothing for light to land on.
+            return film.pixels(0.0);
+        };
+        let center = camera.center();
+        let pixel_area = camera.pixel_area();
+        let eps = scene.epsilon();
+
+        let seed = camera.seed.unwrap_or_else(random_seed);
+        log::info!(target: "render", "Tracing {} light paths...", paths);
+        (0..paths).into_par_iter().for_each(|k| {
+            let mut rng = stream_rng(seed, k as u64);
+            trace_photon(
+                camera.max_depth,
+                scene,
+                &region,
+                &mut rng,
+                |ray, rec, power| {
+                    let Some((x, y)) = camera.project(rec.p) else {
+                        return;
+                    };
+                    let to_camera = center - rec.p;
+                    let distance = to_camera.length();
+                    let wi = to_camera / distance;
+                    let cos_camera = -wi.dot(camera.forward());
+                    if cos_camera <= 0.0 || !rec.shading_agrees(-ray.dir, wi) {
+                        return;
+                    }
+                    let shadow_ray = Ray::with_time(rec.spawn_origin(wi), wi, ray.tm);
+                    ray_stats::count(Counter::ShadowRays);
+                    if scene
+                        .hit(&shadow_ray, Interval::new(0.0, distance - eps))
+                        .is_some()
+                    {
+                        return;
+                    }
+                    // The pinhole's importance spreads each pixel over a solid angle that
+                    // shrinks with cos^3 towards the edges (cos^4, less the projected
+                    // area).
+                    let f = rec.mat.eval(ray, rec, wi);
+                    let importance = 1.0 / (distance * distance * pixel_area * cos_camera.powi(3));
+                    film.add_splat(x, y, power * f * importance);
+                },
+            );
+        });
+
+        let scale = camera.exposure_scale() / paths.max(1) as f64;
+        film.pixels(scale)
+    }
+
+    /// Renders `scene` as an image in `format` into `out`.
+    pub fn render_to<W: Write>(
+        &self,
+        camera: &Camera,
+        scene: &Scene,
+        out: W,
+        format: ImageFormat,
+    ) -> io::Result<()> {
+        let pixels = self.render_pixels(camera, scene);
+        format.write(out, camera.film_width(), camera.film_height(), &pixels)
+    }
+}
+
+// Box around what a coarse grid of camera rays hits, padded so that objects just out
+// of view still get light paths. Lights aim their paths at it.
+fn visible_region(camera: &Camera, scene: &Scene) -> Option<Aabb> {
+    const PROBES: u32 = 64;
+    let interval = Interval::new(0.0, INFINITY);
+    let mut region = Aabb::EMPTY;
+    for py in 0..PROBES {
+        for px in 0..PROBES {
+            let i = px * camera.film_width() / PROBES;
+            let j = py * camera.film_height() / PROBES;
+            let r = camera.get_ray(i, j, &mut camera.pixel_rng(i, j));
+            if let Some(rec) = scene.hit_primary(&r, interval) {
+                region = region.union(&Aabb::from_points(rec.p, rec.p));
+            }
+        }
+    }
+    if !region.is_bounded() {
+        return None;
+    }
+    let pad = (region.max() - region.min()).length() * 0.25;
+    let pad = Vec3::new(pad, pad, pad);
+    Some(Aabb::from_points(region.min() - pad, region.max() + pad))
+}
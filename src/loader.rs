@@ -0,0 +1,99 @@
+use crate::parallel::*;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Assets taking longer than this are listed in the summary after loading.
+const SLOW_ASSET_THRESHOLD: Duration = Duration::from_millis(500);
+
+/// A named unit of work producing one scene asset (mesh, texture, ...).
+pub struct LoadJob<T> {
+    pub name: String,
+    load: Box<dyn FnOnce() -> io::Result<T> + Send>,
+}
+
+impl<T> LoadJob<T> {
+    pub fn new(
+        name: impl Into<String>,
+        load: impl FnOnce() -> io::Result<T> + Send + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            load: Box::new(load),
+        }
+    }
+}
+
+pub struct LoadReport {
+    pub timings: Vec<(String, Duration)>,
+    pub total: Duration,
+}
+
+impl LoadReport {
+    /// Assets over the slow threshold, slowest first.
+    pub fn slow_assets(&self) -> Vec<&(String, Duration)> {
+        let mut slow: Vec<_> = self
+            .timings
+            .iter()
+            .filter(|(_, elapsed)| *elapsed >= SLOW_ASSET_THRESHOLD)
+            .collect();
+        slow.sort_by_key(|(_, elapsed)| std::cmp::Reverse(*elapsed));
+        slow
+    }
+
+    pub fn log_summary(&self) {
+        log::info!(
+            target: "loader",
+            "Loaded {} assets in {:.2}s",
+            self.timings.len(),
+            self.total.as_secs_f64()
+        );
+        for (name, elapsed) in self.slow_assets() {
+            log::warn!(target: "loader", "slow: {:<40} {:.2}s", name, elapsed.as_secs_f64());
+        }
+    }
+}
+
+/// Runs all jobs in parallel, reporting progress as each asset finishes.
+/// Results come back in job order so callers can build the world (and its BVH)
+/// deterministically afterwards.
+pub fn load_all<T: Send>(jobs: Vec<LoadJob<T>>) -> io::Result<(Vec<T>, LoadReport)> {
+    let start = Instant::now();
+    let total = jobs.len();
+    let done = AtomicUsize::new(0);
+
+    let results: Vec<(String, Duration, io::Result<T>)> = jobs
+        .into_par_iter()
+        .map(|job| {
+            let job_start = Instant::now();
+            let result = (job.load)();
+            let elapsed = job_start.elapsed();
+            let finished = done.fetch_add(1, Ordering::Relaxed) + 1;
+            let status = if result.is_ok() { "loaded" } else { "FAILED" };
+            log::info!(
+                target: "loader",
+                "[{}/{}] {} {} ({} ms)",
+                finished,
+                total,
+                status,
+                job.name,
+                elapsed.as_millis()
+            );
+            (job.name, elapsed, result)
+        })
+        .collect();
+
+    let mut assets = Vec::with_capacity(total);
+    let mut timings = Vec::with_capacity(total);
+    for (name, elapsed, result) in results {
+        let asset = result.map_err(|e| io::Error::new(e.kind(), format!("{}: {}", name, e)))?;
+        assets.push(asset);
+        timings.push((name, elapsed));
+    }
+
+    let report = LoadReport {
+        timings,
+        total: start.elapsed(),
+    };
+    Ok((assets, report))
+}
@@ -1,83 +1,723 @@
-mod camera;
-mod color;
-mod hittable;
-mod hittable_list;
-mod interval;
-mod material;
-mod ray;
-mod rtweekend;
-mod sphere;
-mod vec3;
-
-use crate::rtweekend::*;
-use std::io;
-use std::sync::Arc; // Make sure PI is available
+use rand::SeedableRng;
+use ray_tracing_weekend::interrupt;
+use ray_tracing_weekend::output::{ImageFormat, OutputVars, expand_template};
+use ray_tracing_weekend::rtweekend::*;
+use ray_tracing_weekend::scene_file::{MAIN_CAMERA, MaterialDesc, SceneFile, ShapeDesc};
+use ray_tracing_weekend::texture::parse_ppm;
+use ray_tracing_weekend::video::is_video_path;
+use std::io::{self, Write};
+use std::path::Path;
 
 // Assuming your imports for Color, Point3, Vec3, Lambertian, Dielectric, Metal,
 // Sphere, HittableList, Camera, etc., are at the top of your main.rs
 
+// Value following `name` on the command line, e.g. `--output renders/{frame}.ppm`.
+fn arg_value(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != name);
+    args.next()?;
+    args.next()
+}
+
+// The `n` values following `name`, e.g. the four corners after `--crop`.
+fn arg_values(name: &str, n: usize) -> Option<Vec<String>> {
+    let values: Vec<String> = std::env::args()
+        .skip_while(|arg| arg != name)
+        .skip(1)
+        .take(n)
+        .collect();
+    (values.len() == n).then_some(values)
+}
+
+// `--crop x0 y0 x1 y1` as a tile of the film, the end corner exclusive.
+fn parse_crop(values: &[String], cam: &Camera) -> io::Result<Tile> {
+    let invalid = || {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "--crop expects x0 y0 x1 y1 with 0 <= x0 < x1 <= {} and 0 <= y0 < y1 <= {}",
+                cam.film_width(),
+                cam.film_height()
+            ),
+        )
+    };
+    let mut corners = [0u32; 4];
+    for (corner, value) in corners.iter_mut().zip(values) {
+        *corner = value.parse().map_err(|_| invalid())?;
+    }
+    let [x0, y0, x1, y1] = corners;
+    if x0 >= x1 || y0 >= y1 || x1 > cam.film_width() || y1 > cam.film_height() {
+        return Err(invalid());
+    }
+    Ok(Tile {
+        x: x0,
+        y: y0,
+        width: x1 - x0,
+        height: y1 - y0,
+    })
+}
+
+// Pastes the rendered `crop` over a full frame rendered earlier, read from `base`.
+fn composite(base: &str, cam: &Camera, crop: Tile, pixels: &[Color]) -> io::Result<Vec<Color>> {
+    let (width, height, frame) = parse_ppm(&std::fs::read(base)?)?;
+    if (width, height) != (cam.film_width(), cam.film_height()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} is {}x{}, but the film is {}x{}",
+                base,
+                width,
+                height,
+                cam.film_width(),
+                cam.film_height()
+            ),
+        ));
+    }
+    // The file holds gamma-encoded values; `write_ppm` expects linear ones.
+    let mut frame: Vec<Color> = frame.into_iter().map(|c| c * c).collect();
+    for (row, line) in pixels.chunks(crop.width as usize).enumerate() {
+        let start = ((crop.y + row as u32) * width + crop.x) as usize;
+        frame[start..start + line.len()].copy_from_slice(line);
+    }
+    Ok(frame)
+}
+
+// Renderer messages go through `log`, under the targets `render`, `loader`, `bvh`,
+// `server` and `distributed`: info and up by default, debug with `--verbose` and only
+// warnings and errors with `--quiet`. `RUST_LOG`, e.g. `RUST_LOG=bvh=debug`, refines that.
+fn init_logging() {
+    let level = if std::env::args().any(|arg| arg == "--verbose") {
+        log::LevelFilter::Debug
+    } else if std::env::args().any(|arg| arg == "--quiet") {
+        log::LevelFilter::Warn
+    } else {
+        log::LevelFilter::Info
+    };
+    env_logger::Builder::new()
+        .filter_level(level)
+        .parse_env("RUST_LOG")
+        .format(|buf, record| {
+            // Plain progress messages, tagged warnings and the like.
+            if record.level() == log::Level::Info {
+                writeln!(buf, "{}", record.args())
+            } else {
+                let level = record.level().as_str().to_lowercase();
+                writeln!(buf, "{} [{}]: {}", level, record.target(), record.args())
+            }
+        })
+        .init();
+}
+
 fn main() -> io::Result<()> {
+    init_logging();
+    if std::env::args().nth(1).as_deref() == Some("bench") {
+        ray_tracing_weekend::bench::run();
+        return Ok(());
+    }
+    if std::env::args().nth(1).as_deref() == Some("audit") {
+        let failures = ray_tracing_weekend::audit::run();
+        if !failures.is_empty() {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if std::env::args().nth(1).as_deref() == Some("furnace") {
+        let failures = ray_tracing_weekend::furnace::run();
+        if !failures.is_empty() {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if std::env::args().nth(1).as_deref() == Some("serve") {
+        let addr = arg_value("--listen").unwrap_or_else(|| "127.0.0.1:8080".to_string());
+        let mut limits = ServerLimits::default();
+        let count = |name: &str| -> io::Result<Option<u64>> {
+            arg_value(name)
+                .map(|value| {
+                    value.parse().map_err(|_| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!("{} expects a count", name),
+                        )
+                    })
+                })
+                .transpose()
+        };
+        if let Some(n) = count("--max-pixels")? {
+            limits.max_pixels = n;
+        }
+        if let Some(n) = count("--max-spp")? {
+            limits.max_samples = n.try_into().unwrap_or(u32::MAX);
+        }
+        if let Some(n) = count("--keep-jobs")? {
+            limits.max_finished_jobs = n as usize;
+        }
+        return RenderServer::with_limits(limits).serve(addr);
+    }
+    if std::env::args().nth(1).as_deref() == Some("worker") {
+        // Local only unless --listen says otherwise, as workers take scenes from anyone.
+        let addr = arg_value("--listen").unwrap_or_else(|| "127.0.0.1:7878".to_string());
+        return run_worker(addr);
+    }
+
+    // A saved scene replaces the procedural one entirely.
+    let file = match arg_value("--load-scene") {
+        Some(path) => SceneFile::load(path)?,
+        None => build_scene()?,
+    };
+    if let Some(path) = arg_value("--save-scene") {
+        file.save(&path)?;
+        log::info!(target: "render", "Saved scene to {}", path);
+    }
+    let (_, mut scene) = file.build();
+    let accelerator = match arg_value("--accel") {
+        Some(name) => Accelerator::parse(&name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "--accel expects list, bvh or grid",
+            )
+        })?,
+        None => Accelerator::default(),
+    };
+    scene.accelerate(accelerator);
+    // From here on Ctrl-C stops the render and writes what it has; see `finish`.
+    interrupt::install_handler();
+    if std::env::args().any(|arg| arg == "--watch") {
+        // Keep refining the saved scene, starting over whenever the file is saved.
+        let path = arg_value("--load-scene").ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "--watch needs --load-scene")
+        })?;
+        let output = output_template().unwrap_or_else(|| "preview.ppm".to_string());
+        let mut preview = LivePreview::new(output);
+        preview.accelerator = accelerator;
+        if let Some(camera) = arg_value("--camera") {
+            preview.camera = camera;
+        }
+        if let Some(samples) = arg_value("--pass-samples") {
+            preview.pass_samples = samples.parse().map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "--pass-samples expects a count",
+                )
+            })?;
+        }
+        return preview.run(path);
+    }
+    // Every camera shares the scene built above, so switching views costs no rebuild.
+    let camera_name = arg_value("--camera").unwrap_or_else(|| MAIN_CAMERA.to_string());
+    let mut cam = file.camera(&camera_name).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "no camera `{}` (have: {})",
+                camera_name,
+                file.camera_names().join(", ")
+            ),
+        )
+    })?;
+    if std::env::args().any(|arg| arg == "--adaptive") {
+        cam.adaptive = true;
+    }
+    if std::env::args().any(|arg| arg == "--panorama") {
+        // Full 360° view from the camera position, usable as an environment map.
+        cam.projection = Projection::Equirectangular;
+        cam.aspect_ratio = 2.0;
+        cam.initialize();
+    } else if let Some(fov) = arg_value("--fisheye") {
+        let fov = fov.parse().map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidInput, "--fisheye expects an angle")
+        })?;
+        cam.projection = Projection::Fisheye { fov };
+    }
+    if let Some(filter) = arg_value("--filter") {
+        // A name with an optional radius in pixels, e.g. `gaussian` or `mitchell:1.5`.
+        let (name, radius) = match filter.split_once(':') {
+            Some((name, radius)) => (name, Some(radius)),
+            None => (filter.as_str(), None),
+        };
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "--filter expects box, tent, gaussian, mitchell or blackman-harris, \
+                 optionally followed by :RADIUS",
+            )
+        };
+        cam.filter = Filter::named(name).ok_or_else(invalid)?;
+        if let Some(radius) = radius {
+            let radius: f64 = radius.parse().map_err(|_| invalid())?;
+            if radius <= 0.0 {
+                return Err(invalid());
+            }
+            cam.filter = cam.filter.with_radius(radius);
+        }
+    }
+    if let Some(backend) = arg_value("--backend") {
+        cam.backend = Backend::parse(&backend).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "--backend expects megakernel or wavefront",
+            )
+        })?;
+    }
+    if std::env::args().any(|arg| arg == "--check-nan") {
+        cam.check_finite = true;
+    }
+    if let Some(seed) = arg_value("--seed") {
+        // Same seed, same image: for comparing renders and bisecting changes.
+        cam.seed = Some(seed.parse().map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidInput, "--seed expects a whole number")
+        })?);
+    }
+    if let Some(integrator) = arg_value("--integrator") {
+        cam.integrator = IntegratorKind::parse(&integrator).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "--integrator expects path, nee, ao[:DISTANCE], normals or depth[:FAR]",
+            )
+        })?;
+    }
+    if let Some(workers) = arg_value("--workers") {
+        // Comma-separated addresses of machines running `rt worker`.
+        for addr in workers.split(',').filter(|a| !a.is_empty()) {
+            let device = RemoteDevice::new(addr, &file)?;
+            device
+                .connect()
+                .map_err(|e| io::Error::new(e.kind(), format!("worker {}: {}", addr, e)))?;
+            cam = cam.with_device(Arc::new(device));
+        }
+    }
+    #[cfg(feature = "gpu")]
+    if std::env::args().any(|arg| arg == "--gpu") {
+        // Renders on the GPU as well as the CPU, or on the CPU alone if the scene or
+        // the machine isn't up to it.
+        match ray_tracing_weekend::gpu::GpuDevice::new(&file) {
+            Ok(device) => {
+                log::info!(target: "render", "Rendering on {} as well", device.name());
+                cam = cam.with_device(Arc::new(device));
+            }
+            Err(e) => log::warn!(target: "render", "{}; rendering on the CPU", e),
+        }
+    }
+
+    // --- Render ---
+    log::info!(target: "render", "Starting render with high quality settings...");
+    log::info!(
+        target: "render",
+        "Image Width: {}, Samples/Pixel: {}, Max Depth: {}",
+        cam.image_width,
+        cam.samples_per_pixel,
+        cam.max_depth
+    );
+    if std::env::args().any(|arg| arg == "--dry-run") {
+        // Probe a 16x16 grid of pixels instead of rendering, and report the projection.
+        cam.estimate(&scene, 16).log_summary();
+        return Ok(());
+    }
+    if std::env::args().any(|arg| arg == "--depth-stats") {
+        // Depth AOV in place of the image, with the per-bounce breakdown logged.
+        ppm_only("--depth-stats")?;
+        let stats = cam.depth_statistics(&scene);
+        stats.log_summary();
+        return write_output(
+            output_template().as_deref(),
+            &output_vars(&cam, &camera_name)?,
+            |mut out, _| stats.write_depth_aov(&mut out),
+        );
+    }
+    if let Some(values) = arg_values("--debug-pixel", 2) {
+        // Trace a single pixel and log every bounce instead of rendering.
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "--debug-pixel expects x y with x < {} and y < {}",
+                    cam.film_width(),
+                    cam.film_height()
+                ),
+            )
+        };
+        let i: u32 = values[0].parse().map_err(|_| invalid())?;
+        let j: u32 = values[1].parse().map_err(|_| invalid())?;
+        if i >= cam.film_width() || j >= cam.film_height() {
+            return Err(invalid());
+        }
+        let samples = match arg_value("--samples") {
+            Some(n) => n.parse().map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidInput, "--samples expects a count")
+            })?,
+            None => 16,
+        };
+        cam.debug_pixel(&scene, i, j, samples).log_trace();
+        return Ok(());
+    }
+    if let Some(pass) = arg_value("--id-pass") {
+        // Color-coded object or material IDs in place of the image.
+        let pass = IdPass::parse(&pass).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "--id-pass expects `object` or `material`",
+            )
+        })?;
+        ppm_only("--id-pass")?;
+        let ids = cam.render_ids(&scene, pass);
+        return write_output(
+            output_template().as_deref(),
+            &output_vars(&cam, &camera_name)?,
+            |mut out, _| ids.write_color_coded(&mut out),
+        );
+    }
+    if let Some(name) = arg_value("--matte") {
+        // Anti-aliased coverage matte of one named object in place of the image.
+        ppm_only("--matte")?;
+        let coverage = cam.render_coverage(&scene, IdPass::Object, 6);
+        return write_output(
+            output_template().as_deref(),
+            &output_vars(&cam, &camera_name)?,
+            |mut out, _| coverage.write_matte(name_id(&name), &mut out),
+        );
+    }
+    if let Some(frames) = arg_value("--turntable") {
+        // Orbit the camera once around the scene, one image per frame.
+        let frames = frames.parse().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "--turntable expects a frame count",
+            )
+        })?;
+        let template = output_template().unwrap_or_else(|| "frame_{frame:04}.ppm".to_string());
+        let animation = Animation::turntable(&cam, 24.0, frames);
+        // A video extension pipes the frames into ffmpeg instead of writing images.
+        if is_video_path(Path::new(&template)) {
+            animation.render_video(&cam, &scene, Path::new(&template))?;
+        } else {
+            animation.render(&cam, &scene, &template, "spheres", format_arg()?)?;
+        }
+        return finish();
+    }
+    if std::env::args().any(|arg| arg == "--all-cameras") {
+        let template = output_template().unwrap_or_else(|| "{scene}_{camera}.ppm".to_string());
+        for name in file.camera_names() {
+            if interrupt::requested() {
+                break;
+            }
+            let mut view = file.camera(name).expect("listed camera exists");
+            view.adaptive = cam.adaptive;
+            let vars = output_vars(&view, name)?;
+            write_output(Some(&template), &vars, |out, format| {
+                view.render_to(&scene, out, format)
+            })?;
+        }
+        return finish();
+    }
+    if let Some(iterations) = arg_value("--sppm") {
+        // Progressive photon mapping instead of path tracing, for caustics.
+        let iterations = iterations.parse().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "--sppm expects an iteration count",
+            )
+        })?;
+        let mut sppm = Sppm::new(iterations, 200_000);
+        if let Some(photons) = arg_value("--photons") {
+            sppm.photons_per_iteration = photons.parse().map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidInput, "--photons expects a count")
+            })?;
+        }
+        write_output(
+            output_template().as_deref(),
+            &output_vars(&cam, &camera_name)?,
+            |out, format| sppm.render_to(&cam, &scene, out, format),
+        )?;
+        return finish();
+    }
+    if let Some(paths) = arg_value("--light-trace") {
+        // Trace from the lights instead of the camera, N paths per pixel.
+        let paths = paths.parse().map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "--light-trace expects a path count per pixel",
+            )
+        })?;
+        let tracer = LightTracer::new(paths);
+        write_output(
+            output_template().as_deref(),
+            &output_vars(&cam, &camera_name)?,
+            |out, format| tracer.render_to(&cam, &scene, out, format),
+        )?;
+        return finish();
+    }
+    if let Some(layout) = arg_value("--stereo") {
+        // Left/right eye pair for VR viewers, in one image.
+        let mut rig = StereoRig::human(scene.units);
+        rig.layout = match layout.as_str() {
+            "sbs" => StereoLayout::SideBySide,
+            "tb" => StereoLayout::TopBottom,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "--stereo expects `sbs` or `tb`",
+                ));
+            }
+        };
+        if let Some(iod) = arg_value("--interocular") {
+            rig.interocular = iod.parse().map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "--interocular expects a distance",
+                )
+            })?;
+        }
+        write_output(
+            output_template().as_deref(),
+            &output_vars(&cam, &camera_name)?,
+            |out, format| rig.render_to(&cam, &scene, out, format),
+        )?;
+        return finish();
+    }
+    if let Some(path) = arg_value("--checkpoint") {
+        // Saved on Ctrl-C and resumed from on the next run with the same settings. The
+        // procedural scene is different every run, so only --load-scene ones resume.
+        cam = cam.with_checkpoint(path, file.fingerprint());
+    }
+    if let Some(values) = arg_values("--crop", 4) {
+        // Re-render just a region, e.g. at high spp while debugging a material. With
+        // --composite BASE.ppm it's pasted into that earlier full render.
+        let crop = parse_crop(&values, &cam)?;
+        let mut pixels = cam.render_crop(&scene, crop);
+        let (mut width, mut height) = (crop.width, crop.height);
+        if let Some(base) = arg_value("--composite") {
+            pixels = composite(&base, &cam, crop, &pixels)?;
+            (width, height) = (cam.film_width(), cam.film_height());
+        }
+        write_output(
+            output_template().as_deref(),
+            &output_vars(&cam, &camera_name)?,
+            |out, format| format.write(out, width, height, &pixels),
+        )?;
+        return finish();
+    }
+    write_output(
+        output_template().as_deref(),
+        &output_vars(&cam, &camera_name)?,
+        |out, format| cam.render_to(&scene, out, format),
+    )?;
+    finish()
+}
+
+// The `--output` (or `-o`) filename template, if one was given.
+fn output_template() -> Option<String> {
+    arg_value("--output").or_else(|| arg_value("-o"))
+}
+
+// Template values for what `cam` renders as camera `name`, plus `--frame`.
+fn output_vars(cam: &Camera, name: &str) -> io::Result<OutputVars> {
+    let mut vars = OutputVars::new("spheres", cam);
+    vars.camera = name.to_string();
+    if let Some(frame) = arg_value("--frame") {
+        vars.frame = frame
+            .parse()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "--frame expects a number"))?;
+    }
+    Ok(vars)
+}
+
+// The ID, matte and depth passes come out as plain PPM, so `mode` refuses an output
+// asking for another format before rendering anything.
+fn ppm_only(mode: &str) -> io::Result<()> {
+    let template = output_template();
+    match image_format(template.as_deref().map(Path::new))? {
+        ImageFormat::PlainPpm | ImageFormat::Ppm => Ok(()),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} only writes PPM images", mode),
+        )),
+    }
+}
+
+// Writes one image with `write`, to `template` expanded with `vars` (creating its
+// directories), or to stdout without one, in the format `image_format` picks.
+fn write_output(
+    template: Option<&str>,
+    vars: &OutputVars,
+    write: impl FnOnce(&mut dyn Write, ImageFormat) -> io::Result<()>,
+) -> io::Result<()> {
+    let Some(template) = template else {
+        let mut out = io::BufWriter::new(io::stdout().lock());
+        write(&mut out, image_format(None)?)?;
+        return out.flush();
+    };
+    let path = expand_template(template, vars)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let format = image_format(Some(&path))?;
+    let mut out = io::BufWriter::new(std::fs::File::create(&path)?);
+    write(&mut out, format)?;
+    out.flush()?;
+    log::info!(target: "render", "Wrote {}", path.display());
+    Ok(())
+}
+
+// Format named by `--format`, if there is one.
+fn format_arg() -> io::Result<Option<ImageFormat>> {
+    match arg_value("--format") {
+        Some(name) => ImageFormat::parse(&name).map(Some).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "--format expects p3, ppm, pam or png",
+            )
+        }),
+        None => Ok(None),
+    }
+}
+
+// Format of an image written to `path`, or to stdout without one: `--format`, else the
+// one the extension calls for, else plain PPM.
+fn image_format(path: Option<&Path>) -> io::Result<ImageFormat> {
+    if let Some(format) = format_arg()? {
+        return Ok(format);
+    }
+    let Some(path) = path else {
+        return Ok(ImageFormat::PlainPpm);
+    };
+    Ok(ImageFormat::from_path(path).unwrap_or_else(|| {
+        log::warn!(
+            target: "render",
+            "Unknown image extension, writing {} as plain PPM",
+            path.display()
+        );
+        ImageFormat::PlainPpm
+    }))
+}
+
+// Reports how the render went. An interrupted one has written its partial image by now,
+// and exits with the status a shell gives a process stopped by Ctrl-C.
+fn finish() -> io::Result<()> {
+    if interrupt::requested() {
+        log::warn!(target: "render", "Render interrupted, the output holds what was finished");
+        std::process::exit(130);
+    }
+    log::info!(target: "render", "Render finished!");
+    Ok(())
+}
+
+// Make sure you have random utilities. If not, you can use the `rand` crate:
+// Add `rand = "0.8"` to your Cargo.toml
+// And `use rand::Rng;` in your file.
+// Then replace `rand::random::<f64>()` with `rand::thread_rng().gen::<f64>()`
+// and `rand::random_range(min, max)` with `rand::thread_rng().gen_range(min..max)`
+// Or adapt to your Vec3::random() and Vec3::random_range() if you have them.
+
+// The showcase scene: three large orbs over a dark mirror floor, surrounded by a field
+// of small random spheres. Built as a `SceneFile` so it can be saved with --save-scene.
+fn build_scene() -> io::Result<SceneFile> {
+    // --- Camera Settings ---
+    // High quality settings - WILL BE SLOW!
+    let aspect_ratio = 16.0 / 9.0;
+    let image_width: u32 = 600; // Higher resolution (e.g., 1920 or 2560)
+    let sample_per_pixel: u32 = 10; // Significantly more samples for AA and soft effects
+    // Consider 1000-5000 for "final" quality if you have patience
+    let max_depth: u32 = 10; // Good depth for complex interactions
+
+    // Camera positioning for a dramatic, slightly low angle shot
+    let vfov = 25.0; // A bit wider than your example, but not too extreme
+    let lookfrom = Point3::new(8.0, 2.5, 10.0); // Further back, slightly elevated, off to the side
+    let lookat = Point3::new(0.0, 0.5, 0.0); // Look towards the center of the large orbs, slightly above ground
+    let vup = Vec3::new(0.0, 1.0, 0.0);
+
+    // Depth of field settings - focus on one of the main orbs or a point between them
+    let defocus_angle = 0.8; // Subtle defocus, increase for more blur (e.g., 1.0 to 2.0)
+
+    let cam = Camera::builder()
+        .aspect_ratio(aspect_ratio)
+        .image_width(image_width)
+        .samples_per_pixel(sample_per_pixel)
+        .max_depth(max_depth)
+        .vfov(vfov)
+        .lookfrom(lookfrom)
+        .lookat(lookat)
+        .vup(vup)
+        .defocus_angle(defocus_angle)
+        .focus_on("center_glass_orb") // Focus on the central large sphere
+        .build();
+    // A second view looking down on the orbs from high above.
+    let mut overhead = cam.clone();
+    overhead.lookfrom = Point3::new(0.0, 18.0, 6.0);
+    overhead.lookat = Point3::new(0.0, 0.0, 0.0);
+    overhead.defocus_angle = 0.0;
+    overhead.vfov = 40.0;
+    let mut file = SceneFile::new(cam);
+    file.add_camera("overhead", overhead);
+
     // --- Materials ---
     // Ground
-    let material_ground_reflective_dark = Arc::new(Metal::new(Color::new(0.1, 0.1, 0.15), 0.05)); // Dark, slightly fuzzy mirror
-    // let material_ground_diffuse_dark = Arc::new(Lambertian::new(Color::new(0.05, 0.05, 0.05))); // Alternative very dark diffuse
+    let material_ground_reflective_dark = file.add_material(MaterialDesc::Metal {
+        albedo: Color::new(0.1, 0.1, 0.15),
+        fuzz: 0.05,
+    }); // Dark, slightly fuzzy mirror
 
     // Primary Orbs
-    let material_large_glass = Arc::new(Dielectric::new(1.5)); // Standard glass
-    let material_large_metal_gold = Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.0)); // Polished gold
-    let material_large_metal_silver = Arc::new(Metal::new(Color::new(0.01, 0.0, 0.6), 0.0)); // Slightly fuzzy silver
+    let material_large_glass = file.add_material(MaterialDesc::Dielectric { ior: 1.5 }); // Standard glass
+    let material_large_metal_gold = file.add_material(MaterialDesc::Metal {
+        albedo: Color::new(0.8, 0.6, 0.2),
+        fuzz: 0.0,
+    }); // Polished gold
+    let material_large_metal_silver = file.add_material(MaterialDesc::Metal {
+        albedo: Color::new(0.01, 0.0, 0.6),
+        fuzz: 0.0,
+    }); // Slightly fuzzy silver
 
     // Accent / Small Orbs
-    let material_diffuse_blue = Arc::new(Lambertian::new(Color::new(0.1, 0.2, 0.7)));
-    let material_diffuse_red = Arc::new(Lambertian::new(Color::new(0.7, 0.1, 0.1)));
-    let material_metal_copper_fuzzy = Arc::new(Metal::new(Color::new(0.7, 0.3, 0.1), 0.4));
-    let material_small_glass_bubbles = Arc::new(Dielectric::new(1.3)); // Slightly different IOR for variety
-    let material_glowing_emitter_placeholder = Arc::new(Lambertian::new(Color::new(0.9, 0.9, 0.7))); // Brighter diffuse to simulate glow
+    let material_small_glass_bubbles = file.add_material(MaterialDesc::Dielectric { ior: 1.3 }); // Slightly different IOR for variety
+    let material_glowing_emitter_placeholder = file.add_material(MaterialDesc::Lambertian {
+        albedo: Color::new(0.9, 0.9, 0.7),
+    }); // Brighter diffuse to simulate glow
 
     // --- World ---
-    let mut world = HittableList::new();
+    let sphere = |center: Point3, radius: f64| ShapeDesc::Sphere {
+        center,
+        radius,
+        motion: Vec3::zero(),
+    };
 
     // Ground Plane (Large Sphere)
-    world.add(Arc::new(Sphere::new(
-        Point3::new(0.0, -1000.0, -1.0), // Y very low to make it flat
-        1000.0,
-        material_ground_reflective_dark.clone(), // Use clone for Arc if used elsewhere, or just pass
-    )));
+    file.add(
+        sphere(Point3::new(0.0, -1000.0, -1.0), 1000.0), // Y very low to make it flat
+        material_ground_reflective_dark,
+    )?;
 
     // --- Primary Large Spheres ---
     // Central Glass Orb
-    world.add(Arc::new(Sphere::new(
-        Point3::new(0.0, 1.0, 0.0),
-        1.0,
-        material_large_glass.clone(),
-    )));
+    file.add_named(
+        "center_glass_orb",
+        sphere(Point3::new(0.0, 1.0, 0.0), 1.0),
+        material_large_glass,
+    )?;
 
     // Left Gold Metal Orb
-    world.add(Arc::new(Sphere::new(
-        Point3::new(-4.0, 1.0, 0.0),
-        1.0,
-        material_large_metal_gold.clone(),
-    )));
+    file.add(
+        sphere(Point3::new(-4.0, 1.0, 0.0), 1.0),
+        material_large_metal_gold,
+    )?;
 
     // Right Silver Metal Orb
-    world.add(Arc::new(Sphere::new(
-        Point3::new(4.0, 1.0, 0.0),
-        1.0,
-        material_large_metal_silver.clone(),
-    )));
+    file.add(
+        sphere(Point3::new(4.0, 1.0, 0.0), 1.0),
+        material_large_metal_silver,
+    )?;
 
     // --- Scattered Smaller Spheres ---
     // This loop creates a field of smaller, randomly placed and materialized spheres.
     // Adjust the range (-10 to 10) and density as desired.
 
+    let mut rng = SmallRng::seed_from_u64(random_seed());
     let small_sphere_radius = 0.2;
     for a in -3..3 {
         for b in -3..3 {
-            let choose_mat = rand::random::<f64>(); // Using rand crate for random numbers
+            let choose_mat = random_f64(&mut rng);
             let center = Point3::new(
-                a as f64 + 0.9 * rand::random::<f64>(),
+                a as f64 + 0.9 * random_f64(&mut rng),
                 small_sphere_radius, // Place them just above the ground (y=0)
-                b as f64 + 0.9 * rand::random::<f64>(),
+                b as f64 + 0.9 * random_f64(&mut rng),
             );
 
             // Ensure small spheres don't overlap too much with the large ones
@@ -85,82 +725,30 @@ fn main() -> io::Result<()> {
                 && (center - Point3::new(-4.0, 1.0, 0.0)).length() > 1.0 + small_sphere_radius
                 && (center - Point3::new(4.0, 1.0, 0.0)).length() > 1.0 + small_sphere_radius
             {
-                let sphere_material: Arc<dyn Material + Send + Sync>;
-                if choose_mat < 0.3 {
+                let sphere_material = if choose_mat < 0.3 {
                     // 30% diffuse
-                    let albedo = Color::random() * Color::random(); // Random diffuse color
-                    sphere_material = Arc::new(Lambertian::new(albedo));
+                    let albedo = Color::random(&mut rng) * Color::random(&mut rng); // Random diffuse color
+                    file.add_material(MaterialDesc::Lambertian { albedo })
                 } else if choose_mat < 0.6 {
                     // 30% metal
                     let albedo = Color::new(
-                        random_f64_range(0.5, 1.0),
-                        random_f64_range(0.5, 1.0),
-                        random_f64_range(0.5, 1.0),
+                        random_f64_range(&mut rng, 0.5, 1.0),
+                        random_f64_range(&mut rng, 0.5, 1.0),
+                        random_f64_range(&mut rng, 0.5, 1.0),
                     );
-                    let fuzz = random_f64_range(0.0, 0.5); // Using your utility if available, else rand::random
-                    sphere_material = Arc::new(Metal::new(albedo, fuzz));
+                    let fuzz = random_f64_range(&mut rng, 0.0, 0.5); // Using your utility if available, else rand::random
+                    file.add_material(MaterialDesc::Metal { albedo, fuzz })
                 } else if choose_mat < 0.8 {
                     // 20% glass
-                    sphere_material = material_small_glass_bubbles.clone();
+                    material_small_glass_bubbles
                 } else {
                     // 20% "glowing" (brighter diffuse)
-                    sphere_material = material_glowing_emitter_placeholder.clone();
-                }
-                world.add(Arc::new(Sphere::new(
-                    center,
-                    small_sphere_radius,
-                    sphere_material,
-                )));
+                    material_glowing_emitter_placeholder
+                };
+                file.add(sphere(center, small_sphere_radius), sphere_material)?;
             }
         }
     }
 
-    // --- Camera Settings ---
-    // High quality settings - WILL BE SLOW!
-    let aspect_ratio = 16.0 / 9.0;
-    let image_width: u32 = 600; // Higher resolution (e.g., 1920 or 2560)
-    let sample_per_pixel: u32 = 10; // Significantly more samples for AA and soft effects
-    // Consider 1000-5000 for "final" quality if you have patience
-    let max_depth: u32 = 10; // Good depth for complex interactions
-
-    // Camera positioning for a dramatic, slightly low angle shot
-    let vfov = 25.0; // A bit wider than your example, but not too extreme
-    let lookfrom = Point3::new(8.0, 2.5, 10.0); // Further back, slightly elevated, off to the side
-    let lookat = Point3::new(0.0, 0.5, 0.0); // Look towards the center of the large orbs, slightly above ground
-    let vup = Vec3::new(0.0, 1.0, 0.0);
-
-    // Depth of field settings - focus on one of the main orbs or a point between them
-    let defocus_angle = 0.8; // Subtle defocus, increase for more blur (e.g., 1.0 to 2.0)
-    let focus_dist = (lookfrom - Point3::new(0.0, 1.0, 0.0)).length(); // Focus on the central large sphere
-
-    let cam = Camera::new(
-        aspect_ratio,
-        image_width,
-        sample_per_pixel,
-        max_depth,
-        vfov,
-        lookfrom,
-        lookat,
-        vup,
-        defocus_angle,
-        focus_dist,
-    );
-
-    // --- Render ---
-    eprintln!("Starting render with high quality settings...");
-    eprintln!(
-        "Image Width: {}, Samples/Pixel: {}, Max Depth: {}",
-        image_width, sample_per_pixel, max_depth
-    );
-    cam.render(&world)?;
-    eprintln!("Render finished!");
-
-    Ok(())
+    Ok(file)
 }
-
-// Make sure you have random utilities. If not, you can use the `rand` crate:
-// Add `rand = "0.8"` to your Cargo.toml
-// And `use rand::Rng;` in your file.
-// Then replace `rand::random::<f64>()` with `rand::thread_rng().gen::<f64>()`
-// and `rand::random_range(min, max)` with `rand::thread_rng().gen_range(min..max)`
-// Or adapt to your Vec3::random() and Vec3::random_range() if you have them.
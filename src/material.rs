@@ -1,44 +1,184 @@
+use crate::microfacet::{
+    Conductor, Fresnel, Ggx, fresnel_dielectric, fresnel_schlick, from_shading_frame,
+    to_shading_frame,
+};
 use crate::rtweekend::*;
+use serde::{Deserialize, Serialize};
 
 pub trait Material: Send + Sync {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Color)>;
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut SmallRng) -> Option<(Ray, Color)>;
+
+    /// BRDF times cosine for light arriving from `wi`, used for explicit light sampling.
+    /// Perfectly specular materials can't be hit by a shadow ray and keep the default.
+    fn eval(&self, _r_in: &Ray, _rec: &HitRecord, _wi: Vec3) -> Color {
+        Color::BLACK
+    }
+
+    /// Density of `scatter` producing `scattered`. Zero marks specular materials,
+    /// which are skipped when importance-sampling emissive geometry.
+    fn scattering_pdf(&self, _r_in: &Ray, _rec: &HitRecord, _scattered: &Ray) -> f64 {
+        0.0
+    }
+
+    fn emitted(&self, _rec: &HitRecord) -> Color {
+        Color::BLACK
+    }
+
+    /// Chance that a ray hitting `rec` stops there instead of passing straight through.
+    fn opacity(&self, _rec: &HitRecord) -> f64 {
+        1.0
+    }
+
+    /// Type name without its module path, for diagnostics.
+    fn name(&self) -> &'static str {
+        let full = std::any::type_name::<Self>();
+        full.rsplit("::").next().unwrap_or(full)
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct NullMaterial;
 
 impl Material for NullMaterial {
-    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Ray, Color)> {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _rng: &mut SmallRng) -> Option<(Ray, Color)> {
         // A null material typically absorbs all light or doesn't scatter
         None
     }
 }
 
 pub struct Lambertian {
-    albedo: Color,
+    tex: Arc<dyn Texture>,
 }
 
 impl Lambertian {
     pub fn new(albedo: Color) -> Self {
-        Self { albedo }
+        Self::from_texture(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>) -> Self {
+        Self { tex }
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        let albedo = self.tex.value_at_hit(rec);
+        rec.overrides.apply_color(albedo, rec.instance_id)
     }
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
-        let mut scatter_direction = rec.normal + Vec3::random_unit_vector();
+    fn scatter(&self, _r_in: &Ray, rec: &HitRecord, rng: &mut SmallRng) -> Option<(Ray, Color)> {
+        let mut scatter_direction = rec.normal + Vec3::random_unit_vector(rng);
         if scatter_direction.near_zero() {
             scatter_direction = rec.normal;
         }
         let scattered = Ray::new(rec.p, scatter_direction);
-        let attenuation = self.albedo;
+        let attenuation = self.albedo(rec);
         Some((scattered, attenuation))
     }
+
+    fn eval(&self, _r_in: &Ray, rec: &HitRecord, wi: Vec3) -> Color {
+        let cosine = rec.normal.dot(wi).max(0.0);
+        self.albedo(rec) * (cosine / PI)
+    }
+
+    fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let cos_theta = rec.normal.dot(scattered.dir.normalized());
+        cos_theta.max(0.0) / PI
+    }
+}
+
+/// Rough diffuse surface (Oren-Nayar, qualitative model). Unlike Lambertian it
+/// back-scatters towards the light, which flattens the shading of the moon or concrete.
+pub struct OrenNayar {
+    tex: Arc<dyn Texture>,
+    a: f64,
+    b: f64,
+}
+
+impl OrenNayar {
+    /// `sigma` is the standard deviation of the facet slope angle, in radians.
+    pub fn new(albedo: Color, sigma: f64) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(albedo)), sigma)
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>, sigma: f64) -> Self {
+        let s2 = sigma * sigma;
+        Self {
+            tex,
+            a: 1.0 - 0.5 * s2 / (s2 + 0.33),
+            b: 0.45 * s2 / (s2 + 0.09),
+        }
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        let albedo = self.tex.value_at_hit(rec);
+        rec.overrides.apply_color(albedo, rec.instance_id)
+    }
+}
+
+impl Material for OrenNayar {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut SmallRng) -> Option<(Ray, Color)> {
+        let mut scatter_direction = rec.normal + Vec3::random_unit_vector(rng);
+        if scatter_direction.near_zero() {
+            scatter_direction = rec.normal;
+        }
+        let scattered = Ray::new(rec.p, scatter_direction);
+        // Cosine-weighted sampling, so the weight is just the Oren-Nayar factor.
+        let pdf = self.scattering_pdf(r_in, rec, &scattered);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let attenuation = self.eval(r_in, rec, scattered.dir) / pdf;
+        Some((scattered, attenuation))
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, wi: Vec3) -> Color {
+        let n = rec.normal;
+        let wo = -r_in.dir.normalized();
+        let wi = wi.normalized();
+        let cos_i = n.dot(wi);
+        let cos_o = n.dot(wo);
+        if cos_i <= 0.0 || cos_o <= 0.0 {
+            return Color::BLACK;
+        }
+
+        // cos(phi_i - phi_o) from the directions projected onto the tangent plane.
+        let pi = wi - cos_i * n;
+        let po = wo - cos_o * n;
+        let denom = (pi.length_squared() * po.length_squared()).sqrt();
+        let cos_phi = if denom > 1e-12 {
+            (pi.dot(po) / denom).max(0.0)
+        } else {
+            0.0
+        };
+
+        // sin(alpha) * tan(beta) with alpha the larger and beta the smaller polar angle.
+        let sin_i = (1.0 - cos_i * cos_i).max(0.0).sqrt();
+        let sin_o = (1.0 - cos_o * cos_o).max(0.0).sqrt();
+        let (sin_alpha, tan_beta) = if cos_i < cos_o {
+            (sin_i, sin_o / cos_o)
+        } else {
+            (sin_o, sin_i / cos_i)
+        };
+
+        let factor = self.a + self.b * cos_phi * sin_alpha * tan_beta;
+        self.albedo(rec) * (factor * cos_i / PI)
+    }
+
+    fn scattering_pdf(&self, _r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let cos_theta = rec.normal.dot(scattered.dir.normalized());
+        cos_theta.max(0.0) / PI
+    }
 }
 
 pub struct Metal {
     albedo: Color,
     fuzz: f64,
+    film: Option<ThinFilm>,
+    /// When set, reflectance follows the conductor Fresnel equations instead of `albedo`.
+    conductor: Option<Conductor>,
+    /// Grayscale map that replaces the constant `fuzz`.
+    fuzz_map: Option<Arc<dyn Texture>>,
 }
 
 impl Metal {
@@ -46,16 +186,59 @@ impl Metal {
         Self {
             albedo,
             fuzz: fuzz.clamp(0.0, 1.0),
+            film: None,
+            conductor: None,
+            fuzz_map: None,
+        }
+    }
+
+    /// Metal whose fuzz varies over the surface, read from a grayscale texture, for
+    /// smudges, fingerprints or scratches.
+    pub fn with_fuzz_map(albedo: Color, fuzz_map: Arc<dyn Texture>) -> Self {
+        Self {
+            fuzz_map: Some(fuzz_map),
+            ..Self::new(albedo, 0.0)
+        }
+    }
+
+    /// Metal described by its complex IOR, e.g. `Conductor::GOLD`, so its color shifts
+    /// towards white at grazing angles like the real thing.
+    pub fn conductor(conductor: Conductor, fuzz: f64) -> Self {
+        Self {
+            conductor: Some(conductor),
+            ..Self::new(conductor.reflectance(1.0), fuzz)
+        }
+    }
+
+    /// Metal under a thin transparent film, like heat-tinted or anodized metal.
+    pub fn with_film(albedo: Color, fuzz: f64, film: ThinFilm) -> Self {
+        Self {
+            film: Some(film),
+            ..Self::new(albedo, fuzz)
         }
     }
 }
 
 impl Material for Metal {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut SmallRng) -> Option<(Ray, Color)> {
         let mut reflected = Vec3::reflect(&r_in.dir, &rec.normal);
-        reflected = reflected.normalized() + (self.fuzz * Vec3::random_unit_vector());
+        let fuzz = match &self.fuzz_map {
+            Some(map) => map.scalar_at_hit(rec).clamp(0.0, 1.0),
+            None => self.fuzz,
+        };
+        let fuzz = rec.overrides.apply_roughness(fuzz);
+        reflected = reflected.normalized() + (fuzz * Vec3::random_unit_vector(rng));
         let scattered = Ray::new(rec.p, reflected);
-        let attenuation = self.albedo;
+        let cos_i = rec.normal.dot(-r_in.dir.normalized());
+        let reflectance = match &self.conductor {
+            Some(conductor) => conductor.reflectance(cos_i),
+            None => self.albedo,
+        };
+        let albedo = rec.overrides.apply_color(reflectance, rec.instance_id);
+        let attenuation = match &self.film {
+            Some(film) => film.reflectance_over_metal(cos_i, albedo),
+            None => albedo,
+        };
 
         if scattered.dir.dot(rec.normal) > 0.0 {
             Some((scattered, attenuation))
@@ -65,14 +248,581 @@ impl Material for Metal {
     }
 }
 
+/// Brushed metal: GGX reflection with separate roughness along the hit tangent (`u`)
+/// and bitangent (`v`) directions.
+#[derive(Debug, Clone, Copy)]
+pub struct AnisotropicMetal {
+    albedo: Color,
+    ggx: Ggx,
+}
+
+impl AnisotropicMetal {
+    pub fn new(albedo: Color, roughness_u: f64, roughness_v: f64) -> Self {
+        Self {
+            albedo,
+            ggx: Ggx::from_roughness(roughness_u, roughness_v),
+        }
+    }
+
+    // This material with the hit's per-instance overrides applied.
+    fn resolved(&self, rec: &HitRecord) -> Self {
+        Self {
+            albedo: rec.overrides.apply_color(self.albedo, rec.instance_id),
+            ggx: self.ggx.scaled(rec.overrides.roughness_scale),
+        }
+    }
+
+    fn sample(&self, r_in: &Ray, rec: &HitRecord, rng: &mut SmallRng) -> Option<(Ray, Color)> {
+        let wo = to_shading_frame(rec, -r_in.dir.normalized());
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let h = self.ggx.sample_visible_normal(wo, rng);
+        let wi = Vec3::reflect(&-wo, &h);
+        if wi.z <= 0.0 {
+            return None;
+        }
+
+        // With visible-normal sampling, f * cos / pdf reduces to F * G2 / G1.
+        let weight = self.ggx.g2(wo, wi) / self.ggx.g1(wo);
+        let attenuation = fresnel_schlick(self.albedo, wo.dot(h)) * weight;
+        Some((Ray::new(rec.p, from_shading_frame(rec, wi)), attenuation))
+    }
+
+    fn bsdf(&self, r_in: &Ray, rec: &HitRecord, wi: Vec3) -> Color {
+        let wo = to_shading_frame(rec, -r_in.dir.normalized());
+        let wi = to_shading_frame(rec, wi.normalized());
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return Color::BLACK;
+        }
+        let h = (wo + wi).normalized();
+        let f = fresnel_schlick(self.albedo, wo.dot(h));
+        f * (self.ggx.d(h) * self.ggx.g2(wo, wi) / (4.0 * wo.z))
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let wo = to_shading_frame(rec, -r_in.dir.normalized());
+        let wi = to_shading_frame(rec, scattered.dir.normalized());
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+        let h = (wo + wi).normalized();
+        self.ggx.visible_normal_pdf(wo, h) / (4.0 * wo.dot(h))
+    }
+}
+
+impl Material for AnisotropicMetal {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut SmallRng) -> Option<(Ray, Color)> {
+        self.resolved(rec).sample(r_in, rec, rng)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, wi: Vec3) -> Color {
+        self.resolved(rec).bsdf(r_in, rec, wi)
+    }
+
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.resolved(rec).pdf(r_in, rec, scattered)
+    }
+}
+
+/// Isotropic GGX metal, a physically based replacement for `Metal`'s fuzz.
+pub struct RoughMetal {
+    inner: AnisotropicMetal,
+}
+
+impl RoughMetal {
+    pub fn new(albedo: Color, roughness: f64) -> Self {
+        Self {
+            inner: AnisotropicMetal::new(albedo, roughness, roughness),
+        }
+    }
+}
+
+impl Material for RoughMetal {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut SmallRng) -> Option<(Ray, Color)> {
+        self.inner.scatter(r_in, rec, rng)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, wi: Vec3) -> Color {
+        self.inner.eval(r_in, rec, wi)
+    }
+
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.inner.scattering_pdf(r_in, rec, scattered)
+    }
+}
+
+/// Rough glass: GGX microfacet reflection and refraction (Walter et al. 2007),
+/// choosing between the two by exact Fresnel reflectance.
+#[derive(Debug, Clone, Copy)]
+pub struct RoughDielectric {
+    ir: f64,
+    ggx: Ggx,
+}
+
+impl RoughDielectric {
+    pub fn new(refraction_index: f64, roughness: f64) -> Self {
+        Self {
+            ir: refraction_index,
+            ggx: Ggx::isotropic(roughness),
+        }
+    }
+
+    // Transmitted over incident index of refraction for the side the ray arrives from.
+    fn eta(&self, rec: &HitRecord) -> f64 {
+        if rec.front_face {
+            self.ir
+        } else {
+            1.0 / self.ir
+        }
+    }
+
+    // Microfacet normal relating `wo` and `wi`, facing the incident side, and whether
+    // the pair is a reflection.
+    fn half_vector(&self, wo: Vec3, wi: Vec3, eta: f64) -> Option<(Vec3, bool)> {
+        let reflect = wi.z > 0.0;
+        let h = if reflect { wo + wi } else { wo + eta * wi };
+        if h.near_zero() {
+            return None;
+        }
+        let h = h.normalized();
+        let h = if h.z < 0.0 { -h } else { h };
+        // Back-facing microfacets can't produce this pair.
+        if wo.dot(h) <= 0.0 || (!reflect && wi.dot(h) >= 0.0) {
+            return None;
+        }
+        Some((h, reflect))
+    }
+
+    fn resolved(&self, rec: &HitRecord) -> Self {
+        Self {
+            ir: self.ir,
+            ggx: self.ggx.scaled(rec.overrides.roughness_scale),
+        }
+    }
+
+    fn sample(&self, r_in: &Ray, rec: &HitRecord, rng: &mut SmallRng) -> Option<(Ray, Color)> {
+        let wo = to_shading_frame(rec, -r_in.dir.normalized());
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let eta = self.eta(rec);
+        let h = self.ggx.sample_visible_normal(wo, rng);
+        let cos_o = wo.dot(h);
+
+        let reflect = random_f64(rng) < fresnel_dielectric(cos_o, eta);
+        let wi = if reflect {
+            Vec3::reflect(&-wo, &h)
+        } else {
+            Vec3::refract(&-wo, &h, 1.0 / eta)
+        };
+        // Samples that end up on the wrong side of the macrosurface are absorbed.
+        if reflect != (wi.z > 0.0) {
+            return None;
+        }
+
+        // Choosing the lobe by Fresnel and the normal by visibility leaves G2 / G1.
+        let weight = self.ggx.g2(wo, wi) / self.ggx.g1(wo);
+        Some((
+            Ray::new(rec.p, from_shading_frame(rec, wi)),
+            Color::new(weight, weight, weight),
+        ))
+    }
+
+    fn bsdf(&self, r_in: &Ray, rec: &HitRecord, wi: Vec3) -> Color {
+        let wo = to_shading_frame(rec, -r_in.dir.normalized());
+        let wi = to_shading_frame(rec, wi.normalized());
+        let eta = self.eta(rec);
+        let Some((h, reflect)) = self.half_vector(wo, wi, eta) else {
+            return Color::BLACK;
+        };
+        let d = self.ggx.d(h);
+        let g = self.ggx.g2(wo, wi);
+        let f = fresnel_dielectric(wo.dot(h), eta);
+
+        let value = if reflect {
+            f * d * g / (4.0 * wo.z)
+        } else {
+            let denom = wo.dot(h) + eta * wi.dot(h);
+            (1.0 - f) * d * g * eta * eta * wi.dot(h).abs() * wo.dot(h) / (wo.z * denom * denom)
+        };
+        Color::new(value, value, value)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let wo = to_shading_frame(rec, -r_in.dir.normalized());
+        let wi = to_shading_frame(rec, scattered.dir.normalized());
+        let eta = self.eta(rec);
+        let Some((h, reflect)) = self.half_vector(wo, wi, eta) else {
+            return 0.0;
+        };
+        let pdf_h = self.ggx.visible_normal_pdf(wo, h);
+        let f = fresnel_dielectric(wo.dot(h), eta);
+
+        if reflect {
+            f * pdf_h / (4.0 * wo.dot(h))
+        } else {
+            let denom = wo.dot(h) + eta * wi.dot(h);
+            (1.0 - f) * pdf_h * eta * eta * wi.dot(h).abs() / (denom * denom)
+        }
+    }
+}
+
+impl Material for RoughDielectric {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut SmallRng) -> Option<(Ray, Color)> {
+        self.resolved(rec).sample(r_in, rec, rng)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, wi: Vec3) -> Color {
+        self.resolved(rec).bsdf(r_in, rec, wi)
+    }
+
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.resolved(rec).pdf(r_in, rec, scattered)
+    }
+}
+
+/// Principled BSDF with the usual Disney/Blender/glTF parameter set. A Lambertian base
+/// with sheen, a GGX specular layer, rough transmission and a clear coat are combined
+/// and sampled by picking one lobe per bounce.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Principled {
+    pub base_color: Color,
+    pub metallic: f64,
+    pub roughness: f64,
+    /// Dielectric specular level; 0.5 is a normal-incidence reflectance of 4%.
+    pub specular: f64,
+    pub transmission: f64,
+    pub ior: f64,
+    pub clearcoat: f64,
+    pub clearcoat_roughness: f64,
+    pub sheen: f64,
+}
+
+impl Default for Principled {
+    fn default() -> Self {
+        Self::new(Color::new(0.8, 0.8, 0.8))
+    }
+}
+
+impl Principled {
+    pub fn new(base_color: Color) -> Self {
+        Self {
+            base_color,
+            metallic: 0.0,
+            roughness: 0.5,
+            specular: 0.5,
+            transmission: 0.0,
+            ior: 1.45,
+            clearcoat: 0.0,
+            clearcoat_roughness: 0.03,
+            sheen: 0.0,
+        }
+    }
+
+    // Parameters with the hit's per-instance overrides applied.
+    fn resolved(&self, rec: &HitRecord) -> Self {
+        let overrides = &rec.overrides;
+        Self {
+            base_color: overrides.apply_color(self.base_color, rec.instance_id),
+            roughness: overrides.apply_roughness(self.roughness),
+            clearcoat_roughness: overrides.apply_roughness(self.clearcoat_roughness),
+            ..*self
+        }
+    }
+
+    // Weights of the diffuse, specular, transmission and clear coat lobes.
+    fn lobe_weights(&self) -> [f64; 4] {
+        let dielectric = 1.0 - self.metallic.clamp(0.0, 1.0);
+        let transmission = dielectric * self.transmission.clamp(0.0, 1.0);
+        [
+            dielectric - transmission,
+            // The glass lobe has its own reflection, so specular fades out under it.
+            1.0 - transmission,
+            transmission,
+            0.25 * self.clearcoat.clamp(0.0, 1.0),
+        ]
+    }
+
+    fn specular_lobe(&self) -> AnisotropicMetal {
+        let dielectric_f0 = 0.08 * self.specular;
+        let f0 = (1.0 - self.metallic) * Color::new(dielectric_f0, dielectric_f0, dielectric_f0)
+            + self.metallic * self.base_color;
+        AnisotropicMetal::new(f0, self.roughness, self.roughness)
+    }
+
+    fn transmission_lobe(&self) -> RoughDielectric {
+        RoughDielectric::new(self.ior, self.roughness)
+    }
+
+    fn clearcoat_lobe(&self) -> AnisotropicMetal {
+        let f0 = Color::new(0.04, 0.04, 0.04);
+        AnisotropicMetal::new(f0, self.clearcoat_roughness, self.clearcoat_roughness)
+    }
+
+    fn diffuse_eval(&self, r_in: &Ray, rec: &HitRecord, wi: Vec3) -> Color {
+        let cosine = rec.normal.dot(wi).max(0.0);
+        if cosine <= 0.0 {
+            return Color::BLACK;
+        }
+        // Sheen whitens the diffuse lobe at grazing angles, like cloth fibers.
+        let h = (wi - r_in.dir.normalized()).normalized();
+        let sheen = (self.sheen * (1.0 - wi.dot(h).clamp(0.0, 1.0)).powi(5)).clamp(0.0, 1.0);
+        let white = Color::new(1.0, 1.0, 1.0);
+        ((1.0 - sheen) * self.base_color + sheen * white) * (cosine / PI)
+    }
+
+    // Light passing through a layer with normal-incidence reflectance `f0` on the way in
+    // and out, so lower layers only get what the upper one didn't reflect.
+    fn layer_transmittance(f0: f64, cos_o: f64, cos_i: f64) -> f64 {
+        let reflect = |c: f64| fresnel_schlick(Color::splat(f0), c.abs()).r;
+        (1.0 - reflect(cos_o)) * (1.0 - reflect(cos_i))
+    }
+}
+
+impl Principled {
+    fn sample(&self, r_in: &Ray, rec: &HitRecord, rng: &mut SmallRng) -> Option<(Ray, Color)> {
+        let weights = self.lobe_weights();
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return None;
+        }
+
+        let mut pick = random_f64(rng) * total;
+        let mut lobe = 0;
+        while lobe < 3 && pick >= weights[lobe] {
+            pick -= weights[lobe];
+            lobe += 1;
+        }
+
+        let scattered = match lobe {
+            0 => {
+                let mut direction = rec.normal + Vec3::random_unit_vector(rng);
+                if direction.near_zero() {
+                    direction = rec.normal;
+                }
+                Ray::new(rec.p, direction)
+            }
+            1 => self.specular_lobe().sample(r_in, rec, rng)?.0,
+            2 => self.transmission_lobe().sample(r_in, rec, rng)?.0,
+            _ => self.clearcoat_lobe().sample(r_in, rec, rng)?.0,
+        };
+
+        // One-sample MIS over the lobes: the full BSDF over the combined density.
+        let pdf = self.pdf(r_in, rec, &scattered);
+        if pdf <= 0.0 {
+            return None;
+        }
+        let attenuation = self.bsdf(r_in, rec, scattered.dir) / pdf;
+        Some((scattered, attenuation))
+    }
+
+    fn bsdf(&self, r_in: &Ray, rec: &HitRecord, wi: Vec3) -> Color {
+        let wi = wi.normalized();
+        let [diffuse, specular, transmission, clearcoat] = self.lobe_weights();
+        let cos_o = rec.normal.dot(-r_in.dir.normalized());
+        let cos_i = rec.normal.dot(wi);
+
+        let mut value = Color::BLACK;
+        if diffuse > 0.0 {
+            // The diffuse base sits under the dielectric specular layer.
+            let under_specular = Self::layer_transmittance(0.08 * self.specular, cos_o, cos_i);
+            value += diffuse * under_specular * self.diffuse_eval(r_in, rec, wi);
+        }
+        if specular > 0.0 {
+            value += specular * self.specular_lobe().bsdf(r_in, rec, wi);
+        }
+        if transmission > 0.0 {
+            value += transmission * self.base_color * self.transmission_lobe().bsdf(r_in, rec, wi);
+        }
+        if clearcoat > 0.0 {
+            // Everything else sits under the clear coat.
+            let f0 = 0.04 * clearcoat;
+            value = value * Self::layer_transmittance(f0, cos_o, cos_i)
+                + clearcoat * self.clearcoat_lobe().bsdf(r_in, rec, wi);
+        }
+        value
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let [diffuse, specular, transmission, clearcoat] = self.lobe_weights();
+        let total = diffuse + specular + transmission + clearcoat;
+        if total <= 0.0 {
+            return 0.0;
+        }
+        let mut pdf = 0.0;
+        if diffuse > 0.0 {
+            let cosine = rec.normal.dot(scattered.dir.normalized());
+            pdf += diffuse * cosine.max(0.0) / PI;
+        }
+        if specular > 0.0 {
+            pdf += specular * self.specular_lobe().pdf(r_in, rec, scattered);
+        }
+        if transmission > 0.0 {
+            pdf += transmission * self.transmission_lobe().pdf(r_in, rec, scattered);
+        }
+        if clearcoat > 0.0 {
+            pdf += clearcoat * self.clearcoat_lobe().pdf(r_in, rec, scattered);
+        }
+        pdf / total
+    }
+}
+
+impl Material for Principled {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut SmallRng) -> Option<(Ray, Color)> {
+        self.resolved(rec).sample(r_in, rec, rng)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, wi: Vec3) -> Color {
+        self.resolved(rec).bsdf(r_in, rec, wi)
+    }
+
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.resolved(rec).pdf(r_in, rec, scattered)
+    }
+}
+
+/// `Principled` with some of its parameters read from textures, so they can vary over
+/// a surface. Maps left as `None` use the constant from `params`.
+#[derive(Clone)]
+pub struct TexturedPrincipled {
+    pub params: Principled,
+    pub base_color: Option<Arc<dyn Texture>>,
+    /// Grayscale maps.
+    pub metallic: Option<Arc<dyn Texture>>,
+    pub roughness: Option<Arc<dyn Texture>>,
+}
+
+impl TexturedPrincipled {
+    pub fn new(params: Principled) -> Self {
+        Self {
+            params,
+            base_color: None,
+            metallic: None,
+            roughness: None,
+        }
+    }
+
+    // Parameters at the hit point, with overrides applied.
+    fn resolved(&self, rec: &HitRecord) -> Principled {
+        let mut params = self.params;
+        if let Some(map) = &self.base_color {
+            params.base_color = map.value_at_hit(rec);
+        }
+        if let Some(map) = &self.metallic {
+            params.metallic = map.scalar_at_hit(rec).clamp(0.0, 1.0);
+        }
+        if let Some(map) = &self.roughness {
+            params.roughness = map.scalar_at_hit(rec).clamp(0.0, 1.0);
+        }
+        params.resolved(rec)
+    }
+}
+
+impl Material for TexturedPrincipled {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut SmallRng) -> Option<(Ray, Color)> {
+        self.resolved(rec).sample(r_in, rec, rng)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, wi: Vec3) -> Color {
+        self.resolved(rec).bsdf(r_in, rec, wi)
+    }
+
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.resolved(rec).pdf(r_in, rec, scattered)
+    }
+}
+
 pub struct Dielectric {
     ir: f64,
+    film: Option<ThinFilm>,
+    /// Absorption coefficient per unit of distance travelled inside (Beer-Lambert).
+    absorption: Color,
+    /// Which volume owns the space where dielectrics overlap; see `prioritized`.
+    priority: u32,
+    /// Wavelength dependent IOR, used when the camera renders in spectral mode.
+    dispersion: Option<Dispersion>,
+    fresnel: Fresnel,
 }
 
 impl Dielectric {
     pub fn new(refraction_index: f64) -> Self {
         Self {
             ir: refraction_index,
+            film: None,
+            absorption: Color::BLACK,
+            priority: 0,
+            dispersion: None,
+            fresnel: Fresnel::Schlick,
+        }
+    }
+
+    /// Glass whose IOR varies with wavelength, splitting white light into a rainbow when
+    /// the camera is in spectral mode. `refraction_index` is the IOR at 587.6 nm; lower
+    /// `abbe` numbers mean stronger dispersion (crown glass ~60, flint glass ~35).
+    pub fn dispersive(refraction_index: f64, abbe: f64) -> Self {
+        Self {
+            dispersion: Some(Dispersion::from_abbe(refraction_index, abbe)),
+            ..Self::new(refraction_index)
+        }
+    }
+
+    /// IOR seen by `r`, which only depends on its wavelength for dispersive glass.
+    fn ior_for(&self, r: &Ray) -> f64 {
+        match (self.dispersion, r.wavelength) {
+            (Some(dispersion), Some(lambda)) => dispersion.ior(lambda),
+            _ => self.ir,
+        }
+    }
+
+    /// Where this volume overlaps another dielectric, the higher priority one fills the
+    /// overlap and the other's surfaces inside it are ignored. Modelling ice in a drink
+    /// as a liquid that overlaps the glass and the ice avoids coincident surfaces.
+    pub fn prioritized(self, priority: u32) -> Self {
+        Self { priority, ..self }
+    }
+
+    /// Selects how reflectance is computed. `Fresnel::Exact` is slightly slower but more
+    /// accurate at grazing angles and for high IORs.
+    pub fn with_fresnel(self, fresnel: Fresnel) -> Self {
+        Self { fresnel, ..self }
+    }
+
+    fn medium(&self, ior: f64) -> Medium {
+        Medium {
+            id: std::ptr::from_ref(self) as *const () as usize,
+            priority: self.priority,
+            ior,
+        }
+    }
+
+    /// Colored glass that absorbs `absorption` per unit of distance inside it, so thick
+    /// parts look darker than thin edges.
+    pub fn with_absorption(refraction_index: f64, absorption: Color) -> Self {
+        Self {
+            absorption,
+            ..Self::new(refraction_index)
+        }
+    }
+
+    /// Colored glass that lets through `color` after light travels `distance` inside.
+    pub fn tinted(refraction_index: f64, color: Color, distance: f64) -> Self {
+        let coefficient = |c: f64| -c.clamp(1e-6, 1.0).ln() / distance;
+        let absorption = color.map(coefficient);
+        Self::with_absorption(refraction_index, absorption)
+    }
+
+    /// Dielectric with a thin film on its outside, like a soap bubble or oil on water.
+    pub fn with_film(refraction_index: f64, film: ThinFilm) -> Self {
+        Self::new(refraction_index).coated(film)
+    }
+
+    /// Adds a thin film on the outside of any dielectric, e.g. tinted or prioritized
+    /// glass. The film only changes how much is reflected at the surface.
+    pub fn coated(self, film: ThinFilm) -> Self {
+        Self {
+            film: Some(film),
+            ..self
         }
     }
 
@@ -81,31 +831,381 @@ impl Dielectric {
         r0 = r0 * r0;
         r0 + (1.0 - r0) * (1.0 - cosine).powi(5)
     }
+
+    // `refraction_index` is incident over transmitted IOR, as in `reflanctance`.
+    fn reflectance(&self, cosine: f64, refraction_index: f64) -> f64 {
+        match self.fresnel {
+            Fresnel::Schlick => Dielectric::reflanctance(cosine, refraction_index),
+            Fresnel::Exact => fresnel_dielectric(cosine, 1.0 / refraction_index),
+        }
+    }
+
+    // Whether to reflect rather than refract, and the weight that choice carries. The
+    // film's reflectance is colored, so it reflects with its average probability and
+    // reweights each channel by what it actually reflects or transmits.
+    fn choose_reflection(
+        &self,
+        rec: &HitRecord,
+        cos_theta: f64,
+        ri: f64,
+        rng: &mut SmallRng,
+    ) -> (bool, Color) {
+        let white = Color::new(1.0, 1.0, 1.0);
+        match &self.film {
+            Some(film) if rec.front_face => {
+                // The film sits on the outside, against a substrate of relative IOR 1/ri.
+                let reflectance = film.reflectance(cos_theta, 1.0 / ri);
+                let p_reflect =
+                    ((reflectance.r + reflectance.g + reflectance.b) / 3.0).clamp(1e-4, 1.0);
+                if random_f64(rng) < p_reflect {
+                    (true, reflectance / p_reflect)
+                } else {
+                    (false, (white - reflectance) / (1.0 - p_reflect))
+                }
+            }
+            _ => (self.reflectance(cos_theta, ri) > random_f64(rng), white),
+        }
+    }
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Ray, Color)> {
-        let attenuation = Color::new(1.0, 1.0, 1.0);
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut SmallRng) -> Option<(Ray, Color)> {
+        let ir = self.ior_for(r_in);
+        let medium = self.medium(ir);
+        let media = r_in.media.unwrap_or_default();
+        let outside = media.current_excluding(medium.id);
+
+        // Inside a higher priority volume this surface doesn't exist, so carry on straight
+        // through while remembering which side of it the ray is now on.
+        if outside.is_some_and(|o| o.priority > self.priority) {
+            let mut passed = Ray::new(rec.p, r_in.dir);
+            passed.media = Some(if rec.front_face {
+                media.with(medium)
+            } else {
+                media.without(medium.id)
+            });
+            return Some((passed, Color::new(1.0, 1.0, 1.0)));
+        }
+
+        // Hitting the inside means `r_in` crossed the interior, so apply its absorption.
+        let attenuation = if rec.front_face {
+            Color::new(1.0, 1.0, 1.0)
+        } else {
+            let distance = rec.t * r_in.dir.length();
+            self.absorption.map(|a| (-a * distance).exp())
+        };
+        let outside_ior = media.ior_excluding(medium.id);
         let ri = if rec.front_face {
-            1.0 / self.ir
+            outside_ior / ir
         } else {
-            self.ir
+            ir / outside_ior
         };
         let unit_direction = r_in.dir.normalized();
 
         let cos_theta = rec.normal.dot(-unit_direction).min(1.0);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
         let cant_refract = { ri * sin_theta > 1.0 };
-        let direction: Vec3;
 
-        if cant_refract || Dielectric::reflanctance(cos_theta, ri) > random_f64() {
-            direction = Vec3::reflect(&unit_direction, &rec.normal);
+        let (reflect, weight) = if cant_refract {
+            (true, Color::new(1.0, 1.0, 1.0))
+        } else {
+            self.choose_reflection(rec, cos_theta, ri, rng)
+        };
+        let mut scattered = if reflect {
+            Ray::new(rec.p, Vec3::reflect(&unit_direction, &rec.normal))
+        } else {
+            let mut refracted = Ray::new(rec.p, Vec3::refract(&unit_direction, &rec.normal, ri));
+            refracted.media = Some(if rec.front_face {
+                media.with(medium)
+            } else {
+                media.without(medium.id)
+            });
+            refracted
+        };
+        scattered.media = scattered.media.or(Some(media));
+
+        Some((scattered, attenuation * weight))
+    }
+}
+
+/// Emits from the front of the surface only, unless made two-sided.
+pub struct DiffuseLight {
+    emit: Color,
+    two_sided: bool,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Color) -> Self {
+        Self {
+            emit,
+            two_sided: false,
+        }
+    }
+
+    /// Emits from both sides, like a thin light panel hanging in the open.
+    pub fn two_sided(emit: Color) -> Self {
+        Self {
+            emit,
+            two_sided: true,
+        }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord, _rng: &mut SmallRng) -> Option<(Ray, Color)> {
+        None
+    }
+
+    fn emitted(&self, rec: &HitRecord) -> Color {
+        if !rec.front_face && !self.two_sided {
+            return Color::BLACK;
+        }
+        self.emit
+    }
+}
+
+/// Fine surface detail that perturbs the shading normal.
+pub enum SurfaceDetail {
+    /// Tangent-space normal map (RGB in [0, 1] mapped to [-1, 1]); `strength` blends
+    /// between the geometric normal (0) and the full map (1).
+    NormalMap {
+        map: Arc<dyn Texture>,
+        strength: f64,
+    },
+    /// Height map (texture luminance); `scale` is the height of the full range in (u, v) units.
+    BumpMap { map: Arc<dyn Texture>, scale: f64 },
+}
+
+/// Adds a normal- or bump-map slot to any material by perturbing the hit normal
+/// before handing the hit to `base`.
+pub struct NormalMapped {
+    base: Arc<dyn Material + Send + Sync>,
+    detail: SurfaceDetail,
+}
+
+impl NormalMapped {
+    pub fn new(base: Arc<dyn Material + Send + Sync>, detail: SurfaceDetail) -> Self {
+        Self { base, detail }
+    }
+
+    pub fn normal_map(base: Arc<dyn Material + Send + Sync>, map: Arc<dyn Texture>) -> Self {
+        Self::new(base, SurfaceDetail::NormalMap { map, strength: 1.0 })
+    }
+
+    pub fn bump_map(
+        base: Arc<dyn Material + Send + Sync>,
+        map: Arc<dyn Texture>,
+        scale: f64,
+    ) -> Self {
+        Self::new(base, SurfaceDetail::BumpMap { map, scale })
+    }
+
+    fn height(map: &Arc<dyn Texture>, uv: Vec2, p: &Point3) -> f64 {
+        let c = map.value(uv, p);
+        (c.r + c.g + c.b) / 3.0
+    }
+
+    fn perturbed(&self, rec: &HitRecord) -> HitRecord {
+        let (t, b, n) = rec.tangent_frame();
+        let shading = match &self.detail {
+            SurfaceDetail::NormalMap { map, strength } => {
+                let c = map.value(rec.uv, &rec.p);
+                let local = 2.0 * c.to_vec3() - Vec3::new(1.0, 1.0, 1.0);
+                let mapped = local.x * t + local.y * b + local.z * n;
+                (1.0 - strength) * n + *strength * mapped
+            }
+            SurfaceDetail::BumpMap { map, scale } => {
+                const DELTA: f64 = 1.0 / 1024.0;
+                let h = Self::height(map, rec.uv, &rec.p);
+                let dhdu = (Self::height(map, rec.uv + Vec2::new(DELTA, 0.0), &rec.p) - h) / DELTA;
+                let dhdv = (Self::height(map, rec.uv + Vec2::new(0.0, DELTA), &rec.p) - h) / DELTA;
+                n - *scale * (dhdu * t + dhdv * b)
+            }
+        };
+
+        let mut perturbed = rec.clone();
+        // Never let the shading normal tip past the surface, or light would leak through it.
+        if !shading.near_zero() && shading.dot(n) > 0.0 {
+            perturbed.normal = shading.normalized();
+        }
+        perturbed
+    }
+}
+
+impl Material for NormalMapped {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut SmallRng) -> Option<(Ray, Color)> {
+        self.base.scatter(r_in, &self.perturbed(rec), rng)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, wi: Vec3) -> Color {
+        self.base.eval(r_in, &self.perturbed(rec), wi)
+    }
+
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.base
+            .scattering_pdf(r_in, &self.perturbed(rec), scattered)
+    }
+
+    fn emitted(&self, rec: &HitRecord) -> Color {
+        self.base.emitted(rec)
+    }
+
+    fn opacity(&self, rec: &HitRecord) -> f64 {
+        self.base.opacity(rec)
+    }
+}
+
+/// Adds an opacity slot to any material, for cutouts like leaves on a quad. Rays pass
+/// through where the map (texture luminance) is 0, and partially transparent areas let
+/// that fraction of rays through.
+pub struct Cutout {
+    base: Arc<dyn Material + Send + Sync>,
+    opacity: Arc<dyn Texture>,
+}
+
+impl Cutout {
+    pub fn new(base: Arc<dyn Material + Send + Sync>, opacity: Arc<dyn Texture>) -> Self {
+        Self { base, opacity }
+    }
+}
+
+impl Material for Cutout {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut SmallRng) -> Option<(Ray, Color)> {
+        self.base.scatter(r_in, rec, rng)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, wi: Vec3) -> Color {
+        self.base.eval(r_in, rec, wi)
+    }
+
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        self.base.scattering_pdf(r_in, rec, scattered)
+    }
+
+    fn emitted(&self, rec: &HitRecord) -> Color {
+        self.base.emitted(rec)
+    }
+
+    fn opacity(&self, rec: &HitRecord) -> f64 {
+        self.opacity.scalar_at_hit(rec).clamp(0.0, 1.0) * self.base.opacity(rec)
+    }
+}
+
+/// Layers a thin dielectric coat (lacquer, car paint clear coat) over any base material.
+/// Light reflects off the coat with Fresnel probability and reaches the base otherwise,
+/// losing what the coat reflects on the way in and out.
+pub struct Coated {
+    base: Arc<dyn Material + Send + Sync>,
+    ior: f64,
+    ggx: Ggx,
+}
+
+impl Coated {
+    pub fn new(base: Arc<dyn Material + Send + Sync>, ior: f64, roughness: f64) -> Self {
+        Self {
+            base,
+            ior,
+            ggx: Ggx::isotropic(roughness),
+        }
+    }
+
+    // Fresnel reflectance of the coat for a direction making `cosine` with the normal.
+    fn fresnel(&self, cosine: f64) -> f64 {
+        fresnel_dielectric(cosine, self.ior)
+    }
+
+    fn coat_eval(&self, wo: Vec3, wi: Vec3) -> f64 {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+        let h = (wo + wi).normalized();
+        self.fresnel(wo.dot(h)) * self.ggx.d(h) * self.ggx.g2(wo, wi) / (4.0 * wo.z)
+    }
+
+    fn coat_pdf(&self, wo: Vec3, wi: Vec3) -> f64 {
+        if wo.z <= 0.0 || wi.z <= 0.0 {
+            return 0.0;
+        }
+        let h = (wo + wi).normalized();
+        self.ggx.visible_normal_pdf(wo, h) / (4.0 * wo.dot(h))
+    }
+}
+
+impl Material for Coated {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord, rng: &mut SmallRng) -> Option<(Ray, Color)> {
+        // The coat is only on the outside.
+        if !rec.front_face {
+            return self.base.scatter(r_in, rec, rng);
+        }
+        let wo = to_shading_frame(rec, -r_in.dir.normalized());
+        if wo.z <= 0.0 {
+            return None;
+        }
+        let f_o = self.fresnel(wo.z);
+
+        if random_f64(rng) < f_o {
+            let h = self.ggx.sample_visible_normal(wo, rng);
+            let wi = Vec3::reflect(&-wo, &h);
+            if wi.z <= 0.0 {
+                return None;
+            }
+            let scattered = Ray::new(rec.p, from_shading_frame(rec, wi));
+            if self.base.scattering_pdf(r_in, rec, &scattered) > 0.0 {
+                let pdf = self.scattering_pdf(r_in, rec, &scattered);
+                return Some((scattered, self.eval(r_in, rec, scattered.dir) / pdf));
+            }
+            // Specular base: no shared density, so weight the coat sample on its own.
+            let weight = self.fresnel(wo.dot(h)) * self.ggx.g2(wo, wi) / (self.ggx.g1(wo) * f_o);
+            return Some((scattered, Color::new(weight, weight, weight)));
+        }
+
+        let (scattered, attenuation) = self.base.scatter(r_in, rec, rng)?;
+        if self.base.scattering_pdf(r_in, rec, &scattered) > 0.0 {
+            let pdf = self.scattering_pdf(r_in, rec, &scattered);
+            if pdf <= 0.0 {
+                return None;
+            }
+            return Some((scattered, self.eval(r_in, rec, scattered.dir) / pdf));
+        }
+        // Specular base: the sample was chosen with probability 1 - F_o, which cancels
+        // the coat's transmittance on the way in, leaving the way out.
+        let cos_i = rec.normal.dot(scattered.dir.normalized());
+        let transmittance = if cos_i > 0.0 {
+            1.0 - self.fresnel(cos_i)
         } else {
-            direction = Vec3::refract(&unit_direction, &rec.normal, ri);
+            1.0
+        };
+        Some((scattered, attenuation * transmittance))
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, wi: Vec3) -> Color {
+        if !rec.front_face {
+            return self.base.eval(r_in, rec, wi);
         }
+        let wi = wi.normalized();
+        let wo = to_shading_frame(rec, -r_in.dir.normalized());
+        let wi_local = to_shading_frame(rec, wi);
+        if wo.z <= 0.0 || wi_local.z <= 0.0 {
+            return Color::BLACK;
+        }
+        let coat = self.coat_eval(wo, wi_local);
+        let transmittance = (1.0 - self.fresnel(wo.z)) * (1.0 - self.fresnel(wi_local.z));
+        Color::new(coat, coat, coat) + transmittance * self.base.eval(r_in, rec, wi)
+    }
 
-        let scattered = Ray::new(rec.p, direction);
+    fn scattering_pdf(&self, r_in: &Ray, rec: &HitRecord, scattered: &Ray) -> f64 {
+        let base_pdf = self.base.scattering_pdf(r_in, rec, scattered);
+        if !rec.front_face || base_pdf <= 0.0 {
+            // Specular bases (and their coat samples) are weighted inside `scatter`.
+            return if rec.front_face { 0.0 } else { base_pdf };
+        }
+        let wo = to_shading_frame(rec, -r_in.dir.normalized());
+        let wi = to_shading_frame(rec, scattered.dir.normalized());
+        let f_o = self.fresnel(wo.z);
+        f_o * self.coat_pdf(wo, wi) + (1.0 - f_o) * base_pdf
+    }
 
-        Some((scattered, attenuation))
+    fn emitted(&self, rec: &HitRecord) -> Color {
+        self.base.emitted(rec)
     }
 }
@@ -0,0 +1,92 @@
+use crate::rtweekend::*;
+use crate::scene_file::MaterialDesc;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Materials by name, so a scene file can define one once and have any number of
+/// objects refer to it by name. Edit a material here and build the scene again to see
+/// the change on every object using it. Saved on its own, a library can be shared by
+/// several scene files.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct MaterialLibrary {
+    materials: BTreeMap<String, MaterialDesc>,
+}
+
+impl MaterialLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `material` as `name`, returning the material it replaces, if any.
+    pub fn insert(&mut self, name: &str, material: MaterialDesc) -> Option<MaterialDesc> {
+        self.materials.insert(name.to_string(), material)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&MaterialDesc> {
+        self.materials.get(name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut MaterialDesc> {
+        self.materials.get_mut(name)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<MaterialDesc> {
+        self.materials.remove(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.materials.contains_key(name)
+    }
+
+    /// Names in sorted order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.materials.keys().map(String::as_str)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &MaterialDesc)> {
+        self.materials.iter().map(|(name, m)| (name.as_str(), m))
+    }
+
+    pub fn len(&self) -> usize {
+        self.materials.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.materials.is_empty()
+    }
+
+    /// Adds every material of `other`, replacing ones with the same name.
+    pub fn extend(&mut self, other: &Self) {
+        for (name, material) in other.iter() {
+            self.insert(name, material.clone());
+        }
+    }
+
+    /// Builds every material once, to be shared by all objects naming it.
+    pub fn build(&self) -> BTreeMap<String, Arc<dyn Material + Send + Sync>> {
+        self.iter()
+            .map(|(name, material)| (name.to_string(), material.build()))
+            .collect()
+    }
+
+    pub fn to_json(&self) -> io::Result<String> {
+        serde_json::to_string_pretty(self).map_err(io::Error::other)
+    }
+
+    pub fn from_json(text: &str) -> io::Result<Self> {
+        serde_json::from_str(text)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("materials: {}", e)))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_json()?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+}
@@ -0,0 +1,81 @@
+/// Most nesting a ray can track at once; deeper entries are dropped, so the innermost
+/// media are treated as if they were the outer ones.
+const MAX_MEDIA: usize = 4;
+
+/// A refractive medium the ray is currently inside.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Medium {
+    /// Identifies the material that owns the volume, so leaving it removes the right entry.
+    pub id: usize,
+    /// Where volumes overlap, the one with the highest priority wins.
+    pub priority: u32,
+    pub ior: f64,
+}
+
+/// The stack of dielectric volumes a ray is travelling through, used to compute the
+/// relative IOR at interfaces between nested media (a bubble in water, ice in a drink).
+/// Anything not on the stack is air.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MediumStack {
+    entries: [Medium; MAX_MEDIA],
+    len: usize,
+}
+
+impl MediumStack {
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        self.entries[..self.len].iter().any(|m| m.id == id)
+    }
+
+    /// The medium the ray is actually in: the highest priority entry, the most recently
+    /// entered one on ties.
+    pub fn current(&self) -> Option<Medium> {
+        self.current_excluding(usize::MAX)
+    }
+
+    /// Like `current`, ignoring the volume owned by `id`.
+    pub fn current_excluding(&self, id: usize) -> Option<Medium> {
+        self.entries[..self.len]
+            .iter()
+            .rev()
+            .filter(|m| m.id != id)
+            .fold(None, |best: Option<Medium>, m| match best {
+                Some(b) if b.priority >= m.priority => Some(b),
+                _ => Some(*m),
+            })
+    }
+
+    /// IOR of the surroundings, treating an empty stack as air.
+    pub fn ior_excluding(&self, id: usize) -> f64 {
+        self.current_excluding(id).map_or(1.0, |m| m.ior)
+    }
+
+    pub fn push(&mut self, medium: Medium) {
+        if self.len == MAX_MEDIA {
+            self.entries.copy_within(1.., 0);
+            self.len -= 1;
+        }
+        self.entries[self.len] = medium;
+        self.len += 1;
+    }
+
+    pub fn remove(&mut self, id: usize) {
+        if let Some(i) = self.entries[..self.len].iter().rposition(|m| m.id == id) {
+            self.entries.copy_within(i + 1..self.len, i);
+            self.len -= 1;
+        }
+    }
+
+    pub fn with(mut self, medium: Medium) -> Self {
+        self.push(medium);
+        self
+    }
+
+    pub fn without(mut self, id: usize) -> Self {
+        self.remove(id);
+        self
+    }
+}
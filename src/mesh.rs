@@ -0,0 +1,315 @@
+use crate::rtweekend::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Polygon mesh: shared vertex positions and faces of three or more vertex indices.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Mesh {
+    pub positions: Vec<Point3>,
+    pub faces: Vec<Vec<usize>>,
+    /// One normal per position for smooth shading, facing the same way as the faces'
+    /// winding; empty for flat faces.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub normals: Vec<Vec3>,
+}
+
+impl Mesh {
+    pub fn new(positions: Vec<Point3>, faces: Vec<Vec<usize>>) -> Self {
+        Self {
+            positions,
+            faces,
+            normals: Vec::new(),
+        }
+    }
+
+    /// Gives every position the average of the normals of the faces around it, weighted
+    /// by their area, so the mesh shades smoothly across its edges.
+    pub fn with_smooth_normals(mut self) -> Self {
+        let mut normals = vec![Vec3::zero(); self.positions.len()];
+        for face in &self.faces {
+            // Twice the area vector of the polygon, which works for non-planar ones too.
+            let p0 = self.positions[face[0]];
+            let mut area = Vec3::zero();
+            for k in 1..face.len() - 1 {
+                area += (self.positions[face[k]] - p0).cross(self.positions[face[k + 1]] - p0);
+            }
+            for &index in face {
+                normals[index] += area;
+            }
+        }
+        self.normals = normals
+            .into_iter()
+            .map(|n| if n.near_zero() { n } else { n.normalized() })
+            .collect();
+        self
+    }
+
+    // Vertex normals for a triangle fan corner, if the mesh has them.
+    /// Checks that every face has at least three vertices, every index names a position
+    /// and the normals, if any, match the positions one for one, as the rest of `Mesh`
+    /// assumes. Meshes from outside, such as scene files, go through this first.
+    pub fn validate(&self) -> io::Result<()> {
+        let invalid = |msg: String| Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+        if !self.normals.is_empty() && self.normals.len() != self.positions.len() {
+            return invalid(format!(
+                "mesh: {} normals for {} positions",
+                self.normals.len(),
+                self.positions.len()
+            ));
+        }
+        for (index, face) in self.faces.iter().enumerate() {
+            if face.len() < 3 {
+                return invalid(format!("mesh: face {} has fewer than 3 vertices", index));
+            }
+            if let Some(&vertex) = face.iter().find(|&&v| v >= self.positions.len()) {
+                return invalid(format!(
+                    "mesh: face {} uses vertex {} of {}",
+                    index,
+                    vertex,
+                    self.positions.len()
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn corner_normals(&self, a: usize, b: usize, c: usize) -> Option<[Vec3; 3]> {
+        (self.normals.len() == self.positions.len())
+            .then(|| [self.normals[a], self.normals[b], self.normals[c]])
+    }
+
+    /// Reads vertex positions (`v`) and faces (`f`) from Wavefront OBJ text.
+    /// Texture/normal indices and all other statements are ignored.
+    pub fn parse_obj(text: &str) -> io::Result<Self> {
+        let mut mesh = Self::default();
+        for (line_no, line) in text.lines().enumerate() {
+            let invalid = |msg: &str| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("obj line {}: {}", line_no + 1, msg),
+                )
+            };
+            let mut tokens = line.split_whitespace();
+            match tokens.next() {
+                Some("v") => {
+                    let coords: Vec<f64> = tokens
+                        .take(3)
+                        .map(|t| t.parse::<f64>().map_err(|_| invalid("bad vertex")))
+                        .collect::<io::Result<_>>()?;
+                    if coords.len() != 3 {
+                        return Err(invalid("vertex needs three coordinates"));
+                    }
+                    mesh.positions
+                        .push(Point3::new(coords[0], coords[1], coords[2]));
+                }
+                Some("f") => {
+                    let mut face = Vec::new();
+                    for token in tokens {
+                        let index = token
+                            .split('/')
+                            .next()
+                            .and_then(|i| i.parse::<i64>().ok())
+                            .ok_or_else(|| invalid("bad face index"))?;
+                        // OBJ indices are 1-based; negative ones count back from the last vertex.
+                        let resolved = if index < 0 {
+                            mesh.positions.len() as i64 + index
+                        } else {
+                            index - 1
+                        };
+                        if resolved < 0 || resolved as usize >= mesh.positions.len() {
+                            return Err(invalid("face index out of range"));
+                        }
+                        face.push(resolved as usize);
+                    }
+                    if face.len() < 3 {
+                        return Err(invalid("face needs at least three vertices"));
+                    }
+                    mesh.faces.push(face);
+                }
+                _ => {}
+            }
+        }
+        Ok(mesh)
+    }
+
+    pub fn load_obj(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::parse_obj(&fs::read_to_string(path)?)
+    }
+
+    /// Applies `levels` rounds of Catmull-Clark subdivision. A smooth-shaded mesh gets
+    /// its normals recomputed for the new vertices.
+    pub fn subdivided(&self, levels: u32) -> Self {
+        let mut mesh = self.clone();
+        for _ in 0..levels {
+            mesh = catmull_clark(&mesh);
+        }
+        if levels > 0 && !self.normals.is_empty() {
+            mesh = mesh.with_smooth_normals();
+        }
+        mesh
+    }
+
+    pub fn scale(&mut self, factor: f64) {
+        for p in self.positions.iter_mut() {
+            *p *= factor;
+        }
+    }
+
+    pub fn translate(&mut self, offset: Vec3) {
+        for p in self.positions.iter_mut() {
+            *p += offset;
+        }
+    }
+
+    /// Fan-triangulates every face into a list of triangles sharing one material.
+    pub fn triangles(&self, mat: Arc<dyn Material + Send + Sync>) -> HittableList {
+        self.sided_triangles(mat, Sides::Both)
+    }
+
+    /// Like `triangles`, hittable only from `sides` (see `Triangle::with_sides`).
+    pub fn sided_triangles(
+        &self,
+        mat: Arc<dyn Material + Send + Sync>,
+        sides: Sides,
+    ) -> HittableList {
+        let mut list = HittableList::new();
+        for face in &self.faces {
+            for k in 1..face.len() - 1 {
+                let (a, b, c) = (face[0], face[k], face[k + 1]);
+                let mut triangle = Triangle::new(
+                    self.positions[a],
+                    self.positions[b],
+                    self.positions[c],
+                    mat.clone(),
+                )
+                .with_sides(sides);
+                if let Some(normals) = self.corner_normals(a, b, c) {
+                    triangle = triangle.with_normals(normals);
+                }
+                list.add(Arc::new(triangle));
+            }
+        }
+        list
+    }
+}
+
+/// One round of Catmull-Clark subdivision. Every n-gon becomes n quads; boundary
+/// edges and vertices use the standard crease rules so open meshes keep their outline.
+pub fn catmull_clark(mesh: &Mesh) -> Mesh {
+    use std::collections::HashMap;
+
+    let vertex_count = mesh.positions.len();
+    let edge_key = |a: usize, b: usize| if a < b { (a, b) } else { (b, a) };
+
+    let face_points: Vec<Point3> = mesh
+        .faces
+        .iter()
+        .map(|face| {
+            let sum = face
+                .iter()
+                .fold(Vec3::zero(), |acc, &i| acc + mesh.positions[i]);
+            sum / face.len() as f64
+        })
+        .collect();
+
+    // Adjacent faces of every edge.
+    let mut edge_faces: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for (f, face) in mesh.faces.iter().enumerate() {
+        for k in 0..face.len() {
+            let key = edge_key(face[k], face[(k + 1) % face.len()]);
+            edge_faces.entry(key).or_default().push(f);
+        }
+    }
+
+    let mut edges: Vec<(usize, usize)> = edge_faces.keys().copied().collect();
+    edges.sort_unstable();
+    let edge_index: HashMap<(usize, usize), usize> = edges
+        .iter()
+        .enumerate()
+        .map(|(i, &key)| (key, vertex_count + face_points.len() + i))
+        .collect();
+
+    let edge_points: Vec<Point3> = edges
+        .iter()
+        .map(|&(a, b)| {
+            let faces = &edge_faces[&(a, b)];
+            let midpoint = 0.5 * (mesh.positions[a] + mesh.positions[b]);
+            if faces.len() == 2 {
+                (mesh.positions[a]
+                    + mesh.positions[b]
+                    + face_points[faces[0]]
+                    + face_points[faces[1]])
+                    / 4.0
+            } else {
+                midpoint
+            }
+        })
+        .collect();
+
+    // Per-vertex adjacency for the vertex update.
+    let mut vertex_faces: Vec<Vec<usize>> = vec![Vec::new(); vertex_count];
+    for (f, face) in mesh.faces.iter().enumerate() {
+        for &v in face {
+            vertex_faces[v].push(f);
+        }
+    }
+    let mut vertex_edges: Vec<Vec<(usize, usize)>> = vec![Vec::new(); vertex_count];
+    for &(a, b) in &edges {
+        vertex_edges[a].push((a, b));
+        vertex_edges[b].push((a, b));
+    }
+
+    let updated: Vec<Point3> = (0..vertex_count)
+        .map(|v| {
+            let p = mesh.positions[v];
+            let boundary: Vec<usize> = vertex_edges[v]
+                .iter()
+                .filter(|key| edge_faces[key].len() == 1)
+                .map(|&(a, b)| if a == v { b } else { a })
+                .collect();
+
+            if boundary.len() == 2 {
+                return 0.125 * (mesh.positions[boundary[0]] + mesh.positions[boundary[1]])
+                    + 0.75 * p;
+            }
+            if !boundary.is_empty() || vertex_faces[v].is_empty() {
+                // Corners and non-manifold vertices stay put.
+                return p;
+            }
+
+            let n = vertex_faces[v].len() as f64;
+            let f = vertex_faces[v]
+                .iter()
+                .fold(Vec3::zero(), |acc, &fi| acc + face_points[fi])
+                / n;
+            let r = vertex_edges[v].iter().fold(Vec3::zero(), |acc, &(a, b)| {
+                acc + 0.5 * (mesh.positions[a] + mesh.positions[b])
+            }) / vertex_edges[v].len() as f64;
+            (f + 2.0 * r + (n - 3.0) * p) / n
+        })
+        .collect();
+
+    let mut positions = updated;
+    positions.extend(face_points.iter().copied());
+    positions.extend(edge_points);
+
+    let mut faces = Vec::new();
+    for (f, face) in mesh.faces.iter().enumerate() {
+        let face_point = vertex_count + f;
+        for k in 0..face.len() {
+            let prev = face[(k + face.len() - 1) % face.len()];
+            let curr = face[k];
+            let next = face[(k + 1) % face.len()];
+            faces.push(vec![
+                curr,
+                edge_index[&edge_key(curr, next)],
+                face_point,
+                edge_index[&edge_key(prev, curr)],
+            ]);
+        }
+    }
+
+    Mesh::new(positions, faces)
+}
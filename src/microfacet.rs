@@ -0,0 +1,189 @@
+use crate::rtweekend::*;
+
+/// Anisotropic Trowbridge-Reitz (GGX) microfacet distribution. All directions are in
+/// the local shading frame: x along the tangent, y along the bitangent, z along the normal.
+#[derive(Debug, Clone, Copy)]
+pub struct Ggx {
+    pub alpha_x: f64,
+    pub alpha_y: f64,
+}
+
+impl Ggx {
+    /// Maps perceptual roughness (0 = mirror, 1 = very rough) to GGX alpha.
+    pub fn from_roughness(roughness_x: f64, roughness_y: f64) -> Self {
+        let alpha = |r: f64| (r * r).max(1e-4);
+        Self {
+            alpha_x: alpha(roughness_x.clamp(0.0, 1.0)),
+            alpha_y: alpha(roughness_y.clamp(0.0, 1.0)),
+        }
+    }
+
+    pub fn isotropic(roughness: f64) -> Self {
+        Self::from_roughness(roughness, roughness)
+    }
+
+    /// The same distribution with roughness multiplied by `roughness_scale`.
+    pub fn scaled(&self, roughness_scale: f64) -> Self {
+        let s2 = roughness_scale * roughness_scale;
+        Self {
+            alpha_x: (self.alpha_x * s2).clamp(1e-4, 1.0),
+            alpha_y: (self.alpha_y * s2).clamp(1e-4, 1.0),
+        }
+    }
+
+    /// Normal distribution D(h).
+    pub fn d(&self, h: Vec3) -> f64 {
+        if h.z <= 0.0 {
+            return 0.0;
+        }
+        let e = (h.x / self.alpha_x).powi(2) + (h.y / self.alpha_y).powi(2) + h.z * h.z;
+        1.0 / (PI * self.alpha_x * self.alpha_y * e * e)
+    }
+
+    pub fn lambda(&self, w: Vec3) -> f64 {
+        if w.z.abs() < 1e-12 {
+            return INFINITY;
+        }
+        let a2 = (self.alpha_x * w.x).powi(2) + (self.alpha_y * w.y).powi(2);
+        0.5 * (-1.0 + (1.0 + a2 / (w.z * w.z)).sqrt())
+    }
+
+    /// Smith masking for one direction.
+    pub fn g1(&self, w: Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(w))
+    }
+
+    /// Height-correlated Smith masking-shadowing.
+    pub fn g2(&self, wo: Vec3, wi: Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(wo) + self.lambda(wi))
+    }
+
+    /// Samples a microfacet normal visible from `wo` (Heitz 2018).
+    pub fn sample_visible_normal(&self, wo: Vec3, rng: &mut SmallRng) -> Vec3 {
+        let vh = Vec3::new(self.alpha_x * wo.x, self.alpha_y * wo.y, wo.z).normalized();
+        let lensq = vh.x * vh.x + vh.y * vh.y;
+        let t1 = if lensq > 0.0 {
+            Vec3::new(-vh.y, vh.x, 0.0) / lensq.sqrt()
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let t2 = vh.cross(t1);
+
+        let r = random_f64(rng).sqrt();
+        let phi = 2.0 * PI * random_f64(rng);
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + vh.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+
+        let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * vh;
+        Vec3::new(self.alpha_x * nh.x, self.alpha_y * nh.y, nh.z.max(0.0)).normalized()
+    }
+
+    /// Density of `sample_visible_normal` producing `h`, as a density of normals.
+    pub fn visible_normal_pdf(&self, wo: Vec3, h: Vec3) -> f64 {
+        if wo.z <= 0.0 {
+            return 0.0;
+        }
+        self.g1(wo) * wo.dot(h).max(0.0) * self.d(h) / wo.z
+    }
+}
+
+/// Exact unpolarized Fresnel reflectance of a dielectric interface. `cos_i` is measured
+/// on the incident side and `eta` is the transmitted over incident index of refraction.
+pub fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let cos_i = cos_i.clamp(0.0, 1.0);
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0; // Total internal reflection
+    }
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let rs = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    let rp = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    0.5 * (rs * rs + rp * rp)
+}
+
+/// Exact unpolarized Fresnel reflectance of a conductor with complex index of refraction
+/// `eta + i k`, evaluated per color channel.
+pub fn fresnel_conductor(cos_i: f64, eta: Color, k: Color) -> Color {
+    let channel = |eta: f64, k: f64| {
+        let cos2 = cos_i.clamp(0.0, 1.0).powi(2);
+        let sin2 = 1.0 - cos2;
+        let t0 = eta * eta - k * k - sin2;
+        let a2_plus_b2 = (t0 * t0 + 4.0 * eta * eta * k * k).sqrt();
+        let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+        let t1 = a2_plus_b2 + cos2;
+        let t2 = 2.0 * cos2.sqrt() * a;
+        let rs = (t1 - t2) / (t1 + t2);
+        let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+        let t4 = t2 * sin2;
+        let rp = rs * (t3 - t4) / (t3 + t4);
+        0.5 * (rs + rp)
+    };
+    Color::new(
+        channel(eta.r, k.r),
+        channel(eta.g, k.g),
+        channel(eta.b, k.b),
+    )
+}
+
+/// How a dielectric computes its reflectance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Fresnel {
+    /// Schlick's approximation, cheap and close except near grazing angles.
+    #[default]
+    Schlick,
+    /// The full Fresnel equations for unpolarized light.
+    Exact,
+}
+
+/// Complex index of refraction of a metal at the red, green and blue wavelengths,
+/// which gives it its colored edge tint.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Conductor {
+    pub eta: Color,
+    pub k: Color,
+}
+
+impl Conductor {
+    pub const GOLD: Self = Self::new(
+        Color::new(0.143, 0.374, 1.442),
+        Color::new(3.983, 2.386, 1.603),
+    );
+    pub const SILVER: Self = Self::new(
+        Color::new(0.155, 0.117, 0.138),
+        Color::new(4.828, 3.122, 2.147),
+    );
+    pub const COPPER: Self = Self::new(
+        Color::new(0.200, 0.924, 1.102),
+        Color::new(3.912, 2.452, 2.142),
+    );
+    pub const ALUMINIUM: Self = Self::new(
+        Color::new(1.657, 0.880, 0.521),
+        Color::new(9.224, 6.270, 4.837),
+    );
+
+    pub const fn new(eta: Color, k: Color) -> Self {
+        Self { eta, k }
+    }
+
+    pub fn reflectance(&self, cos_i: f64) -> Color {
+        fresnel_conductor(cos_i, self.eta, self.k)
+    }
+}
+
+/// Schlick's approximation with a colored normal-incidence reflectance.
+pub fn fresnel_schlick(f0: Color, cosine: f64) -> Color {
+    let w = (1.0 - cosine.clamp(0.0, 1.0)).powi(5);
+    f0 + (Color::new(1.0, 1.0, 1.0) - f0) * w
+}
+
+/// Transforms a world direction into the (tangent, bitangent, normal) frame of a hit.
+pub fn to_shading_frame(rec: &HitRecord, w: Vec3) -> Vec3 {
+    let (t, b, n) = rec.tangent_frame();
+    Vec3::new(w.dot(t), w.dot(b), w.dot(n))
+}
+
+pub fn from_shading_frame(rec: &HitRecord, w: Vec3) -> Vec3 {
+    let (t, b, n) = rec.tangent_frame();
+    w.x * t + w.y * b + w.z * n
+}
@@ -0,0 +1,74 @@
+use crate::rtweekend::*;
+
+/// Moves any hittable along a keyframed path. Rays are tested against the object at the
+/// position it has at the ray's time, so long exposures draw the whole path; with an
+/// emissive object this paints light trails.
+pub struct MotionPath {
+    object: Arc<dyn Hittable>,
+    /// (time, offset) pairs sorted by time. The offset is clamped outside the keyframes.
+    keyframes: Vec<(f64, Vec3)>,
+}
+
+impl MotionPath {
+    pub fn new(object: Arc<dyn Hittable>, mut keyframes: Vec<(f64, Vec3)>) -> Self {
+        keyframes.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self { object, keyframes }
+    }
+
+    /// Offset of the object at `time`, interpolated linearly between keyframes.
+    pub fn offset_at(&self, time: f64) -> Vec3 {
+        let Some(&(first_time, first)) = self.keyframes.first() else {
+            return Vec3::zero();
+        };
+        if time <= first_time {
+            return first;
+        }
+        for pair in self.keyframes.windows(2) {
+            let ((t0, p0), (t1, p1)) = (pair[0], pair[1]);
+            if time <= t1 {
+                let s = if t1 > t0 {
+                    (time - t0) / (t1 - t0)
+                } else {
+                    1.0
+                };
+                return p0 + s * (p1 - p0);
+            }
+        }
+        self.keyframes[self.keyframes.len() - 1].1
+    }
+}
+
+impl Hittable for MotionPath {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let offset = self.offset_at(r.tm);
+        let moved = Ray::with_time(r.orig - offset, r.dir, r.tm);
+        let mut rec = self.object.hit(&moved, ray_t)?;
+        rec.p += offset;
+        Some(rec)
+    }
+
+    // Light sampling can't know the ray time, so it aims at the starting position.
+    // Sampling stays unbiased because the mixture still covers every direction.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        self.object
+            .pdf_value(origin - self.offset_at(0.0), direction)
+    }
+
+    fn random(&self, origin: Point3, rng: &mut SmallRng) -> Vec3 {
+        self.object.random(origin - self.offset_at(0.0), rng)
+    }
+
+    // Linear segments between keyframes stay inside the box spanned by the keyframes.
+    fn bounding_box(&self) -> Aabb {
+        let bbox = self.object.bounding_box();
+        if self.keyframes.is_empty() {
+            return bbox;
+        }
+        self.keyframes.iter().fold(Aabb::EMPTY, |acc, (_, offset)| {
+            acc.union(&Aabb::from_points(
+                bbox.min() + *offset,
+                bbox.max() + *offset,
+            ))
+        })
+    }
+}
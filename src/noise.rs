@@ -0,0 +1,156 @@
+use crate::rtweekend::*;
+
+// Mixes lattice coordinates and a seed into a well-scrambled 32-bit value.
+fn hash(coords: [i64; 4], seed: u32) -> u32 {
+    let mut h = seed as u64 ^ 0x9e37_79b9_7f4a_7c15;
+    for c in coords {
+        h ^= c as u64;
+        h = (h ^ (h >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        h = (h ^ (h >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        h ^= h >> 31;
+    }
+    (h >> 32) as u32
+}
+
+fn unit_random(h: u32) -> f64 {
+    h as f64 / (u32::MAX as f64 + 1.0)
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + t * (b - a)
+}
+
+/// Seeded Perlin gradient noise over space and time. Time is a fourth dimension, so
+/// the pattern evolves in place rather than sliding along.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Perlin {
+    pub seed: u32,
+}
+
+impl Perlin {
+    pub fn new(seed: u32) -> Self {
+        Self { seed }
+    }
+
+    // Dot product of the offset with one of 32 gradients along the edges of a 4D cube.
+    fn gradient(&self, corner: [i64; 4], d: [f64; 4]) -> f64 {
+        let h = hash(corner, self.seed) & 31;
+        let skip = (h >> 3) as usize; // Which axis the gradient is zero along.
+        let mut sum = 0.0;
+        let mut bit = 0;
+        for (axis, d) in d.iter().enumerate() {
+            if axis == skip {
+                continue;
+            }
+            sum += if h & (1 << bit) == 0 { *d } else { -*d };
+            bit += 1;
+        }
+        sum
+    }
+
+    /// Noise value in roughly [-1, 1] at `p` and `time`.
+    pub fn noise(&self, p: Point3, time: f64) -> f64 {
+        let x = [p.x, p.y, p.z, time];
+        let base = x.map(|c| c.floor());
+        let frac: [f64; 4] = std::array::from_fn(|k| x[k] - base[k]);
+        let fades = frac.map(fade);
+        let base = base.map(|c| c as i64);
+
+        // Blend the 16 corner contributions one axis at a time.
+        let mut values = [0.0; 16];
+        for (corner, value) in values.iter_mut().enumerate() {
+            let offset: [i64; 4] = std::array::from_fn(|k| ((corner >> k) & 1) as i64);
+            let lattice = std::array::from_fn(|k| base[k] + offset[k]);
+            let d = std::array::from_fn(|k| frac[k] - offset[k] as f64);
+            *value = self.gradient(lattice, d);
+        }
+        let mut len = 16;
+        for t in fades {
+            len /= 2;
+            for k in 0..len {
+                values[k] = lerp(values[2 * k], values[2 * k + 1], t);
+            }
+        }
+        values[0]
+    }
+
+    /// Sum of `octaves` layers of noise, each at double the frequency and half the
+    /// amplitude of the last, normalized to roughly [-1, 1].
+    pub fn fbm(&self, p: Point3, time: f64, octaves: u32) -> f64 {
+        let mut sum = 0.0;
+        let mut amplitude = 1.0;
+        let mut total = 0.0;
+        let mut frequency = 1.0;
+        for _ in 0..octaves.max(1) {
+            sum += amplitude * self.noise(frequency * p, frequency * time);
+            total += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+        sum / total
+    }
+
+    /// Like `fbm` over the absolute value of each layer, giving billowing, smoke-like
+    /// patterns in [0, 1].
+    pub fn turbulence(&self, p: Point3, time: f64, octaves: u32) -> f64 {
+        let mut sum = 0.0;
+        let mut amplitude = 1.0;
+        let mut total = 0.0;
+        let mut frequency = 1.0;
+        for _ in 0..octaves.max(1) {
+            sum += amplitude * self.noise(frequency * p, frequency * time).abs();
+            total += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+        (sum / total).min(1.0)
+    }
+}
+
+/// Seeded Worley (cellular) noise: the distance to the nearest of one randomly placed
+/// feature point per unit cell. Over time each point drifts around its home position,
+/// so cells wobble and merge like caustics or boiling liquid.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Worley {
+    pub seed: u32,
+}
+
+impl Worley {
+    pub fn new(seed: u32) -> Self {
+        Self { seed }
+    }
+
+    fn feature_point(&self, cell: [i64; 3], time: f64) -> Point3 {
+        let coord = |k: i64| {
+            let h = hash([cell[0], cell[1], cell[2], k], self.seed);
+            let home = 0.25 + 0.5 * unit_random(h);
+            let phase = 2.0 * PI * unit_random(h.rotate_left(16));
+            home + 0.25 * (time * 2.0 * PI + phase).sin()
+        };
+        Point3::new(
+            cell[0] as f64 + coord(0),
+            cell[1] as f64 + coord(1),
+            cell[2] as f64 + coord(2),
+        )
+    }
+
+    /// Distance from `p` to the nearest feature point at `time`, in cell units.
+    pub fn distance(&self, p: Point3, time: f64) -> f64 {
+        let cell = [p.x.floor(), p.y.floor(), p.z.floor()].map(|c| c as i64);
+        let mut nearest = INFINITY;
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let neighbor = [cell[0] + dx, cell[1] + dy, cell[2] + dz];
+                    let d = (self.feature_point(neighbor, time) - p).length();
+                    nearest = nearest.min(d);
+                }
+            }
+        }
+        nearest
+    }
+}
@@ -0,0 +1,52 @@
+use crate::rtweekend::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Drawn in place of samples that came out NaN or infinite, so they stand out instead
+/// of vanishing into black or white specks.
+pub const NONFINITE_COLOR: Color = Color::new(1.0, 0.0, 1.0);
+
+/// Samples that came out NaN or infinite during a render with `Camera::check_finite`.
+/// The first few are reported as they are found, with their pixel and the material
+/// the path was on when its color stopped being finite; the rest are only counted.
+#[derive(Debug, Default)]
+pub struct NonFiniteLog {
+    count: AtomicU64,
+}
+
+impl NonFiniteLog {
+    /// Samples reported individually before the log goes quiet.
+    const REPORTED: u64 = 10;
+
+    /// Records a non-finite sample in film pixel (`i`, `j`) and returns the color to
+    /// put on the film instead. `material` is `None` when the integrator can't tell.
+    pub fn record(&self, i: u32, j: u32, color: Color, material: Option<&str>) -> Color {
+        let n = self.count.fetch_add(1, Ordering::Relaxed);
+        if n < Self::REPORTED {
+            log::warn!(
+                target: "render",
+                "Non-finite sample at pixel ({}, {}): [{}, {}, {}], material {}",
+                i,
+                j,
+                color.r,
+                color.g,
+                color.b,
+                material.unwrap_or("unknown")
+            );
+        } else if n == Self::REPORTED {
+            log::warn!(target: "render", "Further non-finite samples are only counted");
+        }
+        NONFINITE_COLOR
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Logs the total if there were any.
+    pub fn log_summary(&self) {
+        let n = self.count();
+        if n > 0 {
+            log::warn!(target: "render", "{} non-finite samples, drawn in magenta", n);
+        }
+    }
+}
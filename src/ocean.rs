@@ -0,0 +1,152 @@
+use crate::rtweekend::*;
+
+const GRAVITY: f64 = 9.81;
+
+/// One Gerstner wave travelling across the XZ plane.
+#[derive(Debug, Clone, Copy)]
+pub struct GerstnerWave {
+    /// Unit travel direction in the XZ plane, as (x, z).
+    pub direction: (f64, f64),
+    pub wavelength: f64,
+    pub amplitude: f64,
+    /// 0 gives a sine wave, 1 the sharpest crest before the wave loops over itself.
+    pub steepness: f64,
+}
+
+impl GerstnerWave {
+    pub fn new(direction: (f64, f64), wavelength: f64, amplitude: f64, steepness: f64) -> Self {
+        let len = (direction.0 * direction.0 + direction.1 * direction.1).sqrt();
+        Self {
+            direction: (direction.0 / len, direction.1 / len),
+            wavelength,
+            amplitude,
+            steepness,
+        }
+    }
+}
+
+/// Horizontal water plane at height `level` whose shading normal follows a sum of
+/// Gerstner waves. The geometry stays flat, so it is cheap to intersect, while
+/// reflections and refractions see the waves. Advance `time` (in seconds) per
+/// animation frame to move the waves.
+pub struct Ocean {
+    pub level: f64,
+    /// Half-size of the square patch around the origin; `None` for an endless sea.
+    pub extent: Option<f64>,
+    pub waves: Vec<GerstnerWave>,
+    pub time: f64,
+    pub mat: Arc<dyn Material + Send + Sync>,
+}
+
+impl Ocean {
+    pub fn new(level: f64, waves: Vec<GerstnerWave>, mat: Arc<dyn Material + Send + Sync>) -> Self {
+        Self {
+            level,
+            extent: None,
+            waves,
+            time: 0.0,
+            mat,
+        }
+    }
+
+    /// Endless sea of clear water with a few waves spread around `wind` (x, z).
+    pub fn water(level: f64, wind: (f64, f64), scale: f64) -> Self {
+        Self::new(
+            level,
+            Self::wind_waves(wind, scale),
+            Arc::new(Dielectric::new(1.333)),
+        )
+    }
+
+    /// A small spectrum of waves around the wind direction, longest first.
+    pub fn wind_waves(wind: (f64, f64), scale: f64) -> Vec<GerstnerWave> {
+        let base = wind.1.atan2(wind.0);
+        [
+            (0.0, 1.0, 0.5),
+            (0.35, 0.61, 0.4),
+            (-0.5, 0.37, 0.35),
+            (0.9, 0.23, 0.3),
+            (-1.1, 0.13, 0.25),
+        ]
+        .iter()
+        .map(|&(angle, length, steepness)| {
+            let a = base + angle;
+            let wavelength = length * scale;
+            GerstnerWave::new((a.cos(), a.sin()), wavelength, wavelength * 0.03, steepness)
+        })
+        .collect()
+    }
+
+    /// Surface height offset at (x, z), for placing objects on the water.
+    pub fn height(&self, x: f64, z: f64) -> f64 {
+        self.waves
+            .iter()
+            .map(|w| w.amplitude * self.phase(w, x, z).sin())
+            .sum::<f64>()
+            + self.level
+    }
+
+    fn phase(&self, wave: &GerstnerWave, x: f64, z: f64) -> f64 {
+        let k = 2.0 * PI / wave.wavelength;
+        let omega = (GRAVITY * k).sqrt();
+        k * (wave.direction.0 * x + wave.direction.1 * z) - omega * self.time
+    }
+
+    fn wave_normal(&self, x: f64, z: f64) -> Vec3 {
+        let mut n = Vec3::new(0.0, 1.0, 0.0);
+        for wave in &self.waves {
+            let k = 2.0 * PI / wave.wavelength;
+            let wa = k * wave.amplitude;
+            let phase = self.phase(wave, x, z);
+            n.x -= wave.direction.0 * wa * phase.cos();
+            n.z -= wave.direction.1 * wa * phase.cos();
+            n.y -= wave.steepness * wa * phase.sin();
+        }
+        n.normalized()
+    }
+}
+
+impl Hittable for Ocean {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        if r.dir.y.abs() < 1e-12 {
+            return None;
+        }
+        let t = (self.level - r.orig.y) / r.dir.y;
+        if !ray_t.surrounds(t) {
+            return None;
+        }
+        let p = r.at(t);
+        if let Some(extent) = self.extent
+            && (p.x.abs() > extent || p.z.abs() > extent)
+        {
+            return None;
+        }
+
+        // Which side was hit comes from the flat plane; only the shading normal is perturbed.
+        let front_face = r.dir.y < 0.0;
+        let n = self.wave_normal(p.x, p.z);
+
+        Some(HitRecord {
+            p,
+            normal: if front_face { n } else { -n },
+            geometric_normal: Vec3::new(0.0, if front_face { 1.0 } else { -1.0 }, 0.0),
+            t,
+            uv: Vec2::new(p.x, p.z),
+            tangent: Vec3::new(1.0, 0.0, 0.0),
+            uv_scale: 1.0,
+            mat: self.mat.clone(),
+            front_face,
+            ..Default::default()
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        match self.extent {
+            Some(extent) => Aabb::from_points(
+                Point3::new(-extent, self.level, -extent),
+                Point3::new(extent, self.level, extent),
+            ),
+            None => Aabb::UNIVERSE,
+        }
+    }
+}
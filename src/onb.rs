@@ -0,0 +1,33 @@
+use crate::rtweekend::*;
+
+/// Orthonormal basis with `w` aligned to a given direction.
+#[derive(Debug, Copy, Clone)]
+pub struct Onb {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl Onb {
+    pub fn new(n: Vec3) -> Self {
+        let w = n.normalized();
+        let a = if w.x.abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = w.cross(a).normalized();
+        let u = w.cross(v);
+        Self { u, v, w }
+    }
+
+    /// Maps a vector expressed in this basis back to world space.
+    pub fn transform(&self, v: Vec3) -> Vec3 {
+        (v.x * self.u) + (v.y * self.v) + (v.z * self.w)
+    }
+
+    /// Expresses a world-space vector in this basis.
+    pub fn to_local(&self, v: Vec3) -> Vec3 {
+        Vec3::new(v.dot(self.u), v.dot(self.v), v.dot(self.w))
+    }
+}
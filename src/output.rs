@@ -0,0 +1,226 @@
+use crate::rtweekend::*;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// File format of a rendered image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImageFormat {
+    /// Plain-text PPM (P3): readable by anything, even a text editor, but about four
+    /// times the size of the others and slow to write.
+    #[default]
+    PlainPpm,
+    /// Binary PPM (P6), 8 bits per channel.
+    Ppm,
+    /// PAM (P7) with 16 bits per channel, for grading afterwards.
+    Pam,
+    Png,
+}
+
+impl ImageFormat {
+    /// Formats by the names `--format` takes: `p3`, `ppm` (or `p6`), `pam` and `png`.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "p3" => Some(Self::PlainPpm),
+            "ppm" | "p6" => Some(Self::Ppm),
+            "pam" => Some(Self::Pam),
+            "png" => Some(Self::Png),
+            _ => None,
+        }
+    }
+
+    /// The format a file's extension calls for, `None` for one it doesn't know. A .ppm
+    /// or .pnm file is written binary; plain text has to be asked for.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "ppm" | "pnm" => Some(Self::Ppm),
+            "pam" => Some(Self::Pam),
+            "png" => Some(Self::Png),
+            _ => None,
+        }
+    }
+
+    /// Writes row-major `pixels`, top row first, in this format.
+    pub fn write<W: Write>(
+        self,
+        out: W,
+        width: u32,
+        height: u32,
+        pixels: &[Color],
+    ) -> io::Result<()> {
+        match self {
+            Self::PlainPpm => write_ppm(out, width, height, pixels),
+            Self::Ppm => write_ppm_binary(out, width, height, pixels),
+            Self::Pam => write_pam(out, width, height, pixels),
+            Self::Png => write_png(out, width, height, pixels),
+        }
+    }
+}
+
+/// Values available to output filename templates.
+#[derive(Debug, Clone)]
+pub struct OutputVars {
+    pub scene: String,
+    pub camera: String,
+    pub spp: u32,
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+    pub frame: u32,
+    /// Moment the render started, for `{date}` and `{time}`.
+    pub started: SystemTime,
+}
+
+impl OutputVars {
+    pub fn new(scene: &str, camera: &crate::camera::Camera) -> Self {
+        Self {
+            scene: scene.to_string(),
+            camera: crate::scene_file::MAIN_CAMERA.to_string(),
+            spp: camera.samples_per_pixel,
+            width: camera.image_width,
+            height: camera.image_height(),
+            depth: camera.max_depth,
+            frame: 0,
+            started: SystemTime::now(),
+        }
+    }
+}
+
+// Year, month and day (UTC) of a count of days since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+// Characters that aren't allowed in file names on some platforms.
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '/' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+fn format_number(value: u64, spec: Option<&str>) -> io::Result<String> {
+    let Some(spec) = spec else {
+        return Ok(value.to_string());
+    };
+    let width: usize = spec.trim_start_matches('0').parse().unwrap_or(0);
+    if !spec.chars().all(|c| c.is_ascii_digit()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("output template: unsupported format `{}`", spec),
+        ));
+    }
+    if spec.starts_with('0') {
+        Ok(format!("{:0width$}", value))
+    } else {
+        Ok(format!("{:width$}", value))
+    }
+}
+
+/// Expands placeholders in an output path template, e.g.
+/// `renders/{scene}_{spp}spp_{date}_f{frame:04}.ppm`.
+///
+/// Placeholders are `{scene}`, `{camera}`, `{spp}`, `{width}`, `{height}`, `{depth}`,
+/// `{frame}`, `{date}` (YYYY-MM-DD) and `{time}` (HHMMSS), both in UTC. Numbers take a
+/// width, zero-padded if it starts with 0 (`{frame:04}`). `{{` and `}}` are literal braces.
+/// Both `/` and `\` separate directories, and substituted values are made safe for
+/// file names on every platform.
+pub fn expand_template(template: &str, vars: &OutputVars) -> io::Result<PathBuf> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidInput, msg);
+    let since_epoch = vars
+        .started
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+    let (year, month, day) = civil_from_days(since_epoch.div_euclid(86_400));
+    let seconds = since_epoch.rem_euclid(86_400);
+
+    let mut path = PathBuf::new();
+    let mut component = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                component.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                component.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => {
+                            return Err(invalid(format!(
+                                "output template: unclosed `{{{}`",
+                                placeholder
+                            )));
+                        }
+                    }
+                }
+                let (name, spec) = match placeholder.split_once(':') {
+                    Some((name, spec)) => (name, Some(spec)),
+                    None => (placeholder.as_str(), None),
+                };
+                let value = match name {
+                    "scene" => sanitize(&vars.scene),
+                    "camera" => sanitize(&vars.camera),
+                    "spp" => format_number(vars.spp.into(), spec)?,
+                    "width" => format_number(vars.width.into(), spec)?,
+                    "height" => format_number(vars.height.into(), spec)?,
+                    "depth" => format_number(vars.depth.into(), spec)?,
+                    "frame" => format_number(vars.frame.into(), spec)?,
+                    "date" => format!("{:04}-{:02}-{:02}", year, month, day),
+                    "time" => format!(
+                        "{:02}{:02}{:02}",
+                        seconds / 3600,
+                        seconds / 60 % 60,
+                        seconds % 60
+                    ),
+                    _ => {
+                        return Err(invalid(format!(
+                            "output template: unknown placeholder `{{{}}}`",
+                            name
+                        )));
+                    }
+                };
+                component.push_str(&value);
+            }
+            '}' => return Err(invalid("output template: unmatched `}`".to_string())),
+            '/' | '\\' if path.as_os_str().is_empty() && component.is_empty() => {
+                path.push(std::path::MAIN_SEPARATOR_STR);
+            }
+            '/' | '\\' => {
+                path.push(&component);
+                component.clear();
+            }
+            c => component.push(c),
+        }
+    }
+    if component.is_empty() {
+        return Err(invalid(format!(
+            "output template: `{}` doesn't name a file",
+            template
+        )));
+    }
+    path.push(component);
+    Ok(path)
+}
@@ -0,0 +1,85 @@
+//! Data parallelism for the rest of the crate. With the `parallel` feature (on by
+//! default) this is rayon's. Without it the same calls run in order on the calling
+//! thread, for builds that have no threads, like wasm32 in a browser. Modules import
+//! `crate::parallel::*` rather than rayon's prelude, so they build either way.
+
+#[cfg(feature = "parallel")]
+pub use rayon::prelude::*;
+#[cfg(feature = "parallel")]
+pub use rayon::{ThreadPool, ThreadPoolBuilder, current_num_threads, join};
+
+#[cfg(not(feature = "parallel"))]
+pub use sequential::*;
+
+#[cfg(not(feature = "parallel"))]
+mod sequential {
+    /// There's only the calling thread.
+    pub fn current_num_threads() -> usize {
+        1
+    }
+
+    /// Runs `a`, then `b`.
+    pub fn join<A, B, RA, RB>(a: A, b: B) -> (RA, RB)
+    where
+        A: FnOnce() -> RA,
+        B: FnOnce() -> RB,
+    {
+        (a(), b())
+    }
+
+    /// Stand-in for a rayon pool: work runs on the calling thread.
+    pub struct ThreadPool;
+
+    impl ThreadPool {
+        pub fn install<R>(&self, op: impl FnOnce() -> R) -> R {
+            op()
+        }
+
+        pub fn current_num_threads(&self) -> usize {
+            1
+        }
+    }
+
+    /// Builds the stand-in pool; the thread count is ignored.
+    #[derive(Default)]
+    pub struct ThreadPoolBuilder;
+
+    impl ThreadPoolBuilder {
+        pub fn new() -> Self {
+            Self
+        }
+
+        pub fn num_threads(self, _threads: usize) -> Self {
+            self
+        }
+
+        pub fn build(self) -> Result<ThreadPool, std::convert::Infallible> {
+            Ok(ThreadPool)
+        }
+    }
+
+    /// `into_par_iter` as the plain iterator.
+    pub trait IntoParallelIterator: IntoIterator + Sized {
+        fn into_par_iter(self) -> Self::IntoIter {
+            self.into_iter()
+        }
+    }
+
+    impl<I: IntoIterator> IntoParallelIterator for I {}
+
+    /// `par_iter` and `par_iter_mut` as the plain slice iterators.
+    pub trait ParallelSlice<T> {
+        fn par_iter(&self) -> std::slice::Iter<'_, T>;
+        fn par_iter_mut(&mut self) -> std::slice::IterMut<'_, T>;
+    }
+
+    impl<T> ParallelSlice<T> for [T] {
+        fn par_iter(&self) -> std::slice::Iter<'_, T> {
+            self.iter()
+        }
+
+        fn par_iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
+            self.iter_mut()
+        }
+    }
+}
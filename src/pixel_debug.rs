@@ -0,0 +1,233 @@
+use crate::rtweekend::*;
+
+/// How a path left a surface.
+#[derive(Debug, Clone, Copy)]
+pub struct ScatterLog {
+    pub direction: Vec3,
+    /// Factor applied to the light coming back along `direction` (BSDF times cosine
+    /// over the density, or the material's own attenuation for specular bounces).
+    pub weight: Color,
+    /// Density the direction was drawn with, `None` for specular bounces, which don't
+    /// have one.
+    pub pdf: Option<f64>,
+    /// Whether the direction was aimed at emissive geometry rather than drawn from
+    /// the material.
+    pub toward_light: bool,
+}
+
+/// One surface hit along a path.
+#[derive(Debug, Clone)]
+pub struct Bounce {
+    /// 0 for the surface the camera ray hit.
+    pub bounce: u32,
+    pub point: Point3,
+    pub normal: Vec3,
+    pub t: f64,
+    pub front_face: bool,
+    pub material: &'static str,
+    pub object_id: u32,
+    pub emitted: Color,
+    /// Light from the explicit lights, through shadow rays.
+    pub direct: Color,
+    /// `None` where the material absorbed the path.
+    pub scatter: Option<ScatterLog>,
+}
+
+impl Bounce {
+    pub fn new(
+        bounce: u32,
+        rec: &HitRecord,
+        emitted: Color,
+        direct: Color,
+        scatter: Option<ScatterLog>,
+    ) -> Self {
+        Self {
+            bounce,
+            point: rec.p,
+            normal: rec.normal,
+            t: rec.t,
+            front_face: rec.front_face,
+            material: rec.mat.name(),
+            object_id: rec.object_id,
+            emitted,
+            direct,
+            scatter,
+        }
+    }
+}
+
+/// Something that happened to a path, in the order it happened.
+#[derive(Debug, Clone)]
+pub enum PathEvent {
+    Hit(Bounce),
+    /// Left the scene and picked up the background.
+    Escaped {
+        bounce: u32,
+        direction: Vec3,
+        background: Color,
+    },
+    /// Ended by Russian roulette after scattering at `bounce`.
+    Roulette {
+        bounce: u32,
+    },
+    /// Cut off by `max_depth`.
+    MaxDepth,
+}
+
+/// One sample through the pixel and everything along its path.
+#[derive(Debug, Clone)]
+pub struct SampleTrace {
+    pub ray: Ray,
+    pub radiance: Color,
+    pub events: Vec<PathEvent>,
+}
+
+/// The samples traced by `Camera::debug_pixel`.
+#[derive(Debug, Clone)]
+pub struct PixelTrace {
+    pub i: u32,
+    pub j: u32,
+    pub samples: Vec<SampleTrace>,
+}
+
+impl PixelTrace {
+    /// Average of the samples' radiance, before exposure.
+    pub fn mean(&self) -> Color {
+        let sum = self
+            .samples
+            .iter()
+            .fold(Color::BLACK, |acc, s| acc + s.radiance);
+        sum / self.samples.len().max(1) as f64
+    }
+
+    /// Logs every sample's path under the `render` target, flagging non-finite radiance
+    /// and samples far brighter than the pixel's median (fireflies).
+    pub fn log_trace(&self) {
+        let mut luminances: Vec<f64> = self.samples.iter().map(|s| luminance(s.radiance)).collect();
+        luminances.sort_by(f64::total_cmp);
+        let median = luminances.get(luminances.len() / 2).copied().unwrap_or(0.0);
+
+        log::info!(
+            target: "render",
+            "Pixel ({}, {}): {} samples",
+            self.i,
+            self.j,
+            self.samples.len()
+        );
+        for (n, sample) in self.samples.iter().enumerate() {
+            let y = luminance(sample.radiance);
+            let flag = if !y.is_finite() {
+                "  <-- NOT FINITE"
+            } else if y > 10.0 * median.max(1e-3) {
+                "  <-- firefly"
+            } else {
+                ""
+            };
+            log::info!(
+                target: "render",
+                "sample {}: radiance {}{}",
+                n,
+                format_color(sample.radiance),
+                flag
+            );
+            log::info!(
+                target: "render",
+                "  ray from {} dir {}",
+                format_vec(sample.ray.orig),
+                format_vec(sample.ray.dir.normalized())
+            );
+            for event in &sample.events {
+                match event {
+                    PathEvent::Hit(b) => {
+                        log::info!(
+                            target: "render",
+                            "  [{}] hit {} (object {}) at {} t={:.4} normal {}{}",
+                            b.bounce,
+                            b.material,
+                            b.object_id,
+                            format_vec(b.point),
+                            b.t,
+                            format_vec(b.normal),
+                            if b.front_face { "" } else { " (back face)" }
+                        );
+                        if !b.emitted.near_zero() || !b.direct.near_zero() {
+                            log::info!(
+                                target: "render",
+                                "      emitted {} direct {}",
+                                format_color(b.emitted),
+                                format_color(b.direct)
+                            );
+                        }
+                        match &b.scatter {
+                            Some(s) => log::info!(
+                                target: "render",
+                                "      scatter {} weight {} pdf {}{}",
+                                format_vec(s.direction.normalized()),
+                                format_color(s.weight),
+                                s.pdf
+                                    .map_or("specular".to_string(), |p| format!("{:.4}", p)),
+                                if s.toward_light {
+                                    " (light sample)"
+                                } else {
+                                    ""
+                                }
+                            ),
+                            None => log::info!(target: "render", "      absorbed"),
+                        }
+                    }
+                    PathEvent::Escaped {
+                        bounce,
+                        direction,
+                        background,
+                    } => log::info!(
+                        target: "render",
+                        "  [{}] escaped along {}, background {}",
+                        bounce,
+                        format_vec(direction.normalized()),
+                        format_color(*background)
+                    ),
+                    PathEvent::Roulette { bounce } => {
+                        log::info!(target: "render", "  [{}] ended by Russian roulette", bounce)
+                    }
+                    PathEvent::MaxDepth => log::info!(target: "render", "  cut off at max depth"),
+                }
+            }
+        }
+        log::info!(target: "render", "Mean radiance: {}", format_color(self.mean()));
+    }
+}
+
+fn format_vec(v: Vec3) -> String {
+    format!("({:.4}, {:.4}, {:.4})", v.x, v.y, v.z)
+}
+
+fn format_color(c: Color) -> String {
+    format!("[{:.4}, {:.4}, {:.4}]", c.r, c.g, c.b)
+}
+
+impl Camera {
+    /// Traces `samples` paths through film pixel (`i`, `j`) with the path tracer and
+    /// logs every bounce, for tracking down black pixels and fireflies. Rays are plain
+    /// RGB ones, without spectral sampling or chromatic aberration.
+    pub fn debug_pixel(&self, scene: &Scene, i: u32, j: u32, samples: u32) -> PixelTrace {
+        let tracer = match self.integrator {
+            IntegratorKind::Path => PathTracer::naive(),
+            _ => PathTracer::nee(),
+        };
+        let mut rng = self.pixel_rng(i, j);
+        let samples = (0..samples)
+            .map(|_| {
+                let ray = self.get_ray(i, j, &mut rng);
+                let mut stats = PathStats::logging(self.max_depth);
+                let radiance =
+                    tracer.trace(&ray, self.max_depth, scene, &mut rng, Some(&mut stats));
+                SampleTrace {
+                    ray,
+                    radiance,
+                    events: stats.events.unwrap_or_default(),
+                }
+            })
+            .collect();
+        PixelTrace { i, j, samples }
+    }
+}
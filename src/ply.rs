@@ -0,0 +1,269 @@
+use crate::rtweekend::*;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("ply: {}", msg))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    LittleEndian,
+    BigEndian,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> io::Result<Self> {
+        Ok(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            _ => return Err(invalid(&format!("unknown property type `{}`", name))),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::F64 => 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Property {
+    Scalar(String, Scalar),
+    /// Name, type of the length prefix and type of the items.
+    List(String, Scalar, Scalar),
+}
+
+#[derive(Debug, Clone)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+// Values from the body of the file, as text tokens or raw bytes.
+struct Reader<'a> {
+    format: Format,
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn token(&mut self) -> io::Result<&str> {
+        while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        let start = self.pos;
+        while self.pos < self.bytes.len() && !self.bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+        if start == self.pos {
+            return Err(invalid("unexpected end of file"));
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos]).map_err(|_| invalid("bad number"))
+    }
+
+    fn read(&mut self, ty: Scalar) -> io::Result<f64> {
+        if self.format == Format::Ascii {
+            return self.token()?.parse().map_err(|_| invalid("bad number"));
+        }
+        let end = self.pos + ty.size();
+        let b = self
+            .bytes
+            .get(self.pos..end)
+            .ok_or_else(|| invalid("unexpected end of file"))?;
+        self.pos = end;
+        let little = self.format == Format::LittleEndian;
+        macro_rules! num {
+            ($t:ty) => {{
+                let raw = b.try_into().unwrap();
+                (if little {
+                    <$t>::from_le_bytes(raw)
+                } else {
+                    <$t>::from_be_bytes(raw)
+                }) as f64
+            }};
+        }
+        Ok(match ty {
+            Scalar::I8 => num!(i8),
+            Scalar::U8 => num!(u8),
+            Scalar::I16 => num!(i16),
+            Scalar::U16 => num!(u16),
+            Scalar::I32 => num!(i32),
+            Scalar::U32 => num!(u32),
+            Scalar::F32 => num!(f32),
+            Scalar::F64 => num!(f64),
+        })
+    }
+}
+
+// Parses the header, returning the format, the elements and where the body starts.
+fn parse_header(bytes: &[u8]) -> io::Result<(Format, Vec<Element>, usize)> {
+    const END: &[u8] = b"end_header";
+    let end = bytes
+        .windows(END.len())
+        .position(|w| w == END)
+        .ok_or_else(|| invalid("missing end_header"))?;
+    let mut body = end + END.len();
+    // The header ends with a single newline, which may be CRLF.
+    if bytes.get(body) == Some(&b'\r') {
+        body += 1;
+    }
+    if bytes.get(body) == Some(&b'\n') {
+        body += 1;
+    }
+    let header = std::str::from_utf8(&bytes[..end]).map_err(|_| invalid("bad header"))?;
+
+    let mut lines = header.lines();
+    if lines.next().map(str::trim) != Some("ply") {
+        return Err(invalid("not a PLY file"));
+    }
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in lines {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["format", kind, _version] => {
+                format = Some(match *kind {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::LittleEndian,
+                    "binary_big_endian" => Format::BigEndian,
+                    _ => return Err(invalid(&format!("unknown format `{}`", kind))),
+                });
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| invalid("bad element count"))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count_ty, item_ty, name] => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| invalid("property before element"))?;
+                element.properties.push(Property::List(
+                    name.to_string(),
+                    Scalar::parse(count_ty)?,
+                    Scalar::parse(item_ty)?,
+                ));
+            }
+            ["property", ty, name] => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| invalid("property before element"))?;
+                element
+                    .properties
+                    .push(Property::Scalar(name.to_string(), Scalar::parse(ty)?));
+            }
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => return Err(invalid(&format!("unexpected header line `{}`", line))),
+        }
+    }
+    let format = format.ok_or_else(|| invalid("missing format line"))?;
+    Ok((format, elements, body))
+}
+
+/// Reads vertex positions, vertex normals if every vertex has them, and faces from a
+/// PLY file, ASCII or binary. Other vertex properties (colors) and other elements are
+/// skipped.
+pub fn parse_ply(bytes: &[u8]) -> io::Result<Mesh> {
+    let (format, elements, body) = parse_header(bytes)?;
+    let mut reader = Reader {
+        format,
+        bytes,
+        pos: body,
+    };
+    let mut mesh = Mesh::default();
+    let has_normals = elements.iter().any(|element| {
+        element.name == "vertex"
+            && ["nx", "ny", "nz"].iter().all(|axis| {
+                element
+                    .properties
+                    .iter()
+                    .any(|p| matches!(p, Property::Scalar(name, _) if name == axis))
+            })
+    });
+    for element in &elements {
+        for _ in 0..element.count {
+            let mut position = [0.0; 3];
+            let mut normal = [0.0; 3];
+            for property in &element.properties {
+                match property {
+                    Property::Scalar(name, ty) => {
+                        let value = reader.read(*ty)?;
+                        match name.as_str() {
+                            "x" => position[0] = value,
+                            "y" => position[1] = value,
+                            "z" => position[2] = value,
+                            "nx" => normal[0] = value,
+                            "ny" => normal[1] = value,
+                            "nz" => normal[2] = value,
+                            _ => {}
+                        }
+                    }
+                    Property::List(name, count_ty, item_ty) => {
+                        let count = reader.read(*count_ty)? as usize;
+                        // Not preallocated: `count` comes from the file and may be garbage.
+                        let mut items = Vec::new();
+                        for _ in 0..count {
+                            items.push(reader.read(*item_ty)?);
+                        }
+                        let is_face = element.name == "face"
+                            && (name == "vertex_indices" || name == "vertex_index");
+                        if !is_face {
+                            continue;
+                        }
+                        if items.len() < 3 {
+                            return Err(invalid("face needs at least three vertices"));
+                        }
+                        mesh.faces.push(items.iter().map(|&i| i as usize).collect());
+                    }
+                }
+            }
+            if element.name == "vertex" {
+                mesh.positions
+                    .push(Point3::new(position[0], position[1], position[2]));
+                if has_normals {
+                    mesh.normals
+                        .push(Vec3::new(normal[0], normal[1], normal[2]));
+                }
+            }
+        }
+    }
+    if mesh
+        .faces
+        .iter()
+        .flatten()
+        .any(|&i| i >= mesh.positions.len())
+    {
+        return Err(invalid("face index out of range"));
+    }
+    Ok(mesh)
+}
+
+pub fn load_ply(path: impl AsRef<Path>) -> io::Result<Mesh> {
+    parse_ply(&fs::read(path)?)
+}
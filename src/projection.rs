@@ -0,0 +1,47 @@
+use crate::rtweekend::*;
+use serde::{Deserialize, Serialize};
+
+/// How the camera maps film positions to ray directions.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum Projection {
+    /// Pinhole/thin-lens perspective set up by `vfov`, with depth of field.
+    #[default]
+    Perspective,
+    /// Equidistant fisheye: the angle from the view axis grows linearly with the
+    /// distance from the image center, reaching `fov / 2` degrees in the corners.
+    /// Values past 180 see behind the camera.
+    Fisheye { fov: f64 },
+    /// Full 360° x 180° latitude-longitude panorama, centered on the view direction.
+    /// Use a 2:1 aspect ratio; the result works as an environment map.
+    Equirectangular,
+}
+
+impl Projection {
+    /// Direction in camera space (x right, y up, looking down -z) through the film
+    /// position (`x`, `y`), both in [0, 1] from the top-left corner. `aspect` is the
+    /// film's width over its height. Perspective is handled by the camera's viewport.
+    pub fn direction(self, x: f64, y: f64, aspect: f64) -> Option<Vec3> {
+        match self {
+            Self::Perspective => None,
+            Self::Fisheye { fov } => {
+                let px = (2.0 * x - 1.0) * aspect;
+                let py = 1.0 - 2.0 * y;
+                let r = (px * px + py * py).sqrt();
+                let corner = (aspect * aspect + 1.0).sqrt();
+                let theta = degrees_to_radians(fov / 2.0) * r / corner;
+                let (sin, cos) = theta.sin_cos();
+                if r == 0.0 {
+                    return Some(Vec3::new(0.0, 0.0, -1.0));
+                }
+                Some(Vec3::new(sin * px / r, sin * py / r, -cos))
+            }
+            Self::Equirectangular => {
+                let longitude = (x - 0.5) * 2.0 * PI;
+                let latitude = (0.5 - y) * PI;
+                let (sin_lat, cos_lat) = latitude.sin_cos();
+                let (sin_lon, cos_lon) = longitude.sin_cos();
+                Some(Vec3::new(cos_lat * sin_lon, sin_lat, -cos_lat * cos_lon))
+            }
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use crate::rtweekend::*;
+
+pub struct Quad {
+    pub q: Point3,
+    pub u: Vec3,
+    pub v: Vec3,
+    pub mat: Arc<dyn Material + Send + Sync>,
+    w: Vec3,
+    normal: Vec3,
+    d: f64,
+    area: f64,
+}
+
+impl Quad {
+    pub fn new(q: Point3, u: Vec3, v: Vec3, mat: Arc<dyn Material + Send + Sync>) -> Self {
+        let n = u.cross(v);
+        let normal = n.normalized();
+        Self {
+            q,
+            u,
+            v,
+            mat,
+            w: n / n.dot(n),
+            normal,
+            d: normal.dot(q),
+            area: n.length(),
+        }
+    }
+
+    fn is_interior(a: f64, b: f64) -> bool {
+        let unit_interval = Interval::new(0.0, 1.0);
+        unit_interval.contains(a) && unit_interval.contains(b)
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let denom = self.normal.dot(r.dir);
+
+        // No hit if the ray is parallel to the plane.
+        if denom.abs() < 1e-8 {
+            return None;
+        }
+
+        let t = (self.d - self.normal.dot(r.orig)) / denom;
+        if !ray_t.contains(t) {
+            return None;
+        }
+
+        // Planar coordinates of the hit point, relative to the quad's corner.
+        let intersection = r.at(t);
+        let planar_hitpt_vector = intersection - self.q;
+        let alpha = self.w.dot(planar_hitpt_vector.cross(self.v));
+        let beta = self.w.dot(self.u.cross(planar_hitpt_vector));
+        if !Self::is_interior(alpha, beta) {
+            return None;
+        }
+
+        let mut rec = HitRecord {
+            p: intersection,
+            t,
+            uv: Vec2::new(alpha, beta),
+            tangent: self.u,
+            uv_scale: self.u.cross(self.v).length().sqrt(),
+            mat: self.mat.clone(),
+            ..Default::default()
+        };
+        rec.set_face_normal(r, self.normal);
+
+        Some(rec)
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let Some(rec) = self.hit(&Ray::new(origin, direction), Interval::new(0.001, INFINITY))
+        else {
+            return 0.0;
+        };
+
+        let distance_squared = rec.t * rec.t * direction.length_squared();
+        let cosine = (direction.dot(rec.normal) / direction.length()).abs();
+
+        distance_squared / (cosine * self.area)
+    }
+
+    fn random(&self, origin: Point3, rng: &mut SmallRng) -> Vec3 {
+        let p = self.q + (random_f64(rng) * self.u) + (random_f64(rng) * self.v);
+        p - origin
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let diagonal1 = Aabb::from_points(self.q, self.q + self.u + self.v);
+        let diagonal2 = Aabb::from_points(self.q + self.u, self.q + self.v);
+        // Padded, since a quad in an axis plane would otherwise have a flat box.
+        diagonal1.union(&diagonal2).pad(1e-4)
+    }
+}
+
+/// Closed box with opposite vertices `a` and `b`, made of six quads.
+pub fn make_box(a: Point3, b: Point3, mat: Arc<dyn Material + Send + Sync>) -> HittableList {
+    let mut sides = HittableList::new();
+
+    let min = Point3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z));
+    let max = Point3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z));
+
+    let dx = Vec3::new(max.x - min.x, 0.0, 0.0);
+    let dy = Vec3::new(0.0, max.y - min.y, 0.0);
+    let dz = Vec3::new(0.0, 0.0, max.z - min.z);
+
+    sides.add(Arc::new(Quad::new(
+        Point3::new(min.x, min.y, max.z),
+        dx,
+        dy,
+        mat.clone(),
+    ))); // front
+    sides.add(Arc::new(Quad::new(
+        Point3::new(max.x, min.y, max.z),
+        -dz,
+        dy,
+        mat.clone(),
+    ))); // right
+    sides.add(Arc::new(Quad::new(
+        Point3::new(max.x, min.y, min.z),
+        -dx,
+        dy,
+        mat.clone(),
+    ))); // back
+    sides.add(Arc::new(Quad::new(
+        Point3::new(min.x, min.y, min.z),
+        dz,
+        dy,
+        mat.clone(),
+    ))); // left
+    sides.add(Arc::new(Quad::new(
+        Point3::new(min.x, max.y, max.z),
+        dx,
+        -dz,
+        mat.clone(),
+    ))); // top
+    sides.add(Arc::new(Quad::new(
+        Point3::new(min.x, min.y, min.z),
+        dx,
+        dz,
+        mat,
+    ))); // bottom
+
+    sides
+}
@@ -0,0 +1,185 @@
+use crate::rtweekend::*;
+use serde::{Deserialize, Serialize};
+use std::ops::Mul;
+
+/// Unit quaternion for orientations. Cameras look down their local -Z axis with +Y up,
+/// so `rotate` maps those local axes into the world.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Quat {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Default for Quat {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Quat {
+    pub const IDENTITY: Self = Self {
+        w: 1.0,
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+
+    pub fn from_axis_angle(axis: Vec3, degrees: f64) -> Self {
+        let axis = axis.normalized();
+        let (sin, cos) = (degrees_to_radians(degrees) / 2.0).sin_cos();
+        Self {
+            w: cos,
+            x: axis.x * sin,
+            y: axis.y * sin,
+            z: axis.z * sin,
+        }
+    }
+
+    /// Yaw about +Y, then pitch about the local +X, then roll about the local view
+    /// axis, all in degrees. Zero everywhere looks down -Z.
+    pub fn from_euler(yaw: f64, pitch: f64, roll: f64) -> Self {
+        Self::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), yaw)
+            * Self::from_axis_angle(Vec3::new(1.0, 0.0, 0.0), pitch)
+            * Self::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), roll)
+    }
+
+    /// Rotation taking the local axes onto the orthonormal right-handed basis (u, v, w).
+    pub fn from_basis(u: Vec3, v: Vec3, w: Vec3) -> Self {
+        let trace = u.x + v.y + w.z;
+        let q = if trace > 0.0 {
+            let s = 2.0 * (trace + 1.0).sqrt();
+            Self {
+                w: 0.25 * s,
+                x: (v.z - w.y) / s,
+                y: (w.x - u.z) / s,
+                z: (u.y - v.x) / s,
+            }
+        } else if u.x > v.y && u.x > w.z {
+            let s = 2.0 * (1.0 + u.x - v.y - w.z).sqrt();
+            Self {
+                w: (v.z - w.y) / s,
+                x: 0.25 * s,
+                y: (v.x + u.y) / s,
+                z: (w.x + u.z) / s,
+            }
+        } else if v.y > w.z {
+            let s = 2.0 * (1.0 + v.y - u.x - w.z).sqrt();
+            Self {
+                w: (w.x - u.z) / s,
+                x: (v.x + u.y) / s,
+                y: 0.25 * s,
+                z: (w.y + v.z) / s,
+            }
+        } else {
+            let s = 2.0 * (1.0 + w.z - u.x - v.y).sqrt();
+            Self {
+                w: (u.y - v.x) / s,
+                x: (w.x + u.z) / s,
+                y: (w.y + v.z) / s,
+                z: 0.25 * s,
+            }
+        };
+        q.normalized()
+    }
+
+    /// Orientation looking along `forward` with `up` as close to up as possible. When
+    /// the two are parallel (looking straight up or down) the world axis least aligned
+    /// with `forward` stands in for `up`, so the result never degenerates.
+    pub fn look_rotation(forward: Vec3, up: Vec3) -> Self {
+        let (u, v, w) = look_basis(forward, up);
+        Self::from_basis(u, v, w)
+    }
+
+    pub fn conjugate(self) -> Self {
+        Self {
+            w: self.w,
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+
+    pub fn normalized(self) -> Self {
+        let len = (self.w * self.w + self.x * self.x + self.y * self.y + self.z * self.z).sqrt();
+        if len == 0.0 {
+            return Self::IDENTITY;
+        }
+        Self {
+            w: self.w / len,
+            x: self.x / len,
+            y: self.y / len,
+            z: self.z / len,
+        }
+    }
+
+    pub fn rotate(self, v: Vec3) -> Vec3 {
+        let q = Vec3::new(self.x, self.y, self.z);
+        let t = 2.0 * q.cross(v);
+        v + self.w * t + q.cross(t)
+    }
+
+    /// Interpolates along the shorter arc between two orientations at constant speed.
+    pub fn slerp(self, other: Self, s: f64) -> Self {
+        let mut dot = self.w * other.w + self.x * other.x + self.y * other.y + self.z * other.z;
+        let other = if dot < 0.0 {
+            dot = -dot;
+            Self {
+                w: -other.w,
+                x: -other.x,
+                y: -other.y,
+                z: -other.z,
+            }
+        } else {
+            other
+        };
+        // Nearly identical orientations: the arc is a line, and sin(theta) would vanish.
+        let (a, b) = if dot > 0.9995 {
+            (1.0 - s, s)
+        } else {
+            let theta = dot.acos();
+            let sin = theta.sin();
+            (((1.0 - s) * theta).sin() / sin, (s * theta).sin() / sin)
+        };
+        Self {
+            w: a * self.w + b * other.w,
+            x: a * self.x + b * other.x,
+            y: a * self.y + b * other.y,
+            z: a * self.z + b * other.z,
+        }
+        .normalized()
+    }
+}
+
+impl Mul for Quat {
+    type Output = Self;
+
+    fn mul(self, o: Self) -> Self {
+        Self {
+            w: self.w * o.w - self.x * o.x - self.y * o.y - self.z * o.z,
+            x: self.w * o.x + self.x * o.w + self.y * o.z - self.z * o.y,
+            y: self.w * o.y - self.x * o.z + self.y * o.w + self.z * o.x,
+            z: self.w * o.z + self.x * o.y - self.y * o.x + self.z * o.w,
+        }
+    }
+}
+
+/// Camera basis (right, up, backward) for looking along `forward`, with the same pole
+/// fallback as `Quat::look_rotation`.
+pub fn look_basis(forward: Vec3, up: Vec3) -> (Vec3, Vec3, Vec3) {
+    let w = -forward.normalized();
+    let mut u = up.cross(w);
+    if u.length_squared() < 1e-12 {
+        let axis = if w.x.abs() < w.y.abs().min(w.z.abs()) {
+            Vec3::new(1.0, 0.0, 0.0)
+        } else if w.y.abs() < w.z.abs() {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(0.0, 0.0, 1.0)
+        };
+        u = axis.cross(w);
+    }
+    let u = u.normalized();
+    (u, w.cross(u), w)
+}
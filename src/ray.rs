@@ -1,16 +1,36 @@
+use crate::medium::MediumStack;
 use crate::rtweekend::*;
 
 #[derive(Debug, Copy, Clone, PartialEq, Default)]
 pub struct Ray {
     pub orig: Point3,
     pub dir: Vec3,
+    /// Moment within the frame this ray samples, used for motion blur.
+    pub tm: f64,
+    /// Dielectric volumes the ray is inside. `None` means the same as the ray it was
+    /// scattered from.
+    pub media: Option<MediumStack>,
+    /// Wavelength in nm the ray carries in spectral mode; `None` when rendering RGB.
+    pub wavelength: Option<f64>,
+    /// How fast the width of the ray's footprint grows per unit of distance along it,
+    /// treating the ray as a thin cone. Camera rays cover about a pixel, which lets
+    /// textures pick a matching level of detail; 0 means a point sample.
+    pub cone: f64,
 }
 
 impl Ray {
     pub fn new(origin: Point3, direction: Vec3) -> Self {
+        Self::with_time(origin, direction, 0.0)
+    }
+
+    pub fn with_time(origin: Point3, direction: Vec3, time: f64) -> Self {
         Self {
             orig: origin,
             dir: direction,
+            tm: time,
+            media: None,
+            wavelength: None,
+            cone: 0.0,
         }
     }
 
@@ -18,3 +38,31 @@ impl Ray {
         self.orig + t * self.dir
     }
 }
+
+/// Start for a ray leaving a surface at `p`, with normal `n`, in direction `dir`: moved
+/// off the surface onto the side `dir` goes, far enough that the ray can't hit the
+/// surface it starts on. That is `p_error`, how far rounding may have put `p` from the
+/// true surface, plus a fixed number of ulps of every coordinate (Wächter and Binder's
+/// offset, in "Ray Tracing Gems"), so no minimum hit distance is needed.
+pub fn offset_ray_origin(p: Point3, n: Vec3, p_error: f64, dir: Vec3) -> Point3 {
+    // Ulps to move every coordinate by, scaled by the normal.
+    const INT_SCALE: f64 = 1024.0;
+    // Near 0 ulps get too small, so coordinates below ORIGIN move by a fixed distance.
+    const ORIGIN: f64 = 1.0 / 32.0;
+    const FLOAT_SCALE: f64 = 1e-13;
+
+    let n = if n.dot(dir) < 0.0 { -n } else { n };
+    let moved = p + p_error * n;
+    let mut out = moved;
+    for axis in 0..3 {
+        let (x, offset) = (moved[axis], n[axis]);
+        out[axis] = if x.abs() < ORIGIN {
+            x + FLOAT_SCALE * offset
+        } else {
+            let ulps = (INT_SCALE * offset) as i64;
+            let ulps = if x < 0.0 { -ulps } else { ulps };
+            f64::from_bits((x.to_bits() as i64 + ulps) as u64)
+        };
+    }
+    out
+}
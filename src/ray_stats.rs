@@ -0,0 +1,130 @@
+#[cfg(feature = "stats")]
+use std::sync::atomic::{AtomicU64, Ordering};
+#[cfg(feature = "stats")]
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Ray and intersection counters, for tuning the acceleration structures. They only
+/// count when the crate is built with the `stats` feature; without it `count` compiles
+/// to nothing and every snapshot is zero.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Counter {
+    /// Rays from the camera.
+    PrimaryRays,
+    /// Visibility rays towards explicit lights and, for light tracing, the camera.
+    ShadowRays,
+    /// Nodes of acceleration structures entered by a ray.
+    NodeVisits,
+    /// Ray-triangle intersection tests.
+    TriangleTests,
+}
+
+#[cfg(feature = "stats")]
+const COUNTERS: usize = 4;
+
+// Every thread counts into its own block, so counting never contends; snapshots add
+// up the blocks of all the threads that have counted anything.
+#[cfg(feature = "stats")]
+type Block = Arc<[AtomicU64; COUNTERS]>;
+
+#[cfg(feature = "stats")]
+static BLOCKS: Mutex<Vec<Block>> = Mutex::new(Vec::new());
+
+#[cfg(feature = "stats")]
+thread_local! {
+    static LOCAL: Block = {
+        let block: Block = Arc::new(Default::default());
+        BLOCKS.lock().unwrap().push(block.clone());
+        block
+    };
+}
+
+/// Adds one to `counter`.
+#[inline(always)]
+pub fn count(counter: Counter) {
+    #[cfg(feature = "stats")]
+    LOCAL.with(|block| block[counter as usize].fetch_add(1, Ordering::Relaxed));
+    #[cfg(not(feature = "stats"))]
+    let _ = counter;
+}
+
+/// Whether the counters are compiled in.
+pub const fn enabled() -> bool {
+    cfg!(feature = "stats")
+}
+
+/// Totals of the counters, over all threads.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RayStats {
+    pub primary_rays: u64,
+    pub shadow_rays: u64,
+    pub node_visits: u64,
+    pub triangle_tests: u64,
+}
+
+impl RayStats {
+    /// Totals counted since the program started.
+    pub fn snapshot() -> Self {
+        #[cfg(feature = "stats")]
+        {
+            let mut totals = [0u64; COUNTERS];
+            for block in BLOCKS.lock().unwrap().iter() {
+                for (total, cell) in totals.iter_mut().zip(block.iter()) {
+                    *total += cell.load(Ordering::Relaxed);
+                }
+            }
+            let [primary_rays, shadow_rays, node_visits, triangle_tests] = totals;
+            Self {
+                primary_rays,
+                shadow_rays,
+                node_visits,
+                triangle_tests,
+            }
+        }
+        #[cfg(not(feature = "stats"))]
+        Self::default()
+    }
+
+    /// What was counted between the `earlier` snapshot and this one.
+    pub fn since(&self, earlier: &Self) -> Self {
+        Self {
+            primary_rays: self.primary_rays - earlier.primary_rays,
+            shadow_rays: self.shadow_rays - earlier.shadow_rays,
+            node_visits: self.node_visits - earlier.node_visits,
+            triangle_tests: self.triangle_tests - earlier.triangle_tests,
+        }
+    }
+
+    /// Logs the totals under the `render` target, with rates over `elapsed` and averages
+    /// per primary ray.
+    pub fn log_summary(&self, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64().max(1e-9);
+        let per_primary = |n: u64| n as f64 / self.primary_rays.max(1) as f64;
+        log::info!(target: "render", "Ray statistics ({:.2}s):", seconds);
+        log::info!(
+            target: "render",
+            "  primary rays:   {:>14} ({:.2} M/s)",
+            self.primary_rays,
+            self.primary_rays as f64 / seconds / 1e6
+        );
+        log::info!(
+            target: "render",
+            "  shadow rays:    {:>14} ({:.2} M/s, {:.2} per primary)",
+            self.shadow_rays,
+            self.shadow_rays as f64 / seconds / 1e6,
+            per_primary(self.shadow_rays)
+        );
+        log::info!(
+            target: "render",
+            "  node visits:    {:>14} ({:.2} per primary)",
+            self.node_visits,
+            per_primary(self.node_visits)
+        );
+        log::info!(
+            target: "render",
+            "  triangle tests: {:>14} ({:.2} per primary)",
+            self.triangle_tests,
+            per_primary(self.triangle_tests)
+        );
+    }
+}
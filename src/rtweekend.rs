@@ -1,15 +1,89 @@
-pub use crate::camera::Camera;
-pub use crate::color::{Color, write_color};
+pub use crate::aabb::Aabb;
+pub use crate::adaptive::SampleMap;
+pub use crate::animation::{Animated, Animation, CameraAnimation, Easing, Lerp, Track};
+pub use crate::bvh::{Bvh, BvhBuild, BvhInstance, TopLevelBvh};
+pub use crate::camera::{Camera, CameraBuilder};
+pub use crate::color::{
+    Color, luminance, to_rgba8, write_color, write_pam, write_ppm, write_ppm_binary,
+};
+pub use crate::cone::Cone;
+pub use crate::csg::{Csg, CsgOp};
+pub use crate::culling::{CullOptions, CullReport};
+pub use crate::cylinder::Cylinder;
+pub use crate::depth_stats::{DepthStats, PathStats};
+pub use crate::disk::Disk;
+pub use crate::distributed::{RemoteDevice, run_worker};
+pub use crate::estimate::RenderEstimate;
+pub use crate::film::{Film, FilmTile, Filter};
+pub use crate::focus::{FocusMap, FocusRegion};
+pub use crate::grid::UniformGrid;
 pub use crate::hittable::{HitRecord, Hittable};
 pub use crate::hittable_list::HittableList;
+pub use crate::id_pass::{CoverageImage, IdImage, IdPass};
+pub use crate::instance::{Instance, MaterialOverrides, instance_random};
+pub use crate::integrator::{
+    AmbientOcclusion, DepthView, Integrator, IntegratorKind, NormalView, PathTracer,
+};
 pub use crate::interval::Interval;
-pub use crate::material::{Dielectric, Lambertian, Material, Metal, NullMaterial};
-pub use crate::ray::Ray;
+pub use crate::lens::{Exposure, Lens, SensorFormat};
+pub use crate::light::{DirectionalLight, Light, LightSample, PointLight};
+pub use crate::light_tracer::LightTracer;
+pub use crate::loader::{LoadJob, LoadReport, load_all};
+pub use crate::material::{
+    AnisotropicMetal, Coated, Cutout, Dielectric, DiffuseLight, Lambertian, Material, Metal,
+    NormalMapped, NullMaterial, OrenNayar, Principled, RoughDielectric, RoughMetal, SurfaceDetail,
+    TexturedPrincipled,
+};
+pub use crate::material_library::MaterialLibrary;
+pub use crate::medium::{Medium, MediumStack};
+pub use crate::mesh::{Mesh, catmull_clark};
+pub use crate::microfacet::{Conductor, Fresnel, Ggx};
+pub use crate::motion::MotionPath;
+pub use crate::noise::{Perlin, Worley};
+pub use crate::nonfinite::{NONFINITE_COLOR, NonFiniteLog};
+pub use crate::ocean::{GerstnerWave, Ocean};
+pub use crate::onb::Onb;
+pub use crate::pixel_debug::{Bounce, PathEvent, PixelTrace, SampleTrace, ScatterLog};
+pub use crate::ply::{load_ply, parse_ply};
+pub use crate::png::write_png;
+pub use crate::projection::Projection;
+pub use crate::quad::{Quad, make_box};
+pub use crate::quat::Quat;
+pub use crate::ray::{Ray, offset_ray_origin};
+pub use crate::ray_stats::{self, Counter, RayStats};
+pub use crate::scatter::{Scatter, ScatterPoint};
+pub use crate::scene::{Accelerator, Scene};
+pub use crate::scene_graph::{Named, SceneNode, Transform, name_id};
+pub use crate::scheduler::{CpuDevice, RenderDevice, RenderProgress, Tile, TileScheduler};
+pub use crate::server::{RenderServer, ServerLimits};
+pub use crate::sided::{Sided, Sides};
+pub use crate::sky::{Background, GradientBackground, Moon, NightSky, PreethamSky};
+pub use crate::spectrum::Dispersion;
 pub use crate::sphere::Sphere;
+pub use crate::sppm::Sppm;
+pub use crate::stereo::{StereoLayout, StereoRig};
+pub use crate::stl::{load_stl, parse_stl};
+pub use crate::svo::{SparseVoxelOctree, VoxelGrid};
+pub use crate::texture::{
+    CellTexture, CheckerTexture, ImageTexture, NoisePattern, NoiseTexture, PerInstance, SolidColor,
+    Texture, TextureFilter, Triplanar,
+};
+pub use crate::texture_registry::{LazyImage, TextureRegistry};
+pub use crate::thin_film::ThinFilm;
+pub use crate::transform::{Mat4, Transformed};
+pub use crate::triangle::Triangle;
+pub use crate::units::Unit;
+pub use crate::vec2::Vec2;
 pub use crate::vec3::{Point3, Vec3};
+pub use crate::video::VideoEncoder;
+pub use crate::vox::{VoxFile, VoxMaterial, load_vox, parse_vox};
+pub use crate::watch::{FileWatcher, LivePreview};
+pub use crate::wavefront::{Backend, WavefrontDevice};
 
 use rand::prelude::*;
+pub use rand::rngs::SmallRng;
 pub use std::sync::Arc;
+use std::time::Duration;
 
 pub const INFINITY: f64 = f64::INFINITY;
 pub const PI: f64 = std::f64::consts::PI;
@@ -19,13 +93,64 @@ pub fn degrees_to_radians(degrees: f64) -> f64 {
     degrees * PI / 180.0
 }
 
+/// A seed from the OS, for renders and scenes that weren't given one.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn random_seed() -> u64 {
+    rand::rng().random()
+}
+
+/// A seed for renders and scenes that weren't given one. There's no OS randomness in a
+/// browser without JS glue, so it's the next of a sequence.
+#[cfg(target_arch = "wasm32")]
+pub fn random_seed() -> u64 {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    static NEXT_SEED: AtomicU64 = AtomicU64::new(0x5eed);
+    NEXT_SEED.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Xoshiro256++ generator for stream `stream` of a render seeded with `seed`. The same
+/// seed and stream always draw the same numbers, whichever thread asks, and different
+/// streams draw unrelated ones.
+pub fn stream_rng(seed: u64, stream: u64) -> SmallRng {
+    SmallRng::seed_from_u64(seed ^ stream.wrapping_mul(0x9e37_79b9_7f4a_7c15))
+}
+
+/// `stream_rng` for the samples of film pixel (`i`, `j`), so a pixel draws the same
+/// numbers whichever tile or device renders it.
+pub fn pixel_rng(seed: u64, i: u32, j: u32) -> SmallRng {
+    stream_rng(seed, ((j as u64) << 32) | i as u64)
+}
+
 #[inline]
-pub fn random_f64() -> f64 {
-    let mut rng = rand::rng();
+pub fn random_f64(rng: &mut SmallRng) -> f64 {
     rng.random::<f64>()
 }
 
 #[inline]
-pub fn random_f64_range(min: f64, max: f64) -> f64 {
-    min + (max - min) * random_f64()
+pub fn random_f64_range(rng: &mut SmallRng, min: f64, max: f64) -> f64 {
+    min + (max - min) * random_f64(rng)
+}
+
+/// Time since `start`, like `Instant`, but reading zero on wasm32 in a browser, where
+/// `Instant::now` panics. Used on the render path so it runs there.
+#[derive(Debug, Clone, Copy)]
+pub struct Stopwatch {
+    #[cfg(not(target_arch = "wasm32"))]
+    start: std::time::Instant,
+}
+
+impl Stopwatch {
+    pub fn start() -> Self {
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            start: std::time::Instant::now(),
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        #[cfg(not(target_arch = "wasm32"))]
+        return self.start.elapsed();
+        #[cfg(target_arch = "wasm32")]
+        return Duration::ZERO;
+    }
 }
@@ -0,0 +1,150 @@
+use crate::rtweekend::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// A placement produced by `Scatter`: where an instance goes and how big it should be.
+#[derive(Debug, Clone, Copy)]
+pub struct ScatterPoint {
+    pub position: Point3,
+    /// Geometric normal of the mesh face the point lies on.
+    pub normal: Vec3,
+    pub scale: f64,
+    /// Map coordinates the density and scale maps were sampled at.
+    pub uv: Vec2,
+}
+
+/// Distributes instances over a mesh surface, uniformly by area and then thinned by an
+/// optional density map, for placing grass, pebbles and the like from painted maps.
+///
+/// Meshes don't carry texture coordinates, so maps are sampled top-down: `u` runs
+/// along x and `v` along z across the mesh's bounding box.
+pub struct Scatter {
+    pub count: usize,
+    pub seed: u64,
+    /// Acceptance probability by map luminance (white keeps every candidate).
+    pub density: Option<Arc<dyn Texture>>,
+    /// Per-point size multiplier by map luminance.
+    pub scale_map: Option<Arc<dyn Texture>>,
+    pub scale: f64,
+}
+
+impl Scatter {
+    // Candidates tried per requested point before giving up on a sparse density map.
+    const MAX_ATTEMPTS_PER_POINT: usize = 64;
+
+    pub fn new(count: usize) -> Self {
+        Self {
+            count,
+            seed: 0,
+            density: None,
+            scale_map: None,
+            scale: 1.0,
+        }
+    }
+
+    /// Picks up to `count` points on `mesh`. Fewer are returned if the density map is
+    /// mostly black.
+    pub fn points(&self, mesh: &Mesh) -> Vec<ScatterPoint> {
+        let triangles: Vec<[Point3; 3]> = mesh
+            .faces
+            .iter()
+            .flat_map(|face| {
+                (1..face.len() - 1).map(move |k| {
+                    [
+                        mesh.positions[face[0]],
+                        mesh.positions[face[k]],
+                        mesh.positions[face[k + 1]],
+                    ]
+                })
+            })
+            .collect();
+
+        // Running total of triangle areas for area-weighted picking.
+        let mut cdf = Vec::with_capacity(triangles.len());
+        let mut total_area = 0.0;
+        for [a, b, c] in &triangles {
+            total_area += 0.5 * (*b - *a).cross(*c - *a).length();
+            cdf.push(total_area);
+        }
+        if total_area <= 0.0 {
+            return Vec::new();
+        }
+
+        let (min, max) = mesh.positions.iter().fold(
+            (
+                Point3::new(INFINITY, INFINITY, INFINITY),
+                -Point3::new(INFINITY, INFINITY, INFINITY),
+            ),
+            |(lo, hi), p| {
+                (
+                    Point3::new(lo.x.min(p.x), lo.y.min(p.y), lo.z.min(p.z)),
+                    Point3::new(hi.x.max(p.x), hi.y.max(p.y), hi.z.max(p.z)),
+                )
+            },
+        );
+        let extent = max - min;
+        let map_uv = |p: Point3| {
+            let u = if extent.x > 0.0 {
+                (p.x - min.x) / extent.x
+            } else {
+                0.5
+            };
+            let v = if extent.z > 0.0 {
+                (p.z - min.z) / extent.z
+            } else {
+                0.5
+            };
+            Vec2::new(u, v)
+        };
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut points = Vec::with_capacity(self.count);
+        for _ in 0..self.count * Self::MAX_ATTEMPTS_PER_POINT {
+            if points.len() == self.count {
+                break;
+            }
+            let pick = rng.random::<f64>() * total_area;
+            let index = cdf.partition_point(|&c| c < pick).min(triangles.len() - 1);
+            let [a, b, c] = triangles[index];
+
+            // Uniform point on the triangle by folding the unit square.
+            let (mut s, mut t) = (rng.random::<f64>(), rng.random::<f64>());
+            if s + t > 1.0 {
+                (s, t) = (1.0 - s, 1.0 - t);
+            }
+            let position = a + s * (b - a) + t * (c - a);
+            let uv = map_uv(position);
+
+            if let Some(density) = &self.density
+                && rng.random::<f64>() >= luminance(density.value(uv, &position)).clamp(0.0, 1.0)
+            {
+                continue;
+            }
+            let scale = match &self.scale_map {
+                Some(map) => self.scale * luminance(map.value(uv, &position)).clamp(0.0, 1.0),
+                None => self.scale,
+            };
+            points.push(ScatterPoint {
+                position,
+                normal: (b - a).cross(c - a).normalized(),
+                scale,
+                uv,
+            });
+        }
+        points
+    }
+
+    /// Builds one object per point with `make` and wraps each in an `Instance` with a
+    /// distinct ID (starting at 1), so per-instance textures and overrides vary them.
+    pub fn instances(
+        &self,
+        mesh: &Mesh,
+        mut make: impl FnMut(&ScatterPoint) -> Arc<dyn Hittable>,
+    ) -> HittableList {
+        let mut list = HittableList::new();
+        for (i, point) in self.points(mesh).iter().enumerate() {
+            list.add(Arc::new(Instance::new(make(point), i as u32 + 1)));
+        }
+        list
+    }
+}
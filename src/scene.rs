@@ -0,0 +1,194 @@
+use crate::rtweekend::*;
+
+/// Structure that speeds up finding what a ray hits; see `Scene::accelerate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Accelerator {
+    /// Test every object: fine for a handful of them.
+    List,
+    /// Bounding volume hierarchy, the all-rounder.
+    #[default]
+    Bvh,
+    /// Uniform grid, often the fastest for evenly spread objects of similar size.
+    Grid,
+}
+
+impl Accelerator {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "list" => Some(Self::List),
+            "bvh" => Some(Self::Bvh),
+            "grid" => Some(Self::Grid),
+            _ => None,
+        }
+    }
+
+    // The objects of `list` under this accelerator, as a list of one object.
+    fn build(self, list: HittableList) -> HittableList {
+        match self {
+            Self::List => list,
+            _ if list.objects.len() <= 1 => list,
+            Self::Bvh => HittableList::new_with(Arc::new(Bvh::from_list(list))),
+            Self::Grid => HittableList::new_with(Arc::new(UniformGrid::from_list(list))),
+        }
+    }
+}
+
+pub struct Scene {
+    pub world: HittableList,
+    pub lights: Vec<Arc<dyn Light>>,
+    /// Emissive geometry that is importance-sampled directly (also present in `world`).
+    pub emitters: HittableList,
+    /// Radiance for rays that leave the scene.
+    pub background: Arc<dyn Background>,
+    /// What camera rays see where they miss everything, in place of `background`. The
+    /// scene is still lit by `background`, e.g. a sky lighting a model shown against a
+    /// plain gray backdrop.
+    pub visible_background: Option<Arc<dyn Background>>,
+    /// Unit the scene is authored in; drives epsilons, light falloff and asset import scale.
+    pub units: Unit,
+    /// Objects culled from the camera's view that still cast shadows and show up in
+    /// reflections and indirect light. See `cull_for_camera`.
+    pub camera_culled: HittableList,
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Self::new(HittableList::new())
+    }
+}
+
+impl Scene {
+    pub fn new(world: HittableList) -> Self {
+        Self {
+            world,
+            lights: Vec::new(),
+            emitters: HittableList::new(),
+            background: Arc::new(GradientBackground::default()),
+            visible_background: None,
+            units: Unit::default(),
+            camera_culled: HittableList::new(),
+        }
+    }
+
+    pub fn add_light(&mut self, light: Arc<dyn Light>) {
+        self.lights.push(light);
+    }
+
+    /// Scale to apply to geometry of an asset authored in `asset_units`.
+    pub fn import_scale(&self, asset_units: Unit) -> f64 {
+        asset_units.scale_to(self.units)
+    }
+
+    /// About a millimeter in scene units: the gap left between a shadow ray's end and
+    /// the light, and the step past a cut-out hit.
+    pub fn epsilon(&self) -> f64 {
+        self.units.epsilon()
+    }
+
+    /// Puts the world, and the objects culled from the camera, under `accelerator`.
+    /// Do this last: afterwards `world` holds the single accelerated object, so culling
+    /// has nothing left to sort.
+    pub fn accelerate(&mut self, accelerator: Accelerator) {
+        self.world = accelerator.build(std::mem::take(&mut self.world));
+        self.camera_culled = accelerator.build(std::mem::take(&mut self.camera_culled));
+    }
+
+    /// Adds an emissive object to the world and to the set of sampled emitters.
+    pub fn add_area_light(&mut self, object: Arc<dyn Hittable>) {
+        self.world.add(object.clone());
+        self.emitters.add(object);
+    }
+}
+
+impl Scene {
+    /// Box around everything in the scene, including objects culled from the camera.
+    pub fn bounds(&self) -> Aabb {
+        self.bounding_box()
+    }
+
+    /// Box around the object called `name` (see `Named` and `SceneNode`).
+    pub fn find_bounds(&self, name: &str) -> Option<Aabb> {
+        self.world
+            .named_bounds(name)
+            .or_else(|| self.camera_culled.named_bounds(name))
+    }
+
+    /// Background seen by a ray that escaped, `primary` being true for camera rays.
+    pub fn background_color(&self, dir: Vec3, primary: bool) -> Color {
+        match &self.visible_background {
+            Some(visible) if primary => visible.color(dir),
+            _ => self.background.color(dir),
+        }
+    }
+
+    /// Closest hit for a camera ray, which skips objects in `camera_culled`.
+    pub fn hit_primary(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        ray_stats::count(Counter::PrimaryRays);
+        self.closest_opaque(r, ray_t, |r, ray_t| self.world.hit(r, ray_t))
+    }
+
+    // Closest hit that isn't cut out by its material's opacity, carrying on past the
+    // ones that are.
+    fn closest_opaque(
+        &self,
+        r: &Ray,
+        mut ray_t: Interval,
+        hit: impl Fn(&Ray, Interval) -> Option<HitRecord>,
+    ) -> Option<HitRecord> {
+        loop {
+            let mut rec = hit(r, ray_t)?;
+            if r.cone > 0.0 {
+                // The cone's cross-section, stretched along the surface where the ray
+                // meets it at a slant. Textures filter it as a square of the same area,
+                // which blurs less at grazing angles than going by its long axis.
+                let distance = rec.t * r.dir.length();
+                let cos = r.dir.normalized().dot(rec.geometric_normal).abs();
+                rec.footprint = r.cone * distance / cos.max(1e-6).sqrt();
+            }
+            let opacity = rec.mat.opacity(&rec);
+            if opacity >= 1.0 || cutout_random(r, rec.t) < opacity {
+                return Some(rec);
+            }
+            ray_t.min = rec.t + self.epsilon() / r.dir.length();
+        }
+    }
+
+    // Closest hit among the world and the objects culled from the camera.
+    fn hit_all(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let rec = self.world.hit(r, ray_t);
+        if self.camera_culled.objects.is_empty() {
+            return rec;
+        }
+        let closest = rec.as_ref().map_or(ray_t.max, |rec| rec.t);
+        self.camera_culled
+            .hit(r, Interval::new(ray_t.min, closest))
+            .or(rec)
+    }
+}
+
+impl Hittable for Scene {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        self.closest_opaque(r, ray_t, |r, ray_t| self.hit_all(r, ray_t))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.world
+            .bounding_box()
+            .union(&self.camera_culled.bounding_box())
+    }
+}
+
+// Number in [0, 1) for the stochastic cut-out test at distance `t` along `r`. Hashed
+// from the ray rather than drawn, as hits take no generator; the ray itself came from
+// the sample's generator, so seeded renders still repeat.
+fn cutout_random(r: &Ray, t: f64) -> f64 {
+    let bits = [r.orig.x, r.orig.y, r.orig.z, r.dir.x, r.dir.y, r.dir.z, t];
+    let mut z = 0u64;
+    for b in bits {
+        z = (z ^ b.to_bits()).wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+    }
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
@@ -0,0 +1,469 @@
+use crate::rtweekend::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Serializable description of a material.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum MaterialDesc {
+    Lambertian {
+        albedo: Color,
+    },
+    OrenNayar {
+        albedo: Color,
+        sigma: f64,
+    },
+    Metal {
+        albedo: Color,
+        fuzz: f64,
+    },
+    RoughMetal {
+        albedo: Color,
+        roughness: f64,
+    },
+    AnisotropicMetal {
+        albedo: Color,
+        roughness_u: f64,
+        roughness_v: f64,
+    },
+    Dielectric {
+        ior: f64,
+    },
+    RoughDielectric {
+        ior: f64,
+        roughness: f64,
+    },
+    DiffuseLight {
+        emit: Color,
+        /// Emit from the back as well as the front.
+        #[serde(default)]
+        two_sided: bool,
+    },
+    Principled(Principled),
+}
+
+impl MaterialDesc {
+    pub fn build(&self) -> Arc<dyn Material + Send + Sync> {
+        match self {
+            Self::Lambertian { albedo } => Arc::new(Lambertian::new(*albedo)),
+            Self::OrenNayar { albedo, sigma } => Arc::new(OrenNayar::new(*albedo, *sigma)),
+            Self::Metal { albedo, fuzz } => Arc::new(Metal::new(*albedo, *fuzz)),
+            Self::RoughMetal { albedo, roughness } => {
+                Arc::new(RoughMetal::new(*albedo, *roughness))
+            }
+            Self::AnisotropicMetal {
+                albedo,
+                roughness_u,
+                roughness_v,
+            } => Arc::new(AnisotropicMetal::new(*albedo, *roughness_u, *roughness_v)),
+            Self::Dielectric { ior } => Arc::new(Dielectric::new(*ior)),
+            Self::RoughDielectric { ior, roughness } => {
+                Arc::new(RoughDielectric::new(*ior, *roughness))
+            }
+            Self::DiffuseLight { emit, two_sided } => {
+                if *two_sided {
+                    Arc::new(DiffuseLight::two_sided(*emit))
+                } else {
+                    Arc::new(DiffuseLight::new(*emit))
+                }
+            }
+            Self::Principled(params) => Arc::new(*params),
+        }
+    }
+}
+
+/// Serializable description of a piece of geometry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ShapeDesc {
+    Sphere {
+        center: Point3,
+        radius: f64,
+        /// Distance travelled over the shutter interval, for motion blur.
+        #[serde(default)]
+        motion: Vec3,
+    },
+    Quad {
+        q: Point3,
+        u: Vec3,
+        v: Vec3,
+    },
+    Triangle {
+        a: Point3,
+        b: Point3,
+        c: Point3,
+    },
+    Disk {
+        center: Point3,
+        normal: Vec3,
+        radius: f64,
+    },
+    Cylinder {
+        base: Point3,
+        axis: Vec3,
+        radius: f64,
+        height: f64,
+        capped: bool,
+    },
+    Mesh(Mesh),
+}
+
+impl ShapeDesc {
+    /// Middle of the shape, where the camera focuses when asked to focus on it.
+    pub fn center(&self) -> Point3 {
+        match self {
+            Self::Sphere { center, .. } => *center,
+            Self::Quad { q, u, v } => *q + 0.5 * (*u + *v),
+            Self::Triangle { a, b, c } => (*a + *b + *c) / 3.0,
+            Self::Disk { center, .. } => *center,
+            Self::Cylinder {
+                base, axis, height, ..
+            } => *base + axis.normalized() * (height / 2.0),
+            Self::Mesh(mesh) => {
+                let bounds = mesh
+                    .positions
+                    .iter()
+                    .fold(Aabb::EMPTY, |acc, &p| acc.union(&Aabb::from_points(p, p)));
+                bounds.center()
+            }
+        }
+    }
+}
+
+/// How an object picks its material: a number is an index into `SceneFile::materials`,
+/// a string a name in `SceneFile::library`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MaterialRef {
+    Index(usize),
+    Name(String),
+}
+
+impl From<usize> for MaterialRef {
+    fn from(index: usize) -> Self {
+        Self::Index(index)
+    }
+}
+
+impl From<&str> for MaterialRef {
+    fn from(name: &str) -> Self {
+        Self::Name(name.to_string())
+    }
+}
+
+impl fmt::Display for MaterialRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Index(index) => write!(f, "material index {}", index),
+            Self::Name(name) => write!(f, "material `{}`", name),
+        }
+    }
+}
+
+/// One object: a shape and the material it is made of.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectDesc {
+    /// Lets cameras and tools refer to the object, e.g. `Camera::focus_on`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub shape: ShapeDesc,
+    pub material: MaterialRef,
+    /// Also importance-sample it as an area light.
+    #[serde(default)]
+    pub emitter: bool,
+    /// Sides rays can hit; the other side is see-through.
+    #[serde(default)]
+    pub sides: Sides,
+}
+
+/// Serializable description of the background.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum BackgroundDesc {
+    Gradient {
+        bottom: Color,
+        top: Color,
+    },
+    Sky {
+        elevation: f64,
+        azimuth: f64,
+        turbidity: f64,
+    },
+}
+
+impl Default for BackgroundDesc {
+    fn default() -> Self {
+        let gradient = GradientBackground::default();
+        Self::Gradient {
+            bottom: gradient.bottom,
+            top: gradient.top,
+        }
+    }
+}
+
+/// Name the default camera goes by next to `SceneFile::cameras`.
+pub const MAIN_CAMERA: &str = "main";
+
+/// A scene and its camera as plain data, so a procedurally generated scene can be saved
+/// as JSON and rendered again identically later. Materials are listed once and shared
+/// by index or by name, like `Arc`s in a built scene.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SceneFile {
+    /// The default view, called `MAIN_CAMERA`.
+    pub camera: Camera,
+    /// Further named views of the same scene, rendered from the one build.
+    #[serde(default)]
+    pub cameras: BTreeMap<String, Camera>,
+    #[serde(default)]
+    pub materials: Vec<MaterialDesc>,
+    /// Named materials, for objects to refer to by name instead of index.
+    #[serde(default, skip_serializing_if = "MaterialLibrary::is_empty")]
+    pub library: MaterialLibrary,
+    pub objects: Vec<ObjectDesc>,
+    #[serde(default)]
+    pub background: BackgroundDesc,
+    #[serde(default)]
+    pub units: Unit,
+}
+
+impl SceneFile {
+    pub fn new(camera: Camera) -> Self {
+        Self {
+            camera,
+            cameras: BTreeMap::new(),
+            materials: Vec::new(),
+            library: MaterialLibrary::new(),
+            objects: Vec::new(),
+            background: BackgroundDesc::default(),
+            units: Unit::default(),
+        }
+    }
+
+    /// Adds a material and returns the index objects refer to it by.
+    pub fn add_material(&mut self, material: MaterialDesc) -> usize {
+        self.materials.push(material);
+        self.materials.len() - 1
+    }
+
+    /// Adds a material to the library under `name`, replacing any material called that,
+    /// and returns the reference objects use for it.
+    pub fn add_named_material(&mut self, name: &str, material: MaterialDesc) -> MaterialRef {
+        self.library.insert(name, material);
+        MaterialRef::from(name)
+    }
+
+    /// The material `material` refers to, if there is one.
+    pub fn material(&self, material: &MaterialRef) -> Option<&MaterialDesc> {
+        match material {
+            MaterialRef::Index(index) => self.materials.get(*index),
+            MaterialRef::Name(name) => self.library.get(name),
+        }
+    }
+
+    /// Adds an object of `material`, which must already be in the file.
+    pub fn add(&mut self, shape: ShapeDesc, material: impl Into<MaterialRef>) -> io::Result<()> {
+        let material = material.into();
+        if self.material(&material).is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("scene: unknown {}", material),
+            ));
+        }
+        self.objects.push(ObjectDesc {
+            name: None,
+            shape,
+            material,
+            emitter: false,
+            sides: Sides::Both,
+        });
+        Ok(())
+    }
+
+    /// Adds an object other parts of the file can refer to by `name`.
+    pub fn add_named(
+        &mut self,
+        name: &str,
+        shape: ShapeDesc,
+        material: impl Into<MaterialRef>,
+    ) -> io::Result<()> {
+        self.add(shape, material)?;
+        if let Some(object) = self.objects.last_mut() {
+            object.name = Some(name.to_string());
+        }
+        Ok(())
+    }
+
+    pub fn object(&self, name: &str) -> Option<&ObjectDesc> {
+        self.objects
+            .iter()
+            .find(|o| o.name.as_deref() == Some(name))
+    }
+
+    pub fn add_camera(&mut self, name: &str, camera: Camera) {
+        self.cameras.insert(name.to_string(), camera);
+    }
+
+    /// Names of every camera, the main one first.
+    pub fn camera_names(&self) -> Vec<&str> {
+        std::iter::once(MAIN_CAMERA)
+            .chain(self.cameras.keys().map(String::as_str))
+            .collect()
+    }
+
+    /// A ready-to-use copy of the camera called `name`, focused on its `focus_on`
+    /// object if it has one.
+    pub fn camera(&self, name: &str) -> Option<Camera> {
+        let mut camera = if name == MAIN_CAMERA {
+            self.camera.clone()
+        } else {
+            self.cameras.get(name)?.clone()
+        };
+        camera.initialize();
+        if let Some(object) = camera.focus_on.as_deref().and_then(|n| self.object(n)) {
+            camera.focus_at(object.shape.center());
+        }
+        Some(camera)
+    }
+
+    pub fn to_json(&self) -> io::Result<String> {
+        serde_json::to_string_pretty(self).map_err(io::Error::other)
+    }
+
+    pub fn from_json(text: &str) -> io::Result<Self> {
+        let file: Self = serde_json::from_str(text)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("scene: {}", e)))?;
+        if file.cameras.contains_key(MAIN_CAMERA) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("scene: camera name `{}` is reserved", MAIN_CAMERA),
+            ));
+        }
+        let cameras = std::iter::once(&file.camera).chain(file.cameras.values());
+        for target in cameras.filter_map(|c| c.focus_on.as_deref()) {
+            if file.object(target).is_none() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("scene: focus_on names unknown object `{}`", target),
+                ));
+            }
+        }
+        if let Some(object) = file
+            .objects
+            .iter()
+            .find(|o| file.material(&o.material).is_none())
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("scene: unknown {}", object.material),
+            ));
+        }
+        for object in &file.objects {
+            if let ShapeDesc::Mesh(mesh) = &object.shape {
+                mesh.validate()
+                    .map_err(|e| io::Error::new(e.kind(), format!("scene: {}", e)))?;
+            }
+        }
+        Ok(file)
+    }
+
+    /// A hash of everything in the file, the same from one run and build to the next,
+    /// e.g. for `Camera::with_checkpoint`.
+    pub fn fingerprint(&self) -> u64 {
+        // FNV-1a, since std's hasher may change between releases.
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        json.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_json()?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// Creates the renderable scene and a ready-to-use camera.
+    pub fn build(&self) -> (Camera, Scene) {
+        let materials: Vec<_> = self.materials.iter().map(MaterialDesc::build).collect();
+        let library = self.library.build();
+        let background: Arc<dyn Background> = match self.background {
+            BackgroundDesc::Gradient { bottom, top } => {
+                Arc::new(GradientBackground::new(bottom, top))
+            }
+            BackgroundDesc::Sky {
+                elevation,
+                azimuth,
+                turbidity,
+            } => Arc::new(PreethamSky::new(elevation, azimuth, turbidity)),
+        };
+        let mut scene = Scene {
+            background,
+            units: self.units,
+            ..Scene::default()
+        };
+
+        for (index, object) in self.objects.iter().enumerate() {
+            // Named materials number on from the listed ones for the material ID pass.
+            let (mat, material_id) = match &object.material {
+                MaterialRef::Index(i) => (materials[*i].clone(), *i + 1),
+                MaterialRef::Name(name) => {
+                    let rank = library
+                        .keys()
+                        .position(|n| n == name)
+                        .expect("known material");
+                    (library[name].clone(), materials.len() + rank + 1)
+                }
+            };
+            let hittable: Arc<dyn Hittable> = match &object.shape {
+                ShapeDesc::Sphere {
+                    center,
+                    radius,
+                    motion,
+                } => Arc::new(Sphere::moving(*center, *center + *motion, *radius, mat)),
+                ShapeDesc::Quad { q, u, v } => Arc::new(Quad::new(*q, *u, *v, mat)),
+                ShapeDesc::Triangle { a, b, c } => {
+                    Arc::new(Triangle::new(*a, *b, *c, mat).with_sides(object.sides))
+                }
+                ShapeDesc::Disk {
+                    center,
+                    normal,
+                    radius,
+                } => Arc::new(Disk::new(*center, *normal, *radius, mat)),
+                ShapeDesc::Cylinder {
+                    base,
+                    axis,
+                    radius,
+                    height,
+                    capped,
+                } => Arc::new(Cylinder::new(*base, *axis, *radius, *height, *capped, mat)),
+                ShapeDesc::Mesh(mesh) => {
+                    Arc::new(Bvh::from_list(mesh.sided_triangles(mat, object.sides)))
+                }
+            };
+            // Triangles cull their own back sides; other shapes are wrapped.
+            let hittable = match &object.shape {
+                ShapeDesc::Triangle { .. } | ShapeDesc::Mesh(_) => hittable,
+                _ => Sided::wrap(hittable, object.sides),
+            };
+            // Tagged with stable IDs for the object and material ID passes. Unnamed
+            // objects go by their position in the file.
+            let name = match &object.name {
+                Some(name) => name.clone(),
+                None => format!("object{}", index),
+            };
+            let hittable: Arc<dyn Hittable> =
+                Arc::new(Named::new(&name, hittable).with_material_id(material_id as u32));
+            if object.emitter {
+                scene.add_area_light(hittable);
+            } else {
+                scene.world.add(hittable);
+            }
+        }
+
+        let camera = self.camera(MAIN_CAMERA).expect("main camera exists");
+        (camera, scene)
+    }
+}
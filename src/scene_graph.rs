@@ -0,0 +1,232 @@
+use crate::rtweekend::*;
+
+/// Similarity transform: uniform scale, then rotation, then translation. Uniform scale
+/// keeps normals and angles intact, so hits and light sampling map over directly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: f64,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        translation: Vec3::new(0.0, 0.0, 0.0),
+        rotation: Quat::IDENTITY,
+        scale: 1.0,
+    };
+
+    pub fn translation(offset: Vec3) -> Self {
+        Self {
+            translation: offset,
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn rotation(rotation: Quat) -> Self {
+        Self {
+            rotation,
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn scale(scale: f64) -> Self {
+        Self {
+            scale,
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn point(&self, p: Point3) -> Point3 {
+        self.rotation.rotate(p * self.scale) + self.translation
+    }
+
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        self.rotation.rotate(v * self.scale)
+    }
+
+    pub fn inverse_point(&self, p: Point3) -> Point3 {
+        self.rotation.conjugate().rotate(p - self.translation) / self.scale
+    }
+
+    pub fn inverse_vector(&self, v: Vec3) -> Vec3 {
+        self.rotation.conjugate().rotate(v) / self.scale
+    }
+
+    /// Box around `bbox` after transforming it.
+    pub fn bounds(&self, bbox: &Aabb) -> Aabb {
+        if !bbox.is_bounded() {
+            return *bbox;
+        }
+        bbox.corners().iter().fold(Aabb::EMPTY, |acc, &c| {
+            let p = self.point(c);
+            acc.union(&Aabb::from_points(p, p))
+        })
+    }
+}
+
+/// Stable nonzero ID for a name (32-bit FNV-1a), the same in every run and build.
+pub fn name_id(name: &str) -> u32 {
+    let hash = name.bytes().fold(0x811c_9dc5u32, |h, b| {
+        (h ^ b as u32).wrapping_mul(0x0100_0193)
+    });
+    hash.max(1)
+}
+
+/// Gives any hittable a name, so it can be looked up in a built scene
+/// (`Scene::find_bounds`) without keeping a handle to it. Hits on it carry the name's
+/// ID for the object ID pass.
+pub struct Named {
+    pub name: String,
+    pub id: u32,
+    /// Tagged on hits for the material ID pass; 0 leaves it to objects further in.
+    pub material_id: u32,
+    object: Arc<dyn Hittable>,
+}
+
+impl Named {
+    pub fn new(name: &str, object: Arc<dyn Hittable>) -> Self {
+        Self {
+            name: name.to_string(),
+            id: name_id(name),
+            material_id: 0,
+            object,
+        }
+    }
+
+    pub fn with_material_id(self, material_id: u32) -> Self {
+        Self {
+            material_id,
+            ..self
+        }
+    }
+}
+
+impl Hittable for Named {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let mut rec = self.object.hit(r, ray_t)?;
+        if rec.object_id == 0 {
+            rec.object_id = self.id;
+        }
+        if rec.material_id == 0 {
+            rec.material_id = self.material_id;
+        }
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.object.bounding_box()
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        self.object.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: Point3, rng: &mut SmallRng) -> Vec3 {
+        self.object.random(origin, rng)
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.name)
+    }
+
+    fn named_bounds(&self, name: &str) -> Option<Aabb> {
+        if self.name == name {
+            Some(self.bounding_box())
+        } else {
+            self.object.named_bounds(name)
+        }
+    }
+}
+
+/// A node of the scene graph: a transform applied to a group of children, which may
+/// be nodes themselves. Assemblies built this way move, scale and rotate as one, and
+/// the same `Arc<SceneNode>` added under several parents is an instance of it. Wrap a
+/// node in `Animated` to animate the whole group.
+#[derive(Default)]
+pub struct SceneNode {
+    pub name: Option<String>,
+    pub transform: Transform,
+    pub children: HittableList,
+}
+
+impl SceneNode {
+    pub fn new(transform: Transform) -> Self {
+        Self {
+            name: None,
+            transform,
+            children: HittableList::new(),
+        }
+    }
+
+    pub fn named(name: &str, transform: Transform) -> Self {
+        Self {
+            name: Some(name.to_string()),
+            ..Self::new(transform)
+        }
+    }
+
+    pub fn add(&mut self, child: Arc<dyn Hittable>) {
+        self.children.add(child);
+    }
+
+    /// Builder-style `add`.
+    pub fn with(mut self, child: Arc<dyn Hittable>) -> Self {
+        self.add(child);
+        self
+    }
+}
+
+impl Hittable for SceneNode {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let t = &self.transform;
+        // `t` along the ray is the same in both spaces, since the map is affine.
+        let local = Ray {
+            orig: t.inverse_point(r.orig),
+            dir: t.inverse_vector(r.dir),
+            ..*r
+        };
+        let mut rec = self.children.hit(&local, ray_t)?;
+        rec.p = t.point(rec.p);
+        rec.normal = t.rotation.rotate(rec.normal);
+        rec.geometric_normal = t.rotation.rotate(rec.geometric_normal);
+        rec.tangent = t.rotation.rotate(rec.tangent);
+        rec.p_error *= t.scale;
+        rec.uv_scale *= t.scale;
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.transform.bounds(&self.children.bounding_box())
+    }
+
+    // A similarity transform preserves solid angles, so densities carry over as is.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let t = &self.transform;
+        self.children
+            .pdf_value(t.inverse_point(origin), t.inverse_vector(direction))
+    }
+
+    fn random(&self, origin: Point3, rng: &mut SmallRng) -> Vec3 {
+        let t = &self.transform;
+        t.vector(self.children.random(t.inverse_point(origin), rng))
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    fn named_bounds(&self, name: &str) -> Option<Aabb> {
+        if self.name() == Some(name) {
+            return Some(self.bounding_box());
+        }
+        let local = self.children.named_bounds(name)?;
+        Some(self.transform.bounds(&local))
+    }
+}
@@ -0,0 +1,382 @@
+use crate::interrupt;
+use crate::parallel::{self, ThreadPool, ThreadPoolBuilder};
+use crate::rtweekend::*;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub const DEFAULT_TILE_SIZE: u32 = 32;
+
+/// Tile sizes tried by `TileScheduler::autotune`.
+const TUNE_TILE_SIZES: [u32; 4] = [8, 16, 32, 64];
+/// Side of each square probe window rendered while auto-tuning.
+const TUNE_WINDOW: u32 = 48;
+/// Samples per pixel used for probes, so tuning stays cheap at high spp.
+const TUNE_SAMPLES: u32 = 4;
+
+/// Rectangular block of film pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Tile {
+    pub fn pixel_count(&self) -> usize {
+        (self.width * self.height) as usize
+    }
+}
+
+/// How far a render has got, in pixels, for showing progress from another thread. Give
+/// one to a camera with `Camera::with_progress`.
+#[derive(Debug, Default)]
+pub struct RenderProgress {
+    done: AtomicUsize,
+    total: AtomicUsize,
+}
+
+impl RenderProgress {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pixels finished so far, and in the region being rendered.
+    pub fn pixels(&self) -> (usize, usize) {
+        (
+            self.done.load(Ordering::Relaxed),
+            self.total.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Fraction finished, from 0 to 1; 0 before the render starts.
+    pub fn fraction(&self) -> f64 {
+        let (done, total) = self.pixels();
+        if total == 0 {
+            0.0
+        } else {
+            done as f64 / total as f64
+        }
+    }
+
+    fn start(&self, total: usize) {
+        self.done.store(0, Ordering::Relaxed);
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    fn advance(&self, pixels: usize) {
+        self.done.fetch_add(pixels, Ordering::Relaxed);
+    }
+}
+
+/// Splits a `width` x `height` film into row-major tiles of at most `tile_size` pixels a side.
+pub fn split_into_tiles(width: u32, height: u32, tile_size: u32) -> Vec<Tile> {
+    let tile_size = tile_size.max(1);
+    let mut tiles = Vec::new();
+    for y in (0..height).step_by(tile_size as usize) {
+        for x in (0..width).step_by(tile_size as usize) {
+            tiles.push(Tile {
+                x,
+                y,
+                width: tile_size.min(width - x),
+                height: tile_size.min(height - y),
+            });
+        }
+    }
+    tiles
+}
+
+/// Something that can render tiles: the CPU thread pool (`CpuDevice`), a GPU
+/// (`gpu::GpuDevice`, with the `gpu` feature) or another machine (`RemoteDevice`).
+pub trait RenderDevice: Send + Sync {
+    fn name(&self) -> String;
+
+    /// Whether this device runs on the host CPU (and is therefore replaced by auto-tuning).
+    fn is_cpu(&self) -> bool {
+        false
+    }
+
+    /// Renders the samples of `film_tile`'s tile into it.
+    fn render_tile(&self, camera: &Camera, scene: &Scene, film_tile: &mut FilmTile);
+}
+
+/// Renders tiles on a rayon thread pool (the global one unless a thread count is given),
+/// or on the calling thread alone without the `parallel` feature.
+#[derive(Default)]
+pub struct CpuDevice {
+    pool: Option<ThreadPool>,
+}
+
+impl CpuDevice {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_threads(threads: usize) -> Self {
+        let pool = ThreadPoolBuilder::new().num_threads(threads).build().ok();
+        Self { pool }
+    }
+
+    pub fn threads(&self) -> usize {
+        self.pool
+            .as_ref()
+            .map_or_else(parallel::current_num_threads, |pool| {
+                pool.current_num_threads()
+            })
+    }
+}
+
+impl RenderDevice for CpuDevice {
+    fn name(&self) -> String {
+        format!("cpu ({} threads)", self.threads())
+    }
+
+    fn is_cpu(&self) -> bool {
+        true
+    }
+
+    fn render_tile(&self, camera: &Camera, scene: &Scene, film_tile: &mut FilmTile) {
+        match &self.pool {
+            Some(pool) => pool.install(|| camera.render_tile(scene, film_tile)),
+            None => camera.render_tile(scene, film_tile),
+        }
+    }
+}
+
+#[derive(Default, Clone, Copy)]
+struct DeviceStats {
+    tiles: usize,
+    pixels: usize,
+    seconds: f64,
+    /// Exponential moving average of recent throughput, in pixels per second.
+    recent_throughput: f64,
+}
+
+/// Hands tiles out to every registered device at once. Devices pull work from a
+/// shared queue, and faster devices grab bigger batches based on their recent throughput.
+pub struct TileScheduler {
+    devices: Vec<Arc<dyn RenderDevice>>,
+    pub tile_size: u32,
+}
+
+impl TileScheduler {
+    pub fn new(devices: Vec<Arc<dyn RenderDevice>>) -> Self {
+        Self {
+            devices,
+            tile_size: DEFAULT_TILE_SIZE,
+        }
+    }
+
+    pub fn cpu_only() -> Self {
+        Self::new(vec![Arc::new(CpuDevice::new())])
+    }
+
+    pub fn add_device(&mut self, device: Arc<dyn RenderDevice>) {
+        self.devices.push(device);
+    }
+
+    // Number of tiles a device takes per trip to the queue, proportional to its
+    // share of the combined recent throughput.
+    fn batch_size(stats: &[DeviceStats], index: usize) -> usize {
+        let total: f64 = stats.iter().map(|s| s.recent_throughput).sum();
+        if total <= 0.0 || stats[index].recent_throughput <= 0.0 {
+            return 1;
+        }
+        let share = stats[index].recent_throughput / total;
+        ((share * stats.len() as f64).round() as usize).clamp(1, 8)
+    }
+
+    /// Renders probe windows spread over the film with every candidate tile size and
+    /// CPU thread count, then keeps the fastest combination for the full render.
+    /// Replaces the scheduler's CPU device; other devices are left alone.
+    pub fn autotune(&mut self, camera: &Camera, scene: &Scene) {
+        let mut probe_cam = camera.clone();
+        probe_cam.samples_per_pixel = camera.samples_per_pixel.min(TUNE_SAMPLES);
+        probe_cam.initialize();
+
+        let width = camera.film_width();
+        let height = camera.film_height();
+        let window = TUNE_WINDOW.min(width).min(height);
+        let windows: Vec<(u32, u32)> = [(0.25, 0.25), (0.5, 0.5), (0.75, 0.75)]
+            .iter()
+            .map(|(fx, fy)| {
+                let x = ((width - window) as f64 * fx) as u32;
+                let y = ((height - window) as f64 * fy) as u32;
+                (x, y)
+            })
+            .collect();
+
+        let max_threads = parallel::current_num_threads();
+        let mut thread_counts = vec![max_threads];
+        let mut n = max_threads / 2;
+        while n >= 1 && thread_counts.len() < 3 {
+            thread_counts.push(n);
+            n /= 2;
+        }
+
+        let scratch = Film::new(width, height, Filter::default());
+        let mut best: Option<(f64, u32, usize)> = None;
+        for &threads in &thread_counts {
+            let device = CpuDevice::with_threads(threads);
+            for &tile_size in &TUNE_TILE_SIZES {
+                let start = Stopwatch::start();
+                for &(x, y) in &windows {
+                    for tile in split_into_tiles(window, window, tile_size) {
+                        let tile = Tile {
+                            x: tile.x + x,
+                            y: tile.y + y,
+                            ..tile
+                        };
+                        device.render_tile(&probe_cam, scene, &mut scratch.tile(tile));
+                    }
+                }
+                let seconds = start.elapsed().as_secs_f64();
+                if best.is_none_or(|(best_seconds, _, _)| seconds < best_seconds) {
+                    best = Some((seconds, tile_size, threads));
+                }
+            }
+        }
+
+        if let Some((seconds, tile_size, threads)) = best {
+            log::info!(
+                target: "render",
+                "Auto-tune: tile size {}, {} threads ({:.1} ms probe)",
+                tile_size,
+                threads,
+                seconds * 1000.0
+            );
+            self.tile_size = tile_size;
+            self.devices.retain(|device| !device.is_cpu());
+            let cpu: Arc<dyn RenderDevice> = if threads == max_threads {
+                Arc::new(CpuDevice::new())
+            } else {
+                Arc::new(CpuDevice::with_threads(threads))
+            };
+            self.devices.insert(0, cpu);
+        }
+    }
+
+    /// Renders the camera's whole film and returns its pixels in row-major order.
+    pub fn render(&self, camera: &Camera, scene: &Scene) -> Vec<Color> {
+        let film = Film::new(camera.film_width(), camera.film_height(), camera.filter);
+        self.render_into(camera, scene, &film);
+        film.pixels(0.0)
+    }
+
+    /// Renders the camera's whole film into `film`, merging tiles as they finish.
+    pub fn render_into(&self, camera: &Camera, scene: &Scene, film: &Film) {
+        let full = Tile {
+            x: 0,
+            y: 0,
+            width: film.width,
+            height: film.height,
+        };
+        self.render_region(camera, scene, film, full);
+    }
+
+    /// The tiles `render_region` splits `region` into.
+    pub fn tiles(&self, region: Tile) -> Vec<Tile> {
+        split_into_tiles(region.width, region.height, self.tile_size)
+            .into_iter()
+            .map(|tile| Tile {
+                x: tile.x + region.x,
+                y: tile.y + region.y,
+                ..tile
+            })
+            .collect()
+    }
+
+    /// `render_into` for just the pixels in `region`, e.g. a crop window. Tiles the film
+    /// has finished already, e.g. one read back with `Film::read_from`, are skipped, and
+    /// after `interrupt::request` no more tiles are started.
+    pub fn render_region(&self, camera: &Camera, scene: &Scene, film: &Film, region: Tile) {
+        let finished = film.finished_tiles();
+        let (done, todo): (Vec<Tile>, Vec<Tile>) = self
+            .tiles(region)
+            .into_iter()
+            .partition(|tile| finished.contains(tile));
+        let total = done.len() + todo.len();
+        let queue = Mutex::new(todo.into_iter().collect::<VecDeque<_>>());
+        let stats = Mutex::new(vec![DeviceStats::default(); self.devices.len()]);
+        let progress = camera.progress();
+        if let Some(progress) = progress {
+            progress.start(region.pixel_count());
+            progress.advance(done.iter().map(Tile::pixel_count).sum());
+        }
+
+        // Each device pulls batches of tiles from the queue until it runs dry.
+        let work = |index: usize, device: &Arc<dyn RenderDevice>| {
+            while !interrupt::requested() {
+                let batch = Self::batch_size(&stats.lock().unwrap(), index);
+                let tiles: Vec<Tile> = {
+                    let mut queue = queue.lock().unwrap();
+                    let n = batch.min(queue.len());
+                    queue.drain(..n).collect()
+                };
+                if tiles.is_empty() {
+                    break;
+                }
+
+                for tile in tiles {
+                    if interrupt::requested() {
+                        break;
+                    }
+                    let start = Stopwatch::start();
+                    let mut film_tile = film.tile(tile);
+                    device.render_tile(camera, scene, &mut film_tile);
+                    let seconds = start.elapsed().as_secs_f64().max(1e-9);
+                    film.merge_tile(film_tile);
+                    if let Some(progress) = progress {
+                        progress.advance(tile.pixel_count());
+                    }
+
+                    let mut stats = stats.lock().unwrap();
+                    let s = &mut stats[index];
+                    let throughput = tile.pixel_count() as f64 / seconds;
+                    s.recent_throughput = if s.tiles == 0 {
+                        throughput
+                    } else {
+                        0.7 * s.recent_throughput + 0.3 * throughput
+                    };
+                    s.tiles += 1;
+                    s.pixels += tile.pixel_count();
+                    s.seconds += seconds;
+                }
+            }
+        };
+        if let [device] = self.devices.as_slice() {
+            // A lone device needs no thread of its own, which also keeps targets
+            // without threads (wasm32 in a browser) working.
+            work(0, device);
+        } else {
+            std::thread::scope(|s| {
+                for (index, device) in self.devices.iter().enumerate() {
+                    let work = &work;
+                    s.spawn(move || work(index, device));
+                }
+            });
+        }
+
+        if interrupt::requested() {
+            let rendered: usize = stats.lock().unwrap().iter().map(|s| s.tiles).sum();
+            log::warn!(
+                target: "render",
+                "Interrupted with {} of {} tiles rendered",
+                done.len() + rendered,
+                total
+            );
+        }
+        if self.devices.len() > 1 {
+            for (device, s) in self.devices.iter().zip(stats.into_inner().unwrap()) {
+                log::info!(
+                    target: "render",
+                    "{}: {} tiles, {:.2} Mpixel/s",
+                    device.name(),
+                    s.tiles,
+                    s.pixels as f64 / s.seconds.max(1e-9) / 1e6
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,412 @@
+use crate::png::write_png;
+use crate::rtweekend::*;
+use crate::scene_file::{MAIN_CAMERA, SceneFile};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::Duration;
+
+/// Largest request body accepted, so one bad client can't exhaust memory.
+const MAX_BODY: usize = 64 << 20;
+/// Largest request line plus headers accepted.
+const MAX_HEADER: u64 = 64 << 10;
+/// How long a read from a client may stall before the connection is dropped.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// How often `/events` sends progress.
+const EVENT_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone)]
+enum JobState {
+    Queued,
+    Rendering,
+    Done(Arc<Vec<u8>>),
+    Failed(String),
+}
+
+struct Job {
+    id: u64,
+    progress: Arc<RenderProgress>,
+    state: Mutex<JobState>,
+}
+
+impl Job {
+    fn state(&self) -> JobState {
+        self.state.lock().unwrap().clone()
+    }
+
+    fn set_state(&self, state: JobState) {
+        *self.state.lock().unwrap() = state;
+    }
+
+    fn status(&self) -> Value {
+        let (state, progress) = match self.state() {
+            JobState::Queued => ("queued", 0.0),
+            JobState::Rendering => ("rendering", self.progress.fraction()),
+            JobState::Done(_) => ("done", 1.0),
+            JobState::Failed(error) => {
+                return json!({ "id": self.id, "state": "failed", "error": error });
+            }
+        };
+        json!({ "id": self.id, "state": state, "progress": progress })
+    }
+
+    fn finished(&self) -> bool {
+        matches!(self.state(), JobState::Done(_) | JobState::Failed(_))
+    }
+}
+
+type Work = (Arc<Job>, Camera, Scene);
+
+/// What `RenderServer` accepts, so one client can't take all its memory or time.
+#[derive(Debug, Clone, Copy)]
+pub struct ServerLimits {
+    /// Most pixels in a render, overscan included.
+    pub max_pixels: u64,
+    /// Most samples per pixel.
+    pub max_samples: u32,
+    /// Finished jobs kept for clients to fetch; the oldest go first beyond this.
+    pub max_finished_jobs: usize,
+}
+
+impl Default for ServerLimits {
+    fn default() -> Self {
+        Self {
+            max_pixels: 4096 * 4096,
+            max_samples: 4096,
+            max_finished_jobs: 64,
+        }
+    }
+}
+
+/// Headless renderer behind a small HTTP/JSON API, for web demos and for driving the
+/// renderer from other languages. Jobs are rendered one at a time, each on every core.
+///
+/// - `POST /render` with a scene file (see `SceneFile`) as the body queues a render and
+///   answers `{"id": N}`. `?camera=NAME` picks one of the file's cameras.
+/// - `GET /jobs/N` gives the job's state (`queued`, `rendering`, `done` or `failed`)
+///   and progress from 0 to 1.
+/// - `GET /jobs/N/events` streams the same as server-sent events until the job ends.
+/// - `GET /jobs/N/image` returns the finished PNG.
+/// - `DELETE /jobs/N` forgets the job and its image.
+///
+/// Scenes over the `ServerLimits` are turned away with a 400, and only the most recent
+/// finished jobs are kept.
+pub struct RenderServer {
+    limits: ServerLimits,
+    jobs: Mutex<HashMap<u64, Arc<Job>>>,
+    next_id: Mutex<u64>,
+    queue: Mutex<Sender<Work>>,
+}
+
+impl RenderServer {
+    /// Starts the render thread with the default limits; `serve` then takes requests.
+    pub fn new() -> Arc<Self> {
+        Self::with_limits(ServerLimits::default())
+    }
+
+    /// `new` with other limits.
+    pub fn with_limits(limits: ServerLimits) -> Arc<Self> {
+        let (queue, work) = mpsc::channel::<Work>();
+        thread::spawn(move || {
+            for (job, camera, scene) in work {
+                job.set_state(JobState::Rendering);
+                let rendered = panic::catch_unwind(AssertUnwindSafe(|| {
+                    let pixels = camera.render_pixels(&scene);
+                    let mut png = Vec::new();
+                    write_png(&mut png, camera.film_width(), camera.film_height(), &pixels)
+                        .map(|_| png)
+                }));
+                job.set_state(match rendered {
+                    Ok(Ok(png)) => JobState::Done(Arc::new(png)),
+                    Ok(Err(e)) => JobState::Failed(e.to_string()),
+                    Err(_) => JobState::Failed("the renderer panicked".to_string()),
+                });
+            }
+        });
+        Arc::new(Self {
+            limits,
+            jobs: Mutex::new(HashMap::new()),
+            next_id: Mutex::new(1),
+            queue: Mutex::new(queue),
+        })
+    }
+
+    /// Takes requests on `addr` until the listener fails, each connection on its own
+    /// thread.
+    pub fn serve(self: &Arc<Self>, addr: impl ToSocketAddrs) -> io::Result<()> {
+        let listener = TcpListener::bind(addr)?;
+        log::info!(target: "server", "Render server listening on {}", listener.local_addr()?);
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    log::warn!(target: "server", "Connection failed: {}", e);
+                    continue;
+                }
+            };
+            let server = self.clone();
+            thread::spawn(move || {
+                if let Err(e) = server.handle(stream) {
+                    log::warn!(target: "server", "Request failed: {}", e);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    /// Answers one request on `stream`, then closes it.
+    pub fn handle(&self, stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut out = stream;
+        let request = match Request::read(&mut reader) {
+            Ok(request) => request,
+            Err(e) => return respond_error(&mut out, 400, &e.to_string()),
+        };
+        let segments: Vec<&str> = request.path.split('/').filter(|s| !s.is_empty()).collect();
+        match (request.method.as_str(), segments.as_slice()) {
+            ("OPTIONS", _) => respond(&mut out, 204, "text/plain", b""),
+            ("POST", ["render"]) => match self.submit(&request) {
+                Ok(id) => respond_json(&mut out, 202, &json!({ "id": id })),
+                Err(e) => respond_error(&mut out, 400, &e.to_string()),
+            },
+            (method, ["jobs", id, rest @ ..]) => {
+                let Some(job) = id.parse().ok().and_then(|id| self.job(id)) else {
+                    return respond_error(&mut out, 404, "no such job");
+                };
+                match (method, rest) {
+                    ("GET", []) => respond_json(&mut out, 200, &job.status()),
+                    ("GET", ["events"]) => stream_events(&mut out, &job),
+                    ("GET", ["image"]) => match job.state() {
+                        JobState::Done(png) => respond(&mut out, 200, "image/png", &png),
+                        JobState::Failed(error) => respond_error(&mut out, 500, &error),
+                        _ => respond_error(&mut out, 409, "the render hasn't finished"),
+                    },
+                    ("DELETE", []) => {
+                        self.jobs.lock().unwrap().remove(&job.id);
+                        respond(&mut out, 204, "text/plain", b"")
+                    }
+                    _ => respond_error(&mut out, 405, "method not allowed"),
+                }
+            }
+            _ => respond_error(&mut out, 404, "not found"),
+        }
+    }
+
+    fn job(&self, id: u64) -> Option<Arc<Job>> {
+        self.jobs.lock().unwrap().get(&id).cloned()
+    }
+
+    // Builds the scene in the body and queues it, so mistakes in it are reported to
+    // the client right away.
+    fn submit(&self, request: &Request) -> io::Result<u64> {
+        let text = std::str::from_utf8(&request.body)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "body isn't UTF-8"))?;
+        let file = SceneFile::from_json(text)?;
+        let name = request
+            .query
+            .get("camera")
+            .map_or(MAIN_CAMERA, String::as_str);
+        let camera = file.camera(name).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("no camera `{}`", name))
+        })?;
+        let pixels = camera.film_width() as u64 * camera.film_height() as u64;
+        if pixels > self.limits.max_pixels {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} pixels is over this server's limit of {}",
+                    pixels, self.limits.max_pixels
+                ),
+            ));
+        }
+        if camera.samples_per_pixel > self.limits.max_samples {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "{} samples per pixel is over this server's limit of {}",
+                    camera.samples_per_pixel, self.limits.max_samples
+                ),
+            ));
+        }
+        let (_, mut scene) = file.build();
+        scene.accelerate(Accelerator::default());
+
+        let id = {
+            let mut next_id = self.next_id.lock().unwrap();
+            *next_id += 1;
+            *next_id - 1
+        };
+        let progress = Arc::new(RenderProgress::new());
+        let job = Arc::new(Job {
+            id,
+            progress: progress.clone(),
+            state: Mutex::new(JobState::Queued),
+        });
+        {
+            let mut jobs = self.jobs.lock().unwrap();
+            Self::forget_old_jobs(&mut jobs, self.limits.max_finished_jobs);
+            jobs.insert(id, job.clone());
+        }
+        let camera = camera.with_progress(progress);
+        self.queue
+            .lock()
+            .unwrap()
+            .send((job, camera, scene))
+            .map_err(|_| io::Error::other("the render thread has stopped"))?;
+        Ok(id)
+    }
+
+    // Drops the oldest finished jobs, and their images, beyond `keep`.
+    fn forget_old_jobs(jobs: &mut HashMap<u64, Arc<Job>>, keep: usize) {
+        let mut finished: Vec<u64> = jobs
+            .values()
+            .filter(|job| job.finished())
+            .map(|job| job.id)
+            .collect();
+        if finished.len() > keep {
+            finished.sort_unstable();
+            for id in &finished[..finished.len() - keep] {
+                jobs.remove(id);
+            }
+        }
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    query: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn read(reader: &mut impl BufRead) -> io::Result<Self> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+        // Every line of the head must end within MAX_HEADER bytes of the start.
+        let mut head = (&mut *reader).take(MAX_HEADER);
+        let mut read_line = |line: &mut String| -> io::Result<()> {
+            if head.read_line(line)? == 0 {
+                return Err(invalid("headers cut short"));
+            }
+            if !line.ends_with('\n') {
+                return Err(invalid(if head.limit() == 0 {
+                    "headers too large"
+                } else {
+                    "headers cut short"
+                }));
+            }
+            Ok(())
+        };
+        let mut line = String::new();
+        read_line(&mut line)?;
+        let mut parts = line.split_whitespace();
+        let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+            return Err(invalid("bad request line"));
+        };
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        let query = query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+
+        let mut length = 0;
+        loop {
+            let mut header = String::new();
+            read_line(&mut header)?;
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':')
+                && name.eq_ignore_ascii_case("content-length")
+            {
+                length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| invalid("bad Content-Length"))?;
+            }
+        }
+        if length > MAX_BODY {
+            return Err(invalid("body too large"));
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body)?;
+        Ok(Self {
+            method: method.to_string(),
+            path: path.to_string(),
+            query,
+            body,
+        })
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        202 => "Accepted",
+        204 => "No Content",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        _ => "Internal Server Error",
+    }
+}
+
+// Headers every response carries. Any origin may call the API, so a page served from
+// elsewhere can use it.
+fn write_head(out: &mut impl Write, status: u16, content_type: &str) -> io::Result<()> {
+    write!(
+        out,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nConnection: close\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Allow-Methods: GET, POST, DELETE, OPTIONS\r\n\
+         Access-Control-Allow-Headers: Content-Type\r\n",
+        status,
+        reason(status),
+        content_type
+    )
+}
+
+fn respond(out: &mut impl Write, status: u16, content_type: &str, body: &[u8]) -> io::Result<()> {
+    write_head(out, status, content_type)?;
+    write!(out, "Content-Length: {}\r\n\r\n", body.len())?;
+    out.write_all(body)?;
+    out.flush()
+}
+
+fn respond_json(out: &mut impl Write, status: u16, value: &Value) -> io::Result<()> {
+    respond(
+        out,
+        status,
+        "application/json",
+        value.to_string().as_bytes(),
+    )
+}
+
+fn respond_error(out: &mut impl Write, status: u16, error: &str) -> io::Result<()> {
+    respond_json(out, status, &json!({ "error": error }))
+}
+
+// The job's status as a server-sent event every EVENT_INTERVAL, and once more when it
+// finishes, after which the stream ends.
+fn stream_events(out: &mut impl Write, job: &Job) -> io::Result<()> {
+    write_head(out, 200, "text/event-stream")?;
+    write!(out, "Cache-Control: no-cache\r\n\r\n")?;
+    loop {
+        let finished = job.finished();
+        write!(out, "data: {}\n\n", job.status())?;
+        out.flush()?;
+        if finished {
+            return Ok(());
+        }
+        thread::sleep(EVENT_INTERVAL);
+    }
+}
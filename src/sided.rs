@@ -0,0 +1,84 @@
+use crate::rtweekend::*;
+use serde::{Deserialize, Serialize};
+
+/// Which sides of a surface rays can hit. The front is the side the outward normal
+/// points to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Sides {
+    /// Both sides, as for thin quads like leaves or paper.
+    #[default]
+    Both,
+    /// Only the front: rays arriving from behind pass straight through, as for the
+    /// inside of a closed mesh that no ray should ever reach.
+    Front,
+    /// Only the back.
+    Back,
+}
+
+impl Sides {
+    pub fn accepts(self, front_face: bool) -> bool {
+        match self {
+            Self::Both => true,
+            Self::Front => front_face,
+            Self::Back => !front_face,
+        }
+    }
+}
+
+/// An object whose hits on the culled side are skipped, carrying on to whatever lies
+/// behind them within the same object.
+pub struct Sided {
+    object: Arc<dyn Hittable>,
+    sides: Sides,
+}
+
+impl Sided {
+    pub fn new(object: Arc<dyn Hittable>, sides: Sides) -> Self {
+        Self { object, sides }
+    }
+
+    /// `object` itself when nothing is culled, so two-sided objects cost nothing extra.
+    pub fn wrap(object: Arc<dyn Hittable>, sides: Sides) -> Arc<dyn Hittable> {
+        match sides {
+            Sides::Both => object,
+            _ => Arc::new(Self::new(object, sides)),
+        }
+    }
+
+    pub fn sides(&self) -> Sides {
+        self.sides
+    }
+}
+
+impl Hittable for Sided {
+    fn hit(&self, r: &Ray, mut ray_t: Interval) -> Option<HitRecord> {
+        loop {
+            let rec = self.object.hit(r, ray_t)?;
+            if self.sides.accepts(rec.front_face) {
+                return Some(rec);
+            }
+            // Just past the culled hit, so the same surface isn't found again.
+            ray_t.min = rec.t.next_up();
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.object.bounding_box()
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        self.object.pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: Point3, rng: &mut SmallRng) -> Vec3 {
+        self.object.random(origin, rng)
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.object.name()
+    }
+
+    fn named_bounds(&self, name: &str) -> Option<Aabb> {
+        self.object.named_bounds(name)
+    }
+}
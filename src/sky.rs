@@ -0,0 +1,293 @@
+use crate::rtweekend::*;
+
+/// Radiance for rays that escape the scene.
+pub trait Background: Send + Sync {
+    fn color(&self, direction: Vec3) -> Color;
+}
+
+/// The original two-color vertical blend.
+pub struct GradientBackground {
+    pub bottom: Color,
+    pub top: Color,
+}
+
+impl GradientBackground {
+    pub fn new(bottom: Color, top: Color) -> Self {
+        Self { bottom, top }
+    }
+}
+
+impl Default for GradientBackground {
+    fn default() -> Self {
+        Self::new(Color::new(1.0, 1.0, 1.0), Color::new(0.5, 0.7, 1.0))
+    }
+}
+
+impl Background for GradientBackground {
+    fn color(&self, direction: Vec3) -> Color {
+        let unit_direction = direction.normalized();
+        let a = 0.5 * (unit_direction.y + 1.0);
+        (1.0 - a) * self.bottom + a * self.top
+    }
+}
+
+/// Preetham et al. "A Practical Analytic Model for Daylight" sky, plus a sun disk.
+pub struct PreethamSky {
+    sun_direction: Vec3,
+    /// Scale from the model's kcd/m² to scene radiance units.
+    pub luminance_scale: f64,
+    /// Angular radius of the visible sun disk, in radians.
+    pub sun_angular_radius: f64,
+    /// Radiance of the sun disk (0 hides it).
+    pub sun_radiance: Color,
+
+    theta_s: f64,
+    zenith: Vec3, // (Y, x, y) at the zenith
+    perez_y: [f64; 5],
+    perez_x: [f64; 5],
+    perez_yc: [f64; 5],
+}
+
+impl PreethamSky {
+    /// `elevation` and `azimuth` are in degrees; azimuth is measured from +x towards +z.
+    /// Turbidity ranges from about 2 (very clear) to 10 (hazy).
+    pub fn new(elevation: f64, azimuth: f64, turbidity: f64) -> Self {
+        let el = degrees_to_radians(elevation.clamp(0.0, 90.0));
+        let az = degrees_to_radians(azimuth);
+        let sun_direction = Vec3::new(el.cos() * az.cos(), el.sin(), el.cos() * az.sin());
+        let theta_s = PI / 2.0 - el;
+        let t = turbidity;
+
+        let perez_y = [
+            0.1787 * t - 1.4630,
+            -0.3554 * t + 0.4275,
+            -0.0227 * t + 5.3251,
+            0.1206 * t - 2.5771,
+            -0.0670 * t + 0.3703,
+        ];
+        let perez_x = [
+            -0.0193 * t - 0.2592,
+            -0.0665 * t + 0.0008,
+            -0.0004 * t + 0.2125,
+            -0.0641 * t - 0.8989,
+            -0.0033 * t + 0.0452,
+        ];
+        let perez_yc = [
+            -0.0167 * t - 0.2608,
+            -0.0950 * t + 0.0092,
+            -0.0079 * t + 0.2102,
+            -0.0441 * t - 1.6537,
+            -0.0109 * t + 0.0529,
+        ];
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+        let th = theta_s;
+        let th2 = th * th;
+        let th3 = th2 * th;
+        let t2 = t * t;
+        let zenith_x = t2 * (0.00166 * th3 - 0.00375 * th2 + 0.00209 * th)
+            + t * (-0.02903 * th3 + 0.06377 * th2 - 0.03202 * th + 0.00394)
+            + (0.11693 * th3 - 0.21196 * th2 + 0.06052 * th + 0.25886);
+        let zenith_y = t2 * (0.00275 * th3 - 0.00610 * th2 + 0.00317 * th)
+            + t * (-0.04214 * th3 + 0.08970 * th2 - 0.04153 * th + 0.00516)
+            + (0.15346 * th3 - 0.26756 * th2 + 0.06670 * th + 0.26688);
+
+        Self {
+            sun_direction,
+            luminance_scale: 0.15,
+            sun_angular_radius: degrees_to_radians(0.5),
+            sun_radiance: Color::new(60.0, 56.0, 50.0),
+            theta_s,
+            zenith: Vec3::new(zenith_luminance, zenith_x, zenith_y),
+            perez_y,
+            perez_x,
+            perez_yc,
+        }
+    }
+
+    pub fn sun_direction(&self) -> Vec3 {
+        self.sun_direction
+    }
+
+    fn perez(coeffs: &[f64; 5], cos_theta: f64, gamma: f64) -> f64 {
+        let [a, b, c, d, e] = *coeffs;
+        let cos_gamma = gamma.cos();
+        (1.0 + a * (b / cos_theta).exp())
+            * (1.0 + c * (d * gamma).exp() + e * cos_gamma * cos_gamma)
+    }
+
+    // Perez distribution relative to its value at the zenith.
+    fn relative(&self, coeffs: &[f64; 5], cos_theta: f64, gamma: f64) -> f64 {
+        Self::perez(coeffs, cos_theta, gamma) / Self::perez(coeffs, 1.0, self.theta_s)
+    }
+}
+
+impl Background for PreethamSky {
+    fn color(&self, direction: Vec3) -> Color {
+        let dir = direction.normalized();
+        // The model is only defined above the horizon; clamp to avoid blowing up at grazing angles.
+        let cos_theta = dir.y.max(0.01);
+        let cos_gamma = dir.dot(self.sun_direction).clamp(-1.0, 1.0);
+        let gamma = cos_gamma.acos();
+
+        let lum = self.zenith.x * self.relative(&self.perez_y, cos_theta, gamma);
+        let x = self.zenith.y * self.relative(&self.perez_x, cos_theta, gamma);
+        let y = self.zenith.z * self.relative(&self.perez_yc, cos_theta, gamma);
+
+        // xyY -> XYZ -> linear sRGB
+        let cap_x = x / y * lum;
+        let cap_z = (1.0 - x - y) / y * lum;
+        let rgb = Color::new(
+            3.2406 * cap_x - 1.5372 * lum - 0.4986 * cap_z,
+            -0.9689 * cap_x + 1.8758 * lum + 0.0415 * cap_z,
+            0.0557 * cap_x - 0.2040 * lum + 1.0570 * cap_z,
+        );
+        let mut sky = rgb.map(|c| c.max(0.0)) * self.luminance_scale;
+
+        if dir.y > 0.0 && gamma < self.sun_angular_radius {
+            sky += self.sun_radiance;
+        }
+        sky
+    }
+}
+
+/// A disc in the night sky, such as the moon.
+#[derive(Debug, Clone, Copy)]
+pub struct Moon {
+    /// Direction from the scene towards the moon.
+    pub direction: Vec3,
+    /// Angular radius in radians; the real moon is about 0.0045.
+    pub angular_radius: f64,
+    pub radiance: Color,
+}
+
+/// Procedural night sky: a dark gradient scattered with stars and an optional moon.
+/// Stars are fixed on the sphere of directions, so they don't move with the camera.
+pub struct NightSky {
+    pub zenith: Color,
+    pub horizon: Color,
+    /// Chance that a cell of the star grid holds a star.
+    pub density: f64,
+    /// Radiance of the brightest stars.
+    pub brightness: f64,
+    /// Shape of the brightness distribution. Higher values leave fewer bright stars
+    /// among many faint ones.
+    pub falloff: f64,
+    /// Angular radius of a star, in radians.
+    pub star_radius: f64,
+    pub moon: Option<Moon>,
+    pub seed: u64,
+    /// Cells along each side of the six cube faces the stars are laid out on.
+    resolution: u32,
+}
+
+impl Default for NightSky {
+    fn default() -> Self {
+        Self::new(0.15, 20.0)
+    }
+}
+
+impl NightSky {
+    pub fn new(density: f64, brightness: f64) -> Self {
+        Self {
+            zenith: Color::new(0.0005, 0.0008, 0.002),
+            horizon: Color::new(0.003, 0.004, 0.008),
+            density,
+            brightness,
+            falloff: 3.0,
+            star_radius: 0.0015,
+            moon: None,
+            seed: 0,
+            resolution: 400,
+        }
+    }
+
+    /// Adds a moon `elevation` and `azimuth` degrees around the sky, measured like
+    /// `PreethamSky`.
+    pub fn with_moon(mut self, elevation: f64, azimuth: f64, radiance: Color) -> Self {
+        let el = degrees_to_radians(elevation);
+        let az = degrees_to_radians(azimuth);
+        self.moon = Some(Moon {
+            direction: Vec3::new(el.cos() * az.cos(), el.sin(), el.cos() * az.sin()),
+            angular_radius: 0.0045,
+            radiance,
+        });
+        self
+    }
+
+    /// Light matching the moon, for lighting the scene with direct light sampling since
+    /// the disc itself is too small to be hit by chance.
+    pub fn moon_light(&self) -> Option<DirectionalLight> {
+        let moon = self.moon?;
+        let solid_angle = 2.0 * PI * (1.0 - moon.angular_radius.cos());
+        Some(DirectionalLight::new(
+            -moon.direction,
+            moon.radiance * solid_angle,
+        ))
+    }
+
+    // Deterministic random number in [0, 1) for stream `k` of a star grid cell.
+    fn cell_random(&self, cell: u64, k: u64) -> f64 {
+        let mut z = (cell * 4 + k) ^ self.seed.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn star(&self, dir: Vec3) -> Color {
+        // Project onto the cube face the direction points at.
+        let abs = Vec3::new(dir.x.abs(), dir.y.abs(), dir.z.abs());
+        let (face, s, t, major) = if abs.x >= abs.y && abs.x >= abs.z {
+            (u64::from(dir.x > 0.0), dir.y, dir.z, dir.x)
+        } else if abs.y >= abs.z {
+            (2 + u64::from(dir.y > 0.0), dir.x, dir.z, dir.y)
+        } else {
+            (4 + u64::from(dir.z > 0.0), dir.x, dir.y, dir.z)
+        };
+        let n = self.resolution as f64;
+        let to_grid = |c: f64| ((c / major.abs() + 1.0) * 0.5 * n).clamp(0.0, n - 1e-9);
+        let (gs, gt) = (to_grid(s), to_grid(t));
+        let (i, j) = (gs.floor(), gt.floor());
+        let cell = (face * self.resolution as u64 + i as u64) * self.resolution as u64 + j as u64;
+        if self.cell_random(cell, 0) >= self.density {
+            return Color::BLACK;
+        }
+
+        // Keep stars away from cell borders so they aren't cut in half.
+        let star_s = i + 0.3 + 0.4 * self.cell_random(cell, 1);
+        let star_t = j + 0.3 + 0.4 * self.cell_random(cell, 2);
+        let angular_cell = 2.0 / n;
+        let offset = ((gs - star_s).powi(2) + (gt - star_t).powi(2)).sqrt() * angular_cell;
+        if offset > self.star_radius {
+            return Color::BLACK;
+        }
+
+        let u = self.cell_random(cell, 3);
+        let intensity = self.brightness * u.powf(self.falloff);
+        // Cooler stars are redder; blend between an orange and a blue-white tint.
+        let tint = Color::new(1.0, 0.8, 0.6) * (1.0 - u) + Color::new(0.8, 0.9, 1.0) * u;
+        tint * intensity
+    }
+}
+
+impl Background for NightSky {
+    fn color(&self, direction: Vec3) -> Color {
+        let dir = direction.normalized();
+        let a = dir.y.max(0.0);
+        let mut sky = (1.0 - a) * self.horizon + a * self.zenith;
+
+        if let Some(moon) = &self.moon
+            && dir.dot(moon.direction.normalized()) > moon.angular_radius.cos()
+        {
+            return sky + moon.radiance;
+        }
+        if dir.y > 0.0 {
+            sky += self.star(dir);
+        }
+        sky
+    }
+}
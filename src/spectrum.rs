@@ -0,0 +1,86 @@
+use crate::rtweekend::*;
+use std::sync::OnceLock;
+
+/// Visible range sampled in spectral mode, in nanometres.
+pub const LAMBDA_MIN: f64 = 380.0;
+pub const LAMBDA_MAX: f64 = 730.0;
+
+/// Picks the wavelength one spectral sample carries, uniformly over the visible range.
+pub fn sample_wavelength(rng: &mut SmallRng) -> f64 {
+    random_f64_range(rng, LAMBDA_MIN, LAMBDA_MAX)
+}
+
+fn piecewise_gaussian(x: f64, mu: f64, sigma_low: f64, sigma_high: f64) -> f64 {
+    let sigma = if x < mu { sigma_low } else { sigma_high };
+    let t = (x - mu) / sigma;
+    (-0.5 * t * t).exp()
+}
+
+/// CIE 1931 color matching functions at `lambda` nm, using the multi-lobe Gaussian
+/// fit from Wyman, Sloan and Shirley (2013).
+pub fn cie_xyz(lambda: f64) -> Vec3 {
+    let x = 1.056 * piecewise_gaussian(lambda, 599.8, 37.9, 31.0)
+        + 0.362 * piecewise_gaussian(lambda, 442.0, 16.0, 26.7)
+        - 0.065 * piecewise_gaussian(lambda, 501.1, 20.4, 26.2);
+    let y = 0.821 * piecewise_gaussian(lambda, 568.8, 46.9, 40.5)
+        + 0.286 * piecewise_gaussian(lambda, 530.9, 16.3, 31.1);
+    let z = 1.217 * piecewise_gaussian(lambda, 437.0, 11.8, 36.0)
+        + 0.681 * piecewise_gaussian(lambda, 459.0, 26.0, 13.8);
+    Vec3::new(x, y, z)
+}
+
+/// Linear sRGB (Rec. 709 primaries, D65 white) from CIE XYZ.
+pub fn xyz_to_rgb(xyz: Vec3) -> Color {
+    Color::new(
+        3.2406 * xyz.x - 1.5372 * xyz.y - 0.4986 * xyz.z,
+        -0.9689 * xyz.x + 1.8758 * xyz.y + 0.0415 * xyz.z,
+        0.0557 * xyz.x - 0.2040 * xyz.y + 1.0570 * xyz.z,
+    )
+}
+
+/// How much a sample carrying `lambda` contributes to each RGB channel. Averaged over
+/// uniformly sampled wavelengths this is (1, 1, 1), so scenes without dispersion look
+/// the same in spectral mode, only noisier.
+pub fn wavelength_to_rgb(lambda: f64) -> Color {
+    static WHITE: OnceLock<Color> = OnceLock::new();
+    let white = WHITE.get_or_init(|| {
+        const STEPS: usize = 1000;
+        let step = (LAMBDA_MAX - LAMBDA_MIN) / STEPS as f64;
+        let sum = (0..STEPS)
+            .map(|k| xyz_to_rgb(cie_xyz(LAMBDA_MIN + (k as f64 + 0.5) * step)))
+            .fold(Color::BLACK, |acc, c| acc + c);
+        sum / STEPS as f64
+    });
+    let rgb = xyz_to_rgb(cie_xyz(lambda));
+    rgb / *white
+}
+
+/// Wavelength dependent refractive index following Cauchy's equation n = a + b / λ².
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dispersion {
+    pub a: f64,
+    /// In nm².
+    pub b: f64,
+}
+
+impl Dispersion {
+    // Fraunhofer d, F and C lines, which define catalogue IORs and Abbe numbers.
+    const LAMBDA_D: f64 = 587.6;
+    const LAMBDA_F: f64 = 486.1;
+    const LAMBDA_C: f64 = 656.3;
+
+    /// From the IOR at 587.6 nm and the Abbe number, as listed in glass catalogues.
+    /// Lower Abbe numbers spread colors more: crown glass is about 60, flint about 35.
+    pub fn from_abbe(ior: f64, abbe: f64) -> Self {
+        let b =
+            (ior - 1.0) / (abbe * (1.0 / Self::LAMBDA_F.powi(2) - 1.0 / Self::LAMBDA_C.powi(2)));
+        Self {
+            a: ior - b / Self::LAMBDA_D.powi(2),
+            b,
+        }
+    }
+
+    pub fn ior(&self, lambda: f64) -> f64 {
+        self.a + self.b / (lambda * lambda)
+    }
+}
@@ -2,6 +2,8 @@ use crate::rtweekend::*;
 
 pub struct Sphere {
     pub center: Point3,
+    /// Displacement of the center between time 0 and time 1.
+    pub motion: Vec3,
     pub radius: f64,
     pub mat: Arc<dyn Material + Send + Sync>,
 }
@@ -10,15 +12,54 @@ impl Sphere {
     pub fn new(center: Point3, radius: f64, mat: Arc<dyn Material + Send + Sync>) -> Self {
         Self {
             center,
+            motion: Vec3::zero(),
             radius,
             mat,
         }
     }
+
+    /// A sphere moving linearly from `center1` at time 0 to `center2` at time 1.
+    pub fn moving(
+        center1: Point3,
+        center2: Point3,
+        radius: f64,
+        mat: Arc<dyn Material + Send + Sync>,
+    ) -> Self {
+        Self {
+            motion: center2 - center1,
+            ..Self::new(center1, radius, mat)
+        }
+    }
+
+    pub fn center_at(&self, time: f64) -> Point3 {
+        self.center + time * self.motion
+    }
+
+    // u: angle around the Y axis from X=-1, v: angle from Y=-1 to Y=+1, both in [0, 1].
+    fn get_sphere_uv(p: Point3) -> Vec2 {
+        let theta = (-p.y).acos();
+        let phi = (-p.z).atan2(p.x) + PI;
+        Vec2::new(phi / (2.0 * PI), theta / PI)
+    }
+
+    // Uniform direction inside the cone subtended by a sphere, around +z.
+    fn random_to_sphere(rng: &mut SmallRng, radius: f64, distance_squared: f64) -> Vec3 {
+        let r1 = random_f64(rng);
+        let r2 = random_f64(rng);
+        let z = 1.0 + r2 * ((1.0 - radius * radius / distance_squared).sqrt() - 1.0);
+
+        let phi = 2.0 * PI * r1;
+        let x = phi.cos() * (1.0 - z * z).sqrt();
+        let y = phi.sin() * (1.0 - z * z).sqrt();
+
+        Vec3::new(x, y, z)
+    }
 }
 
 impl Hittable for Sphere {
     fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
-        let oc = self.center - r.orig;
+        let center = self.center_at(r.tm);
+        let oc = center - r.orig;
         let a = r.dir.length_squared();
         let h = r.dir.dot(oc);
         let c = oc.length_squared() - self.radius * self.radius;
@@ -42,10 +83,49 @@ impl Hittable for Sphere {
         rec.t = root;
         rec.p = r.at(rec.t);
         //rec.normal = (rec.p - self.center) / self.radius;
-        let outward_normal = (rec.p - self.center) / self.radius;
+        let outward_normal = (rec.p - center) / self.radius;
         rec.set_face_normal(r, outward_normal);
+        rec.uv = Self::get_sphere_uv(outward_normal);
+        rec.tangent = Vec3::new(outward_normal.z, 0.0, -outward_normal.x);
+        // u runs around a circle of 2πr sin θ and v over half a great circle of πr.
+        let sin_theta = (1.0 - outward_normal.y * outward_normal.y).max(0.0).sqrt();
+        rec.uv_scale = PI * self.radius * (2.0 * sin_theta).sqrt();
         rec.mat = self.mat.clone();
+        // Rounding in the quadratic grows with the coordinates involved, so a huge
+        // ground sphere puts its hits much further off its surface than `p` suggests.
+        let magnitude = center.x.abs().max(center.y.abs()).max(center.z.abs()) + self.radius;
+        rec.p_error = 4.0 * f64::EPSILON * magnitude;
 
         Some(rec)
     }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        if self
+            .hit(&Ray::new(origin, direction), Interval::new(0.001, INFINITY))
+            .is_none()
+        {
+            return 0.0;
+        }
+
+        let dist_squared = (self.center - origin).length_squared();
+        let cos_theta_max = (1.0 - self.radius * self.radius / dist_squared).sqrt();
+        let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+
+        1.0 / solid_angle
+    }
+
+    fn random(&self, origin: Point3, rng: &mut SmallRng) -> Vec3 {
+        let direction = self.center - origin;
+        let distance_squared = direction.length_squared();
+        let uvw = Onb::new(direction);
+        uvw.transform(Self::random_to_sphere(rng, self.radius, distance_squared))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let radius = self.radius.abs();
+        let r = Vec3::new(radius, radius, radius);
+        let start = Aabb::from_points(self.center - r, self.center + r);
+        let end = Aabb::from_points(self.center_at(1.0) - r, self.center_at(1.0) + r);
+        start.union(&end)
+    }
 }
@@ -0,0 +1,376 @@
+use crate::interrupt;
+use crate::output::ImageFormat;
+use crate::parallel::*;
+use crate::rtweekend::*;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// Stochastic progressive photon mapping. Each iteration traces one camera path per
+/// pixel through mirrors and glass to the first surface that scatters diffusely (its
+/// visible point), then shoots photons from the lights and the background and gathers
+/// those landing near the visible points. The gather radius shrinks every iteration,
+/// so light focused through glass (caustics) converges instead of staying as the
+/// fireflies a path tracer leaves behind.
+///
+/// Photons are only gathered on surfaces with a scattering density (diffuse, rough),
+/// so caustics show there and in their reflections, not on perfect mirrors. Emissive
+/// geometry is seen along camera paths but doesn't emit photons; light the scene with
+/// the background or explicit lights.
+#[derive(Debug, Clone, Copy)]
+pub struct Sppm {
+    pub iterations: u32,
+    pub photons_per_iteration: usize,
+    /// Starting gather radius in scene units. `None` starts each pixel at twice the
+    /// width of its footprint on the surface it sees.
+    pub initial_radius: Option<f64>,
+}
+
+// Fraction of each iteration's photons kept in the pixel's estimate (SPPM's alpha).
+const ALPHA: f64 = 2.0 / 3.0;
+
+// Streams of an iteration's seed that photons draw from, clear of the pixels' streams.
+const PHOTON_STREAMS: u64 = 1 << 63;
+// Photons traced together on one thread, gathering into one map.
+const PHOTON_BATCH: usize = 4096;
+
+// Where a pixel's camera path first met a diffuse surface this iteration.
+struct VisiblePoint {
+    ray: Ray,
+    rec: HitRecord,
+    beta: Color,
+}
+
+#[derive(Clone, Copy, Default)]
+struct PixelState {
+    radius: f64,
+    photons: f64,
+    flux: Color,
+    direct: Color,
+}
+
+impl Sppm {
+    pub fn new(iterations: u32, photons_per_iteration: usize) -> Self {
+        Self {
+            iterations,
+            photons_per_iteration,
+            initial_radius: None,
+        }
+    }
+
+    /// Renders `scene` through `camera` and returns the pixels like
+    /// `Camera::render_pixels`.
+    pub fn render_pixels(&self, camera: &Camera, scene: &Scene) -> Vec<Color> {
+        let (width, height) = (camera.film_width(), camera.film_height());
+        let mut pixels = vec![PixelState::default(); (width * height) as usize];
+        let mut total_photons = 0.0;
+        let mut completed = 0;
+        let mut seeds = SmallRng::seed_from_u64(camera.seed.unwrap_or_else(random_seed));
+
+        for iteration in 0..self.iterations {
+            // An interrupted render keeps the iterations it finished.
+            if interrupt::requested() {
+                break;
+            }
+            log::debug!(target: "render", "SPPM iteration {}/{}", iteration + 1, self.iterations);
+            let seed: u64 = seeds.random();
+            let visible: Vec<Option<VisiblePoint>> = pixels
+                .par_iter_mut()
+                .enumerate()
+                .map(|(idx, pixel)| {
+                    let (i, j) = (idx as u32 % width, idx as u32 / width);
+                    let mut rng = pixel_rng(seed, i, j);
+                    let (direct, point) = camera_path(camera, scene, i, j, &mut rng);
+                    pixel.direct += direct;
+                    point
+                })
+                .collect();
+
+            let region = visible.iter().flatten().fold(Aabb::EMPTY, |acc, vp| {
+                acc.union(&Aabb::from_points(vp.rec.p, vp.rec.p))
+            });
+            if !region.is_bounded() {
+                continue;
+            }
+            // Pad the region so objects just outside it, like an orb casting a
+            // caustic at the frame's edge, still get photons.
+            let pad = (region.max() - region.min()).length() * 0.25;
+            let pad = Vec3::new(pad, pad, pad);
+            let region = Aabb::from_points(region.min() - pad, region.max() + pad);
+            // Pixels start gathering the first time their path finds a visible point.
+            let pixel_angle =
+                2.0 * degrees_to_radians(camera.vfov / 2.0).tan() / camera.image_height() as f64;
+            for (pixel, vp) in pixels.iter_mut().zip(&visible) {
+                if let Some(vp) = vp
+                    && pixel.radius == 0.0
+                {
+                    let distance = (vp.rec.p - vp.ray.orig).length();
+                    pixel.radius = self
+                        .initial_radius
+                        .unwrap_or(2.0 * pixel_angle * distance)
+                        .max(scene.epsilon());
+                }
+            }
+
+            let grid = PointGrid::new(&visible, &pixels);
+            // Photons go out in fixed batches, each gathering into a map of its own, and
+            // the maps are merged in batch order, so the sums come out the same however
+            // the batches are spread over threads.
+            let batches: Vec<HashMap<usize, (Color, u32)>> =
+                (0..self.photons_per_iteration.div_ceil(PHOTON_BATCH))
+                    .into_par_iter()
+                    .map(|batch| {
+                        let mut acc = HashMap::new();
+                        let first = batch * PHOTON_BATCH;
+                        for k in first..(first + PHOTON_BATCH).min(self.photons_per_iteration) {
+                            let mut rng = stream_rng(seed, PHOTON_STREAMS | k as u64);
+                            trace_photon(
+                                camera.max_depth,
+                                scene,
+                                &region,
+                                &mut rng,
+                                |ray, rec, power| {
+                                    gather(&visible, &pixels, &grid, ray, rec, power, &mut acc)
+                                },
+                            );
+                        }
+                        acc
+                    })
+                    .collect();
+            let mut gathered: HashMap<usize, (Color, u32)> = HashMap::new();
+            for (idx, (flux, count)) in batches.into_iter().flatten() {
+                let entry = gathered.entry(idx).or_insert((Color::BLACK, 0));
+                entry.0 += flux;
+                entry.1 += count;
+            }
+            total_photons += self.photons_per_iteration as f64;
+
+            for (idx, (flux, count)) in gathered {
+                let (Some(vp), pixel) = (&visible[idx], &mut pixels[idx]) else {
+                    continue;
+                };
+                let m = count as f64;
+                let photons = pixel.photons + ALPHA * m;
+                let radius = pixel.radius * (photons / (pixel.photons + m)).sqrt();
+                let shrink = (radius / pixel.radius) * (radius / pixel.radius);
+                pixel.flux = (pixel.flux + vp.beta * flux) * shrink;
+                pixel.photons = photons;
+                pixel.radius = radius;
+            }
+            completed += 1;
+        }
+
+        let iterations = completed.max(1) as f64;
+        let exposure = camera.exposure_scale();
+        pixels
+            .iter()
+            .map(|pixel| {
+                let mut color = pixel.direct / iterations;
+                if total_photons > 0.0 && pixel.radius > 0.0 {
+                    let area = PI * pixel.radius * pixel.radius;
+                    color += pixel.flux / (total_photons * area);
+                }
+                color * exposure
+            })
+            .collect()
+    }
+
+    /// Renders `scene` as an image in `format` into `out`.
+    pub fn render_to<W: Write>(
+        &self,
+        camera: &Camera,
+        scene: &Scene,
+        out: W,
+        format: ImageFormat,
+    ) -> io::Result<()> {
+        let pixels = self.render_pixels(camera, scene);
+        format.write(out, camera.film_width(), camera.film_height(), &pixels)
+    }
+}
+
+// Adds a photon of `power` that arrived along `ray` at `rec` to `acc`, for every visible
+// point close enough to gather it.
+fn gather(
+    visible: &[Option<VisiblePoint>],
+    pixels: &[PixelState],
+    grid: &PointGrid,
+    ray: &Ray,
+    rec: &HitRecord,
+    power: Color,
+    acc: &mut HashMap<usize, (Color, u32)>,
+) {
+    let (p, wi) = (rec.p, -ray.dir.normalized());
+    for &idx in grid.candidates(p) {
+        let Some(vp) = &visible[idx] else { continue };
+        let r = pixels[idx].radius;
+        if (vp.rec.p - p).length_squared() > r * r
+            || vp.rec.normal.dot(rec.normal) <= 0.0
+            || !vp.rec.shading_agrees(-vp.ray.dir, wi)
+        {
+            continue;
+        }
+        // eval() includes the cosine at the visible point, which the photon density
+        // already accounts for.
+        let cos = vp.rec.normal.dot(wi).abs();
+        if cos < 1e-4 {
+            continue;
+        }
+        let f = vp.rec.mat.eval(&vp.ray, &vp.rec, wi) / cos;
+        let entry = acc.entry(idx).or_insert((Color::BLACK, 0));
+        entry.0 += f * power;
+        entry.1 += 1;
+    }
+}
+
+// Follows a camera path through specular bounces. Returns the light picked up on the
+// way (emitters and background) and the visible point, if the path reached a diffuse
+// surface.
+fn camera_path(
+    camera: &Camera,
+    scene: &Scene,
+    i: u32,
+    j: u32,
+    rng: &mut SmallRng,
+) -> (Color, Option<VisiblePoint>) {
+    let hit_interval = Interval::new(0.0, INFINITY);
+    let mut r = camera.get_ray(i, j, rng);
+    let mut beta = Color::new(1.0, 1.0, 1.0);
+    let mut direct = Color::BLACK;
+    for depth in 0..camera.max_depth {
+        let hit = if depth == 0 {
+            scene.hit_primary(&r, hit_interval)
+        } else {
+            scene.hit(&r, hit_interval)
+        };
+        let Some(rec) = hit else {
+            direct += beta * scene.background_color(r.dir, depth == 0);
+            break;
+        };
+        direct += beta * rec.mat.emitted(&rec);
+        let Some((mut scattered, attenuation)) = rec.mat.scatter(&r, &rec, rng) else {
+            break;
+        };
+        if rec.mat.scattering_pdf(&r, &rec, &scattered) > 0.0 {
+            return (direct, Some(VisiblePoint { ray: r, rec, beta }));
+        }
+        if !rec.shading_agrees(-r.dir, scattered.dir) {
+            break;
+        }
+        scattered.orig = rec.spawn_origin(scattered.dir);
+        scattered.tm = r.tm;
+        scattered.media = scattered.media.or(r.media);
+        scattered.wavelength = r.wavelength;
+        beta *= attenuation;
+        r = scattered;
+    }
+    (direct, None)
+}
+
+/// Shoots one photon from a randomly picked light (the background counting as one),
+/// aimed to reach `region`, and calls `deposit(ray, rec, power)` wherever it lands on
+/// a diffuse surface, `ray` being the one that arrived there.
+pub(crate) fn trace_photon(
+    max_depth: u32,
+    scene: &Scene,
+    region: &Aabb,
+    rng: &mut SmallRng,
+    mut deposit: impl FnMut(&Ray, &HitRecord, Color),
+) {
+    let sources = scene.lights.len() + 1;
+    let pick = ((random_f64(rng) * sources as f64) as usize).min(sources - 1);
+    let emitted = match scene.lights.get(pick) {
+        Some(light) => light.emit(region, scene.units, rng),
+        None => Some(emit_background(scene, region, rng)),
+    };
+    let Some((mut ray, power)) = emitted else {
+        return;
+    };
+    let mut power = power * sources as f64;
+
+    let hit_interval = Interval::new(0.0, INFINITY);
+    for _ in 0..max_depth {
+        let Some(rec) = scene.hit(&ray, hit_interval) else {
+            return;
+        };
+        let Some((mut scattered, attenuation)) = rec.mat.scatter(&ray, &rec, rng) else {
+            return;
+        };
+        if rec.mat.scattering_pdf(&ray, &rec, &scattered) > 0.0 {
+            deposit(&ray, &rec, power);
+        }
+        // Russian roulette on the albedo keeps the photons' power roughly constant.
+        let q = attenuation.max_component().min(1.0);
+        if q <= 0.0 || random_f64(rng) >= q || !rec.shading_agrees(-ray.dir, scattered.dir) {
+            return;
+        }
+        power = power * attenuation / q;
+        scattered.orig = rec.spawn_origin(scattered.dir);
+        scattered.tm = ray.tm;
+        scattered.media = scattered.media.or(ray.media);
+        scattered.wavelength = ray.wavelength;
+        ray = scattered;
+    }
+}
+
+// Photon from the background: a uniformly random direction, entering through a disk
+// that covers the bounding sphere of `region`.
+fn emit_background(scene: &Scene, region: &Aabb, rng: &mut SmallRng) -> (Ray, Color) {
+    let radius = (region.max() - region.min()).length() / 2.0;
+    let toward_sky = Vec3::random_unit_vector(rng);
+    let onb = Onb::new(toward_sky);
+    let disk = Vec3::random_in_unit_disk(rng);
+    let origin = region.center() + radius * (toward_sky + disk.x * onb.u + disk.y * onb.v);
+    let radiance = scene.background_color(toward_sky, false);
+    // Radiance times the disk's area over the direction's density (1 / 4pi).
+    let power = radiance * (PI * radius * radius) * (4.0 * PI);
+    (Ray::new(origin, -toward_sky), power)
+}
+
+// Hash grid over the visible points, with cells as wide as the largest gather radius
+// so a photon only needs to look at the cell it lands in.
+struct PointGrid {
+    cell: f64,
+    cells: HashMap<(i64, i64, i64), Vec<usize>>,
+}
+
+impl PointGrid {
+    fn new(visible: &[Option<VisiblePoint>], pixels: &[PixelState]) -> Self {
+        let cell = visible
+            .iter()
+            .zip(pixels)
+            .filter(|(vp, _)| vp.is_some())
+            .map(|(_, pixel)| pixel.radius)
+            .fold(0.0, f64::max)
+            .max(1e-9);
+        let mut grid = Self {
+            cell,
+            cells: HashMap::new(),
+        };
+        for (idx, vp) in visible.iter().enumerate() {
+            let Some(vp) = vp else { continue };
+            let r = pixels[idx].radius;
+            let lo = grid.key(vp.rec.p - Vec3::new(r, r, r));
+            let hi = grid.key(vp.rec.p + Vec3::new(r, r, r));
+            for x in lo.0..=hi.0 {
+                for y in lo.1..=hi.1 {
+                    for z in lo.2..=hi.2 {
+                        grid.cells.entry((x, y, z)).or_default().push(idx);
+                    }
+                }
+            }
+        }
+        grid
+    }
+
+    fn key(&self, p: Point3) -> (i64, i64, i64) {
+        (
+            (p.x / self.cell).floor() as i64,
+            (p.y / self.cell).floor() as i64,
+            (p.z / self.cell).floor() as i64,
+        )
+    }
+
+    fn candidates(&self, p: Point3) -> &[usize] {
+        self.cells.get(&self.key(p)).map_or(&[], Vec::as_slice)
+    }
+}
@@ -0,0 +1,99 @@
+use crate::output::ImageFormat;
+use crate::rtweekend::*;
+use std::io::{self, Write};
+
+/// Average human interocular distance, in meters.
+pub const HUMAN_INTEROCULAR: f64 = 0.064;
+
+/// How the two eyes share one image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StereoLayout {
+    /// Left eye on the left half, right eye on the right half.
+    #[default]
+    SideBySide,
+    /// Left eye on top, right eye below.
+    TopBottom,
+}
+
+/// A pair of cameras for stereoscopic (VR) viewing, built around one camera that
+/// stands for the point between the eyes.
+#[derive(Debug, Clone, Copy)]
+pub struct StereoRig {
+    /// Distance between the eyes, in scene units.
+    pub interocular: f64,
+    /// Distance to the plane where both eyes agree (zero parallax). Things nearer pop
+    /// out of the screen. `None` converges at the camera's focus distance.
+    pub convergence: Option<f64>,
+    pub layout: StereoLayout,
+}
+
+impl StereoRig {
+    pub fn new(interocular: f64) -> Self {
+        Self {
+            interocular,
+            convergence: None,
+            layout: StereoLayout::SideBySide,
+        }
+    }
+
+    /// A rig with human eye spacing for a scene modeled in `units`.
+    pub fn human(units: Unit) -> Self {
+        Self::new(HUMAN_INTEROCULAR / units.meters_per_unit())
+    }
+
+    /// Left and right eye cameras. Each eye is moved half the interocular distance
+    /// sideways and its lens shifted so both frame the convergence plane identically
+    /// (off-axis stereo). Unlike turning the eyes inward this adds no vertical parallax.
+    pub fn eyes(&self, camera: &Camera) -> (Camera, Camera) {
+        let right = camera.view_rotation().rotate(Vec3::new(1.0, 0.0, 0.0));
+        let convergence = self.convergence.unwrap_or(camera.focus_dist).max(1e-6);
+        let half = self.interocular / 2.0;
+        // Width of the picture at the convergence distance, to express the offset
+        // as a shift.
+        let half_v = degrees_to_radians(camera.vfov / 2.0).tan();
+        let width = 2.0 * half_v * convergence * camera.aspect_ratio;
+
+        let eye = |side: f64| {
+            let mut eye = camera.clone();
+            eye.lookfrom = camera.lookfrom + side * half * right;
+            if camera.orientation.is_none() {
+                eye.lookat = camera.lookat + side * half * right;
+            }
+            eye.shift_x = camera.shift_x - side * half / width;
+            eye.initialize();
+            eye
+        };
+        (eye(-1.0), eye(1.0))
+    }
+
+    /// Renders both eyes into one image in `format`, laid out per `layout`.
+    pub fn render_to<W: Write>(
+        &self,
+        camera: &Camera,
+        scene: &Scene,
+        out: W,
+        format: ImageFormat,
+    ) -> io::Result<()> {
+        let (left, right) = self.eyes(camera);
+        let (width, height) = (camera.film_width(), camera.film_height());
+        log::info!(target: "render", "Left eye");
+        let left = left.render_pixels(scene);
+        log::info!(target: "render", "Right eye");
+        let right = right.render_pixels(scene);
+        log::debug!(target: "render", "Writing stereo pair");
+
+        let pixels: Vec<Color> = match self.layout {
+            StereoLayout::SideBySide => left
+                .chunks(width as usize)
+                .zip(right.chunks(width as usize))
+                .flat_map(|(l, r)| l.iter().chain(r))
+                .copied()
+                .collect(),
+            StereoLayout::TopBottom => left.into_iter().chain(right).collect(),
+        };
+        match self.layout {
+            StereoLayout::SideBySide => format.write(out, 2 * width, height, &pixels),
+            StereoLayout::TopBottom => format.write(out, width, 2 * height, &pixels),
+        }
+    }
+}
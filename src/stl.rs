@@ -0,0 +1,105 @@
+use crate::rtweekend::*;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("stl: {}", msg))
+}
+
+// STL repeats every corner for each triangle; merge identical ones so the mesh is
+// connected (which subdivision and smooth shading need).
+#[derive(Default)]
+struct Builder {
+    mesh: Mesh,
+    indices: HashMap<[u64; 3], usize>,
+}
+
+impl Builder {
+    fn vertex(&mut self, p: Point3) -> usize {
+        let key = [p.x.to_bits(), p.y.to_bits(), p.z.to_bits()];
+        *self.indices.entry(key).or_insert_with(|| {
+            self.mesh.positions.push(p);
+            self.mesh.positions.len() - 1
+        })
+    }
+
+    fn triangle(&mut self, corners: [Point3; 3]) {
+        let face = corners.map(|p| self.vertex(p));
+        // Degenerate triangles show up in exported files and only cause trouble.
+        if face[0] != face[1] && face[1] != face[2] && face[0] != face[2] {
+            self.mesh.faces.push(face.to_vec());
+        }
+    }
+}
+
+fn parse_binary(bytes: &[u8]) -> io::Result<Mesh> {
+    let count = u32::from_le_bytes(bytes[80..84].try_into().unwrap()) as usize;
+    let mut builder = Builder::default();
+    for k in 0..count {
+        let record = &bytes[84 + 50 * k..84 + 50 * (k + 1)];
+        let float =
+            |i: usize| f32::from_le_bytes(record[4 * i..4 * i + 4].try_into().unwrap()) as f64;
+        // The first three floats are the facet normal, which the winding already implies.
+        let corner = |c: usize| Point3::new(float(3 + 3 * c), float(4 + 3 * c), float(5 + 3 * c));
+        builder.triangle([corner(0), corner(1), corner(2)]);
+    }
+    Ok(builder.mesh)
+}
+
+fn parse_ascii(text: &str) -> io::Result<Mesh> {
+    let mut builder = Builder::default();
+    let mut corners = Vec::with_capacity(3);
+    for (line_no, line) in text.lines().enumerate() {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("vertex") => {
+                let coords: Vec<f64> = tokens
+                    .take(3)
+                    .map(|t| t.parse::<f64>())
+                    .collect::<Result<_, _>>()
+                    .map_err(|_| invalid(&format!("line {}: bad vertex", line_no + 1)))?;
+                if coords.len() != 3 {
+                    return Err(invalid(&format!(
+                        "line {}: vertex needs three coordinates",
+                        line_no + 1
+                    )));
+                }
+                corners.push(Point3::new(coords[0], coords[1], coords[2]));
+            }
+            Some("endloop") => {
+                if corners.len() != 3 {
+                    return Err(invalid(&format!(
+                        "line {}: facet needs three vertices",
+                        line_no + 1
+                    )));
+                }
+                builder.triangle([corners[0], corners[1], corners[2]]);
+                corners.clear();
+            }
+            _ => {}
+        }
+    }
+    Ok(builder.mesh)
+}
+
+/// Reads the triangles of an STL file, ASCII or binary. Shared corners are merged.
+pub fn parse_stl(bytes: &[u8]) -> io::Result<Mesh> {
+    // Binary files may also start with "solid", so trust the size their header implies.
+    if bytes.len() >= 84 {
+        let count = u32::from_le_bytes(bytes[80..84].try_into().unwrap()) as usize;
+        let size = count.checked_mul(50).and_then(|n| n.checked_add(84));
+        if size == Some(bytes.len()) {
+            return parse_binary(bytes);
+        }
+    }
+    if !bytes.trim_ascii_start().starts_with(b"solid") {
+        return Err(invalid("neither ASCII nor binary STL"));
+    }
+    parse_ascii(&String::from_utf8_lossy(bytes))
+}
+
+pub fn load_stl(path: impl AsRef<Path>) -> io::Result<Mesh> {
+    parse_stl(&fs::read(path)?)
+}
@@ -0,0 +1,307 @@
+use crate::rtweekend::*;
+
+/// Dense voxel volume. A value of 0 is empty; any other value indexes the palette.
+#[derive(Debug, Clone)]
+pub struct VoxelGrid {
+    pub dims: (u32, u32, u32),
+    data: Vec<u8>,
+}
+
+impl VoxelGrid {
+    pub fn new(dims: (u32, u32, u32)) -> Self {
+        Self {
+            dims,
+            data: vec![0; (dims.0 * dims.1 * dims.2) as usize],
+        }
+    }
+
+    fn index(&self, x: u32, y: u32, z: u32) -> usize {
+        (x + self.dims.0 * (y + self.dims.1 * z)) as usize
+    }
+
+    pub fn get(&self, x: u32, y: u32, z: u32) -> u8 {
+        if x >= self.dims.0 || y >= self.dims.1 || z >= self.dims.2 {
+            return 0;
+        }
+        self.data[self.index(x, y, z)]
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, z: u32, value: u8) {
+        let index = self.index(x, y, z);
+        self.data[index] = value;
+    }
+
+    pub fn filled_count(&self) -> usize {
+        self.data.iter().filter(|&&v| v != 0).count()
+    }
+
+    /// Voxelizes a closed hittable inside the box `min`..`max`, `resolution` voxels along
+    /// its longest side. Each row of voxels is classified with a single ray along +x,
+    /// using the entry/exit crossings of the surface.
+    pub fn voxelize(
+        object: &dyn Hittable,
+        min: Point3,
+        max: Point3,
+        resolution: u32,
+        value: u8,
+    ) -> (Self, f64) {
+        let extent = max - min;
+        let voxel_size = extent.x.max(extent.y).max(extent.z) / resolution.max(1) as f64;
+        let dims = (
+            (extent.x / voxel_size).ceil().max(1.0) as u32,
+            (extent.y / voxel_size).ceil().max(1.0) as u32,
+            (extent.z / voxel_size).ceil().max(1.0) as u32,
+        );
+        let mut grid = Self::new(dims);
+
+        for z in 0..dims.2 {
+            for y in 0..dims.1 {
+                let origin = Point3::new(
+                    min.x - voxel_size,
+                    min.y + (y as f64 + 0.5) * voxel_size,
+                    min.z + (z as f64 + 0.5) * voxel_size,
+                );
+                let r = Ray::new(origin, Vec3::new(1.0, 0.0, 0.0));
+
+                let mut t_min = 0.0;
+                let mut inside_from: Option<f64> = None;
+                while let Some(rec) = object.hit(&r, Interval::new(t_min, INFINITY)) {
+                    t_min = rec.t + 1e-7 * rec.t.max(1.0);
+                    match (rec.front_face, inside_from) {
+                        (true, None) => inside_from = Some(rec.t),
+                        (false, Some(start)) => {
+                            grid.fill_row(
+                                y,
+                                z,
+                                start - voxel_size,
+                                rec.t - voxel_size,
+                                voxel_size,
+                                value,
+                            );
+                            inside_from = None;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        (grid, voxel_size)
+    }
+
+    // Fills voxels of row (y, z) whose centers lie between x offsets `from` and `to`.
+    fn fill_row(&mut self, y: u32, z: u32, from: f64, to: f64, voxel_size: f64, value: u8) {
+        for x in 0..self.dims.0 {
+            let center = (x as f64 + 0.5) * voxel_size;
+            if center >= from && center <= to {
+                self.set(x, y, z, value);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SvoNode {
+    Empty,
+    Solid(u8),
+    /// Index of the first of eight consecutive children.
+    Branch(u32),
+}
+
+/// Sparse voxel octree over a `VoxelGrid`. Uniform regions collapse into single
+/// nodes and rays walk the tree front to back, stopping at the first solid leaf.
+pub struct SparseVoxelOctree {
+    root: SvoNode,
+    nodes: Vec<SvoNode>,
+    /// Side of the root cube, in voxels (a power of two).
+    size: u32,
+    origin: Point3,
+    voxel_size: f64,
+    palette: Vec<Arc<dyn Material + Send + Sync>>,
+}
+
+impl SparseVoxelOctree {
+    /// `palette[v]` is the material for voxel value `v` (entry 0 is never used).
+    pub fn new(
+        grid: &VoxelGrid,
+        origin: Point3,
+        voxel_size: f64,
+        palette: Vec<Arc<dyn Material + Send + Sync>>,
+    ) -> Self {
+        let largest = grid.dims.0.max(grid.dims.1).max(grid.dims.2).max(1);
+        let size = largest.next_power_of_two();
+        let mut svo = Self {
+            root: SvoNode::Empty,
+            nodes: Vec::new(),
+            size,
+            origin,
+            voxel_size,
+            palette,
+        };
+        svo.root = svo.build(grid, (0, 0, 0), size);
+        svo
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len() + 1
+    }
+
+    fn build(&mut self, grid: &VoxelGrid, corner: (u32, u32, u32), size: u32) -> SvoNode {
+        if size == 1 {
+            return match grid.get(corner.0, corner.1, corner.2) {
+                0 => SvoNode::Empty,
+                v => SvoNode::Solid(v),
+            };
+        }
+
+        let half = size / 2;
+        let mut children = [SvoNode::Empty; 8];
+        for (octant, child) in children.iter_mut().enumerate() {
+            let child_corner = Self::child_corner(corner, half, octant);
+            *child = self.build(grid, child_corner, half);
+        }
+
+        if children.iter().all(|c| *c == children[0]) && !matches!(children[0], SvoNode::Branch(_))
+        {
+            return children[0];
+        }
+
+        let first = self.nodes.len() as u32;
+        self.nodes.extend_from_slice(&children);
+        SvoNode::Branch(first)
+    }
+
+    fn child_corner(corner: (u32, u32, u32), half: u32, octant: usize) -> (u32, u32, u32) {
+        (
+            corner.0 + if octant & 1 != 0 { half } else { 0 },
+            corner.1 + if octant & 2 != 0 { half } else { 0 },
+            corner.2 + if octant & 4 != 0 { half } else { 0 },
+        )
+    }
+
+    // Slab test against the cube at `min` with side `side`. Returns entry/exit distances
+    // and the axes through which the ray enters and leaves.
+    fn slab(r: &Ray, min: Point3, side: f64) -> Option<(f64, f64, usize, usize)> {
+        let mut t_enter = -INFINITY;
+        let mut t_exit = INFINITY;
+        let (mut enter_axis, mut exit_axis) = (0, 0);
+        for axis in 0..3 {
+            let inv_d = 1.0 / r.dir[axis];
+            let mut t0 = (min[axis] - r.orig[axis]) * inv_d;
+            let mut t1 = (min[axis] + side - r.orig[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+            if t0 > t_enter {
+                t_enter = t0;
+                enter_axis = axis;
+            }
+            if t1 < t_exit {
+                t_exit = t1;
+                exit_axis = axis;
+            }
+        }
+        (t_enter <= t_exit).then_some((t_enter, t_exit, enter_axis, exit_axis))
+    }
+
+    fn hit_node(
+        &self,
+        node: SvoNode,
+        min: Point3,
+        side: f64,
+        r: &Ray,
+        ray_t: Interval,
+    ) -> Option<(f64, usize, bool, u8)> {
+        ray_stats::count(Counter::NodeVisits);
+        match node {
+            SvoNode::Empty => None,
+            SvoNode::Solid(value) => {
+                let (t_enter, t_exit, enter_axis, exit_axis) = Self::slab(r, min, side)?;
+                if ray_t.surrounds(t_enter) {
+                    Some((t_enter, enter_axis, true, value))
+                } else if ray_t.surrounds(t_exit) && t_enter <= ray_t.min {
+                    Some((t_exit, exit_axis, false, value))
+                } else {
+                    None
+                }
+            }
+            SvoNode::Branch(first) => {
+                let half = side / 2.0;
+                let mut order: Vec<(f64, usize)> = (0..8)
+                    .filter(|&octant| self.nodes[first as usize + octant] != SvoNode::Empty)
+                    .filter_map(|octant| {
+                        let child_min = Self::child_min(min, half, octant);
+                        let (t_enter, t_exit, _, _) = Self::slab(r, child_min, half)?;
+                        (t_exit > ray_t.min && t_enter < ray_t.max).then_some((t_enter, octant))
+                    })
+                    .collect();
+                order.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+                let mut closest = ray_t.max;
+                let mut best = None;
+                for (t_enter, octant) in order {
+                    if t_enter >= closest {
+                        break;
+                    }
+                    let child = self.nodes[first as usize + octant];
+                    let child_min = Self::child_min(min, half, octant);
+                    if let Some(hit) =
+                        self.hit_node(child, child_min, half, r, Interval::new(ray_t.min, closest))
+                    {
+                        closest = hit.0;
+                        best = Some(hit);
+                    }
+                }
+                best
+            }
+        }
+    }
+
+    fn child_min(min: Point3, half: f64, octant: usize) -> Point3 {
+        Point3::new(
+            min.x + if octant & 1 != 0 { half } else { 0.0 },
+            min.y + if octant & 2 != 0 { half } else { 0.0 },
+            min.z + if octant & 4 != 0 { half } else { 0.0 },
+        )
+    }
+}
+
+impl Hittable for SparseVoxelOctree {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let side = self.size as f64 * self.voxel_size;
+        let (t, axis, entering, value) = self.hit_node(self.root, self.origin, side, r, ray_t)?;
+
+        let p = r.at(t);
+        // Entering through a face means the outward normal opposes the ray along that axis.
+        let mut outward_normal = Vec3::zero();
+        outward_normal[axis] = if (r.dir[axis] > 0.0) == entering {
+            -1.0
+        } else {
+            1.0
+        };
+        let local = (p - self.origin) / self.voxel_size;
+        let (u_axis, v_axis) = ((axis + 1) % 3, (axis + 2) % 3);
+
+        let mut rec = HitRecord {
+            p,
+            t,
+            uv: Vec2::new(local[u_axis].fract(), local[v_axis].fract()),
+            tangent: {
+                let mut tangent = Vec3::zero();
+                tangent[u_axis] = 1.0;
+                tangent
+            },
+            mat: self.palette[value as usize].clone(),
+            uv_scale: self.voxel_size,
+            ..Default::default()
+        };
+        rec.set_face_normal(r, outward_normal);
+
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let side = self.size as f64 * self.voxel_size;
+        Aabb::from_points(self.origin, self.origin + Vec3::new(side, side, side))
+    }
+}
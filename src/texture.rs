@@ -0,0 +1,534 @@
+use crate::instance::rotate_hue;
+use crate::noise::{Perlin, Worley};
+use crate::rtweekend::*;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub trait Texture: Send + Sync {
+    fn value(&self, uv: Vec2, p: &Point3) -> Color;
+
+    /// Value at a shading point. Textures that depend on more than the surface
+    /// coordinates (like the instance that was hit) override this.
+    fn value_at_hit(&self, rec: &HitRecord) -> Color {
+        self.value(rec.uv, &rec.p)
+    }
+
+    /// Single-channel value at a shading point, for grayscale maps driving parameters
+    /// like roughness.
+    fn scalar_at_hit(&self, rec: &HitRecord) -> f64 {
+        luminance(self.value_at_hit(rec))
+    }
+}
+
+pub struct SolidColor {
+    albedo: Color,
+}
+
+impl SolidColor {
+    pub fn new(albedo: Color) -> Self {
+        Self { albedo }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _uv: Vec2, _p: &Point3) -> Color {
+        self.albedo
+    }
+}
+
+/// Solid 3D checkerboard with cells of side `scale`.
+pub struct CheckerTexture {
+    inv_scale: f64,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    pub fn new(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+        Self {
+            inv_scale: 1.0 / scale,
+            even,
+            odd,
+        }
+    }
+
+    pub fn from_colors(scale: f64, even: Color, odd: Color) -> Self {
+        Self::new(
+            scale,
+            Arc::new(SolidColor::new(even)),
+            Arc::new(SolidColor::new(odd)),
+        )
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, uv: Vec2, p: &Point3) -> Color {
+        let x = (self.inv_scale * p.x).floor() as i64;
+        let y = (self.inv_scale * p.y).floor() as i64;
+        let z = (self.inv_scale * p.z).floor() as i64;
+        if (x + y + z).rem_euclid(2) == 0 {
+            self.even.value(uv, p)
+        } else {
+            self.odd.value(uv, p)
+        }
+    }
+}
+
+/// Varies another texture per instance so copies of an object don't look identical.
+/// The variation is seeded by the hit's instance ID, so it is stable across samples.
+pub struct PerInstance {
+    base: Arc<dyn Texture>,
+    /// Largest hue rotation, as a fraction of the color wheel.
+    pub hue_jitter: f64,
+    /// Largest relative change in brightness (0.2 scales by 0.8 to 1.2).
+    pub scale_jitter: f64,
+    /// Decorrelates textures that would otherwise vary in lockstep.
+    pub seed: u32,
+}
+
+impl PerInstance {
+    pub fn new(base: Arc<dyn Texture>, hue_jitter: f64, scale_jitter: f64) -> Self {
+        Self {
+            base,
+            hue_jitter,
+            scale_jitter,
+            seed: 0,
+        }
+    }
+
+    pub fn from_color(albedo: Color, hue_jitter: f64, scale_jitter: f64) -> Self {
+        Self::new(Arc::new(SolidColor::new(albedo)), hue_jitter, scale_jitter)
+    }
+
+    fn random(&self, instance_id: u32, stream: u32) -> f64 {
+        let key = self.seed.wrapping_mul(0x9e37_79b9) ^ stream.wrapping_mul(0x85eb_ca6b);
+        instance_random(instance_id ^ key)
+    }
+}
+
+impl Texture for PerInstance {
+    fn value(&self, uv: Vec2, p: &Point3) -> Color {
+        self.base.value(uv, p)
+    }
+
+    fn value_at_hit(&self, rec: &HitRecord) -> Color {
+        let id = rec.instance_id;
+        let base = self.base.value_at_hit(rec);
+        let angle = (2.0 * self.random(id, 0) - 1.0) * self.hue_jitter * 2.0 * PI;
+        let scale = 1.0 + (2.0 * self.random(id, 1) - 1.0) * self.scale_jitter;
+        rotate_hue(base, angle) * scale.max(0.0)
+    }
+}
+
+/// Projects a texture onto the surface from the three axes and blends by the surface
+/// normal, so objects without usable UVs (boxes, CSG, voxels) can still be textured
+/// without stretching. The projection is in world space.
+pub struct Triplanar {
+    texture: Arc<dyn Texture>,
+    /// World units covered by one repeat of the texture.
+    pub scale: f64,
+    /// How quickly the blend switches between projections; higher gives crisper seams.
+    pub sharpness: f64,
+}
+
+impl Triplanar {
+    pub fn new(texture: Arc<dyn Texture>, scale: f64) -> Self {
+        Self {
+            texture,
+            scale,
+            sharpness: 4.0,
+        }
+    }
+}
+
+impl Texture for Triplanar {
+    // Without a normal there is nothing to project along, so use the surface's own UVs.
+    fn value(&self, uv: Vec2, p: &Point3) -> Color {
+        self.texture.value(uv, p)
+    }
+
+    fn value_at_hit(&self, rec: &HitRecord) -> Color {
+        let n = rec.normal;
+        let weights = Vec3::new(
+            n.x.abs().powf(self.sharpness),
+            n.y.abs().powf(self.sharpness),
+            n.z.abs().powf(self.sharpness),
+        );
+        let total = weights.x + weights.y + weights.z;
+        if total <= 0.0 {
+            return self.texture.value_at_hit(rec);
+        }
+        let p = rec.p / self.scale;
+        let x = self.texture.value(Vec2::new(p.z, p.y), &rec.p);
+        let y = self.texture.value(Vec2::new(p.x, p.z), &rec.p);
+        let z = self.texture.value(Vec2::new(p.x, p.y), &rec.p);
+        (x * weights.x + y * weights.y + z * weights.z) / total
+    }
+}
+
+/// How `NoiseTexture` turns Perlin noise into a color.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoisePattern {
+    /// Soft clouds from layered noise.
+    Smooth,
+    /// Billowing, smoke-like layers.
+    Turbulence,
+    /// Stripes along z distorted by turbulence.
+    Marble,
+}
+
+/// Procedural Perlin noise texture. `time` moves the pattern through the noise's time
+/// dimension; set it per animation frame (see `at_frame`) to make smoke or water evolve.
+#[derive(Debug, Clone, Copy)]
+pub struct NoiseTexture {
+    perlin: Perlin,
+    pub pattern: NoisePattern,
+    /// Features per world unit.
+    pub scale: f64,
+    pub octaves: u32,
+    pub time: f64,
+    /// How fast the pattern changes, in noise cells per unit of time.
+    pub speed: f64,
+    pub color: Color,
+}
+
+impl NoiseTexture {
+    pub fn new(pattern: NoisePattern, scale: f64, seed: u32) -> Self {
+        Self {
+            perlin: Perlin::new(seed),
+            pattern,
+            scale,
+            octaves: 6,
+            time: 0.0,
+            speed: 1.0,
+            color: Color::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    /// This texture as it looks on `frame` of an animation running at `fps`.
+    pub fn at_frame(&self, frame: u32, fps: f64) -> Self {
+        Self {
+            time: frame as f64 / fps,
+            ..*self
+        }
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn value(&self, _uv: Vec2, p: &Point3) -> Color {
+        let p = self.scale * *p;
+        let t = self.speed * self.time;
+        let intensity = match self.pattern {
+            NoisePattern::Smooth => 0.5 + 0.5 * self.perlin.fbm(p, t, self.octaves),
+            NoisePattern::Turbulence => self.perlin.turbulence(p, t, self.octaves),
+            NoisePattern::Marble => {
+                0.5 + 0.5 * (p.z + 10.0 * self.perlin.turbulence(p, t, self.octaves)).sin()
+            }
+        };
+        self.color * intensity.clamp(0.0, 1.0)
+    }
+}
+
+/// Procedural Worley (cellular) texture: dark at the cell centers, bright towards the
+/// edges. Feature points drift with `time` like `NoiseTexture`.
+#[derive(Debug, Clone, Copy)]
+pub struct CellTexture {
+    worley: Worley,
+    /// Cells per world unit.
+    pub scale: f64,
+    pub time: f64,
+    /// Cycles of feature point drift per unit of time.
+    pub speed: f64,
+    pub color: Color,
+}
+
+impl CellTexture {
+    pub fn new(scale: f64, seed: u32) -> Self {
+        Self {
+            worley: Worley::new(seed),
+            scale,
+            time: 0.0,
+            speed: 1.0,
+            color: Color::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    /// This texture as it looks on `frame` of an animation running at `fps`.
+    pub fn at_frame(&self, frame: u32, fps: f64) -> Self {
+        Self {
+            time: frame as f64 / fps,
+            ..*self
+        }
+    }
+}
+
+impl Texture for CellTexture {
+    fn value(&self, _uv: Vec2, p: &Point3) -> Color {
+        let d = self
+            .worley
+            .distance(self.scale * *p, self.speed * self.time);
+        self.color * d.min(1.0)
+    }
+}
+
+/// How `ImageTexture` looks up its texels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TextureFilter {
+    /// The texel under the sample: crisp, but far-off detail shimmers as it moves.
+    #[default]
+    Nearest,
+    /// Blend of the four texels around the sample, which softens magnified textures.
+    Bilinear,
+    /// Bilinear lookups in the two levels of a mip pyramid (the image halved again and
+    /// again) closest in texel size to the ray's footprint, blended. Distant textures
+    /// average out instead of shimmering; the pyramid costs a third more memory.
+    Trilinear,
+}
+
+/// One level of an `ImageTexture`'s mip pyramid.
+struct MipLevel {
+    width: u32,
+    height: u32,
+    pixels: Vec<Color>,
+}
+
+impl MipLevel {
+    // Half the size, each texel the average of the (up to) four below it.
+    fn downsampled(&self) -> Self {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut pixels = Vec::with_capacity((width * height) as usize);
+        for j in 0..height {
+            for i in 0..width {
+                let (i0, j0) = (2 * i, 2 * j);
+                let (i1, j1) = ((i0 + 1).min(self.width - 1), (j0 + 1).min(self.height - 1));
+                let sum = self.texel(i0, j0)
+                    + self.texel(i1, j0)
+                    + self.texel(i0, j1)
+                    + self.texel(i1, j1);
+                pixels.push(sum / 4.0);
+            }
+        }
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    fn texel(&self, i: u32, j: u32) -> Color {
+        self.pixels[(j.min(self.height - 1) * self.width + i.min(self.width - 1)) as usize]
+    }
+
+    // The texel containing (x, y), in texels from the top left, wrapping around.
+    fn nearest(&self, x: f64, y: f64) -> Color {
+        let i = (x.floor() as i64).rem_euclid(self.width as i64) as u32;
+        let j = (y.floor() as i64).rem_euclid(self.height as i64) as u32;
+        self.texel(i, j)
+    }
+
+    // Blend of the four texels whose centers surround (x, y), wrapping around.
+    fn bilinear(&self, x: f64, y: f64) -> Color {
+        let (x, y) = (x - 0.5, y - 0.5);
+        let (fx, fy) = (x - x.floor(), y - y.floor());
+        let (x0, y0) = (x.floor(), y.floor());
+        let top = Color::lerp(self.nearest(x0, y0), self.nearest(x0 + 1.0, y0), fx);
+        let bottom = Color::lerp(
+            self.nearest(x0, y0 + 1.0),
+            self.nearest(x0 + 1.0, y0 + 1.0),
+            fx,
+        );
+        Color::lerp(top, bottom, fy)
+    }
+}
+
+/// Image sampled by (u, v), repeating outside [0, 1]. `v = 0` is the bottom row.
+/// Clones share the pixel data.
+#[derive(Clone)]
+pub struct ImageTexture {
+    /// The full image first, then the mip pyramid if the filter uses one.
+    levels: Vec<Arc<MipLevel>>,
+    filter: TextureFilter,
+}
+
+impl ImageTexture {
+    pub fn new(width: u32, height: u32, pixels: Vec<Color>) -> Self {
+        Self {
+            levels: vec![Arc::new(MipLevel {
+                width,
+                height,
+                pixels,
+            })],
+            filter: TextureFilter::Nearest,
+        }
+    }
+
+    /// Loads a color image (PPM), converting from display gamma to linear.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let (width, height, mut pixels) = parse_ppm(&fs::read(path)?)?;
+        for c in pixels.iter_mut() {
+            *c = *c * *c;
+        }
+        Ok(Self::new(width, height, pixels))
+    }
+
+    /// Loads a data image (PPM) as-is, for normal, bump and other non-color maps.
+    pub fn load_linear(path: impl AsRef<Path>) -> io::Result<Self> {
+        let (width, height, pixels) = parse_ppm(&fs::read(path)?)?;
+        Ok(Self::new(width, height, pixels))
+    }
+
+    /// Samples with `filter`, building the mip pyramid if it needs one.
+    pub fn with_filter(mut self, filter: TextureFilter) -> Self {
+        self.levels.truncate(1);
+        if filter == TextureFilter::Trilinear && !self.levels[0].pixels.is_empty() {
+            while let Some(last) = self.levels.last()
+                && (last.width > 1 || last.height > 1)
+            {
+                let next = last.downsampled();
+                self.levels.push(Arc::new(next));
+            }
+        }
+        self.filter = filter;
+        self
+    }
+
+    pub fn filter(&self) -> TextureFilter {
+        self.filter
+    }
+
+    /// Levels in the mip pyramid, counting the full image; 1 without one.
+    pub fn mip_levels(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn width(&self) -> u32 {
+        self.levels[0].width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.levels[0].height
+    }
+
+    pub fn pixel(&self, i: u32, j: u32) -> Color {
+        self.levels[0].texel(i, j)
+    }
+
+    // Bilinear lookup in mip `level`, which may fall between two levels.
+    fn trilinear(&self, uv: Vec2, level: f64) -> Color {
+        let level = level.clamp(0.0, (self.levels.len() - 1) as f64);
+        let fine = level.floor() as usize;
+        let coarse = (fine + 1).min(self.levels.len() - 1);
+        let at = |mip: &MipLevel| {
+            mip.bilinear(uv.x * mip.width as f64, (1.0 - uv.y) * mip.height as f64)
+        };
+        Color::lerp(
+            at(&self.levels[fine]),
+            at(&self.levels[coarse]),
+            level - fine as f64,
+        )
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, uv: Vec2, _p: &Point3) -> Color {
+        let image = &self.levels[0];
+        if image.pixels.is_empty() {
+            return Color::new(0.0, 1.0, 1.0); // Cyan flags missing texture data.
+        }
+        // Flip v so v = 0 is the bottom row.
+        let x = uv.x.rem_euclid(1.0) * image.width as f64;
+        let y = (1.0 - uv.y.rem_euclid(1.0)) * image.height as f64;
+        match self.filter {
+            TextureFilter::Nearest => image.texel(x as u32, y as u32),
+            TextureFilter::Bilinear | TextureFilter::Trilinear => image.bilinear(x, y),
+        }
+    }
+
+    fn value_at_hit(&self, rec: &HitRecord) -> Color {
+        let width = rec.uv_footprint();
+        if self.filter != TextureFilter::Trilinear || self.levels.len() < 2 || width <= 0.0 {
+            return self.value(rec.uv, &rec.p);
+        }
+        // The level whose texels are about as wide as the footprint.
+        let texels = width * self.width().max(self.height()) as f64;
+        self.trilinear(rec.uv, texels.log2())
+    }
+}
+
+/// Parses ASCII (P3) or binary (P6) PPM into colors in [0, 1], top row first.
+pub fn parse_ppm(bytes: &[u8]) -> io::Result<(u32, u32, Vec<Color>)> {
+    let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, format!("ppm: {}", msg));
+
+    // Header tokens, skipping whitespace and '#' comments.
+    let mut pos = 0;
+    let next_token = |pos: &mut usize| -> Option<String> {
+        loop {
+            while *pos < bytes.len() && bytes[*pos].is_ascii_whitespace() {
+                *pos += 1;
+            }
+            if *pos < bytes.len() && bytes[*pos] == b'#' {
+                while *pos < bytes.len() && bytes[*pos] != b'\n' {
+                    *pos += 1;
+                }
+                continue;
+            }
+            break;
+        }
+        let start = *pos;
+        while *pos < bytes.len() && !bytes[*pos].is_ascii_whitespace() {
+            *pos += 1;
+        }
+        (start < *pos).then(|| String::from_utf8_lossy(&bytes[start..*pos]).into_owned())
+    };
+
+    let magic = next_token(&mut pos).ok_or_else(|| invalid("empty file"))?;
+    let mut header = [0u32; 3];
+    for value in header.iter_mut() {
+        *value = next_token(&mut pos)
+            .and_then(|t| t.parse().ok())
+            .ok_or_else(|| invalid("bad header"))?;
+    }
+    let [width, height, max_value] = header;
+    if max_value == 0 || max_value > 65535 {
+        return Err(invalid("bad max value"));
+    }
+    let count = (width * height) as usize;
+    let scale = 1.0 / max_value as f64;
+
+    let mut samples = Vec::with_capacity(count * 3);
+    match magic.as_str() {
+        "P3" => {
+            for _ in 0..count * 3 {
+                let sample: u32 = next_token(&mut pos)
+                    .and_then(|t| t.parse().ok())
+                    .ok_or_else(|| invalid("truncated pixel data"))?;
+                samples.push(sample as f64 * scale);
+            }
+        }
+        "P6" => {
+            pos += 1; // Single whitespace byte after the header.
+            let wide = max_value > 255;
+            let bytes_per_sample = if wide { 2 } else { 1 };
+            let data = bytes
+                .get(pos..pos + count * 3 * bytes_per_sample)
+                .ok_or_else(|| invalid("truncated pixel data"))?;
+            for chunk in data.chunks(bytes_per_sample) {
+                let sample = if wide {
+                    u16::from_be_bytes([chunk[0], chunk[1]]) as f64
+                } else {
+                    chunk[0] as f64
+                };
+                samples.push(sample * scale);
+            }
+        }
+        _ => return Err(invalid("only P3 and P6 are supported")),
+    }
+
+    let pixels = samples
+        .chunks(3)
+        .map(|c| Color::new(c[0], c[1], c[2]))
+        .collect();
+    Ok((width, height, pixels))
+}
@@ -0,0 +1,158 @@
+use crate::loader::{LoadJob, LoadReport, load_all};
+use crate::rtweekend::*;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+/// Image textures by file, so scene files naming the same image many times read and
+/// decode it once. Images are loaded the first time a render samples them, or all at
+/// once with `preload`; every material using a file shares one copy of its pixels,
+/// whatever filter it asks for.
+#[derive(Default)]
+pub struct TextureRegistry {
+    files: Mutex<HashMap<(PathBuf, bool), Arc<ImageFile>>>,
+    textures: Mutex<HashMap<(PathBuf, bool, TextureFilter), Arc<LazyImage>>>,
+}
+
+impl TextureRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Color image at `path` (see `ImageTexture::load`), sampled with `filter`.
+    pub fn image(&self, path: impl AsRef<Path>, filter: TextureFilter) -> Arc<LazyImage> {
+        self.get(path.as_ref(), false, filter)
+    }
+
+    /// Data image at `path`, like a normal or roughness map (see
+    /// `ImageTexture::load_linear`), sampled with `filter`.
+    pub fn data(&self, path: impl AsRef<Path>, filter: TextureFilter) -> Arc<LazyImage> {
+        self.get(path.as_ref(), true, filter)
+    }
+
+    fn get(&self, path: &Path, linear: bool, filter: TextureFilter) -> Arc<LazyImage> {
+        // Different spellings of the same file share an entry.
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let file = self
+            .files
+            .lock()
+            .unwrap()
+            .entry((path.clone(), linear))
+            .or_insert_with(|| {
+                Arc::new(ImageFile {
+                    path: path.clone(),
+                    linear,
+                    image: OnceLock::new(),
+                })
+            })
+            .clone();
+        self.textures
+            .lock()
+            .unwrap()
+            .entry((path, linear, filter))
+            .or_insert_with(|| {
+                Arc::new(LazyImage {
+                    file,
+                    filter,
+                    image: OnceLock::new(),
+                })
+            })
+            .clone()
+    }
+
+    /// Files registered so far.
+    pub fn len(&self) -> usize {
+        self.files.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Files read so far.
+    pub fn loaded(&self) -> usize {
+        let files = self.files.lock().unwrap();
+        files.values().filter(|f| f.image.get().is_some()).count()
+    }
+
+    /// Reads every registered file not read yet, in parallel, so missing or broken
+    /// images are reported before rendering instead of showing up as cyan.
+    pub fn preload(&self) -> io::Result<LoadReport> {
+        let jobs = self
+            .files
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|file| file.image.get().is_none())
+            .map(|file| {
+                let file = file.clone();
+                LoadJob::new(file.path.display().to_string(), move || {
+                    file.load().map(|_| ())
+                })
+            })
+            .collect();
+        let (_, report) = load_all(jobs)?;
+        Ok(report)
+    }
+}
+
+// One image file, decoded on first use.
+struct ImageFile {
+    path: PathBuf,
+    linear: bool,
+    image: OnceLock<Result<ImageTexture, String>>,
+}
+
+impl ImageFile {
+    fn load(&self) -> io::Result<&ImageTexture> {
+        let image = self.image.get_or_init(|| {
+            let image = if self.linear {
+                ImageTexture::load_linear(&self.path)
+            } else {
+                ImageTexture::load(&self.path)
+            };
+            image.map_err(|e| e.to_string())
+        });
+        image
+            .as_ref()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.clone()))
+    }
+}
+
+/// Texture from a `TextureRegistry`, reading its file the first time it is sampled.
+/// A file that can't be read is reported once and drawn in cyan, like an empty
+/// `ImageTexture`.
+pub struct LazyImage {
+    file: Arc<ImageFile>,
+    filter: TextureFilter,
+    image: OnceLock<ImageTexture>,
+}
+
+impl LazyImage {
+    pub fn path(&self) -> &Path {
+        &self.file.path
+    }
+
+    /// The decoded image, reading it now if it hasn't been yet.
+    pub fn image(&self) -> &ImageTexture {
+        self.image.get_or_init(|| match self.file.load() {
+            Ok(image) => image.clone().with_filter(self.filter),
+            Err(e) => {
+                log::error!(target: "loader", "Failed to load texture {}: {}", self.file.path.display(), e);
+                ImageTexture::new(0, 0, Vec::new())
+            }
+        })
+    }
+}
+
+impl Texture for LazyImage {
+    fn value(&self, uv: Vec2, p: &Point3) -> Color {
+        self.image().value(uv, p)
+    }
+
+    fn value_at_hit(&self, rec: &HitRecord) -> Color {
+        self.image().value_at_hit(rec)
+    }
+}
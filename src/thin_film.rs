@@ -0,0 +1,84 @@
+use crate::rtweekend::*;
+
+/// Wavelengths (in nm) that stand in for the red, green and blue channels.
+const RGB_WAVELENGTHS: [f64; 3] = [630.0, 532.0, 465.0];
+
+/// A thin transparent film (soap, oil, anodizing) on top of a surface. Light reflected
+/// at the top and the bottom of the film interferes, tinting reflections with colors
+/// that shift with the viewing angle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThinFilm {
+    /// Film thickness in nanometers; the visible effect is strongest around 100-1000.
+    pub thickness: f64,
+    pub ior: f64,
+}
+
+impl ThinFilm {
+    pub fn new(thickness: f64, ior: f64) -> Self {
+        Self { thickness, ior }
+    }
+
+    /// Reflectance of the film over a dielectric with index `substrate_ior`, for light
+    /// arriving from air at `cos_i` to the normal.
+    pub fn reflectance(&self, cos_i: f64, substrate_ior: f64) -> Color {
+        self.airy(cos_i, |n2, c2| {
+            let Some(c3) = refracted_cos(c2, n2, substrate_ior) else {
+                return None; // Total internal reflection at the substrate.
+            };
+            let n3 = substrate_ior;
+            let rs = (n2 * c2 - n3 * c3) / (n2 * c2 + n3 * c3);
+            let rp = (n3 * c2 - n2 * c3) / (n3 * c2 + n2 * c3);
+            Some([(rs, rs, rs), (rp, rp, rp)])
+        })
+    }
+
+    /// Reflectance of the film over a metal with normal-incidence color `albedo`. The
+    /// metal is treated as an ideal conductor whose reflection flips the phase.
+    pub fn reflectance_over_metal(&self, cos_i: f64, albedo: Color) -> Color {
+        let r = |a: f64| -a.clamp(0.0, 1.0).sqrt();
+        let r23 = (r(albedo.r), r(albedo.g), r(albedo.b));
+        self.airy(cos_i, |_, _| Some([r23, r23]))
+    }
+
+    // Airy summation of the multiple reflections inside the film, averaged over the s
+    // and p polarizations. `bottom` gives the per-channel amplitude reflection
+    // coefficients at the film's lower interface for s and p light, or None if all
+    // light is reflected there.
+    fn airy(&self, cos_i: f64, bottom: impl Fn(f64, f64) -> Option<[(f64, f64, f64); 2]>) -> Color {
+        let one = Color::new(1.0, 1.0, 1.0);
+        let (n1, n2) = (1.0, self.ior);
+        let c1 = cos_i.clamp(0.0, 1.0);
+        let Some(c2) = refracted_cos(c1, n1, n2) else {
+            return one;
+        };
+        let Some(r23) = bottom(n2, c2) else {
+            return one;
+        };
+
+        let r12 = [
+            (n1 * c1 - n2 * c2) / (n1 * c1 + n2 * c2),
+            (n2 * c1 - n1 * c2) / (n2 * c1 + n1 * c2),
+        ];
+        let mut reflectance = [0.0; 3];
+        for (channel, lambda) in RGB_WAVELENGTHS.iter().enumerate() {
+            let phase = 4.0 * PI * n2 * self.thickness * c2 / lambda;
+            let mut sum = 0.0;
+            for (a, b) in r12.iter().zip(r23.iter()) {
+                let b = [b.0, b.1, b.2][channel];
+                let cross = 2.0 * a * b * phase.cos();
+                sum += (a * a + b * b + cross) / (1.0 + a * a * b * b + cross);
+            }
+            reflectance[channel] = (0.5 * sum).clamp(0.0, 1.0);
+        }
+        Color::new(reflectance[0], reflectance[1], reflectance[2])
+    }
+}
+
+// Cosine of the refracted angle going from index `n_from` into `n_to`, if light gets through.
+fn refracted_cos(cos_i: f64, n_from: f64, n_to: f64) -> Option<f64> {
+    let sin2_t = (n_from / n_to).powi(2) * (1.0 - cos_i * cos_i);
+    if sin2_t >= 1.0 {
+        return None;
+    }
+    Some((1.0 - sin2_t).sqrt())
+}
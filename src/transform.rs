@@ -0,0 +1,272 @@
+use crate::quat::look_basis;
+use crate::rtweekend::*;
+use std::ops::Mul;
+
+/// 4×4 matrix acting on column vectors, stored by rows. Points carry an implicit
+/// `w = 1` and pick up the translation column; vectors carry `w = 0` and don't.
+/// `a * b` applies `b` first, then `a`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat4 {
+    pub m: [[f64; 4]; 4],
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Mat4 {
+    pub const IDENTITY: Self = Self {
+        m: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ],
+    };
+
+    pub const fn new(m: [[f64; 4]; 4]) -> Self {
+        Self { m }
+    }
+
+    pub fn translation(offset: Vec3) -> Self {
+        let mut t = Self::IDENTITY;
+        t.m[0][3] = offset.x;
+        t.m[1][3] = offset.y;
+        t.m[2][3] = offset.z;
+        t
+    }
+
+    /// Scale by a factor per axis; negative factors mirror.
+    pub fn scale(factors: Vec3) -> Self {
+        let mut t = Self::IDENTITY;
+        t.m[0][0] = factors.x;
+        t.m[1][1] = factors.y;
+        t.m[2][2] = factors.z;
+        t
+    }
+
+    pub fn rotation(rotation: Quat) -> Self {
+        let x = rotation.rotate(Vec3::new(1.0, 0.0, 0.0));
+        let y = rotation.rotate(Vec3::new(0.0, 1.0, 0.0));
+        let z = rotation.rotate(Vec3::new(0.0, 0.0, 1.0));
+        Self::from_columns(x, y, z, Vec3::new(0.0, 0.0, 0.0))
+    }
+
+    /// Affine map sending the unit axes to `x`, `y`, `z` and the origin to `origin`.
+    pub fn from_columns(x: Vec3, y: Vec3, z: Vec3, origin: Vec3) -> Self {
+        Self::new([
+            [x.x, y.x, z.x, origin.x],
+            [x.y, y.y, z.y, origin.y],
+            [x.z, y.z, z.z, origin.z],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// Camera-to-world matrix for an eye at `from` looking at `at`: local -Z points at
+    /// the target and +Y leans towards `up`, as for `Quat::look_rotation`.
+    pub fn look_at(from: Point3, at: Point3, up: Vec3) -> Self {
+        let (u, v, w) = look_basis(at - from, up);
+        Self::from_columns(u, v, w, from)
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut t = Self::IDENTITY;
+        for (r, row) in t.m.iter_mut().enumerate() {
+            for (c, value) in row.iter_mut().enumerate() {
+                *value = self.m[c][r];
+            }
+        }
+        t
+    }
+
+    /// Inverse by Gauss-Jordan elimination with partial pivoting; `None` if the matrix
+    /// is singular, like a scale by zero.
+    pub fn inverse(&self) -> Option<Self> {
+        let mut a = self.m;
+        let mut inv = Self::IDENTITY.m;
+        for col in 0..4 {
+            let pivot = (col..4).max_by(|&r, &s| a[r][col].abs().total_cmp(&a[s][col].abs()))?;
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+            let scale = 1.0 / a[col][col];
+            for k in 0..4 {
+                a[col][k] *= scale;
+                inv[col][k] *= scale;
+            }
+            for row in 0..4 {
+                let factor = a[row][col];
+                if row == col || factor == 0.0 {
+                    continue;
+                }
+                for k in 0..4 {
+                    a[row][k] -= factor * a[col][k];
+                    inv[row][k] -= factor * inv[col][k];
+                }
+            }
+        }
+        Some(Self::new(inv))
+    }
+
+    /// Determinant of the upper-left 3×3 part: how the map scales volumes, negative if
+    /// it mirrors.
+    pub fn determinant3(&self) -> f64 {
+        let m = &self.m;
+        m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+    }
+
+    /// Upper bound on how much the map lengthens any vector: the sum of the lengths of
+    /// the first three columns.
+    pub fn max_stretch(&self) -> f64 {
+        (0..3)
+            .map(|c| Vec3::new(self.m[0][c], self.m[1][c], self.m[2][c]).length())
+            .sum()
+    }
+
+    pub fn point(&self, p: Point3) -> Point3 {
+        let m = &self.m;
+        let x = m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3];
+        let y = m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3];
+        let z = m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3];
+        let w = m[3][0] * p.x + m[3][1] * p.y + m[3][2] * p.z + m[3][3];
+        if w == 1.0 {
+            Point3::new(x, y, z)
+        } else {
+            Point3::new(x, y, z) / w
+        }
+    }
+
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+
+    /// Maps a surface normal, given this matrix's `inverse`: normals go through the
+    /// inverse transpose so they stay perpendicular to the surface under non-uniform
+    /// scale and shear. Not normalized.
+    pub fn normal(inverse: &Self, n: Vec3) -> Vec3 {
+        inverse.transpose().vector(n)
+    }
+
+    /// The ray with its origin and direction mapped. The direction is not normalized,
+    /// so `t` along it is the same before and after.
+    pub fn ray(&self, r: &Ray) -> Ray {
+        Ray {
+            orig: self.point(r.orig),
+            dir: self.vector(r.dir),
+            ..*r
+        }
+    }
+
+    /// Box around `bbox` after transforming it.
+    pub fn bounds(&self, bbox: &Aabb) -> Aabb {
+        if !bbox.is_bounded() {
+            return *bbox;
+        }
+        bbox.corners().iter().fold(Aabb::EMPTY, |acc, &c| {
+            let p = self.point(c);
+            acc.union(&Aabb::from_points(p, p))
+        })
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Self;
+
+    fn mul(self, o: Self) -> Self {
+        let mut m = [[0.0; 4]; 4];
+        for (r, row) in m.iter_mut().enumerate() {
+            for (c, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[r][k] * o.m[k][c]).sum();
+            }
+        }
+        Self::new(m)
+    }
+}
+
+impl From<Transform> for Mat4 {
+    fn from(t: Transform) -> Self {
+        Self::translation(t.translation)
+            * Self::rotation(t.rotation)
+            * Self::scale(Vec3::new(t.scale, t.scale, t.scale))
+    }
+}
+
+/// An object placed by any affine map, including non-uniform scale, shear and mirroring,
+/// which the similarity `Transform` of a `SceneNode` can't express. Rays are taken into
+/// the object's space by the inverse, which is computed once up front.
+pub struct Transformed {
+    object: Arc<dyn Hittable>,
+    matrix: Mat4,
+    inverse: Mat4,
+    bbox: Aabb,
+}
+
+impl Transformed {
+    /// `None` if `matrix` can't be inverted.
+    pub fn new(object: Arc<dyn Hittable>, matrix: Mat4) -> Option<Self> {
+        let inverse = matrix.inverse()?;
+        let bbox = matrix.bounds(&object.bounding_box());
+        Some(Self {
+            object,
+            matrix,
+            inverse,
+            bbox,
+        })
+    }
+
+    pub fn matrix(&self) -> &Mat4 {
+        &self.matrix
+    }
+}
+
+impl Hittable for Transformed {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let mut rec = self.object.hit(&self.inverse.ray(r), ray_t)?;
+        rec.p = self.matrix.point(rec.p);
+        rec.normal = Mat4::normal(&self.inverse, rec.normal).normalized();
+        rec.geometric_normal = Mat4::normal(&self.inverse, rec.geometric_normal).normalized();
+        rec.tangent = self.matrix.vector(rec.tangent);
+        rec.p_error *= self.matrix.max_stretch();
+        rec.uv_scale *= self.matrix.determinant3().abs().cbrt();
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    // Directions don't keep their solid angle through a general linear map A: a unit
+    // direction d in object space becomes A d / |A d|, stretching solid angle by
+    // |det A| / |A d|^3, so the density shrinks by the same factor.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let local = self.inverse.vector(direction).normalized();
+        let pdf = self.object.pdf_value(self.inverse.point(origin), local);
+        let stretch = self.matrix.vector(local).length();
+        pdf * stretch * stretch * stretch / self.matrix.determinant3().abs()
+    }
+
+    fn random(&self, origin: Point3, rng: &mut SmallRng) -> Vec3 {
+        let local = self.object.random(self.inverse.point(origin), rng);
+        self.matrix.vector(local)
+    }
+
+    fn name(&self) -> Option<&str> {
+        self.object.name()
+    }
+
+    fn named_bounds(&self, name: &str) -> Option<Aabb> {
+        let local = self.object.named_bounds(name)?;
+        Some(self.matrix.bounds(&local))
+    }
+}
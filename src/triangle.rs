@@ -0,0 +1,101 @@
+use crate::rtweekend::*;
+
+pub struct Triangle {
+    pub v0: Point3,
+    pub v1: Point3,
+    pub v2: Point3,
+    pub mat: Arc<dyn Material + Send + Sync>,
+    normal: Vec3,
+    /// Vertex normals interpolated for the shading normal, if any.
+    normals: Option<[Vec3; 3]>,
+    sides: Sides,
+}
+
+impl Triangle {
+    pub fn new(v0: Point3, v1: Point3, v2: Point3, mat: Arc<dyn Material + Send + Sync>) -> Self {
+        let normal = (v1 - v0).cross(v2 - v0).normalized();
+        Self {
+            v0,
+            v1,
+            v2,
+            mat,
+            normal,
+            normals: None,
+            sides: Sides::Both,
+        }
+    }
+
+    /// Only `sides` can be hit. Rays reaching a culled side are turned away before the
+    /// rest of the test, which makes one-sided closed meshes cheaper to trace.
+    pub fn with_sides(self, sides: Sides) -> Self {
+        Self { sides, ..self }
+    }
+
+    /// Shades with normals interpolated from ones given at `v0`, `v1` and `v2`, facing
+    /// the same side as the winding normal, so a mesh of flat triangles looks smooth.
+    /// Hits, shadows and ray offsets still use the flat triangle.
+    pub fn with_normals(self, normals: [Vec3; 3]) -> Self {
+        Self {
+            normals: Some(normals),
+            ..self
+        }
+    }
+}
+
+impl Hittable for Triangle {
+    // Möller–Trumbore; (u, v) are the barycentric weights of v1 and v2.
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        ray_stats::count(Counter::TriangleTests);
+        let edge1 = self.v1 - self.v0;
+        let edge2 = self.v2 - self.v0;
+        let pvec = r.dir.cross(edge2);
+        let det = edge1.dot(pvec);
+        // The ray arrives at the front, against the winding normal, when det > 0.
+        if det.abs() < 1e-12 || !self.sides.accepts(det > 0.0) {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+
+        let tvec = r.orig - self.v0;
+        let u = tvec.dot(pvec) * inv_det;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+
+        let qvec = tvec.cross(edge1);
+        let v = r.dir.dot(qvec) * inv_det;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+
+        let t = edge2.dot(qvec) * inv_det;
+        if !ray_t.surrounds(t) {
+            return None;
+        }
+
+        let mut rec = HitRecord {
+            p: r.at(t),
+            t,
+            uv: Vec2::new(u, v),
+            tangent: edge1,
+            uv_scale: edge1.cross(edge2).length().sqrt(),
+            mat: self.mat.clone(),
+            ..Default::default()
+        };
+        rec.set_face_normal(r, self.normal);
+        if let Some([n0, n1, n2]) = self.normals {
+            let shading = (1.0 - u - v) * n0 + u * n1 + v * n2;
+            if !shading.near_zero() {
+                rec.set_shading_normal(shading.normalized());
+            }
+        }
+
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::from_points(self.v0, self.v1)
+            .union(&Aabb::from_points(self.v2, self.v2))
+            .pad(1e-4)
+    }
+}
@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+/// Length unit a scene (or an imported asset) is authored in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Unit {
+    Millimeters,
+    Centimeters,
+    #[default]
+    Meters,
+    Inches,
+    Feet,
+}
+
+/// Ray-offset epsilon used for a scene authored in meters (1 mm).
+const BASE_EPSILON_METERS: f64 = 0.001;
+
+impl Unit {
+    pub fn meters_per_unit(self) -> f64 {
+        match self {
+            Unit::Millimeters => 0.001,
+            Unit::Centimeters => 0.01,
+            Unit::Meters => 1.0,
+            Unit::Inches => 0.0254,
+            Unit::Feet => 0.3048,
+        }
+    }
+
+    /// Factor that converts lengths in `self` into lengths in `target`.
+    pub fn scale_to(self, target: Unit) -> f64 {
+        self.meters_per_unit() / target.meters_per_unit()
+    }
+
+    /// Small distance expressed in this unit, so it stays ~1 mm in the world.
+    pub fn epsilon(self) -> f64 {
+        BASE_EPSILON_METERS / self.meters_per_unit()
+    }
+
+    /// Multiplier for inverse-square falloff computed with distances in this unit,
+    /// so light intensities are always specified per square meter.
+    pub fn falloff_scale(self) -> f64 {
+        let m = self.meters_per_unit();
+        1.0 / (m * m)
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "mm" | "millimeter" | "millimeters" => Some(Unit::Millimeters),
+            "cm" | "centimeter" | "centimeters" => Some(Unit::Centimeters),
+            "m" | "meter" | "meters" => Some(Unit::Meters),
+            "in" | "inch" | "inches" => Some(Unit::Inches),
+            "ft" | "foot" | "feet" => Some(Unit::Feet),
+            _ => None,
+        }
+    }
+}
@@ -1,8 +1,9 @@
 use crate::rtweekend::*;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::ops::{Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub};
 
-#[derive(Debug, Copy, Clone, PartialEq, Default)]
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
 pub struct Vec3 {
     pub x: f64,
     pub y: f64,
@@ -10,7 +11,7 @@ pub struct Vec3 {
 }
 
 impl Vec3 {
-    pub fn new(x: f64, y: f64, z: f64) -> Self {
+    pub const fn new(x: f64, y: f64, z: f64) -> Self {
         Self { x, y, z }
     }
 
@@ -51,37 +52,62 @@ impl Vec3 {
         *self / self.length()
     }
 
-    pub fn random() -> Self {
+    pub fn random(rng: &mut SmallRng) -> Self {
         Self {
-            x: random_f64(),
-            y: random_f64(),
-            z: random_f64(),
+            x: random_f64(rng),
+            y: random_f64(rng),
+            z: random_f64(rng),
         }
     }
 
-    pub fn random_interval(min: f64, max: f64) -> Self {
+    pub fn random_interval(rng: &mut SmallRng, min: f64, max: f64) -> Self {
         Self {
-            x: random_f64_range(min, max),
-            y: random_f64_range(min, max),
-            z: random_f64_range(min, max),
+            x: random_f64_range(rng, min, max),
+            y: random_f64_range(rng, min, max),
+            z: random_f64_range(rng, min, max),
         }
     }
 
-    pub fn random_in_unit_sphere() -> Self {
-        loop {
-            let p = Self::random_interval(-1.0, 1.0);
-            if p.length_squared() < 1.0 {
-                return p;
-            }
-        }
+    /// Uniform point in the unit ball: a uniform direction, pushed out by the cube root
+    /// of a uniform number so the density is even in volume.
+    pub fn random_in_unit_sphere(rng: &mut SmallRng) -> Self {
+        Self::random_unit_vector(rng) * random_f64(rng).cbrt()
+    }
+
+    pub fn random_unit_vector(rng: &mut SmallRng) -> Self {
+        Self::sample_unit_sphere(random_f64(rng), random_f64(rng))
+    }
+
+    /// Maps two uniform numbers in [0, 1) to a uniform direction, area-preserving
+    /// (Archimedes' cylinder projection): `u1` picks the height and `u2` the angle.
+    /// Stratified or low-discrepancy inputs stay well spread on the sphere.
+    pub fn sample_unit_sphere(u1: f64, u2: f64) -> Self {
+        let z = 1.0 - 2.0 * u1;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let (sin, cos) = (2.0 * PI * u2).sin_cos();
+        Self::new(r * cos, r * sin, z)
     }
 
-    pub fn random_unit_vector() -> Self {
-        Self::random_in_unit_sphere().normalized()
+    /// Maps two uniform numbers in [0, 1) to a uniform point in the unit disk in the
+    /// xy plane, with Shirley and Chiu's concentric mapping: squares around the center
+    /// of [-1, 1]² become rings, so nearby inputs stay nearby on the disk.
+    pub fn sample_unit_disk(u1: f64, u2: f64) -> Self {
+        let a = 2.0 * u1 - 1.0;
+        let b = 2.0 * u2 - 1.0;
+        if a == 0.0 && b == 0.0 {
+            return Self::new(0.0, 0.0, 0.0);
+        }
+        let (r, theta) = if a.abs() > b.abs() {
+            (a, PI / 4.0 * (b / a))
+        } else {
+            (b, PI / 2.0 - PI / 4.0 * (a / b))
+        };
+        let (sin, cos) = theta.sin_cos();
+        Self::new(r * cos, r * sin, 0.0)
     }
 
-    pub fn random_on_hemisphere(normal: &Self) -> Self {
-        let on_unit_sphere = Self::random_unit_vector();
+    pub fn random_on_hemisphere(rng: &mut SmallRng, normal: &Self) -> Self {
+        let on_unit_sphere = Self::random_unit_vector(rng);
         if on_unit_sphere.dot(*normal) > 0.0 {
             on_unit_sphere
         } else {
@@ -113,17 +139,8 @@ impl Vec3 {
     //     r_out_perp + r_out_parallel
     //    }
 
-    pub fn random_in_unit_disk() -> Self {
-        loop {
-            let p = Vec3::new(
-                random_f64_range(-1.0, 1.0),
-                random_f64_range(-1.0, 1.0),
-                0.0,
-            );
-            if p.length_squared() < 1.0 {
-                return p;
-            }
-        }
+    pub fn random_in_unit_disk(rng: &mut SmallRng) -> Self {
+        Self::sample_unit_disk(random_f64(rng), random_f64(rng))
     }
 }
 
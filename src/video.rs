@@ -0,0 +1,86 @@
+use crate::output::ImageFormat;
+use crate::rtweekend::*;
+use std::io;
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+
+/// Extensions `VideoEncoder` picks a codec for.
+pub const VIDEO_EXTENSIONS: [&str; 4] = ["mp4", "mov", "mkv", "webm"];
+
+pub fn is_video_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| VIDEO_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Streams rendered frames into an `ffmpeg` process as PPM images, so an animation
+/// comes out as a single video file without writing the frames to disk.
+pub struct VideoEncoder {
+    child: Child,
+    stdin: Option<ChildStdin>,
+}
+
+impl VideoEncoder {
+    /// Starts `ffmpeg` writing to `path`. The codec follows the extension: VP9 for
+    /// .webm, H.264 otherwise.
+    pub fn new(path: impl AsRef<Path>, fps: f64) -> io::Result<Self> {
+        let path = path.as_ref();
+        let webm = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("webm"));
+        let codec: &[&str] = if webm {
+            &["-c:v", "libvpx-vp9", "-b:v", "0", "-crf", "30"]
+        } else {
+            &["-c:v", "libx264", "-crf", "18"]
+        };
+        let mut child = Command::new("ffmpeg")
+            .args([
+                "-y",
+                "-loglevel",
+                "error",
+                "-f",
+                "image2pipe",
+                "-c:v",
+                "ppm",
+            ])
+            .args(["-framerate", &fps.to_string(), "-i", "-"])
+            .args(codec)
+            // Players expect 4:2:0, and x264 needs even dimensions for it.
+            .args([
+                "-pix_fmt",
+                "yuv420p",
+                "-vf",
+                "pad=ceil(iw/2)*2:ceil(ih/2)*2",
+            ])
+            .arg(path)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "ffmpeg not found; install it or render an image sequence instead",
+                ),
+                _ => e,
+            })?;
+        let stdin = child.stdin.take();
+        Ok(Self { child, stdin })
+    }
+
+    pub fn write_frame(&mut self, camera: &Camera, scene: &Scene) -> io::Result<()> {
+        let stdin = self
+            .stdin
+            .as_mut()
+            .ok_or_else(|| io::Error::other("video encoder already finished"))?;
+        camera.render_to(scene, stdin, ImageFormat::Ppm)
+    }
+
+    /// Closes the stream and waits for `ffmpeg` to finish writing the file.
+    pub fn finish(mut self) -> io::Result<()> {
+        drop(self.stdin.take());
+        let status = self.child.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!("ffmpeg exited with {}", status)));
+        }
+        Ok(())
+    }
+}
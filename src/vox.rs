@@ -0,0 +1,247 @@
+use crate::rtweekend::*;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Material settings from a MATL chunk, keyed by palette index.
+#[derive(Debug, Clone, Default)]
+pub struct VoxMaterial {
+    pub kind: String,
+    pub roughness: f64,
+    pub ior: f64,
+    pub emission: f64,
+}
+
+/// Contents of a MagicaVoxel `.vox` file. Models are converted to Y-up grids.
+pub struct VoxFile {
+    pub models: Vec<VoxelGrid>,
+    /// Linear RGB per color index (index 0 is unused).
+    pub palette: Vec<Color>,
+    pub materials: HashMap<u8, VoxMaterial>,
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("vox: {}", msg))
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.bytes.len());
+        let end = end.ok_or_else(|| invalid("unexpected end of file"))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        let b = self.take(4)?;
+        Ok(i32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.i32()?.max(0) as usize;
+        Ok(String::from_utf8_lossy(self.take(len)?).into_owned())
+    }
+}
+
+// MagicaVoxel's built-in palette: a 6x6x6 color cube without black, then red, green,
+// blue and gray ramps. Used when the file has no RGBA chunk.
+fn default_palette() -> Vec<Color> {
+    let mut palette = vec![Color::BLACK];
+    let steps = [0xff, 0xcc, 0x99, 0x66, 0x33, 0x00];
+    for &r in &steps {
+        for &g in &steps {
+            for &b in &steps {
+                if palette.len() < 216 {
+                    palette.push(srgb_bytes_to_linear(r, g, b));
+                }
+            }
+        }
+    }
+    let ramp = [0xee, 0xdd, 0xbb, 0xaa, 0x88, 0x77, 0x55, 0x44, 0x22, 0x11];
+    for channel in 0..4 {
+        for &v in &ramp {
+            palette.push(match channel {
+                0 => srgb_bytes_to_linear(v, 0, 0),
+                1 => srgb_bytes_to_linear(0, v, 0),
+                2 => srgb_bytes_to_linear(0, 0, v),
+                _ => srgb_bytes_to_linear(v, v, v),
+            });
+        }
+    }
+    palette
+}
+
+fn srgb_bytes_to_linear(r: u8, g: u8, b: u8) -> Color {
+    // Palettes are authored in display space; the renderer works in (approximately) linear space.
+    let f = |c: u8| (c as f64 / 255.0).powi(2);
+    Color::new(f(r), f(g), f(b))
+}
+
+pub fn parse_vox(bytes: &[u8]) -> io::Result<VoxFile> {
+    let mut reader = Reader { bytes, pos: 0 };
+    if reader.take(4)? != b"VOX " {
+        return Err(invalid("missing 'VOX ' header"));
+    }
+    let _version = reader.i32()?;
+
+    let mut models = Vec::new();
+    let mut palette = default_palette();
+    let mut materials = HashMap::new();
+    let mut pending_size: Option<(u32, u32, u32)> = None;
+
+    while reader.pos < bytes.len() {
+        let id = reader.take(4)?;
+        let content_size = reader.i32()?.max(0) as usize;
+        let _children_size = reader.i32()?;
+
+        // MAIN has no content of its own; its children follow inline.
+        if id == b"MAIN" {
+            reader.take(content_size)?;
+            continue;
+        }
+
+        let mut chunk = Reader {
+            bytes: reader.take(content_size)?,
+            pos: 0,
+        };
+        match id {
+            b"SIZE" => {
+                let x = chunk.i32()?.max(0) as u32;
+                let y = chunk.i32()?.max(0) as u32;
+                let z = chunk.i32()?.max(0) as u32;
+                pending_size = Some((x, y, z));
+            }
+            b"XYZI" => {
+                let (sx, sy, sz) = pending_size
+                    .take()
+                    .ok_or_else(|| invalid("XYZI before SIZE"))?;
+                // MagicaVoxel is Z-up; store Y-up with the old +y flipped to keep handedness.
+                let mut grid = VoxelGrid::new((sx, sz, sy));
+                let count = chunk.i32()?.max(0) as usize;
+                for _ in 0..count {
+                    let v = chunk.take(4)?;
+                    let (x, y, z, color) = (v[0] as u32, v[1] as u32, v[2] as u32, v[3]);
+                    if x < sx && y < sy && z < sz {
+                        grid.set(x, z, sy - 1 - y, color);
+                    }
+                }
+                models.push(grid);
+            }
+            b"RGBA" => {
+                let mut rgba = vec![Color::BLACK];
+                for _ in 0..255 {
+                    let c = chunk.take(4)?;
+                    rgba.push(srgb_bytes_to_linear(c[0], c[1], c[2]));
+                }
+                palette = rgba;
+            }
+            b"MATL" => {
+                let index = chunk.i32()?;
+                let pairs = chunk.i32()?.max(0);
+                let mut mat = VoxMaterial {
+                    kind: "_diffuse".to_string(),
+                    ior: 1.5,
+                    ..Default::default()
+                };
+                for _ in 0..pairs {
+                    let key = chunk.string()?;
+                    let value = chunk.string()?;
+                    let number = value.parse::<f64>().unwrap_or(0.0);
+                    match key.as_str() {
+                        "_type" => mat.kind = value,
+                        "_rough" => mat.roughness = number,
+                        "_ri" => mat.ior = number,
+                        "_ior" => mat.ior = 1.0 + number,
+                        "_emit" => mat.emission = number,
+                        _ => {}
+                    }
+                }
+                if (1..=255).contains(&index) {
+                    materials.insert(index as u8, mat);
+                }
+            }
+            // Scene graph, layers, cameras, etc. are not used.
+            _ => {}
+        }
+    }
+
+    if models.is_empty() {
+        return Err(invalid("no models found"));
+    }
+
+    Ok(VoxFile {
+        models,
+        palette,
+        materials,
+    })
+}
+
+pub fn load_vox(path: impl AsRef<Path>) -> io::Result<VoxFile> {
+    parse_vox(&fs::read(path)?)
+}
+
+impl VoxFile {
+    /// One material per color index, honoring MATL settings (metal, glass, emissive)
+    /// and falling back to Lambertian palette colors.
+    pub fn palette_materials(&self) -> Vec<Arc<dyn Material + Send + Sync>> {
+        self.palette
+            .iter()
+            .enumerate()
+            .map(|(index, &color)| -> Arc<dyn Material + Send + Sync> {
+                match self.materials.get(&(index as u8)) {
+                    Some(mat) if mat.kind == "_metal" => Arc::new(Metal::new(color, mat.roughness)),
+                    Some(mat) if mat.kind == "_glass" => Arc::new(Dielectric::new(mat.ior)),
+                    Some(mat) if mat.kind == "_emit" && mat.emission > 0.0 => {
+                        Arc::new(DiffuseLight::new(color * (mat.emission * 10.0)))
+                    }
+                    _ => Arc::new(Lambertian::new(color)),
+                }
+            })
+            .collect()
+    }
+
+    /// Builds an octree for model `index` with its minimum corner at `origin`.
+    pub fn to_svo(&self, index: usize, origin: Point3, voxel_size: f64) -> SparseVoxelOctree {
+        SparseVoxelOctree::new(
+            &self.models[index],
+            origin,
+            voxel_size,
+            self.palette_materials(),
+        )
+    }
+
+    /// Builds one box per filled voxel of model `index` (simple, but far heavier than `to_svo`).
+    pub fn to_boxes(&self, index: usize, origin: Point3, voxel_size: f64) -> HittableList {
+        let grid = &self.models[index];
+        let materials = self.palette_materials();
+        let mut boxes = HittableList::new();
+        for z in 0..grid.dims.2 {
+            for y in 0..grid.dims.1 {
+                for x in 0..grid.dims.0 {
+                    let value = grid.get(x, y, z);
+                    if value == 0 {
+                        continue;
+                    }
+                    let min = origin + voxel_size * Vec3::new(x as f64, y as f64, z as f64);
+                    let max = min + Vec3::new(voxel_size, voxel_size, voxel_size);
+                    boxes.add(Arc::new(make_box(
+                        min,
+                        max,
+                        materials[value as usize].clone(),
+                    )));
+                }
+            }
+        }
+        boxes
+    }
+}
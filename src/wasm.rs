@@ -0,0 +1,58 @@
+use crate::rtweekend::*;
+use crate::scene_file::{MAIN_CAMERA, SceneFile};
+use std::io;
+use wasm_bindgen::prelude::*;
+
+// JavaScript bindings for a wasm32 build, made with
+//
+//     cargo rustc --lib --release --target wasm32-unknown-unknown \
+//         --no-default-features --features wasm --crate-type cdylib
+//     wasm-bindgen --target web --out-dir pkg \
+//         target/wasm32-unknown-unknown/release/ray_tracing_weekend.wasm
+//
+// and used as
+//
+//     import init, { renderJson } from "./pkg/ray_tracing_weekend.js";
+//     await init();
+//     const image = renderJson(sceneJson, 320);
+//     const rgba = new Uint8ClampedArray(image.pixels);
+//     ctx.putImageData(new ImageData(rgba, image.width, image.height), 0, 0);
+//     image.free();
+
+/// A render as RGBA bytes, ready for a canvas: `width * height * 4` of them, row-major,
+/// top row first.
+#[wasm_bindgen(getter_with_clone)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// Renders the scene file `json` through its camera called `name`, `width` pixels wide if
+/// given. Without the `parallel` feature, as in a browser, it runs on the calling thread.
+pub fn render_rgba(json: &str, name: &str, width: Option<u32>) -> io::Result<RgbaImage> {
+    let file = SceneFile::from_json(json)?;
+    let mut camera = file.camera(name).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidInput, format!("no camera `{}`", name))
+    })?;
+    if let Some(width) = width {
+        camera.image_width = width;
+        camera.initialize();
+    }
+    let (_, mut scene) = file.build();
+    scene.accelerate(Accelerator::default());
+    let pixels = camera.render_pixels(&scene);
+    Ok(RgbaImage {
+        width: camera.film_width(),
+        height: camera.film_height(),
+        pixels: to_rgba8(&pixels),
+    })
+}
+
+/// Renders the scene file `json` through its main camera, `width` pixels wide (0 keeps
+/// the file's). Throws with the reason if the scene can't be read.
+#[wasm_bindgen(js_name = renderJson)]
+pub fn render_json(json: &str, width: u32) -> Result<RgbaImage, JsError> {
+    render_rgba(json, MAIN_CAMERA, (width > 0).then_some(width))
+        .map_err(|e| JsError::new(&e.to_string()))
+}
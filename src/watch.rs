@@ -0,0 +1,157 @@
+use crate::interrupt;
+use crate::output::ImageFormat;
+use crate::rtweekend::*;
+use crate::scene_file::SceneFile;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Notices when a file is saved, by polling its modification time and size.
+pub struct FileWatcher {
+    path: PathBuf,
+    stamp: Option<(SystemTime, u64)>,
+}
+
+impl FileWatcher {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let stamp = Self::stamp(&path);
+        Self { path, stamp }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the file changed since the last call, or since the watcher was made.
+    /// A file that can't be read counts as unchanged until it comes back.
+    pub fn changed(&mut self) -> bool {
+        let stamp = Self::stamp(&self.path);
+        if stamp.is_none() || stamp == self.stamp {
+            return false;
+        }
+        self.stamp = stamp;
+        true
+    }
+
+    fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+        let meta = fs::metadata(path).ok()?;
+        Some((meta.modified().ok()?, meta.len()))
+    }
+}
+
+/// Progressive preview of a scene file for look development: renders pass after pass,
+/// writing the running average to `output` after each one, and starts over with the
+/// new scene whenever the file is saved. Changes are picked up between passes, so
+/// small `pass_samples` keep it responsive. A file that fails to load is reported and
+/// the previous scene kept until the next save.
+pub struct LivePreview {
+    pub output: PathBuf,
+    /// Camera in the scene file to look through.
+    pub camera: String,
+    /// Samples per pixel each pass adds.
+    pub pass_samples: u32,
+    /// Passes after which accumulation stops until the next change; `None` keeps
+    /// refining for as long as it runs.
+    pub max_passes: Option<u32>,
+    /// How often to check the file once accumulation has stopped.
+    pub poll_interval: Duration,
+    pub accelerator: Accelerator,
+}
+
+impl LivePreview {
+    pub fn new(output: impl AsRef<Path>) -> Self {
+        Self {
+            output: output.as_ref().to_path_buf(),
+            camera: crate::scene_file::MAIN_CAMERA.to_string(),
+            pass_samples: 4,
+            max_passes: None,
+            poll_interval: Duration::from_millis(250),
+            accelerator: Accelerator::default(),
+        }
+    }
+
+    /// Watches and renders `path` until interrupted. Only a scene that can't be loaded
+    /// at the start is an error.
+    pub fn run(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut watcher = FileWatcher::new(&path);
+        let (mut camera, mut scene) = self.load(watcher.path())?;
+        log::info!(target: "render", "Watching {} for changes", watcher.path().display());
+        loop {
+            let mut sum = vec![Color::BLACK; (camera.film_width() * camera.film_height()) as usize];
+            let mut passes = 0;
+            loop {
+                if interrupt::requested() {
+                    return Ok(());
+                }
+                if watcher.changed() {
+                    match self.load(watcher.path()) {
+                        Ok((c, s)) => {
+                            (camera, scene) = (c, s);
+                            log::info!(target: "render", "Scene changed, starting over");
+                            break;
+                        }
+                        Err(e) => log::warn!(target: "loader", "Keeping the previous scene: {}", e),
+                    }
+                }
+                if self.max_passes.is_some_and(|max| passes >= max) {
+                    thread::sleep(self.poll_interval);
+                    continue;
+                }
+                // A seeded scene's passes take consecutive seeds, so each adds new samples
+                // instead of repeating the first pass.
+                let mut pass = camera.clone();
+                pass.seed = camera.seed.map(|seed| seed.wrapping_add(passes as u64));
+                let pixels = pass.render_pixels(&scene);
+                if interrupt::requested() {
+                    // The pass was cut short; the last whole one is written already.
+                    return Ok(());
+                }
+                for (total, c) in sum.iter_mut().zip(pixels) {
+                    *total += c;
+                }
+                passes += 1;
+                let average: Vec<Color> = sum.iter().map(|&c| c / passes as f64).collect();
+                self.write(&camera, &average)?;
+                log::info!(
+                    target: "render",
+                    "Pass {} ({} samples per pixel) written to {}",
+                    passes,
+                    passes * camera.samples_per_pixel,
+                    self.output.display()
+                );
+            }
+        }
+    }
+
+    fn load(&self, path: &Path) -> io::Result<(Camera, Scene)> {
+        let file = SceneFile::load(path)?;
+        let mut camera = file.camera(&self.camera).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("no camera `{}`", self.camera),
+            )
+        })?;
+        camera.samples_per_pixel = self.pass_samples;
+        camera.initialize();
+        let (_, mut scene) = file.build();
+        scene.accelerate(self.accelerator);
+        Ok((camera, scene))
+    }
+
+    // Written beside the output and renamed over it, so viewers reloading the image
+    // never see half a file.
+    fn write(&self, camera: &Camera, pixels: &[Color]) -> io::Result<()> {
+        let partial = self.output.with_extension("partial");
+        let format = ImageFormat::from_path(&self.output).unwrap_or_default();
+        format.write(
+            fs::File::create(&partial)?,
+            camera.film_width(),
+            camera.film_height(),
+            pixels,
+        )?;
+        fs::rename(&partial, &self.output)
+    }
+}
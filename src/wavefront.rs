@@ -0,0 +1,172 @@
+use crate::integrator::{PathState, intersect};
+use crate::parallel::*;
+use crate::rtweekend::*;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+
+/// How tiles are rendered on the CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Backend {
+    /// Each sample's path is traced start to finish before the next one begins.
+    #[default]
+    Megakernel,
+    /// Paths advance together a bounce at a time; see `WavefrontDevice`.
+    Wavefront,
+}
+
+impl Backend {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "megakernel" => Some(Self::Megakernel),
+            "wavefront" => Some(Self::Wavefront),
+            _ => None,
+        }
+    }
+}
+
+/// Renders tiles as waves of paths that advance together a bounce at a time, in stages
+/// over whole buffers: generate camera rays, intersect them all, shade all the hits
+/// (scattering the next rays), then compact away the paths that ended. Each stage is
+/// one uniform loop over the buffer, the shape SIMD and GPU kernels want, rather than
+/// every path running the whole integrator on its own.
+///
+/// It traces with the camera's path tracer; tiles for the diagnostic integrators are
+/// rendered the usual way.
+#[derive(Debug, Clone, Copy)]
+pub struct WavefrontDevice {
+    /// Most paths in flight at once per tile. Bigger waves keep the stages busier at
+    /// the cost of memory.
+    pub wave_size: usize,
+}
+
+impl Default for WavefrontDevice {
+    fn default() -> Self {
+        Self { wave_size: 1 << 16 }
+    }
+}
+
+// A path in flight and where its sample lands on the film.
+struct WavePath {
+    ray: Ray,
+    state: PathState,
+    /// Factor from the camera: exposure, vignetting and spectral or channel weights.
+    weight: Color,
+    x: f64,
+    y: f64,
+    /// The sample's own generator, split off its pixel's, so paths can shade in any
+    /// order and still draw the same numbers.
+    rng: SmallRng,
+}
+
+impl WavePath {
+    // What the path adds to the film, flagged if it isn't finite.
+    fn color(&self, camera: &Camera) -> Color {
+        let color = self.state.radiance * self.weight;
+        camera.check_sample(self.x as u32, self.y as u32, color, self.state.culprit)
+    }
+}
+
+impl WavefrontDevice {
+    pub fn new(wave_size: usize) -> Self {
+        Self {
+            wave_size: wave_size.max(1),
+        }
+    }
+
+    // Traces `paths` to the end, bounce by bounce, adding each to the film as it ends.
+    fn trace_wave(
+        &self,
+        tracer: &PathTracer,
+        camera: &Camera,
+        scene: &Scene,
+        mut paths: Vec<WavePath>,
+        film_tile: &mut FilmTile,
+    ) {
+        for bounce in 0..camera.max_depth {
+            if paths.is_empty() {
+                return;
+            }
+            // Intersect.
+            let hits: Vec<Option<HitRecord>> = paths
+                .par_iter()
+                .map(|path| intersect(&path.ray, bounce, scene))
+                .collect();
+            // Shade.
+            let next: Vec<Option<Ray>> = paths
+                .par_iter_mut()
+                .zip(hits)
+                .map(|(path, hit)| {
+                    tracer.shade(
+                        &path.ray,
+                        hit,
+                        bounce,
+                        scene,
+                        &mut path.state,
+                        &mut path.rng,
+                        None,
+                    )
+                })
+                .collect();
+            // Compact.
+            let mut alive = Vec::with_capacity(paths.len());
+            for (mut path, next) in paths.into_iter().zip(next) {
+                match next {
+                    Some(ray) => {
+                        path.ray = ray;
+                        alive.push(path);
+                    }
+                    None => film_tile.add_sample(path.x, path.y, path.color(camera)),
+                }
+            }
+            paths = alive;
+        }
+        // Cut off by max_depth.
+        for path in paths {
+            film_tile.add_sample(path.x, path.y, path.color(camera));
+        }
+    }
+}
+
+impl RenderDevice for WavefrontDevice {
+    fn name(&self) -> String {
+        format!("wavefront ({} paths per wave)", self.wave_size)
+    }
+
+    fn render_tile(&self, camera: &Camera, scene: &Scene, film_tile: &mut FilmTile) {
+        let Some(tracer) = camera.path_tracer() else {
+            return camera.render_tile(scene, film_tile);
+        };
+        let tile = film_tile.tile;
+        let exposure = camera.exposure_scale();
+        let mut samples = (0..tile.pixel_count()).flat_map(|idx| {
+            let i = tile.x + idx as u32 % tile.width;
+            let j = tile.y + idx as u32 / tile.width;
+            let mut rng = camera.pixel_rng(i, j);
+            (0..camera.pixel_samples(i, j)).map(move |_| (i, j, SmallRng::from_rng(&mut rng)))
+        });
+
+        loop {
+            // Generate.
+            let pixels: Vec<(u32, u32, SmallRng)> = samples.by_ref().take(self.wave_size).collect();
+            if pixels.is_empty() {
+                return;
+            }
+            let paths = pixels
+                .into_par_iter()
+                .map(|(i, j, mut rng)| {
+                    let offset = camera.sample_square(&mut rng);
+                    let (ray, weight) = camera.generate_ray(i, j, offset, &mut rng);
+                    WavePath {
+                        ray,
+                        state: PathState::new(),
+                        weight: weight * exposure,
+                        x: i as f64 + 0.5 + offset.x,
+                        y: j as f64 + 0.5 + offset.y,
+                        rng,
+                    }
+                })
+                .collect();
+            self.trace_wave(&tracer, camera, scene, paths, film_tile);
+        }
+    }
+}
@@ -0,0 +1,269 @@
+// Invariants of the core math, checked on many random inputs. Every property draws
+// from its own fixed seed, so a failure reproduces and reports the input that broke it.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use ray_tracing_weekend::microfacet::{Ggx, fresnel_dielectric};
+use ray_tracing_weekend::rtweekend::*;
+use std::fmt::Debug;
+
+const CASES: u32 = 2000;
+const EPS: f64 = 1e-9;
+
+fn check<T: Debug>(
+    seed: u64,
+    generate: impl Fn(&mut StdRng) -> T,
+    property: impl Fn(&T) -> Result<(), String>,
+) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    for case in 0..CASES {
+        let input = generate(&mut rng);
+        if let Err(message) = property(&input) {
+            panic!("case {case} failed: {message}\ninput: {input:?}");
+        }
+    }
+}
+
+fn close(a: f64, b: f64, what: &str) -> Result<(), String> {
+    if (a - b).abs() <= 1e-6 * (1.0 + a.abs().max(b.abs())) {
+        Ok(())
+    } else {
+        Err(format!("{what}: {a} != {b}"))
+    }
+}
+
+fn unit_vector(rng: &mut StdRng) -> Vec3 {
+    Vec3::sample_unit_sphere(rng.random(), rng.random())
+}
+
+fn point(rng: &mut StdRng, extent: f64) -> Point3 {
+    let mut coord = || rng.random_range(-extent..extent);
+    Point3::new(coord(), coord(), coord())
+}
+
+fn gray() -> Arc<dyn Material + Send + Sync> {
+    Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))
+}
+
+#[test]
+fn reflect_keeps_length_and_mirrors_the_normal_component() {
+    check(
+        1,
+        |rng| {
+            (
+                unit_vector(rng) * rng.random_range(0.1..10.0),
+                unit_vector(rng),
+            )
+        },
+        |&(v, n)| {
+            let r = Vec3::reflect(&v, &n);
+            close(r.length(), v.length(), "length")?;
+            close(r.dot(n), -v.dot(n), "normal component")?;
+            close(
+                (Vec3::reflect(&r, &n) - v).length(),
+                0.0,
+                "reflecting twice",
+            )
+        },
+    );
+}
+
+#[test]
+fn refract_obeys_snell_and_stays_unit_length() {
+    check(
+        2,
+        |rng| {
+            let n = unit_vector(rng);
+            let mut v = unit_vector(rng);
+            if v.dot(n) > 0.0 {
+                v = -v;
+            }
+            (v, n, rng.random_range(0.3..3.0))
+        },
+        |&(v, n, eta)| {
+            let sin_i = v.cross(n).length();
+            if eta * sin_i >= 1.0 - 1e-6 {
+                return Ok(()); // Total internal reflection: refract doesn't apply.
+            }
+            let r = Vec3::refract(&v, &n, eta);
+            close(r.length(), 1.0, "length")?;
+            close(r.cross(n).length(), eta * sin_i, "Snell's law")?;
+            if r.dot(n) > EPS {
+                return Err("refracted ray comes back out".to_string());
+            }
+            Ok(())
+        },
+    );
+}
+
+#[test]
+fn fresnel_reflectance_is_a_fraction_and_reciprocal() {
+    check(
+        3,
+        |rng| (rng.random_range(0.0..=1.0), rng.random_range(1.0..3.0)),
+        |&(cos_i, eta): &(f64, f64)| {
+            let reflected = fresnel_dielectric(cos_i, eta);
+            if !(0.0..=1.0).contains(&reflected) {
+                return Err(format!("reflectance {reflected}"));
+            }
+            let schlick = Dielectric::reflanctance(cos_i, eta);
+            if !(0.0..=1.0 + EPS).contains(&schlick) {
+                return Err(format!("Schlick reflectance {schlick}"));
+            }
+            // Light crossing the interface the other way reflects the same fraction.
+            let cos_t = (1.0 - (1.0 - cos_i * cos_i) / (eta * eta)).sqrt();
+            close(
+                fresnel_dielectric(cos_t, 1.0 / eta),
+                reflected,
+                "reciprocity",
+            )
+        },
+    );
+}
+
+#[test]
+fn hit_normals_are_unit_and_face_the_ray() {
+    let objects: Vec<Arc<dyn Hittable>> = vec![
+        Arc::new(Sphere::new(Point3::zero(), 1.0, gray())),
+        Arc::new(Quad::new(
+            Point3::new(-1.0, -1.0, 0.0),
+            Vec3::new(2.0, 0.0, 0.0),
+            Vec3::new(0.5, 2.0, 0.5),
+            gray(),
+        )),
+        Arc::new(Triangle::new(
+            Point3::new(-1.0, -1.0, 0.2),
+            Point3::new(1.5, -0.5, -0.3),
+            Point3::new(0.0, 1.2, 0.1),
+            gray(),
+        )),
+    ];
+    for (index, object) in objects.iter().enumerate() {
+        check(
+            4 + index as u64,
+            // Rays from outside aimed near the object, so most of them hit.
+            |rng| {
+                let origin = unit_vector(rng) * rng.random_range(2.0..6.0);
+                let target = point(rng, 1.0);
+                Ray::new(origin, (target - origin) * rng.random_range(0.2..3.0))
+            },
+            |r| {
+                let Some(rec) = object.hit(r, Interval::new(0.001, INFINITY)) else {
+                    return Ok(());
+                };
+                close(rec.normal.length(), 1.0, "normal length")?;
+                close(
+                    rec.geometric_normal.length(),
+                    1.0,
+                    "geometric normal length",
+                )?;
+                if rec.normal.dot(r.dir) > EPS || rec.geometric_normal.dot(r.dir) > EPS {
+                    return Err("normal faces away from the ray".to_string());
+                }
+                if rec.t < 0.001 {
+                    return Err(format!("hit at t = {} outside the interval", rec.t));
+                }
+                close((rec.p - r.at(rec.t)).length(), 0.0, "hit point on the ray")
+            },
+        );
+    }
+}
+
+#[test]
+fn interval_clamp_lands_inside() {
+    check(
+        7,
+        |rng| {
+            let (a, b): (f64, f64) = (rng.random_range(-10.0..10.0), rng.random_range(-10.0..10.0));
+            (
+                Interval::new(a.min(b), a.max(b)),
+                rng.random_range(-20.0..20.0),
+            )
+        },
+        |&(interval, x)| {
+            let clamped = interval.clamp(x);
+            if !interval.contains(clamped) {
+                return Err(format!("clamped to {clamped}"));
+            }
+            if interval.contains(x) && clamped != x {
+                return Err("moved a value already inside".to_string());
+            }
+            close(interval.clamp(clamped), clamped, "clamping twice")
+        },
+    );
+}
+
+#[test]
+fn sampled_directions_lie_where_they_should() {
+    check(
+        8,
+        |rng| {
+            let n = unit_vector(rng);
+            (
+                n,
+                rng.random::<f64>(),
+                rng.random::<f64>(),
+                rng.random::<u64>(),
+            )
+        },
+        |&(n, u1, u2, seed)| {
+            close(
+                Vec3::sample_unit_sphere(u1, u2).length(),
+                1.0,
+                "sphere sample",
+            )?;
+            let disk = Vec3::sample_unit_disk(u1, u2);
+            if disk.length() > 1.0 + EPS || disk.z != 0.0 {
+                return Err(format!("disk sample {disk:?}"));
+            }
+            let hemisphere = Vec3::random_on_hemisphere(&mut SmallRng::seed_from_u64(seed), &n);
+            close(hemisphere.length(), 1.0, "hemisphere sample")?;
+            if hemisphere.dot(n) < 0.0 {
+                return Err("hemisphere sample below the surface".to_string());
+            }
+            Ok(())
+        },
+    );
+}
+
+#[test]
+fn onb_is_orthonormal_and_round_trips() {
+    check(
+        9,
+        |rng| (unit_vector(rng), point(rng, 5.0)),
+        |&(n, v)| {
+            let basis = Onb::new(n);
+            for (a, b) in [(basis.u, basis.v), (basis.v, basis.w), (basis.w, basis.u)] {
+                close(a.dot(b), 0.0, "axes orthogonal")?;
+                close(a.length(), 1.0, "axis length")?;
+            }
+            close(basis.w.dot(n), 1.0, "w along the normal")?;
+            close(
+                (basis.transform(basis.to_local(v)) - v).length(),
+                0.0,
+                "round trip",
+            )
+        },
+    );
+}
+
+#[test]
+fn ggx_visible_normals_are_unit_and_upward() {
+    check(
+        10,
+        |rng| {
+            let mut wo = unit_vector(rng);
+            wo.z = wo.z.abs().max(1e-3);
+            let ggx = Ggx::from_roughness(rng.random(), rng.random());
+            (ggx, wo.normalized(), rng.random::<u64>())
+        },
+        |&(ggx, wo, seed)| {
+            let h = ggx.sample_visible_normal(wo, &mut SmallRng::seed_from_u64(seed));
+            close(h.length(), 1.0, "length")?;
+            if h.z < 0.0 {
+                return Err(format!("microfacet normal {h:?} below the surface"));
+            }
+            Ok(())
+        },
+    );
+}